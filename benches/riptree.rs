@@ -0,0 +1,132 @@
+// Benchmarks of the hot paths of mining, on synthetic datasets from
+// gen_data, so performance regressions show up. Run with `cargo bench`.
+
+#[macro_use]
+extern crate criterion;
+extern crate riptree;
+
+use criterion::Criterion;
+use riptree::bitset_counts::BitsetCounts;
+use riptree::fptree::{rip_growth, sort_transaction, FPTree, SortOrder};
+use riptree::gen_data::{generate, DataParameters};
+use riptree::index::Index;
+use riptree::itemizer::Itemizer;
+use riptree::significance::SignificanceTest;
+use std::collections::{HashMap, HashSet};
+
+fn dataset() -> Vec<Vec<u32>> {
+    generate(&DataParameters {
+        num_transactions: 20000,
+        num_items: 500,
+        num_patterns: 500,
+        ..DataParameters::default()
+    })
+}
+
+fn item_counts(transactions: &[Vec<u32>]) -> HashMap<u32, u32> {
+    let mut item_count: HashMap<u32, u32> = HashMap::new();
+    for transaction in transactions {
+        for &item in transaction {
+            *item_count.entry(item).or_insert(0) += 1;
+        }
+    }
+    item_count
+}
+
+fn build_tree(transactions: &[Vec<u32>], item_count: &HashMap<u32, u32>) -> FPTree {
+    let mut tree = FPTree::new();
+    for transaction in transactions {
+        let mut transaction = transaction.clone();
+        sort_transaction(&mut transaction, item_count, SortOrder::Decreasing);
+        tree.insert(&transaction, 1);
+    }
+    tree
+}
+
+fn build_index(transactions: &[Vec<u32>]) -> Index {
+    let mut index = Index::new();
+    for transaction in transactions {
+        index.insert(transaction, 1);
+    }
+    index
+}
+
+fn bench_tree_build(c: &mut Criterion) {
+    let transactions = dataset();
+    let item_count = item_counts(&transactions);
+    c.bench_function("tree build", |b| b.iter(|| build_tree(&transactions, &item_count)));
+}
+
+fn bench_growth(c: &mut Criterion) {
+    let transactions = dataset();
+    let item_count = item_counts(&transactions);
+    // The rarest tenth of the items are rare, and only the transactions
+    // containing them are in the tree, as when mining.
+    let mut items: Vec<u32> = item_count.keys().cloned().collect();
+    items.sort_by_key(|item| (item_count[item], *item));
+    let rare_items: HashSet<u32> = items[..items.len() / 10].iter().cloned().collect();
+    let rare_transactions: Vec<Vec<u32>> = transactions
+        .iter()
+        .filter(|transaction| transaction.iter().any(|item| rare_items.contains(item)))
+        .cloned()
+        .collect();
+    let tree = build_tree(&rare_transactions, &item_count);
+    let index = build_index(&transactions);
+    let itemizer = Itemizer::new();
+    let significance_test = SignificanceTest::fisher();
+    let required_items = HashSet::new();
+    c.bench_function("growth", |b| {
+        b.iter(|| {
+            rip_growth(
+                &tree,
+                &tree,
+                Some(&rare_items),
+                &[],
+                transactions.len() as u32,
+                &itemizer,
+                &index,
+                &significance_test,
+                Some(3),
+                &required_items,
+                None,
+                None,
+                None,
+                None,
+            )
+        })
+    });
+}
+
+// The pairs and triples of items in the first transactions, so the itemsets
+// counted occur in the dataset.
+fn itemsets_to_count(transactions: &[Vec<u32>]) -> Vec<Vec<u32>> {
+    transactions
+        .iter()
+        .take(500)
+        .filter(|transaction| transaction.len() >= 3)
+        .flat_map(|transaction| vec![transaction[..2].to_vec(), transaction[..3].to_vec()])
+        .collect()
+}
+
+fn bench_index_count(c: &mut Criterion) {
+    let transactions = dataset();
+    let index = build_index(&transactions);
+    let itemsets = itemsets_to_count(&transactions);
+    c.bench_function("index count", |b| {
+        b.iter(|| itemsets.iter().map(|itemset| index.count(itemset)).sum::<usize>())
+    });
+}
+
+fn bench_bitset_count(c: &mut Criterion) {
+    let transactions = dataset();
+    let index = build_index(&transactions);
+    let itemsets = itemsets_to_count(&transactions);
+    let mut items: Vec<u32> = itemsets.iter().flat_map(|itemset| itemset.iter().cloned()).collect();
+    items.sort();
+    items.dedup();
+    let counts = BitsetCounts::new(&index, &items);
+    c.bench_function("bitset count", |b| b.iter(|| counts.count_all(&itemsets)));
+}
+
+criterion_group!(benches, bench_tree_build, bench_growth, bench_index_count, bench_bitset_count);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+/* C interface to the riptree rare association rule miner.
+ *
+ * Generated from src/ffi.rs with:
+ *   cbindgen --config cbindgen.toml --crate riptree --output include/riptree.h
+ */
+
+#ifndef RIPTREE_H
+#define RIPTREE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define RIPTREE_MAX_SUPPORT_GAUSSIAN 0
+
+#define RIPTREE_MAX_SUPPORT_PARETO 1
+
+#define RIPTREE_MAX_SUPPORT_ROBUST 2
+
+#define RIPTREE_MAX_SUPPORT_ELBOW 3
+
+typedef struct RiptreeRules RiptreeRules;
+
+typedef struct RiptreeRule {
+  const char *antecedent;
+  const char *consequent;
+  double confidence;
+  double lift;
+  double support;
+} RiptreeRule;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Mines the CSV dataset at `path` and returns a handle to the rules found,
+ * or NULL on failure. The handle must be released with riptree_free().
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a valid NUL terminated string.
+ */
+struct RiptreeRules *riptree_mine_file(const char *path,
+                                       int max_support_mode,
+                                       double min_confidence,
+                                       double min_lift);
+
+/**
+ * Returns the number of rules in `rules`, or 0 if `rules` is NULL.
+ *
+ * # Safety
+ *
+ * `rules` must be NULL or a handle returned by riptree_mine_file().
+ */
+size_t riptree_rules_len(const struct RiptreeRules *rules);
+
+/**
+ * Copies the rule at `index` into `out`. Returns 0 on success, or -1 if
+ * either pointer is NULL or `index` is out of range. The strings in `out`
+ * are owned by `rules`, and are valid until riptree_free() is called.
+ *
+ * # Safety
+ *
+ * `rules` must be NULL or a handle returned by riptree_mine_file(), and
+ * `out` must be NULL or point to writable memory for a RiptreeRule.
+ */
+int riptree_rule_get(const struct RiptreeRules *rules, size_t index, struct RiptreeRule *out);
+
+/**
+ * Releases a handle returned by riptree_mine_file(). Passing NULL is a no-op.
+ *
+ * # Safety
+ *
+ * `rules` must be NULL or a handle returned by riptree_mine_file() which
+ * hasn't already been freed.
+ */
+void riptree_free(struct RiptreeRules *rules);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RIPTREE_H */
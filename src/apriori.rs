@@ -0,0 +1,80 @@
+// Apriori mines itemsets level by level: each level's candidates are the
+// previous level's itemsets plus one more item, and candidates are counted
+// with the index. It's slower than the other algorithms, but simple enough
+// to check them against.
+//
+// The search space is rip_growth's: items are ordered by decreasing count,
+// as in the tree, and an itemset starts with a rare item and is extended by
+// items which come earlier in that order. A candidate is kept if it occurs,
+// and passes the test of association between the added item and the
+// itemset it extends.
+
+use fptree::ItemSet;
+use index::Index;
+use significance::{Contingency, SignificanceTest};
+use std::collections::{HashMap, HashSet};
+
+pub fn apriori(
+    index: &Index,
+    item_count: &HashMap<u32, u32>,
+    rare_items: &HashSet<u32>,
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+) -> Vec<ItemSet> {
+    // Items by decreasing count, then decreasing id, as in the tree.
+    let mut items: Vec<(u32, u32)> = item_count
+        .iter()
+        .map(|(&item, &count)| (count, item))
+        .collect();
+    items.sort_by(|a, b| b.cmp(a));
+    let position: HashMap<u32, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(position, &(_, item))| (item, position))
+        .collect();
+
+    // Each level's itemsets, in the order their items were added.
+    let mut level: Vec<ItemSet> = items
+        .iter()
+        .filter(|&&(_, item)| rare_items.contains(&item))
+        .map(|&(count, item)| ItemSet {
+            items: vec![item],
+            count,
+        })
+        .collect();
+
+    let n = index.num_transactions() as u32;
+    let mut itemsets: Vec<ItemSet> = vec![];
+    while !level.is_empty() {
+        let mut next_level: Vec<ItemSet> = vec![];
+        if max_itemset_len.is_none_or(|max| level[0].len() < max) {
+            for itemset in level.iter() {
+                let last = itemset.items[itemset.len() - 1];
+                for &(count, item) in items[..position[&last]].iter() {
+                    let mut candidate = itemset.items.clone();
+                    candidate.push(item);
+                    let candidate_count = index.count(&candidate) as u32;
+                    let table = Contingency::new(n, count, itemset.count, candidate_count);
+                    if candidate_count == 0 || significance_test.pval(&table) >= 0.05 {
+                        continue;
+                    }
+                    next_level.push(ItemSet {
+                        items: candidate,
+                        count: candidate_count,
+                    });
+                }
+            }
+        }
+        itemsets.extend(level.into_iter().filter(|itemset| {
+            required_items.is_empty()
+                || itemset.items.iter().any(|item| required_items.contains(item))
+        }));
+        level = next_level;
+    }
+
+    itemsets
+        .into_iter()
+        .map(|itemset| ItemSet::new(itemset.items, itemset.count))
+        .collect()
+}
@@ -0,0 +1,202 @@
+// Reads transactions from Weka's ARFF files, dense or sparse. Each
+// attribute's value in a row becomes an item: values of 1 or t become the
+// attribute's name, as in market basket datasets such as supermarket.arff,
+// other values become "name=value", and missing values (?) and 0 are
+// skipped, as values left out of sparse rows are 0.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+// The rows of an ARFF file's @data section, one at a time.
+pub struct ArffReader {
+    path: String,
+    reader: BufReader<File>,
+    attributes: Vec<String>,
+    line_number: usize,
+}
+
+fn invalid_data(path: &str, line_number: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Can't read {} line {}: {}", path, line_number, message),
+    )
+}
+
+// Removes the quotes around a name or value, if it has any.
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    for quote in &['\'', '"'] {
+        if s.len() >= 2 && s.starts_with(*quote) && s.ends_with(*quote) {
+            return &s[1..s.len() - 1];
+        }
+    }
+    s
+}
+
+// The name of the attribute declared by the rest of an @attribute line,
+// which may be quoted.
+fn attribute_name(declaration: &str) -> Option<&str> {
+    let declaration = declaration.trim_start();
+    for quote in &['\'', '"'] {
+        if let Some(rest) = declaration.strip_prefix(*quote) {
+            return rest.find(*quote).map(|end| &rest[..end]);
+        }
+    }
+    declaration.split_whitespace().next()
+}
+
+fn item(attribute: &str, value: &str) -> Option<String> {
+    match unquote(value) {
+        "?" | "0" | "" => None,
+        "1" | "t" => Some(attribute.to_owned()),
+        value => Some(format!("{}={}", attribute, value)),
+    }
+}
+
+// Whether `line` starts with the ARFF keyword `keyword`, which is case
+// insensitive.
+fn is_keyword(line: &str, keyword: &str) -> bool {
+    line.len() >= keyword.len() && line[..keyword.len()].eq_ignore_ascii_case(keyword)
+}
+
+impl ArffReader {
+    // Opens an ARFF file, reading its header.
+    pub fn open(path: &str) -> io::Result<ArffReader> {
+        let file = File::open(path).map_err(|err| io::Error::new(err.kind(), format!("Can't open {}: {}", path, err)))?;
+        let mut reader = ArffReader {
+            path: path.to_owned(),
+            reader: BufReader::new(file),
+            attributes: vec![],
+            line_number: 0,
+        };
+        loop {
+            let line = match reader.next_line()? {
+                Some(line) => line,
+                None => return Err(invalid_data(path, reader.line_number, String::from("there's no @data section"))),
+            };
+            if is_keyword(&line, "@attribute") {
+                match attribute_name(&line["@attribute".len()..]) {
+                    Some(name) => reader.attributes.push(name.to_owned()),
+                    None => return Err(invalid_data(path, reader.line_number, String::from("attribute has no name"))),
+                }
+            } else if is_keyword(&line, "@data") {
+                return Ok(reader);
+            }
+        }
+    }
+
+    // Returns the next line which isn't blank or a comment, trimmed.
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('%') {
+                return Ok(Some(trimmed.to_owned()));
+            }
+        }
+    }
+
+    fn parse_row(&self, row: &str) -> io::Result<Vec<String>> {
+        let error = |message: String| invalid_data(&self.path, self.line_number, message);
+        let mut items = vec![];
+        if row.starts_with('{') {
+            let entries = row.trim_start_matches('{').trim_end_matches('}');
+            for entry in entries.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let (index, value) = match entry.find(char::is_whitespace) {
+                    Some(space) => (&entry[..space], &entry[space..]),
+                    None => return Err(error(format!("expected 'index value', found '{}'", entry))),
+                };
+                let attribute = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.attributes.get(index))
+                    .ok_or_else(|| error(format!("invalid attribute index '{}'", index)))?;
+                items.extend(item(attribute, value));
+            }
+        } else {
+            let values: Vec<&str> = row.split(',').collect();
+            if values.len() != self.attributes.len() {
+                return Err(error(format!(
+                    "expected {} values, found {}",
+                    self.attributes.len(),
+                    values.len()
+                )));
+            }
+            for (attribute, value) in self.attributes.iter().zip(values) {
+                items.extend(item(attribute, value));
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl Iterator for ArffReader {
+    type Item = io::Result<Vec<String>>;
+    fn next(&mut self) -> Option<io::Result<Vec<String>>> {
+        match self.next_line() {
+            Ok(Some(row)) => Some(self.parse_row(&row)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_arff_reader() {
+        use super::ArffReader;
+        use std::env;
+        use std::fs;
+
+        let dir = env::temp_dir().join(format!("riptree-arff-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("baskets.arff");
+        fs::write(
+            &path,
+            "% Shopping baskets\n\
+             @RELATION baskets\n\
+             \n\
+             @ATTRIBUTE milk {t}\n\
+             @attribute 'white bread' {t}\n\
+             @attribute store {north,south}\n\
+             @attribute eggs numeric\n\
+             @DATA\n\
+             t,?,north,0\n\
+             ?,t,'south',1\n\
+             % A sparse row\n\
+             {0 t, 2 south}\n\
+             {}\n",
+        )
+        .unwrap();
+
+        let rows: Vec<Vec<String>> = ArffReader::open(&path.to_string_lossy())
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["milk", "store=north"],
+                vec!["white bread", "store=south", "eggs"],
+                vec!["milk", "store=south"],
+                vec![],
+            ]
+        );
+
+        fs::write(&path, "@attribute milk {t}\n@data\nt,t\n").unwrap();
+        let mut reader = ArffReader::open(&path.to_string_lossy()).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("line 3: expected 1 values, found 2"));
+
+        fs::write(&path, "@attribute milk {t}\n").unwrap();
+        assert!(ArffReader::open(&path.to_string_lossy()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+// `riptree browse`: explores a rules file previously written with
+// `--output-format json` in a terminal UI. The rules are shown in a table
+// which can be sorted by any column, and filtered as you type by the items
+// they contain. Below the table, the selected rule's contingency table shows
+// how many transactions contain its antecedent, its consequent, both and
+// neither: counted in the dataset given with --input, or otherwise worked
+// out, as fractions of the transactions, from the rule's metrics.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use riptree::command_line_args::BrowseArguments;
+use riptree::index::Index;
+use riptree::itemizer::Itemizer;
+use riptree::rule_reader::{read_rules_json, SavedRule};
+use riptree::transaction_reader::{CsvFile, InputOptions, TransactionSource};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Column {
+    Rule,
+    Confidence,
+    Lift,
+    Support,
+    PValue,
+}
+
+const COLUMNS: [Column; 5] = [
+    Column::Rule,
+    Column::Confidence,
+    Column::Lift,
+    Column::Support,
+    Column::PValue,
+];
+
+impl Column {
+    fn title(self) -> &'static str {
+        match self {
+            Column::Rule => "Rule",
+            Column::Confidence => "Confidence",
+            Column::Lift => "Lift",
+            Column::Support => "Support",
+            Column::PValue => "P-Value",
+        }
+    }
+
+    // Whether the column is sorted from the highest value by default, so
+    // the best rules come first.
+    fn descending(self) -> bool {
+        match self {
+            Column::Rule | Column::PValue => false,
+            Column::Confidence | Column::Lift | Column::Support => true,
+        }
+    }
+
+    // Compares two rules by the column, in increasing order. Rules without
+    // a p-value sort after those with one.
+    fn compare(self, a: &SavedRule, b: &SavedRule) -> Ordering {
+        let compare_f64 = |a: f64, b: f64| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+        match self {
+            Column::Rule => a.to_string().cmp(&b.to_string()),
+            Column::Confidence => compare_f64(a.confidence, b.confidence),
+            Column::Lift => compare_f64(a.lift, b.lift),
+            Column::Support => compare_f64(a.support, b.support),
+            Column::PValue => compare_f64(
+                a.p_value.unwrap_or(f64::INFINITY),
+                b.p_value.unwrap_or(f64::INFINITY),
+            ),
+        }
+    }
+}
+
+// Whether each of the words in the filter is part of one of the rule's
+// items, ignoring case.
+fn matches_filter(rule: &SavedRule, filter: &str) -> bool {
+    filter.split_whitespace().all(|word| {
+        let word = word.to_lowercase();
+        rule.antecedent
+            .iter()
+            .chain(rule.consequent.iter())
+            .any(|item| item.to_lowercase().contains(&word))
+    })
+}
+
+// Returns the positions of the rules matching the filter, sorted by the
+// column if one is given, or otherwise in the order of the rules file. Rules
+// which are equal by the column stay in file order.
+fn visible_rules(rules: &[SavedRule], filter: &str, sort_by: Option<(Column, bool)>) -> Vec<usize> {
+    let mut visible: Vec<usize> = (0..rules.len())
+        .filter(|&position| matches_filter(&rules[position], filter))
+        .collect();
+    if let Some((column, descending)) = sort_by {
+        visible.sort_by(|&a, &b| {
+            let order = column.compare(&rules[a], &rules[b]);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+    visible
+}
+
+// How many transactions contain a rule's antecedent, its consequent, and
+// both, out of the total. Either counts of transactions in the dataset, or
+// fractions of them worked out from the rule's metrics.
+#[derive(Debug, PartialEq)]
+struct RuleSupport {
+    total: f64,
+    antecedent: f64,
+    consequent: f64,
+    both: f64,
+    counted: bool,
+}
+
+impl RuleSupport {
+    // Support is the fraction containing both, confidence is that over the
+    // fraction containing the antecedent, and lift is confidence over the
+    // fraction containing the consequent.
+    fn estimate(rule: &SavedRule) -> RuleSupport {
+        let ratio = |a: f64, b: f64| if b > 0.0 { a / b } else { 0.0 };
+        RuleSupport {
+            total: 1.0,
+            antecedent: ratio(rule.support, rule.confidence),
+            consequent: ratio(rule.confidence, rule.lift),
+            both: rule.support,
+            counted: false,
+        }
+    }
+}
+
+// The dataset given with --input, indexed to count the transactions
+// containing any rule's items. Items are named as in the dataset, so rules
+// mined with options renaming items, such as --lowercase, may not be found.
+struct Dataset {
+    itemizer: Itemizer,
+    index: Index,
+}
+
+impl Dataset {
+    fn read(path: &str) -> io::Result<Dataset> {
+        let mut itemizer = Itemizer::new();
+        let mut index = Index::new();
+        let source = CsvFile::new(vec![String::from(path)], InputOptions::default(), None);
+        for transaction in source.read(&mut itemizer) {
+            let transaction = transaction?;
+            index.insert(&transaction.items, transaction.weight);
+        }
+        Ok(Dataset { itemizer, index })
+    }
+
+    // Counts the transactions containing the items, which none do if any
+    // item isn't in the dataset.
+    fn count(&self, items: &[String]) -> usize {
+        let ids: Option<Vec<u32>> = items.iter().map(|item| self.itemizer.get_id(item)).collect();
+        match ids {
+            Some(ids) => self.index.count(&ids),
+            None => 0,
+        }
+    }
+
+    fn support(&self, rule: &SavedRule) -> RuleSupport {
+        let both: Vec<String> = rule.antecedent.iter().chain(rule.consequent.iter()).cloned().collect();
+        RuleSupport {
+            total: self.index.num_transactions() as f64,
+            antecedent: self.count(&rule.antecedent) as f64,
+            consequent: self.count(&rule.consequent) as f64,
+            both: self.count(&both) as f64,
+            counted: true,
+        }
+    }
+}
+
+struct Browser {
+    rules: Vec<SavedRule>,
+    dataset: Option<Dataset>,
+    filter: String,
+    editing_filter: bool,
+    // The column sorted by, and whether from the highest value.
+    sort_by: Option<(Column, bool)>,
+    // The positions in `rules` of the rules shown, in the order shown.
+    visible: Vec<usize>,
+    table: TableState,
+    show_support: bool,
+}
+
+impl Browser {
+    fn new(rules: Vec<SavedRule>, dataset: Option<Dataset>) -> Browser {
+        let mut browser = Browser {
+            rules,
+            dataset,
+            filter: String::new(),
+            editing_filter: false,
+            sort_by: None,
+            visible: vec![],
+            table: TableState::default(),
+            show_support: false,
+        };
+        browser.refresh();
+        browser
+    }
+
+    fn selected_rule(&self) -> Option<&SavedRule> {
+        self.table
+            .selected()
+            .and_then(|row| self.visible.get(row))
+            .map(|&position| &self.rules[position])
+    }
+
+    // Updates the rules shown after the filter or sort order changes,
+    // keeping the selected rule selected if it's still shown.
+    fn refresh(&mut self) {
+        let selected = self.table.selected().and_then(|row| self.visible.get(row).cloned());
+        self.visible = visible_rules(&self.rules, &self.filter, self.sort_by);
+        let row = selected
+            .and_then(|selected| self.visible.iter().position(|&position| position == selected))
+            .unwrap_or(0);
+        self.table.select(if self.visible.is_empty() { None } else { Some(row) });
+    }
+
+    fn sort(&mut self, column: Option<Column>) {
+        self.sort_by = match (column, self.sort_by) {
+            // Choosing the column already sorted by reverses the order.
+            (Some(column), Some((sorted, descending))) if column == sorted => Some((column, !descending)),
+            (Some(column), _) => Some((column, column.descending())),
+            (None, _) => None,
+        };
+        self.refresh();
+    }
+
+    fn move_selection(&mut self, rows: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let row = self.table.selected().unwrap_or(0) as isize + rows;
+        self.table.select(Some(row.clamp(0, self.visible.len() as isize - 1) as usize));
+    }
+
+    // Handles a key press, returning false to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => return true,
+            }
+            self.refresh();
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('0') => self.sort(None),
+            KeyCode::Char(c @ '1'..='5') => self.sort(Some(COLUMNS[c as usize - '1' as usize])),
+            KeyCode::Enter => self.show_support = !self.show_support,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX / 2),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let support_height = if self.show_support { 7 } else { 0 };
+        let [rules_area, support_area, footer_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(support_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new(COLUMNS.iter().map(|&column| match self.sort_by {
+            Some((sorted, descending)) if sorted == column => {
+                format!("{} {}", column.title(), if descending { "▼" } else { "▲" })
+            }
+            _ => String::from(column.title()),
+        }))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = self.visible.iter().map(|&position| {
+            let rule = &self.rules[position];
+            Row::new(vec![
+                rule.to_string(),
+                format!("{:.4}", rule.confidence),
+                format!("{:.4}", rule.lift),
+                format!("{:.4}", rule.support),
+                rule.p_value.map_or_else(String::new, |p_value| format!("{:.3e}", p_value)),
+            ])
+        });
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                " Rules: {} of {} ",
+                self.visible.len(),
+                self.rules.len()
+            )))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, rules_area, &mut self.table);
+
+        if self.show_support {
+            self.draw_support(frame, support_area);
+        }
+
+        let footer = if self.editing_filter || !self.filter.is_empty() {
+            format!("Filter: {}{}", self.filter, if self.editing_filter { "_" } else { "" })
+        } else {
+            String::from("/ filter  1-5 sort by column  0 file order  Enter transactions  q quit")
+        };
+        frame.render_widget(Paragraph::new(Line::from(footer)), footer_area);
+    }
+
+    fn draw_support(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let rule = match self.selected_rule() {
+            Some(rule) => rule,
+            None => return,
+        };
+        let support = match self.dataset {
+            Some(ref dataset) => dataset.support(rule),
+            None => RuleSupport::estimate(rule),
+        };
+        let format = |value: f64| {
+            if support.counted {
+                format!("{}", value)
+            } else {
+                format!("{:.4}", value)
+            }
+        };
+        let rows = vec![
+            Row::new(vec![
+                String::from("Antecedent"),
+                format(support.both),
+                format(support.antecedent - support.both),
+                format(support.antecedent),
+            ]),
+            Row::new(vec![
+                String::from("No antecedent"),
+                format(support.consequent - support.both),
+                format(support.total - support.antecedent - support.consequent + support.both),
+                format(support.total - support.antecedent),
+            ]),
+            Row::new(vec![
+                String::from("Total"),
+                format(support.consequent),
+                format(support.total - support.consequent),
+                format(support.total),
+            ]),
+        ];
+        let title = if support.counted {
+            format!(" Transactions supporting {} ", rule)
+        } else {
+            format!(" Fractions of transactions supporting {} (pass --input to count them) ", rule)
+        };
+        let table = Table::new(rows, [Constraint::Length(16); 4])
+            .header(
+                Row::new(vec!["", "Consequent", "No consequent", "Total"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(table, area);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, browser: &mut Browser) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !browser.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub fn browse(args: &BrowseArguments) -> Result<(), Box<dyn Error>> {
+    let rules = read_rules_json(BufReader::new(File::open(&args.rules_path)?))?;
+    let dataset = if args.input_file_path.is_empty() {
+        None
+    } else {
+        Some(Dataset::read(&args.input_file_path)?)
+    };
+    let mut browser = Browser::new(rules, dataset);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut browser);
+    ratatui::restore();
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use riptree::rule_reader::SavedRule;
+
+    fn rule(antecedent: &[&str], consequent: &str, confidence: f64, lift: f64) -> SavedRule {
+        SavedRule {
+            antecedent: antecedent.iter().map(|s| String::from(*s)).collect(),
+            consequent: vec![String::from(consequent)],
+            confidence,
+            lift,
+            support: 0.25,
+            p_value: None,
+        }
+    }
+
+    #[test]
+    fn test_visible_rules() {
+        use super::{visible_rules, Column};
+
+        let rules = vec![
+            rule(&["milk"], "bread", 0.5, 2.0),
+            rule(&["eggs", "Milk"], "caviar", 0.9, 8.0),
+            rule(&["bread"], "milk", 0.5, 1.5),
+            rule(&["eggs"], "caviar", 0.8, 4.0),
+        ];
+        assert_eq!(visible_rules(&rules, "", None), vec![0, 1, 2, 3]);
+        // Each word must be part of an item, ignoring case.
+        assert_eq!(visible_rules(&rules, "MIL", None), vec![0, 1, 2]);
+        assert_eq!(visible_rules(&rules, "milk bre", None), vec![0, 2]);
+        assert_eq!(visible_rules(&rules, "cheese", None), Vec::<usize>::new());
+        assert_eq!(visible_rules(&rules, "", Some((Column::Lift, true))), vec![1, 3, 0, 2]);
+        // Ties stay in file order.
+        assert_eq!(visible_rules(&rules, "", Some((Column::Confidence, false))), vec![0, 2, 3, 1]);
+        assert_eq!(visible_rules(&rules, "milk", Some((Column::Rule, false))), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_rule_support() {
+        use super::{Dataset, RuleSupport};
+        use riptree::index::Index;
+        use riptree::itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let mut index = Index::new();
+        let transactions: [(&[&str], u32); 4] =
+            [(&["milk", "bread"], 2), (&["milk"], 1), (&["bread", "eggs"], 1), (&["eggs"], 4)];
+        for &(items, weight) in transactions.iter() {
+            let items: Vec<u32> = items.iter().map(|item| itemizer.id_of(item)).collect();
+            index.insert(&items, weight);
+        }
+        let dataset = Dataset { itemizer, index };
+
+        // Milk is in 3 of the 8 transactions, bread in 3, and both in 2.
+        let milk_bread = rule(&["milk"], "bread", 2.0 / 3.0, 16.0 / 9.0);
+        let counted = dataset.support(&milk_bread);
+        assert_eq!(
+            counted,
+            RuleSupport {
+                total: 8.0,
+                antecedent: 3.0,
+                consequent: 3.0,
+                both: 2.0,
+                counted: true,
+            }
+        );
+        assert_eq!(dataset.support(&rule(&["milk"], "cheese", 0.0, 0.0)).both, 0.0);
+
+        let estimated = RuleSupport::estimate(&milk_bread);
+        assert!(!estimated.counted);
+        assert!((estimated.antecedent - 0.375).abs() < 1e-9);
+        assert!((estimated.consequent - 0.375).abs() < 1e-9);
+        assert_eq!(estimated.both, 0.25);
+    }
+}
@@ -0,0 +1,108 @@
+// Cancellation of a run, e.g. by Ctrl-C. rip_growth and rule generation check
+// for it as they go, and stop early, so that whatever was found so far can be
+// written out as partial results rather than lost.
+//
+// A run is cancelled by the process-wide flag, unless it's run with its own
+// CancelToken by run_cancellable, as each of the server's jobs is, so
+// cancelling one job doesn't stop the others.
+
+use rayon::ThreadPoolBuilder;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // The token of the run this thread is working on, if it has its own.
+    static TOKEN: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    // Asks the run to stop. Returns false if it was already asked and hasn't
+    // stopped yet.
+    pub fn cancel(&self) -> bool {
+        !self.0.swap(true, Ordering::SeqCst)
+    }
+}
+
+// Calls `f` with the flag of the run this thread is working on.
+fn with_flag<R, F: FnOnce(&AtomicBool) -> R>(f: F) -> R {
+    TOKEN.with(|token| match *token.borrow() {
+        Some(ref token) => f(&token.0),
+        None => f(&CANCELLED),
+    })
+}
+
+// Asks the current phase to stop. Returns false if it was already asked and
+// hasn't stopped yet.
+pub fn cancel() -> bool {
+    !CANCELLED.swap(true, Ordering::SeqCst)
+}
+
+pub fn is_cancelled() -> bool {
+    with_flag(|flag| flag.load(Ordering::Relaxed))
+}
+
+// Returns whether the phase just finished was cancelled, and lets the next
+// one run, so e.g. rules can still be generated from the itemsets found
+// before mining was cancelled.
+pub fn take_cancelled() -> bool {
+    with_flag(|flag| flag.swap(false, Ordering::SeqCst))
+}
+
+// The token of the run this thread is working on, for passing on to the
+// threads it starts.
+pub fn current_token() -> Option<CancelToken> {
+    TOKEN.with(|token| token.borrow().clone())
+}
+
+// Calls `f` on this thread with `token` in place of the process-wide flag.
+pub fn with_token<R, F: FnOnce() -> R>(token: Option<CancelToken>, f: F) -> R {
+    let previous = TOKEN.with(|current| current.replace(token));
+    let result = f();
+    TOKEN.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+// Runs `f` cancelled by `token` rather than the process-wide flag, on a
+// thread pool of its own whose threads all see the token, so the rayon
+// tasks `f` starts do too.
+pub fn run_cancellable<R: Send, F: FnOnce() -> R + Send>(token: &CancelToken, f: F) -> R {
+    let worker_token = token.clone();
+    let pool = ThreadPoolBuilder::new()
+        .start_handler(move |_| TOKEN.with(|current| *current.borrow_mut() = Some(worker_token.clone())))
+        .build()
+        .expect("Failed to start a thread pool");
+    with_token(Some(token.clone()), || pool.install(f))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_cancel_token() {
+        use super::{current_token, is_cancelled, run_cancellable, take_cancelled, CancelToken};
+        use rayon::prelude::*;
+
+        let first = CancelToken::new();
+        let second = CancelToken::new();
+        assert!(first.cancel());
+        assert!(!first.cancel());
+        // Every rayon task in the first run sees its cancellation, and none
+        // in the second, nor the process-wide flag.
+        assert!(run_cancellable(&first, || (0..100).into_par_iter().all(|_| is_cancelled())));
+        assert!(run_cancellable(&second, || (0..100).into_par_iter().all(|_| !is_cancelled())));
+        assert!(!is_cancelled());
+        assert!(current_token().is_none());
+
+        assert!(run_cancellable(&first, take_cancelled));
+        assert!(!run_cancellable(&first, is_cancelled));
+    }
+}
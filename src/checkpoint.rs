@@ -0,0 +1,201 @@
+// Checkpoints let long rip_growth runs be resumed after being interrupted.
+// The growth from each rare item at the top level of the tree is independent
+// of the others, so the checkpoint records which of those have finished, and
+// the itemsets found from each. Resuming skips the finished items, and grows
+// only the rest.
+//
+// Checkpoints are JSON, with items stored by name, since ids depend on the
+// order items are read in. Each records a signature of the options and
+// dataset which affect the itemsets found, and is only resumed if the
+// signature matches.
+
+use clock::Instant;
+use fptree::ItemSet;
+use itemizer::Itemizer;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+struct State {
+    // The itemsets found by growing each finished top level item.
+    completed: HashMap<u32, Vec<ItemSet>>,
+    last_saved: Instant,
+}
+
+pub struct Checkpointer<'a> {
+    path: String,
+    interval: Duration,
+    signature: Value,
+    itemizer: &'a Itemizer,
+    state: Mutex<State>,
+}
+
+fn invalid_checkpoint() -> Box<dyn Error> {
+    "Invalid checkpoint file".into()
+}
+
+impl<'a> Checkpointer<'a> {
+    // Saves to `path` at most once per `interval`.
+    pub fn new(path: &str, interval: Duration, signature: Value, itemizer: &'a Itemizer) -> Checkpointer<'a> {
+        Checkpointer {
+            path: String::from(path),
+            interval,
+            signature,
+            itemizer,
+            state: Mutex::new(State {
+                completed: HashMap::new(),
+                last_saved: Instant::now(),
+            }),
+        }
+    }
+
+    // Loads the items finished by the run which saved the checkpoint at
+    // `path`, which must have had the same signature.
+    pub fn resume(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let json: Value = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if json.get("signature") != Some(&self.signature) {
+            return Err(format!(
+                "Checkpoint {} was saved by a run with different options or data",
+                path
+            ).into());
+        }
+        let id_of = |name: &Value| -> Result<u32, Box<dyn Error>> {
+            let name = name.as_str().ok_or_else(invalid_checkpoint)?;
+            self.itemizer
+                .get_id(name)
+                .ok_or_else(|| format!("Checkpointed item '{}' isn't in the dataset", name).into())
+        };
+
+        let mut state = self.state.lock().unwrap();
+        for entry in json.get("completed").and_then(Value::as_array).ok_or_else(invalid_checkpoint)? {
+            let item = id_of(entry.get("item").ok_or_else(invalid_checkpoint)?)?;
+            let mut itemsets = vec![];
+            for itemset in entry.get("itemsets").and_then(Value::as_array).ok_or_else(invalid_checkpoint)? {
+                let items = itemset
+                    .get("items")
+                    .and_then(Value::as_array)
+                    .ok_or_else(invalid_checkpoint)?
+                    .iter()
+                    .map(&id_of)
+                    .collect::<Result<Vec<u32>, Box<dyn Error>>>()?;
+                let count = itemset.get("count").and_then(Value::as_u64).ok_or_else(invalid_checkpoint)?;
+                itemsets.push(ItemSet::new(items, count as u32));
+            }
+            state.completed.insert(item, itemsets);
+        }
+        info!("Resuming with {} rare items already grown.", state.completed.len());
+        Ok(())
+    }
+
+    // Returns the itemsets found from `item`, if it was already grown.
+    pub fn completed(&self, item: u32) -> Option<Vec<ItemSet>> {
+        self.state.lock().unwrap().completed.get(&item).cloned()
+    }
+
+    // Records that growing `item` found `itemsets`, saving the checkpoint if
+    // it's been long enough since the last save.
+    pub fn complete(&self, item: u32, itemsets: &[ItemSet]) {
+        let mut state = self.state.lock().unwrap();
+        state.completed.insert(item, itemsets.to_vec());
+        if state.last_saved.elapsed() >= self.interval {
+            self.save_locked(&mut state);
+        }
+    }
+
+    pub fn save(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.save_locked(&mut state);
+    }
+
+    // Failing to save isn't fatal; the run carries on, and the next save
+    // may succeed.
+    fn save_locked(&self, state: &mut State) {
+        if self.path.is_empty() {
+            return;
+        }
+        match self.write(state) {
+            Ok(()) => debug!(
+                "Saved checkpoint of {} rare items to {}.",
+                state.completed.len(),
+                self.path
+            ),
+            Err(err) => warn!("Failed to save checkpoint to {}: {}", self.path, err),
+        }
+        state.last_saved = Instant::now();
+    }
+
+    fn write(&self, state: &State) -> Result<(), Box<dyn Error>> {
+        let names = |items: &[u32]| -> Vec<&str> { items.iter().map(|&id| self.itemizer.str_of(id)).collect() };
+        let mut items: Vec<&u32> = state.completed.keys().collect();
+        items.sort();
+        let completed: Vec<Value> = items
+            .into_iter()
+            .map(|item| {
+                let itemsets: Vec<Value> = state.completed[item]
+                    .iter()
+                    .map(|itemset| json!({"items": names(&itemset.items), "count": itemset.count}))
+                    .collect();
+                json!({"item": self.itemizer.str_of(*item), "itemsets": itemsets})
+            })
+            .collect();
+        let checkpoint = json!({
+            "signature": self.signature,
+            "completed": completed,
+        });
+        // As in update, write to a temporary file first, so that a failed
+        // write doesn't lose the previous checkpoint.
+        let temp_path = format!("{}.tmp", self.path);
+        {
+            let mut output = BufWriter::new(File::create(&temp_path)?);
+            serde_json::to_writer(&mut output, &checkpoint)?;
+            output.flush()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_checkpoint() {
+        use super::Checkpointer;
+        use fptree::ItemSet;
+        use itemizer::Itemizer;
+        use std::env;
+        use std::fs;
+        use std::time::Duration;
+
+        let mut itemizer = Itemizer::new();
+        let (a, b, c) = (itemizer.id_of("a"), itemizer.id_of("b"), itemizer.id_of("c"));
+        let path = env::temp_dir().join("riptree-test-checkpoint.json");
+        let path = path.to_str().unwrap();
+
+        let checkpointer = Checkpointer::new(path, Duration::from_secs(3600), json!({"n": 3}), &itemizer);
+        checkpointer.complete(a, &[ItemSet::new(vec![a, b], 2), ItemSet::new(vec![a], 3)]);
+        checkpointer.complete(c, &[]);
+        // Not saved until the interval has passed.
+        assert!(fs::metadata(path).is_err());
+        checkpointer.save();
+
+        // Items are stored by name, so ids may differ when resuming.
+        let mut itemizer = Itemizer::new();
+        let (c, b, a) = (itemizer.id_of("c"), itemizer.id_of("b"), itemizer.id_of("a"));
+        let resumed = Checkpointer::new(path, Duration::from_secs(3600), json!({"n": 3}), &itemizer);
+        resumed.resume(path).unwrap();
+        assert_eq!(
+            resumed.completed(a),
+            Some(vec![ItemSet::new(vec![a, b], 2), ItemSet::new(vec![a], 3)])
+        );
+        assert_eq!(resumed.completed(c), Some(vec![]));
+        assert_eq!(resumed.completed(b), None);
+
+        let different = Checkpointer::new(path, Duration::from_secs(3600), json!({"n": 4}), &itemizer);
+        assert!(different.resume(path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
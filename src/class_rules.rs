@@ -0,0 +1,157 @@
+// Finds the rules particular to one class of a labelled dataset, for
+// --class-report. The transactions of each class are mined separately, so
+// rules rare within a class are found even if the class is small, and the
+// rules found in only one class are kept. Each is counted in every class,
+// and a chi-square test of homogeneity of its support across the classes
+// shows how strongly it's tied to its class.
+
+use generate_rules::Rule;
+use index::Index;
+use itemizer::Itemizer;
+use significance::{homogeneity_pval, Contingency};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+pub struct ClassRule {
+    pub rule: Rule,
+    // The position of the class the rule was found in.
+    pub class: usize,
+    // The rule's contingency table in each class.
+    pub tables: Vec<Contingency>,
+    // The p-value of the test that the rule's support is the same in every
+    // class.
+    pub p_value: f64,
+}
+
+pub struct ClassReport {
+    pub classes: Vec<String>,
+    // The rules found in only one class, by class and then by p-value.
+    pub rules: Vec<ClassRule>,
+}
+
+// Keeps the rules mined from only one class, given the rules mined from each
+// class, and an index of the transactions of each.
+pub fn class_specific_rules(classes: Vec<String>, class_rules: Vec<Vec<Rule>>, indexes: &[Index]) -> ClassReport {
+    let mut num_classes: HashMap<(Vec<u32>, Vec<u32>), usize> = HashMap::new();
+    for rules in class_rules.iter() {
+        for rule in rules.iter() {
+            *num_classes
+                .entry((rule.antecedent().to_vec(), rule.consequent().to_vec()))
+                .or_insert(0) += 1;
+        }
+    }
+    let mut rules: Vec<ClassRule> = vec![];
+    for (class, class_rules) in class_rules.into_iter().enumerate() {
+        for rule in class_rules {
+            if num_classes[&(rule.antecedent().to_vec(), rule.consequent().to_vec())] > 1 {
+                continue;
+            }
+            let tables: Vec<Contingency> = indexes.iter().map(|index| rule.contingency(index)).collect();
+            let support: Vec<(u32, u32)> = tables.iter().map(|t| (t.ab, t.n - t.ab)).collect();
+            rules.push(ClassRule {
+                rule,
+                class,
+                tables,
+                p_value: homogeneity_pval(&support),
+            });
+        }
+    }
+    rules.sort_by(|a, b| {
+        a.class
+            .cmp(&b.class)
+            .then(a.p_value.partial_cmp(&b.p_value).unwrap_or(Ordering::Equal))
+    });
+    ClassReport { classes, rules }
+}
+
+// Writes the class report as CSV: a line per rule, with the class it was
+// found in, its confidence, lift and support in that class, the p-value of
+// the test across classes, and then the number of transactions, and of those
+// containing its antecedent, its consequent and both, in each class.
+pub fn write_class_report_csv<W: Write>(
+    output: &mut W,
+    report: &ClassReport,
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    write!(
+        output,
+        "Antecedent => Consequent, Class, Confidence, Lift, Support, Across Classes P-Value"
+    )?;
+    for class in report.classes.iter() {
+        write!(
+            output,
+            ", {0} Transactions, {0} Antecedent, {0} Consequent, {0} Both",
+            class
+        )?;
+    }
+    writeln!(output)?;
+    for class_rule in report.rules.iter() {
+        let rule = &class_rule.rule;
+        write!(
+            output,
+            "{}, {}, {}, {}, {}, {}",
+            rule.to_string(itemizer),
+            report.classes[class_rule.class],
+            rule.confidence(),
+            rule.lift(),
+            rule.support(),
+            class_rule.p_value
+        )?;
+        for table in class_rule.tables.iter() {
+            write!(output, ", {}, {}, {}, {}", table.n, table.a, table.b, table.ab)?;
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_class_specific_rules() {
+        use super::{class_specific_rules, write_class_report_csv};
+        use generate_rules::Rule;
+        use index::Index;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let (milk, bread, eggs, ham) =
+            (itemizer.id_of("milk"), itemizer.id_of("bread"), itemizer.id_of("eggs"), itemizer.id_of("ham"));
+        // Milk and bread go together in both classes, but eggs and ham only
+        // in fraud.
+        let mut fraud = Index::new();
+        let mut legit = Index::new();
+        fraud.insert(&[milk, bread], 5);
+        fraud.insert(&[eggs, ham], 5);
+        legit.insert(&[milk, bread], 50);
+        legit.insert(&[eggs], 40);
+        legit.insert(&[ham], 40);
+
+        let rules = vec![
+            vec![
+                Rule::new(vec![milk], vec![bread], 1.0, 2.0, 0.5),
+                Rule::new(vec![eggs], vec![ham], 1.0, 2.0, 0.5),
+            ],
+            vec![Rule::new(vec![milk], vec![bread], 1.0, 2.6, 0.38)],
+        ];
+        let classes = vec![String::from("fraud"), String::from("legit")];
+        let report = class_specific_rules(classes, rules, &[fraud, legit]);
+        assert_eq!(report.rules.len(), 1);
+        let class_rule = &report.rules[0];
+        assert_eq!(class_rule.class, 0);
+        assert_eq!(class_rule.rule.antecedent(), &[eggs]);
+        assert_eq!((class_rule.tables[0].ab, class_rule.tables[1].ab), (5, 0));
+        assert_eq!((class_rule.tables[1].a, class_rule.tables[1].b), (40, 40));
+        assert!(class_rule.p_value < 1e-5);
+
+        let mut output: Vec<u8> = vec![];
+        write_class_report_csv(&mut output, &report, &itemizer).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(", legit Transactions, legit Antecedent, legit Consequent, legit Both"));
+        assert!(lines[1].starts_with("eggs ==> ham, fraud, 1, 2, 0.5, "));
+        assert!(lines[1].ends_with(", 10, 5, 5, 5, 130, 40, 40, 0"));
+    }
+}
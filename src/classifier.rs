@@ -0,0 +1,344 @@
+// A rule-based classifier built as in CBA (Liu, Hsu and Ma, 1998). Class
+// association rules, rules whose consequent is a class label, are mined from
+// labelled transactions, and sorted by decreasing confidence, then support.
+// Rules are then picked in that order if they correctly classify a training
+// transaction not covered by an earlier rule. The classifier predicts the
+// class of the first rule whose antecedent a transaction contains, or a
+// default class if none do.
+
+use command_line_args::ClassColumn;
+use index::{intersect, Index};
+use itemizer::Itemizer;
+use ordered_float::OrderedFloat;
+use serde_json::{self, Value};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+
+// A transaction's items, and its class.
+pub struct LabelledTransaction {
+    pub items: Vec<String>,
+    pub class: String,
+}
+
+// Reads transactions with a class label in their first or last column.
+pub fn read_labelled_transactions<R: BufRead>(
+    reader: R,
+    class_column: ClassColumn,
+) -> io::Result<Vec<LabelledTransaction>> {
+    let mut transactions = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        let mut items: Vec<String> = line
+            .split(',')
+            .map(|s| String::from(s.trim()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        if items.is_empty() {
+            continue;
+        }
+        let class = match class_column {
+            ClassColumn::First => items.remove(0),
+            ClassColumn::Last => items.pop().unwrap(),
+        };
+        transactions.push(LabelledTransaction { items, class });
+    }
+    Ok(transactions)
+}
+
+pub struct ClassRule {
+    pub antecedent: Vec<String>,
+    pub class: String,
+    pub confidence: f64,
+    pub support: f64,
+}
+
+impl ClassRule {
+    fn matches(&self, items: &HashSet<&str>) -> bool {
+        self.antecedent.iter().all(|item| items.contains(item.as_str()))
+    }
+}
+
+pub struct Classifier {
+    pub rules: Vec<ClassRule>,
+    pub default_class: String,
+}
+
+pub struct TrainingParameters {
+    pub min_support: f64,
+    pub min_confidence: f64,
+    pub max_antecedent_len: usize,
+}
+
+struct RuleMiner<'a> {
+    index: &'a Index,
+    classes: &'a [(String, Vec<usize>)],
+    min_count: usize,
+    parameters: &'a TrainingParameters,
+    rules: Vec<(Vec<u32>, usize, usize, usize)>,
+}
+
+impl<'a> RuleMiner<'a> {
+    // Finds the rules whose antecedent is `antecedent` extended by items in
+    // `extensions`, given the transactions containing the antecedent. Rules
+    // are recorded as antecedent, class, and the counts of the rule and its
+    // antecedent.
+    fn grow(&mut self, antecedent: &[u32], tids: &[usize], extensions: &[u32]) {
+        for (i, &item) in extensions.iter().enumerate() {
+            let item_tids = if antecedent.is_empty() {
+                self.index.tids(item).to_vec()
+            } else {
+                intersect(tids, self.index.tids(item))
+            };
+            // Rules' counts are at most their antecedent's.
+            if item_tids.len() < self.min_count {
+                continue;
+            }
+            let mut grown: Vec<u32> = Vec::from(antecedent);
+            grown.push(item);
+            for (class, class_tids) in self.classes.iter().enumerate() {
+                let count = intersect(&item_tids, &class_tids.1).len();
+                if count >= self.min_count
+                    && count as f64 / item_tids.len() as f64 >= self.parameters.min_confidence
+                {
+                    self.rules.push((grown.clone(), class, count, item_tids.len()));
+                }
+            }
+            if grown.len() < self.parameters.max_antecedent_len {
+                self.grow(&grown, &item_tids, &extensions[i + 1..]);
+            }
+        }
+    }
+}
+
+pub fn train(transactions: &[LabelledTransaction], parameters: &TrainingParameters) -> Classifier {
+    let mut itemizer = Itemizer::new();
+    let mut index = Index::new();
+    let mut class_tids: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut itemized: Vec<HashSet<u32>> = vec![];
+    for (tid, transaction) in transactions.iter().enumerate() {
+        let items: HashSet<u32> = transaction.items.iter().map(|item| itemizer.id_of(item)).collect();
+        index.insert(&items.iter().cloned().collect::<Vec<u32>>(), 1);
+        itemized.push(items);
+        class_tids.entry(&transaction.class).or_default().push(tid);
+    }
+    let mut classes: Vec<(String, Vec<usize>)> = class_tids
+        .into_iter()
+        .map(|(class, tids)| (String::from(class), tids))
+        .collect();
+    classes.sort();
+
+    let min_count = ((parameters.min_support * transactions.len() as f64).ceil() as usize).max(1);
+    let items: Vec<u32> = (1..itemizer.max_item_id() + 1).collect();
+    let mut miner = RuleMiner {
+        index: &index,
+        classes: &classes,
+        min_count,
+        parameters,
+        rules: vec![],
+    };
+    miner.grow(&[], &[], &items);
+    let mut rules = miner.rules;
+
+    // CBA's precedence: decreasing confidence, then decreasing support, then
+    // shorter antecedents. The rest keeps the order deterministic.
+    let names = |antecedent: &[u32]| -> Vec<&str> {
+        let mut names: Vec<&str> = antecedent.iter().map(|&id| itemizer.str_of(id)).collect();
+        names.sort();
+        names
+    };
+    rules.sort_by_cached_key(|&(ref antecedent, class, count, antecedent_count)| {
+        (
+            Reverse(OrderedFloat::from(count as f64 / antecedent_count as f64)),
+            Reverse(count),
+            antecedent.len(),
+            names(antecedent),
+            class,
+        )
+    });
+
+    // Pick rules which correctly classify a transaction not yet covered,
+    // tracking the errors made by the rules picked plus a default class for
+    // the transactions left, and keep the prefix of rules with fewest.
+    let class_of: Vec<usize> = transactions
+        .iter()
+        .map(|transaction| {
+            classes
+                .binary_search_by(|c| c.0.as_str().cmp(&transaction.class))
+                .unwrap()
+        })
+        .collect();
+    let majority = |tids: &mut dyn Iterator<Item = usize>| -> (usize, usize) {
+        let mut counts = vec![0; classes.len()];
+        let mut total = 0;
+        for tid in tids {
+            counts[class_of[tid]] += 1;
+            total += 1;
+        }
+        // Ties go to the first class in name order.
+        let (class, &count) = counts
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(&a.0)))
+            .unwrap_or((0, &0));
+        (class, total - count)
+    };
+
+    let mut uncovered: Vec<bool> = vec![true; transactions.len()];
+    let (overall_default, overall_errors) = majority(&mut (0..transactions.len()));
+    let (mut best_default, mut best_errors) = (overall_default, overall_errors);
+    let mut best_len = 0;
+    let mut picked: Vec<usize> = vec![];
+    let mut rule_errors = 0;
+    for (r, &(ref antecedent, class, _, _)) in rules.iter().enumerate() {
+        let covered: Vec<usize> = (0..transactions.len())
+            .filter(|&tid| uncovered[tid] && antecedent.iter().all(|item| itemized[tid].contains(item)))
+            .collect();
+        if !covered.iter().any(|&tid| class_of[tid] == class) {
+            continue;
+        }
+        picked.push(r);
+        for &tid in covered.iter() {
+            uncovered[tid] = false;
+            if class_of[tid] != class {
+                rule_errors += 1;
+            }
+        }
+        // Once every transaction is covered, fall back to the majority class.
+        let (default, default_errors) = if uncovered.contains(&true) {
+            majority(&mut (0..transactions.len()).filter(|&tid| uncovered[tid]))
+        } else {
+            (overall_default, 0)
+        };
+        if rule_errors + default_errors < best_errors {
+            best_errors = rule_errors + default_errors;
+            best_default = default;
+            best_len = picked.len();
+        }
+    }
+
+    let n = transactions.len() as f64;
+    Classifier {
+        rules: picked[..best_len]
+            .iter()
+            .map(|&r| {
+                let (ref antecedent, class, count, antecedent_count) = rules[r];
+                ClassRule {
+                    antecedent: names(antecedent).into_iter().map(String::from).collect(),
+                    class: classes[class].0.clone(),
+                    confidence: count as f64 / antecedent_count as f64,
+                    support: count as f64 / n,
+                }
+            })
+            .collect(),
+        default_class: classes
+            .get(best_default)
+            .map(|c| c.0.clone())
+            .unwrap_or_default(),
+    }
+}
+
+impl Classifier {
+    pub fn classify(&self, items: &[String]) -> &str {
+        let items: HashSet<&str> = items.iter().map(|s| s.as_str()).collect();
+        match self.rules.iter().find(|rule| rule.matches(&items)) {
+            Some(rule) => &rule.class,
+            None => &self.default_class,
+        }
+    }
+
+    pub fn write_json<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                json!({
+                    "antecedent": rule.antecedent,
+                    "class": rule.class,
+                    "confidence": rule.confidence,
+                    "support": rule.support,
+                })
+            })
+            .collect();
+        let model = json!({
+            "rules": rules,
+            "default_class": self.default_class,
+        });
+        serde_json::to_writer_pretty(&mut *output, &model)?;
+        writeln!(output)
+    }
+
+    pub fn read_json<R: Read>(reader: R) -> Result<Classifier, Box<dyn Error>> {
+        let json: Value = serde_json::from_reader(reader)?;
+        let invalid = || -> Box<dyn Error> { "Invalid classifier file".into() };
+        let mut rules = vec![];
+        for rule in json.get("rules").and_then(Value::as_array).ok_or_else(invalid)? {
+            let antecedent = rule
+                .get("antecedent")
+                .and_then(Value::as_array)
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|item| item.as_str().map(String::from).ok_or_else(invalid))
+                .collect::<Result<Vec<String>, Box<dyn Error>>>()?;
+            let number = |key: &str| rule.get(key).and_then(Value::as_f64).ok_or_else(invalid);
+            rules.push(ClassRule {
+                antecedent,
+                class: String::from(rule.get("class").and_then(Value::as_str).ok_or_else(invalid)?),
+                confidence: number("confidence")?,
+                support: number("support")?,
+            });
+        }
+        let default_class = json
+            .get("default_class")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?;
+        Ok(Classifier {
+            rules,
+            default_class: String::from(default_class),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_classifier() {
+        use super::{read_labelled_transactions, train, Classifier, TrainingParameters};
+        use command_line_args::ClassColumn;
+
+        let transactions = read_labelled_transactions(
+            "fur,legs,mammal\nfur,legs,tail,mammal\nfeathers,legs,bird\nfeathers,wings,bird\n\
+             scales,fins,fish\nscales,fins,tail,fish\nfur,fins,mammal\n"
+                .as_bytes(),
+            ClassColumn::Last,
+        ).unwrap();
+        assert_eq!(transactions.len(), 7);
+        assert_eq!(transactions[0].class, "mammal");
+
+        let parameters = TrainingParameters {
+            min_support: 0.1,
+            min_confidence: 0.8,
+            max_antecedent_len: 2,
+        };
+        let classifier = train(&transactions, &parameters);
+        let classify = |items: &[&str]| -> String {
+            let items: Vec<String> = items.iter().map(|s| String::from(*s)).collect();
+            String::from(classifier.classify(&items))
+        };
+        assert_eq!(classify(&["fur", "tail"]), "mammal");
+        assert_eq!(classify(&["feathers"]), "bird");
+        assert_eq!(classify(&["scales", "legs"]), "fish");
+        for transaction in transactions.iter() {
+            assert_eq!(classifier.classify(&transaction.items), transaction.class);
+        }
+
+        let mut buffer: Vec<u8> = vec![];
+        classifier.write_json(&mut buffer).unwrap();
+        let read = Classifier::read_json(&buffer[..]).unwrap();
+        assert_eq!(read.rules.len(), classifier.rules.len());
+        assert_eq!(read.default_class, classifier.default_class);
+        assert_eq!(read.classify(&[String::from("scales")]), "fish");
+        assert!(Classifier::read_json(&b"[]"[..]).is_err());
+    }
+}
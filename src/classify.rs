@@ -0,0 +1,74 @@
+// `riptree classify-train` and `riptree classify-predict`: train a rule based
+// classifier, and use it to classify transactions.
+
+use riptree::classifier::{read_labelled_transactions, train, Classifier, TrainingParameters};
+use riptree::command_line_args::{ClassifyPredictArguments, ClassifyTrainArguments};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+pub fn classify_train(args: &ClassifyTrainArguments) -> Result<(), Box<dyn Error>> {
+    let transactions = read_labelled_transactions(
+        BufReader::new(File::open(&args.input_file_path)?),
+        args.class_column,
+    )?;
+    info!("Read {} labelled transactions.", transactions.len());
+
+    let classifier = train(
+        &transactions,
+        &TrainingParameters {
+            min_support: args.min_support,
+            min_confidence: args.min_confidence,
+            max_antecedent_len: args.max_antecedent_len,
+        },
+    );
+    let correct = transactions
+        .iter()
+        .filter(|transaction| classifier.classify(&transaction.items) == transaction.class)
+        .count();
+    info!(
+        "Classifier has {} rules, and a default class of '{}'. Training accuracy: {}",
+        classifier.rules.len(),
+        classifier.default_class,
+        correct as f64 / transactions.len() as f64
+    );
+
+    let mut output = BufWriter::new(File::create(&args.model_path)?);
+    classifier.write_json(&mut output)?;
+    Ok(())
+}
+
+pub fn classify_predict(args: &ClassifyPredictArguments) -> Result<(), Box<dyn Error>> {
+    let classifier = Classifier::read_json(BufReader::new(File::open(&args.model_path)?))?;
+    let input = BufReader::new(File::open(&args.input_file_path)?);
+    let mut output = BufWriter::new(File::create(&args.output_path)?);
+    match args.class_column {
+        Some(class_column) => {
+            let transactions = read_labelled_transactions(input, class_column)?;
+            let mut correct = 0;
+            for transaction in transactions.iter() {
+                let class = classifier.classify(&transaction.items);
+                if class == transaction.class {
+                    correct += 1;
+                }
+                writeln!(output, "{}", class)?;
+            }
+            info!(
+                "Classified {} transactions. Accuracy: {}",
+                transactions.len(),
+                correct as f64 / transactions.len() as f64
+            );
+        }
+        None => {
+            for line in input.lines() {
+                let items: Vec<String> = line?
+                    .split(',')
+                    .map(|s| String::from(s.trim()))
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                writeln!(output, "{}", classifier.classify(&items))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+// std::time::Instant::now() panics on wasm32-unknown-unknown, as there's no
+// clock to read without calling out to JavaScript. Timings there report zero.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy, Debug)]
+pub struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    pub fn now() -> Instant {
+        Instant
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+}
+
+#[cfg(feature = "mem-stats")]
+use mem_stats;
+
+// Times a phase of a run, such as reading the dataset or generating rules,
+// logging its duration when dropped at the end of the phase. With the
+// mem-stats feature, it also tracks the most memory allocated during it.
+pub struct Phase {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Phase {
+    pub fn start(name: &'static str) -> Phase {
+        debug!("{}...", name);
+        #[cfg(feature = "mem-stats")]
+        mem_stats::reset_peak();
+        Phase {
+            name,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    // The most bytes allocated at once since the phase started, if built
+    // with the mem-stats feature.
+    #[cfg(feature = "mem-stats")]
+    pub fn peak_allocated_bytes(&self) -> Option<usize> {
+        Some(mem_stats::peak_bytes())
+    }
+
+    #[cfg(not(feature = "mem-stats"))]
+    pub fn peak_allocated_bytes(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        info!(
+            "{} took {:.3} seconds.",
+            self.name,
+            self.seconds()
+        );
+    }
+}
@@ -0,0 +1,534 @@
+// Config files hold mining options, so runs can be repeated exactly. Each key
+// is the name of a command line option without its leading dashes, e.g.
+// `min-confidence = 0.9`, and underscores may be used in place of dashes.
+// Flags such as `yates-correction` take true or false, and options which may
+// be repeated, such as `must-contain`, take a list. Files ending in .yaml or
+// .yml are read as YAML, and others as TOML.
+//
+// The options in a config file are inserted into the command line ahead of
+// those given there, skipping any also given on the command line, so command
+// line options override the config file.
+
+use command_line_args::{Arguments, UtilityArguments, DEFAULT_ITEMS_COLUMN};
+use serde_yaml;
+use std::collections::HashSet;
+use std::fs;
+use toml;
+
+// A config file's value for an option.
+enum Setting {
+    Flag(bool),
+    Value(String),
+    List(Vec<String>),
+}
+
+fn invalid_value(key: &str) -> String {
+    format!("Invalid value for '{}' in config file", key)
+}
+
+fn toml_scalar(key: &str, value: &toml::Value) -> Result<String, String> {
+    match *value {
+        toml::Value::String(ref s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => Err(invalid_value(key)),
+    }
+}
+
+fn read_toml(text: &str) -> Result<Vec<(String, Setting)>, String> {
+    let table = match text.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => table,
+        Ok(_) => return Err(String::from("Config file must be a table of options")),
+        Err(err) => return Err(format!("Invalid config file: {}", err)),
+    };
+    let mut settings = vec![];
+    for (key, value) in table.iter() {
+        let setting = match *value {
+            toml::Value::Boolean(b) => Setting::Flag(b),
+            toml::Value::Array(ref values) => Setting::List(
+                values
+                    .iter()
+                    .map(|value| toml_scalar(key, value))
+                    .collect::<Result<Vec<String>, String>>()?,
+            ),
+            _ => Setting::Value(toml_scalar(key, value)?),
+        };
+        settings.push((key.clone(), setting));
+    }
+    Ok(settings)
+}
+
+fn yaml_scalar(key: &str, value: &serde_yaml::Value) -> Result<String, String> {
+    match *value {
+        serde_yaml::Value::String(ref s) => Ok(s.clone()),
+        serde_yaml::Value::Number(ref n) => Ok(n.to_string()),
+        _ => Err(invalid_value(key)),
+    }
+}
+
+fn read_yaml(text: &str) -> Result<Vec<(String, Setting)>, String> {
+    let mapping = match serde_yaml::from_str::<serde_yaml::Value>(text) {
+        Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+        Ok(_) => return Err(String::from("Config file must be a mapping of options")),
+        Err(err) => return Err(format!("Invalid config file: {}", err)),
+    };
+    let mut settings = vec![];
+    for (key, value) in mapping.iter() {
+        let key = match key.as_str() {
+            Some(key) => String::from(key),
+            None => return Err(String::from("Config file keys must be option names")),
+        };
+        let setting = match *value {
+            // An empty value leaves the option unset.
+            serde_yaml::Value::Null => continue,
+            serde_yaml::Value::Bool(b) => Setting::Flag(b),
+            serde_yaml::Value::Sequence(ref values) => Setting::List(
+                values
+                    .iter()
+                    .map(|value| yaml_scalar(&key, value))
+                    .collect::<Result<Vec<String>, String>>()?,
+            ),
+            _ => Setting::Value(yaml_scalar(&key, value)?),
+        };
+        settings.push((key, setting));
+    }
+    Ok(settings)
+}
+
+// Returns the command line options given by the config file at `path`.
+pub fn read_config(path: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Can't read config file {}: {}", path, err))?;
+    let settings = if path.ends_with(".yaml") || path.ends_with(".yml") {
+        read_yaml(&text)?
+    } else {
+        read_toml(&text)?
+    };
+
+    let mut options = vec![];
+    for (key, setting) in settings {
+        let option = format!("--{}", key.replace('_', "-"));
+        match setting {
+            Setting::Flag(true) => options.push(option),
+            Setting::Flag(false) => {}
+            Setting::Value(value) => {
+                options.push(option);
+                options.push(value);
+            }
+            Setting::List(values) => {
+                for value in values {
+                    options.push(option.clone());
+                    options.push(value);
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+// Returns the option an argument names, if it's an option.
+fn option_name(arg: &str) -> Option<&str> {
+    if arg.starts_with("--") {
+        arg.split('=').next()
+    } else {
+        None
+    }
+}
+
+// Replaces `--config <path>` in `command_line`, which starts with the
+// program name, with the options in the config file which aren't also on the
+// command line.
+pub fn apply_config(mut command_line: Vec<String>) -> Result<Vec<String>, String> {
+    let path = match command_line.iter().position(|arg| option_name(arg) == Some("--config")) {
+        Some(i) => {
+            let arg = command_line.remove(i);
+            match arg.find('=') {
+                Some(equals) => String::from(&arg[equals + 1..]),
+                None if i < command_line.len() => command_line.remove(i),
+                None => return Err(String::from("--config requires a file path")),
+            }
+        }
+        None => return Ok(command_line),
+    };
+
+    let given: HashSet<String> = command_line
+        .iter()
+        .filter_map(|arg| option_name(arg).map(String::from))
+        .collect();
+    let config = read_config(&path)?;
+    let mut merged: Vec<String> = command_line.drain(..1).collect();
+    let mut i = 0;
+    while i < config.len() {
+        // An option's value, if any, follows it.
+        let mut end = i + 1;
+        while end < config.len() && option_name(&config[end]).is_none() {
+            end += 1;
+        }
+        if !given.contains(&config[i]) {
+            merged.extend_from_slice(&config[i..end]);
+        }
+        i = end;
+    }
+    merged.append(&mut command_line);
+    Ok(merged)
+}
+
+// Returns the mining options in `args`, including defaults, keyed by option
+// name.
+pub fn config_table(args: &Arguments) -> toml::value::Table {
+    let mut table = toml::value::Table::new();
+    {
+        let mut set = |key: &str, value: toml::Value| {
+            table.insert(String::from(key), value);
+        };
+        let string = |s: &str| toml::Value::String(String::from(s));
+        let list = |items: &[String]| {
+            toml::Value::Array(items.iter().map(|item| toml::Value::String(item.clone())).collect())
+        };
+
+        match args.input_file_paths.len() {
+            0 => {}
+            1 => set("input", string(&args.input_file_paths[0])),
+            _ => set("input", list(&args.input_file_paths)),
+        }
+        if !args.input_db_url.is_empty() {
+            set("input-db", string(&args.input_db_url));
+            set("query", string(&args.input_query));
+        }
+        if let Some(ref column) = args.weight_column {
+            set("weight-column", string(&column.to_string()));
+        }
+        if let Some(ref column) = args.timestamp_column {
+            set("timestamp-column", string(&column.to_string()));
+        }
+        if let Some(ref column) = args.class_column {
+            set("class-column", string(&column.to_string()));
+        }
+        if let Some(ref format) = args.input_format {
+            set("input-format", string(&format.to_string()));
+        }
+        set("encoding", string(&args.encoding.to_string()));
+        set("on-bad-line", string(&args.on_bad_line.to_string()));
+        if args.items_column != DEFAULT_ITEMS_COLUMN {
+            set("items-column", string(&args.items_column));
+        }
+        if args.tabular {
+            set("tabular", toml::Value::Boolean(true));
+            if !args.null_values.is_empty() {
+                set("null-values", list(&args.null_values));
+            }
+            if !args.missing_value.is_empty() {
+                set("missing-value", string(&args.missing_value));
+            }
+        }
+        if let Some(fraction) = args.sample_fraction {
+            set("sample-fraction", toml::Value::Float(fraction));
+        }
+        if let Some(fraction) = args.holdout_fraction {
+            set("holdout-fraction", toml::Value::Float(fraction));
+        }
+        if !args.test_file_path.is_empty() {
+            set("test-file", string(&args.test_file_path));
+        }
+        set("output", string(&args.output_rules_path));
+        set("output-format", string(&args.output_format.to_string()));
+        if !args.output_columns.is_empty() {
+            let columns: Vec<String> = args.output_columns.iter().map(|c| c.to_string()).collect();
+            set("output-columns", list(&columns));
+        }
+        if let Some(ref delimiter) = args.output_delimiter {
+            set("output-delimiter", string(delimiter));
+        }
+        if args.item_separator != " " {
+            set("item-separator", string(&args.item_separator));
+        }
+        if let Some(ref compression) = args.compress_output {
+            set("compress-output", string(&compression.to_string()));
+        }
+        if args.disable_output_metadata {
+            set("disable-output-metadata", toml::Value::Boolean(true));
+        }
+        if !args.output_dot_path.is_empty() {
+            set("output-dot", string(&args.output_dot_path));
+        }
+        if !args.output_sqlite_path.is_empty() {
+            set("output-sqlite", string(&args.output_sqlite_path));
+        }
+        if !args.output_parquet_path.is_empty() {
+            set("output-parquet", string(&args.output_parquet_path));
+        }
+        if !args.output_html_path.is_empty() {
+            set("output-html", string(&args.output_html_path));
+        }
+        set("max-support", string(&args.max_support_mode.to_string()));
+        if let Some(ref fallback) = args.fallback_max_support {
+            set("fallback-max-support", string(&fallback.to_string()));
+        }
+        let rarity = &args.rarity;
+        set("gaussian-delta", toml::Value::Float(rarity.gaussian.delta));
+        set("gaussian-simulations", toml::Value::Integer(rarity.gaussian.simulations as i64));
+        set("gaussian-null-model", string(&rarity.gaussian.null_model.to_string()));
+        set("pareto-fraction", toml::Value::Float(rarity.pareto_fraction));
+        set("robust-k", toml::Value::Float(rarity.robust_k));
+        set("algorithm", string(&args.algorithm.to_string()));
+        set("min-confidence", toml::Value::Float(args.min_confidence));
+        set("min-lift", toml::Value::Float(args.min_lift));
+        set("min-odds-ratio", toml::Value::Float(args.min_odds_ratio));
+        if let Some(ratio) = args.max_cross_support_ratio {
+            set("max-cross-support-ratio", toml::Value::Float(ratio));
+        }
+        if let Some(count) = args.min_consequent_count {
+            set("min-consequent-count", toml::Value::Integer(count as i64));
+        }
+        if let Some(support) = args.max_consequent_support {
+            set("max-consequent-support", toml::Value::Float(support));
+        }
+        for &(metric, threshold) in &args.metric_thresholds {
+            set(metric.threshold_option().trim_start_matches('-'), toml::Value::Float(threshold));
+        }
+        if !args.metrics.is_empty() {
+            let metrics: Vec<String> = args.metrics.iter().map(|m| m.to_string()).collect();
+            set("metrics", list(&metrics));
+        }
+        if !args.numeric_columns.is_empty() || !args.cut_points.is_empty() {
+            set("numeric-columns", list(&args.numeric_columns));
+            set("binning", string(&args.binning.to_string()));
+            set("bins", toml::Value::Integer(args.bins as i64));
+            let cut_points: Vec<String> = args
+                .cut_points
+                .iter()
+                .map(|(column, cut_points)| {
+                    let cut_points: Vec<String> = cut_points.iter().map(|x| x.to_string()).collect();
+                    format!("{}:{}", column, cut_points.join(","))
+                })
+                .collect();
+            set("cut-points", list(&cut_points));
+        }
+        set("sort-rules-by", string(&args.sort_rules_by.to_string()));
+        set("group-by-consequent", toml::Value::Boolean(args.group_by_consequent));
+        if let Some(max_rules) = args.max_rules_per_consequent {
+            set("max-rules-per-consequent", toml::Value::Integer(max_rules as i64));
+            set("select-by", string(&args.select_by.to_string()));
+        }
+        if let Some(max_examples) = args.emit_examples {
+            set("emit-examples", toml::Value::Integer(max_examples as i64));
+        }
+        if !args.drift_report_path.is_empty() {
+            set("drift-report", string(&args.drift_report_path));
+            set("drift-buckets", toml::Value::Integer(args.drift_buckets as i64));
+        }
+        if !args.class_report_path.is_empty() {
+            set("class-report", string(&args.class_report_path));
+        }
+        set(
+            "disable-family-wise-rule-filtering",
+            toml::Value::Boolean(args.disable_family_wise_rule_filtering),
+        );
+        set(
+            "disable-permutation-rule-filtering",
+            toml::Value::Boolean(args.disable_permutation_rule_filtering),
+        );
+        if let Some(ref key) = args.filter_redundant_rules {
+            set("filter-redundant-rules", string(&key.to_string()));
+        }
+        set("significance-test", string(&args.significance_test.to_string()));
+        set("yates-correction", toml::Value::Boolean(args.yates_correction));
+        set("permutations", toml::Value::Integer(args.permutations as i64));
+        if let Some(resamples) = args.bootstrap_resamples {
+            set("bootstrap-resamples", toml::Value::Integer(resamples as i64));
+        }
+        set("min-antecedent-len", toml::Value::Integer(args.min_antecedent_len as i64));
+        if let Some(len) = args.max_antecedent_len {
+            set("max-antecedent-len", toml::Value::Integer(len as i64));
+        }
+        if let Some(k) = args.top_k {
+            set("top-k", toml::Value::Integer(k as i64));
+        }
+        set("must-contain", list(&args.must_contain_items));
+        set("exclude", list(&args.exclude_items));
+        set("normalize-case", toml::Value::Boolean(args.normalize_case));
+        set(
+            "trim-internal-whitespace",
+            toml::Value::Boolean(args.trim_internal_whitespace),
+        );
+        if !args.stop_items_path.is_empty() {
+            set("stop-items-file", string(&args.stop_items_path));
+        }
+        if !args.alias_path.is_empty() {
+            set("alias-file", string(&args.alias_path));
+        }
+        if let Some(count) = args.min_item_count {
+            set("min-item-count", toml::Value::Integer(count as i64));
+        }
+        if let Some(count) = args.max_item_count {
+            set("max-item-count", toml::Value::Integer(count as i64));
+        }
+        if let Some(len) = args.min_transaction_len {
+            set("min-transaction-len", toml::Value::Integer(len as i64));
+        }
+        if let Some(len) = args.max_transaction_len {
+            set("max-transaction-len", toml::Value::Integer(len as i64));
+            set("long-transactions", string(&args.long_transactions.to_string()));
+        }
+        if !args.taxonomy_path.is_empty() {
+            set("taxonomy", string(&args.taxonomy_path));
+        }
+        if let Some(level) = args.taxonomy_level {
+            set("taxonomy-level", toml::Value::Integer(level as i64));
+        }
+        set("rare-items-by-category", toml::Value::Boolean(args.rare_items_by_category));
+        set("log-rare-items", toml::Value::Boolean(args.log_rare_items));
+        set("dry-run", toml::Value::Boolean(args.dry_run));
+        if !args.report_path.is_empty() {
+            set("report", string(&args.report_path));
+        }
+        if !args.checkpoint_path.is_empty() {
+            set("checkpoint", string(&args.checkpoint_path));
+        }
+        set("checkpoint-interval", toml::Value::Integer(args.checkpoint_interval as i64));
+        if !args.resume_path.is_empty() {
+            set("resume", string(&args.resume_path));
+        }
+        if let Some(gigabytes) = args.max_memory {
+            set("max-memory", toml::Value::Float(gigabytes));
+        }
+        if !args.save_item_counts_path.is_empty() {
+            set("save-item-counts", string(&args.save_item_counts_path));
+        }
+        if !args.load_item_counts_path.is_empty() {
+            set("load-item-counts", string(&args.load_item_counts_path));
+        }
+        if !args.save_index_path.is_empty() {
+            set("save-index", string(&args.save_index_path));
+        }
+        if !args.load_index_path.is_empty() {
+            set("load-index", string(&args.load_index_path));
+        }
+        if let Some(epsilon) = args.approx_index {
+            set("approx-index", toml::Value::Float(epsilon));
+        }
+        set("approx-itemset-len", toml::Value::Integer(args.approx_itemset_len as i64));
+        if let Some(items) = args.count_pairs {
+            set("count-pairs", toml::Value::Integer(items as i64));
+        }
+        if let Some(partition_size) = args.partition_size {
+            set("partition-size", toml::Value::Integer(partition_size as i64));
+        }
+        set("counting", string(&args.counting.to_string()));
+        if !args.distributed_workers.is_empty() {
+            set("distributed", list(&args.distributed_workers));
+        }
+    }
+    table
+}
+
+// Returns the options of `riptree utility` in `args`, keyed by option name.
+pub fn utility_config_table(args: &UtilityArguments) -> toml::value::Table {
+    let mut table = toml::value::Table::new();
+    let string = |s: &str| toml::Value::String(String::from(s));
+    table.insert(String::from("input"), string(&args.input_file_path));
+    table.insert(String::from("utilities"), string(&args.utilities_path));
+    table.insert(String::from("min-utility"), toml::Value::Float(args.min_utility));
+    table.insert(String::from("max-support"), string(&args.max_support_mode.to_string()));
+    table.insert(String::from("output"), string(&args.output_path));
+    if let Some(ref compression) = args.compress_output {
+        table.insert(String::from("compress-output"), string(&compression.to_string()));
+    }
+    table
+}
+
+// Returns the mining options in `args` as a TOML config file, which
+// reproduces the run when passed to --config.
+pub fn dump_config(args: &Arguments) -> String {
+    toml::to_string(&toml::Value::Table(config_table(args))).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_config() {
+        use super::{apply_config, dump_config, read_config};
+        use command_line_args::Arguments;
+        use std::env;
+        use std::fs;
+
+        let dir = env::temp_dir();
+        let toml_path = dir.join("riptree-test-config.toml");
+        let toml_path = toml_path.to_str().unwrap();
+        fs::write(
+            toml_path,
+            "input = \"data.csv\"\nmin_confidence = 0.9\nmin-lift = 5\n\
+             yates-correction = true\nlog-rare-items = false\nmust-contain = [\"a\", \"b\"]\n",
+        ).unwrap();
+        let options = read_config(toml_path).unwrap();
+        assert_eq!(
+            options,
+            vec![
+                "--input", "data.csv", "--min-lift", "5", "--min-confidence", "0.9",
+                "--must-contain", "a", "--must-contain", "b", "--yates-correction",
+            ]
+        );
+
+        let yaml_path = dir.join("riptree-test-config.yaml");
+        let yaml_path = yaml_path.to_str().unwrap();
+        fs::write(yaml_path, "input: data.csv\nmin-lift: 5\ntaxonomy:\nyates-correction: true\n").unwrap();
+        assert_eq!(
+            read_config(yaml_path).unwrap(),
+            vec!["--input", "data.csv", "--min-lift", "5", "--yates-correction"]
+        );
+
+        // Command line options override the config file's.
+        let command_line: Vec<String> = ["riptree", "--min-lift=2", "--config", toml_path, "--output", "out.csv"]
+            .iter()
+            .map(|s| String::from(*s))
+            .collect();
+        assert_eq!(
+            apply_config(command_line).unwrap(),
+            vec![
+                "riptree", "--input", "data.csv", "--min-confidence", "0.9", "--must-contain", "a",
+                "--must-contain", "b", "--yates-correction", "--min-lift=2", "--output", "out.csv",
+            ]
+        );
+
+        fs::write(toml_path, "min-lift = { value = 5 }\n").unwrap();
+        assert!(read_config(toml_path).is_err());
+        fs::remove_file(toml_path).unwrap();
+        fs::remove_file(yaml_path).unwrap();
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("data.csv")],
+            max_antecedent_len: Some(2),
+            ..Arguments::default()
+        };
+        let dump = dump_config(&args);
+        assert!(dump.contains("input = \"data.csv\"\n"));
+        assert!(dump.contains("max-antecedent-len = 2\n"));
+        assert!(dump.contains("max-support = \"gaussian\"\n"));
+        assert!(!dump.contains("top-k"));
+    }
+
+    #[test]
+    fn test_config_log_options() {
+        use command_line_args::parse_args_or_exit;
+        use std::env;
+        use std::fs;
+
+        // The log options can be set in a config file, and are parsed like
+        // any other, so aren't mistaken for the value of another option.
+        let path = env::temp_dir().join("riptree-test-log-config.toml");
+        let path = path.to_str().unwrap();
+        fs::write(path, "machine = true\nverbose = true\nmax-support = \"pareto\"\nmin-lift = 1\n").unwrap();
+        let command_line: Vec<String> = [
+            "riptree", "--config", path, "--input", "data.csv", "--output", "out.csv",
+            "--min-confidence", "0.9", "--tabular", "--null-values", "-q",
+        ]
+        .iter()
+        .map(|s| String::from(*s))
+        .collect();
+        let args = parse_args_or_exit(command_line);
+        fs::remove_file(path).unwrap();
+        assert!(args.log.machine);
+        assert_eq!(args.log.verbose, 1);
+        assert!(!args.log.quiet);
+        assert_eq!(args.null_values, vec!["-q"]);
+    }
+}
@@ -0,0 +1,271 @@
+// Reads transactions from a database, for --input-db and --query. The query
+// returns a row for each item in each transaction, of the transaction's id
+// and the item, e.g.
+//   SELECT order_id, product FROM order_lines
+// and the rows of each transaction are grouped into one. The rows are
+// streamed from the database, ordered by transaction id, so the result of
+// the query needn't fit in memory. Mining makes two passes over the dataset,
+// so the query is run twice.
+//
+// The database is given by a URL, either sqlite:<path>, with riptree built
+// with the 'sqlite' feature, or postgres://... (or postgresql://...) with the
+// 'postgres' feature.
+
+#[cfg(feature = "postgres")]
+use postgres::{self, SimpleQueryMessage};
+use itemizer::Itemizer;
+#[cfg(feature = "sqlite")]
+use rusqlite::types::ValueRef;
+#[cfg(feature = "sqlite")]
+use rusqlite::{Connection, OpenFlags};
+use std::io;
+use std::iter::Peekable;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+use transaction_reader::{itemize, Transaction, TransactionSource, Transactions};
+
+// The number of rows fetched from a PostgreSQL cursor at a time.
+#[cfg(feature = "postgres")]
+const FETCH_SIZE: usize = 10000;
+
+// The number of rows read ahead of mining.
+const READ_AHEAD: usize = 10000;
+
+// A transaction id and an item.
+type Row = io::Result<(String, String)>;
+
+pub struct Database {
+    pub url: String,
+    pub query: String,
+}
+
+impl Database {
+    // The query, with its rows ordered by transaction id, so each
+    // transaction's rows are together.
+    fn ordered_query(&self) -> String {
+        format!(
+            "SELECT * FROM ({}) AS transactions ORDER BY 1",
+            self.query.trim().trim_end_matches(';')
+        )
+    }
+
+    // Runs the query on another thread, which sends the rows back as they're
+    // read.
+    fn rows(&self) -> mpsc::IntoIter<Row> {
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD);
+        let url = self.url.clone();
+        let query = self.ordered_query();
+        thread::spawn(move || {
+            let result = if let Some(path) = url.strip_prefix("sqlite:") {
+                read_sqlite(path, &query, &sender)
+            } else if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+                read_postgres(&url, &query, &sender)
+            } else {
+                Err(format!(
+                    "Unsupported database URL '{}'; expected sqlite:<path> or postgres://...",
+                    url
+                ))
+            };
+            if let Err(err) = result {
+                let _ = sender.send(Err(io::Error::other(err)));
+            }
+        });
+        receiver.into_iter()
+    }
+}
+
+// Sends a row, returning false if mining has stopped reading them.
+fn send(sender: &SyncSender<Row>, id: String, item: String) -> bool {
+    sender.send(Ok((id, item))).is_ok()
+}
+
+#[cfg(feature = "sqlite")]
+fn sqlite_text(value: ValueRef) -> Option<String> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(i) => Some(i.to_string()),
+        ValueRef::Real(f) => Some(f.to_string()),
+        ValueRef::Text(s) | ValueRef::Blob(s) => Some(String::from_utf8_lossy(s).into_owned()),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn read_sqlite(path: &str, query: &str, sender: &SyncSender<Row>) -> Result<(), String> {
+    let error = |err: rusqlite::Error| format!("Can't read transactions from {}: {}", path, err);
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(error)?;
+    let mut statement = connection.prepare(query).map_err(error)?;
+    let mut rows = statement.query([]).map_err(error)?;
+    while let Some(row) = rows.next().map_err(error)? {
+        let id = sqlite_text(row.get_ref(0).map_err(error)?).unwrap_or_default();
+        // Rows without an item, e.g. from outer joins, are skipped.
+        if let Some(item) = sqlite_text(row.get_ref(1).map_err(error)?) {
+            if !send(sender, id, item) {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn read_sqlite(_path: &str, _query: &str, _sender: &SyncSender<Row>) -> Result<(), String> {
+    Err(String::from("Reading from SQLite needs riptree built with the 'sqlite' feature"))
+}
+
+// Reads the rows through a cursor, a batch at a time. The simple query
+// protocol returns values as text, whatever the columns' types.
+#[cfg(feature = "postgres")]
+fn read_postgres(url: &str, query: &str, sender: &SyncSender<Row>) -> Result<(), String> {
+    let error = |err: postgres::Error| format!("Can't read transactions from the database: {}", err);
+    let mut client = postgres::Client::connect(url, postgres::NoTls).map_err(error)?;
+    let mut transaction = client.transaction().map_err(error)?;
+    transaction
+        .batch_execute(&format!("DECLARE transactions NO SCROLL CURSOR FOR {}", query))
+        .map_err(error)?;
+    loop {
+        let messages = transaction
+            .simple_query(&format!("FETCH {} FROM transactions", FETCH_SIZE))
+            .map_err(error)?;
+        let mut num_rows = 0;
+        for message in messages {
+            if let SimpleQueryMessage::Row(row) = message {
+                num_rows += 1;
+                let id = row.get(0).unwrap_or_default().to_owned();
+                if let Some(item) = row.get(1) {
+                    if !send(sender, id, item.to_owned()) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        if num_rows == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+fn read_postgres(_url: &str, _query: &str, _sender: &SyncSender<Row>) -> Result<(), String> {
+    Err(String::from(
+        "Reading from PostgreSQL needs riptree built with the 'postgres' feature",
+    ))
+}
+
+// Groups consecutive rows with the same transaction id into transactions.
+struct Grouper<'a, I: Iterator<Item = Row>> {
+    rows: Peekable<I>,
+    itemizer: &'a mut Itemizer,
+}
+
+impl<'a, I: Iterator<Item = Row>> Iterator for Grouper<'a, I> {
+    type Item = io::Result<Transaction>;
+    fn next(&mut self) -> Option<io::Result<Transaction>> {
+        loop {
+            let (id, item) = match self.rows.next()? {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            let mut items = vec![item];
+            while let Some(&Ok((ref next_id, _))) = self.rows.peek() {
+                if *next_id != id {
+                    break;
+                }
+                if let Some(Ok((_, item))) = self.rows.next() {
+                    items.push(item);
+                }
+            }
+            let items = match itemize(items.iter().map(|item| item.as_str()), self.itemizer) {
+                Ok(items) => items,
+                Err(err) => return Some(Err(err)),
+            };
+            if !items.is_empty() {
+                return Some(Ok(Transaction { items, weight: 1 }));
+            }
+        }
+    }
+}
+
+impl TransactionSource for Database {
+    fn read<'a>(&'a self, itemizer: &'a mut Itemizer) -> Transactions<'a> {
+        Box::new(Grouper {
+            rows: self.rows().peekable(),
+            itemizer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_database_input() {
+        use command_line_args::{Arguments, MaxSupportMode};
+        use miner::mine;
+        use rusqlite::Connection;
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::{BufRead, BufReader};
+
+        let dir = env::temp_dir().join(format!("riptree-db-input-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("zoo.db").to_string_lossy().into_owned();
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute_batch("CREATE TABLE animals (id INTEGER, attribute TEXT)")
+                .unwrap();
+            let lines: Vec<Vec<String>> = BufReader::new(File::open("datasets/UCI-zoo.csv").unwrap())
+                .lines()
+                .map(|line| line.unwrap().split(',').map(String::from).collect())
+                .collect();
+            // Insert the items attribute by attribute, rather than animal by
+            // animal, so each transaction's rows are scattered.
+            let mut insert = connection.prepare("INSERT INTO animals VALUES (?1, ?2)").unwrap();
+            for column in 0..lines[0].len() {
+                for (id, line) in lines.iter().enumerate() {
+                    insert.execute(rusqlite::params![id as i64, line[column]]).unwrap();
+                }
+            }
+        }
+
+        let rule_strings = |args: Arguments| -> Result<Vec<String>, String> {
+            let args = Arguments {
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..args
+            };
+            let result = mine(&args).map_err(|err| err.to_string())?;
+            let mut rules: Vec<String> = result
+                .rules
+                .iter()
+                .map(|rule| format!("{} {}", rule.to_string(&result.itemizer), rule.lift()))
+                .collect();
+            rules.sort();
+            Ok(rules)
+        };
+        let from_db = rule_strings(Arguments {
+            input_db_url: format!("sqlite:{}", path),
+            input_query: String::from("SELECT id, attribute FROM animals;"),
+            ..Arguments::default()
+        })
+        .unwrap();
+        assert!(!from_db.is_empty());
+        let from_csv = rule_strings(Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            ..Arguments::default()
+        })
+        .unwrap();
+        assert_eq!(from_db, from_csv);
+
+        let err = rule_strings(Arguments {
+            input_db_url: format!("sqlite:{}", path),
+            input_query: String::from("SELECT id, attribute FROM plants"),
+            ..Arguments::default()
+        })
+        .unwrap_err();
+        assert!(err.contains("no such table: plants"), "{}", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
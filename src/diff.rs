@@ -0,0 +1,195 @@
+// Compares the rules of two datasets, for `riptree diff`, in the style of
+// emerging patterns (Dong and Li, 1999): each rule found in either dataset
+// has its contingency table counted in both, and a chi-square test of
+// homogeneity of its support, and of its confidence, between the two finds
+// the rules whose strength differs by more than chance would explain.
+
+use generate_rules::Rule;
+use index::Index;
+use itemizer::Itemizer;
+use significance::{homogeneity_pval, Contingency};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+// The significance level of the tests, before the Bonferroni correction for
+// testing the support and confidence of every rule.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+pub struct RuleDiff {
+    pub rule: Rule,
+    // Whether the rule was mined from each dataset.
+    pub in_a: bool,
+    pub in_b: bool,
+    pub table_a: Contingency,
+    pub table_b: Contingency,
+    pub support_p_value: f64,
+    pub confidence_p_value: f64,
+}
+
+impl RuleDiff {
+    pub fn p_value(&self) -> f64 {
+        self.support_p_value.min(self.confidence_p_value)
+    }
+
+    // The rule's support in B over its support in A: above 1 for rules
+    // which emerge in B, and infinite for those only in B.
+    pub fn growth_rate(&self) -> f64 {
+        let (a, b) = (self.table_a.support(), self.table_b.support());
+        if a > 0.0 {
+            b / a
+        } else if b > 0.0 {
+            f64::INFINITY
+        } else {
+            1.0
+        }
+    }
+
+    fn found_in(&self) -> &'static str {
+        match (self.in_a, self.in_b) {
+            (true, true) => "Both",
+            (true, false) => "A",
+            _ => "B",
+        }
+    }
+}
+
+pub struct DiffReport {
+    // The rules which differ significantly, by increasing p-value.
+    pub rules: Vec<RuleDiff>,
+    // The number of distinct rules found in either dataset, all tested.
+    pub num_rules_tested: usize,
+    // The p-value below which a rule differs, Bonferroni corrected.
+    pub threshold: f64,
+}
+
+// Tests each of the rules mined from A and from B, once if found in both, on
+// the indexes of A and B, keeping those which differ significantly.
+pub fn compare_rules(rules_a: Vec<Rule>, rules_b: Vec<Rule>, index_a: &Index, index_b: &Index) -> DiffReport {
+    let in_b: HashSet<(Vec<u32>, Vec<u32>)> = rules_b
+        .iter()
+        .map(|rule| (rule.antecedent().to_vec(), rule.consequent().to_vec()))
+        .collect();
+    let mut seen: HashSet<(Vec<u32>, Vec<u32>)> = HashSet::new();
+    let mut candidates: Vec<(Rule, bool, bool)> = vec![];
+    for rule in rules_a {
+        let key = (rule.antecedent().to_vec(), rule.consequent().to_vec());
+        let found_in_b = in_b.contains(&key);
+        if seen.insert(key) {
+            candidates.push((rule, true, found_in_b));
+        }
+    }
+    for rule in rules_b {
+        if seen.insert((rule.antecedent().to_vec(), rule.consequent().to_vec())) {
+            candidates.push((rule, false, true));
+        }
+    }
+
+    let num_rules_tested = candidates.len();
+    let threshold = SIGNIFICANCE_LEVEL / (2 * num_rules_tested).max(1) as f64;
+    let mut rules: Vec<RuleDiff> = candidates
+        .into_iter()
+        .map(|(rule, in_a, in_b)| {
+            let table_a = rule.contingency(index_a);
+            let table_b = rule.contingency(index_b);
+            let support = [(table_a.ab, table_a.n - table_a.ab), (table_b.ab, table_b.n - table_b.ab)];
+            let confidence = [(table_a.ab, table_a.a_not_b()), (table_b.ab, table_b.a_not_b())];
+            RuleDiff {
+                rule,
+                in_a,
+                in_b,
+                table_a,
+                table_b,
+                support_p_value: homogeneity_pval(&support),
+                confidence_p_value: homogeneity_pval(&confidence),
+            }
+        })
+        .filter(|diff| diff.p_value() < threshold)
+        .collect();
+    rules.sort_by(|a, b| a.p_value().partial_cmp(&b.p_value()).unwrap_or(Ordering::Equal));
+    DiffReport {
+        rules,
+        num_rules_tested,
+        threshold,
+    }
+}
+
+// Writes the rules which differ as CSV: each rule's support, confidence and
+// lift in A and in B, its growth rate, the p-values of the tests, and which
+// dataset it was mined from.
+pub fn write_diff_csv<W: Write>(output: &mut W, report: &DiffReport, itemizer: &Itemizer) -> io::Result<()> {
+    writeln!(
+        output,
+        "Antecedent => Consequent, A Support, A Confidence, A Lift, B Support, B Confidence, B Lift, \
+         Growth Rate, Support P-Value, Confidence P-Value, Found In"
+    )?;
+    for diff in report.rules.iter() {
+        let (a, b) = (&diff.table_a, &diff.table_b);
+        writeln!(
+            output,
+            "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+            diff.rule.to_string(itemizer),
+            a.support(),
+            a.confidence(),
+            a.lift(),
+            b.support(),
+            b.confidence(),
+            b.lift(),
+            diff.growth_rate(),
+            diff.support_p_value,
+            diff.confidence_p_value,
+            diff.found_in()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_compare_rules() {
+        use super::{compare_rules, write_diff_csv};
+        use generate_rules::Rule;
+        use index::Index;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let (milk, bread, eggs, ham) =
+            (itemizer.id_of("milk"), itemizer.id_of("bread"), itemizer.id_of("eggs"), itemizer.id_of("ham"));
+        // Milk and bread go together in both, but eggs and ham only in B.
+        let mut index_a = Index::new();
+        let mut index_b = Index::new();
+        for index in [&mut index_a, &mut index_b].iter_mut() {
+            index.insert(&[milk, bread], 30);
+            index.insert(&[milk], 10);
+            index.insert(&[eggs], 30);
+        }
+        index_a.insert(&[ham], 30);
+        index_b.insert(&[eggs, ham], 30);
+
+        let rules_a = vec![Rule::new(vec![milk], vec![bread], 0.0, 0.0, 0.0)];
+        let rules_b = vec![
+            Rule::new(vec![milk], vec![bread], 0.0, 0.0, 0.0),
+            Rule::new(vec![eggs], vec![ham], 0.0, 0.0, 0.0),
+        ];
+        let report = compare_rules(rules_a, rules_b, &index_a, &index_b);
+        // Milk ==> bread is tested once, and doesn't differ.
+        assert_eq!(report.num_rules_tested, 2);
+        assert_eq!(report.threshold, 0.0125);
+        assert_eq!(report.rules.len(), 1);
+        let diff = &report.rules[0];
+        assert_eq!(diff.rule.consequent(), &[ham]);
+        assert!(!diff.in_a && diff.in_b);
+        assert_eq!((diff.table_a.ab, diff.table_b.ab), (0, 30));
+        assert_eq!(diff.growth_rate(), f64::INFINITY);
+
+        let mut output: Vec<u8> = vec![];
+        write_diff_csv(&mut output, &report, &itemizer).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("Antecedent => Consequent, A Support,"));
+        assert!(lines[1].starts_with("eggs ==> ham, 0, 0, 0, 0.3, 0.5, "));
+        assert!(lines[1].ends_with(", B"));
+    }
+}
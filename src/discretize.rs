@@ -0,0 +1,165 @@
+// Converts numeric attributes to ranges as transactions are read, so mixed
+// tabular data can be mined. Items of the form "column=value" in the columns
+// given by --numeric-columns become e.g. "age=30-40", by cutting the column's
+// values into bins. The cut points are either given with --cut-points, or
+// found with --binning from the values in the dataset: 'equal-width' bins
+// span equal ranges of values, and 'equal-frequency' bins hold roughly equal
+// numbers of transactions.
+//
+// Given cut points c1 < c2 < ... < ck, values below c1 become "column<c1",
+// values in [ci, ci+1) become "column=ci-ci+1", and values from ck up become
+// "column>=ck". Values which aren't numbers are left as they are.
+
+use command_line_args::Binning;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub struct Discretizer {
+    cut_points: HashMap<String, Vec<f64>>,
+}
+
+// Splits an item into its column and value, if it has the form
+// "column=value".
+fn split_item(item: &str) -> Option<(&str, &str)> {
+    item.find('=').map(|i| (&item[..i], &item[i + 1..]))
+}
+
+// Rounds a computed cut point to 4 significant figures, so it reads well in
+// item names.
+fn round_cut_point(x: f64) -> f64 {
+    if x == 0.0 || !x.is_finite() {
+        return x;
+    }
+    let scale = 10f64.powi(3 - x.abs().log10().floor() as i32);
+    (x * scale).round() / scale
+}
+
+fn equal_width(values: &[(f64, u32)], bins: usize) -> Vec<f64> {
+    let min = values.first().map(|&(value, _)| value).unwrap_or(0.0);
+    let max = values.last().map(|&(value, _)| value).unwrap_or(0.0);
+    let width = (max - min) / bins as f64;
+    (1..bins).map(|i| round_cut_point(min + width * i as f64)).collect()
+}
+
+// Each cut point is the smallest value with at least i/bins of all the
+// values below it.
+fn equal_frequency(values: &[(f64, u32)], bins: usize) -> Vec<f64> {
+    let total: u64 = values.iter().map(|&(_, count)| count as u64).sum();
+    let mut cut_points = vec![];
+    let mut below = 0;
+    let mut next_bin = 1;
+    for &(value, count) in values {
+        while below > 0 && next_bin < bins && below * bins as u64 >= total * next_bin as u64 {
+            cut_points.push(value);
+            next_bin += 1;
+        }
+        below += count as u64;
+    }
+    cut_points
+}
+
+impl Discretizer {
+    // Finds the cut points of each numeric column, given the number of
+    // transactions each item is in. Columns with cut points given in
+    // `cut_points` use those instead.
+    pub fn new<'a, I>(
+        columns: &[String],
+        binning: &Binning,
+        bins: usize,
+        cut_points: &[(String, Vec<f64>)],
+        item_counts: I,
+    ) -> Discretizer
+    where
+        I: Iterator<Item = (&'a str, u32)>,
+    {
+        let mut values: HashMap<&str, Vec<(f64, u32)>> = columns.iter().map(|column| (column.as_str(), vec![])).collect();
+        for (item, count) in item_counts {
+            if let Some((column, value)) = split_item(item) {
+                if let (Some(values), Ok(value)) = (values.get_mut(column), value.trim().parse::<f64>()) {
+                    if value.is_finite() {
+                        values.push((value, count));
+                    }
+                }
+            }
+        }
+        let mut discretizer = Discretizer {
+            cut_points: HashMap::new(),
+        };
+        for (column, mut values) in values {
+            values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let mut column_cut_points = match *binning {
+                Binning::EqualWidth => equal_width(&values, bins),
+                Binning::EqualFrequency => equal_frequency(&values, bins),
+            };
+            column_cut_points.dedup();
+            discretizer.cut_points.insert(column.to_owned(), column_cut_points);
+        }
+        for (column, column_cut_points) in cut_points {
+            discretizer.cut_points.insert(column.clone(), column_cut_points.clone());
+        }
+        discretizer
+    }
+
+    pub fn cut_points(&self, column: &str) -> Option<&[f64]> {
+        self.cut_points.get(column).map(|cut_points| cut_points.as_slice())
+    }
+
+    // Returns the item for the range the value of `item` falls in, if it's
+    // in a numeric column, or otherwise `item` itself.
+    pub fn discretize<'s>(&self, item: &'s str) -> Cow<'s, str> {
+        let (column, value) = match split_item(item) {
+            Some(split) => split,
+            None => return Cow::Borrowed(item),
+        };
+        let (cut_points, value) = match (self.cut_points.get(column), value.trim().parse::<f64>()) {
+            (Some(cut_points), Ok(value)) if !cut_points.is_empty() => (cut_points, value),
+            _ => return Cow::Borrowed(item),
+        };
+        let bin = cut_points.iter().take_while(|&&cut_point| value >= cut_point).count();
+        Cow::Owned(if bin == 0 {
+            format!("{}<{}", column, cut_points[0])
+        } else if bin == cut_points.len() {
+            format!("{}>={}", column, cut_points[bin - 1])
+        } else {
+            format!("{}={}-{}", column, cut_points[bin - 1], cut_points[bin])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_discretizer() {
+        use super::Discretizer;
+        use command_line_args::Binning;
+
+        // Ages 20 to 59, twice as many in their 20s as in other decades.
+        let mut counts: Vec<(String, u32)> = (20..60)
+            .map(|age| (format!("age={}", age), if age < 30 { 2 } else { 1 }))
+            .collect();
+        counts.push((String::from("age=unknown"), 5));
+        counts.push((String::from("height=1.5"), 5));
+        let items = || counts.iter().map(|&(ref item, count)| (item.as_str(), count));
+        let columns = vec![String::from("age")];
+
+        let equal_width = Discretizer::new(&columns, &Binning::EqualWidth, 4, &[], items());
+        assert_eq!(equal_width.cut_points("age").unwrap(), &[29.75, 39.5, 49.25]);
+        assert_eq!(equal_width.discretize("age=20"), "age<29.75");
+        assert_eq!(equal_width.discretize("age=30"), "age=29.75-39.5");
+        assert_eq!(equal_width.discretize("age=59"), "age>=49.25");
+        assert_eq!(equal_width.discretize("age=unknown"), "age=unknown");
+        assert_eq!(equal_width.discretize("height=1.5"), "height=1.5");
+        assert_eq!(equal_width.discretize("milk"), "milk");
+
+        // 50 values, so 25 in each of 2 bins.
+        let equal_frequency = Discretizer::new(&columns, &Binning::EqualFrequency, 2, &[], items());
+        assert_eq!(equal_frequency.cut_points("age").unwrap(), &[35.0]);
+        assert_eq!(equal_frequency.discretize("age=34"), "age<35");
+        assert_eq!(equal_frequency.discretize("age=35"), "age>=35");
+
+        let custom = vec![(String::from("age"), vec![18.0, 30.0, 40.0])];
+        let custom = Discretizer::new(&columns, &Binning::EqualFrequency, 4, &custom, items());
+        assert_eq!(custom.discretize("age=35"), "age=30-40");
+        assert_eq!(custom.discretize("age= 12"), "age<18");
+    }
+}
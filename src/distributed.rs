@@ -0,0 +1,265 @@
+// Distributes growth over worker processes, for --distributed, in the style
+// of Parallel FP-Growth (Li et al., 2008). The growth from each rare item at
+// the top level of the tree is independent of the others, so the rare items
+// are split into a shard per worker, and each worker grows its shard from
+// its own tree. The coordinator merges the itemsets the workers return, and
+// generates the rules from them.
+//
+// Workers are started with `riptree worker --listen <address>` and the same
+// dataset and options as the coordinator, and build their tree and index
+// once, before serving shards. Each request is a line of JSON sent over TCP,
+// with the items to grow by name, since ids depend on the order items are
+// read in, and the signature of the options and data which the itemsets
+// depend on, as checkpoints use, so a worker with other data or options
+// refuses the shard rather than returning the wrong itemsets:
+//
+//   {"signature": {...}, "items": ["a", "b"]}
+//
+// The worker replies with a line of JSON, with the itemsets found, or an
+// error:
+//
+//   {"itemsets": [{"items": ["a", "c"], "count": 3}, ...]}
+//   {"error": "..."}
+
+use cancel::take_cancelled;
+use fptree::ItemSet;
+use itemizer::Itemizer;
+use serde_json::{self, Value};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// Splits the rare items into a shard for each worker, balanced by the items'
+// counts, which the sizes of their conditional trees grow with. The most
+// frequent items go first, each to the shard with the least total count.
+pub fn shard_items(
+    rare_items: &HashSet<u32>,
+    item_count: &HashMap<u32, u32>,
+    num_workers: usize,
+) -> Vec<Vec<u32>> {
+    let mut items: Vec<u32> = rare_items.iter().cloned().collect();
+    let count = |item: &u32| item_count.get(item).cloned().unwrap_or(0) as u64;
+    items.sort_by(|a, b| count(b).cmp(&count(a)).then(a.cmp(b)));
+    let mut shards: Vec<(u64, Vec<u32>)> = vec![(0, vec![]); num_workers];
+    for item in items {
+        let shard = shards
+            .iter_mut()
+            .min_by_key(|&&mut (total, _)| total)
+            .expect("No workers to shard items to");
+        shard.0 += count(&item);
+        shard.1.push(item);
+    }
+    shards.into_iter().map(|(_, items)| items).collect()
+}
+
+fn itemsets_to_json(itemsets: &[ItemSet], itemizer: &Itemizer) -> Value {
+    Value::Array(
+        itemsets
+            .iter()
+            .map(|itemset| {
+                let items: Vec<&str> = itemset.items.iter().map(|&id| itemizer.str_of(id)).collect();
+                json!({"items": items, "count": itemset.count})
+            })
+            .collect(),
+    )
+}
+
+fn invalid_reply(worker: &str) -> Box<dyn Error> {
+    format!("Invalid reply from worker {}", worker).into()
+}
+
+// Sends a shard to the worker at `address`, returning the itemsets it grew.
+fn grow_on_worker(
+    address: &str,
+    items: &[String],
+    signature: &Value,
+    itemizer: &Itemizer,
+) -> Result<Vec<ItemSet>, Box<dyn Error>> {
+    let mut stream = TcpStream::connect(address)
+        .map_err(|err| format!("Can't connect to worker {}: {}", address, err))?;
+    writeln!(stream, "{}", json!({"signature": signature, "items": items}))?;
+    stream.flush()?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let reply: Value = serde_json::from_str(&line).map_err(|_| invalid_reply(address))?;
+    if let Some(error) = reply.get("error").and_then(Value::as_str) {
+        return Err(format!("Worker {} failed: {}", address, error).into());
+    }
+    let mut itemsets = vec![];
+    for itemset in reply.get("itemsets").and_then(Value::as_array).ok_or_else(|| invalid_reply(address))? {
+        let items = itemset
+            .get("items")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid_reply(address))?
+            .iter()
+            .map(|name| name.as_str().and_then(|name| itemizer.get_id(name)))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(|| format!("Worker {} returned an item which isn't in the dataset", address))?;
+        let count = itemset.get("count").and_then(Value::as_u64).ok_or_else(|| invalid_reply(address))?;
+        itemsets.push(ItemSet::new(items, count as u32));
+    }
+    Ok(itemsets)
+}
+
+// Grows the rare items on the workers, a shard each, all at once, and merges
+// the itemsets they find. Fails if any worker does, as its shard's itemsets
+// would be missing.
+pub fn grow_on_workers(
+    workers: &[String],
+    rare_items: &HashSet<u32>,
+    item_count: &HashMap<u32, u32>,
+    signature: &Value,
+    itemizer: &Itemizer,
+) -> Result<Vec<ItemSet>, Box<dyn Error>> {
+    let shards = shard_items(rare_items, item_count, workers.len());
+    let results: Vec<Result<Vec<ItemSet>, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = workers
+            .iter()
+            .zip(shards.iter())
+            .map(|(address, shard)| {
+                let items: Vec<String> = shard.iter().map(|&id| String::from(itemizer.str_of(id))).collect();
+                info!("Sending {} rare items to worker {}.", items.len(), address);
+                // Errors are sent back as strings, as boxed errors can't be.
+                scope.spawn(move || {
+                    grow_on_worker(address, &items, signature, itemizer).map_err(|err| err.to_string())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Worker connection panicked"))
+            .collect()
+    });
+    let mut itemsets = vec![];
+    for result in results {
+        itemsets.append(&mut result?);
+    }
+    Ok(itemsets)
+}
+
+// Answers a request for a shard, growing its items with `grow`.
+fn handle_shard<F: Fn(&HashSet<u32>) -> Vec<ItemSet>>(
+    line: &str,
+    signature: &Value,
+    itemizer: &Itemizer,
+    grow: &F,
+) -> Result<Value, String> {
+    let request: Value = serde_json::from_str(line).map_err(|_| String::from("Invalid request"))?;
+    if request.get("signature") != Some(signature) {
+        return Err(String::from(
+            "The worker was started with different options or data from the coordinator",
+        ));
+    }
+    let mut items: HashSet<u32> = HashSet::new();
+    for name in request.get("items").and_then(Value::as_array).ok_or("Invalid request")? {
+        let name = name.as_str().ok_or("Invalid request")?;
+        let id = itemizer
+            .get_id(name)
+            .ok_or_else(|| format!("Item '{}' isn't in the worker's dataset", name))?;
+        items.insert(id);
+    }
+    info!("Growing a shard of {} rare items.", items.len());
+    let itemsets = grow(&items);
+    if take_cancelled() {
+        return Err(String::from("The worker was interrupted"));
+    }
+    info!("Grew {} itemsets.", itemsets.len());
+    Ok(json!({"itemsets": itemsets_to_json(&itemsets, itemizer)}))
+}
+
+// Serves shards to grow from `listener`, one at a time, until the process is
+// killed. A shard is grown by calling `grow` with its items, and only if the
+// coordinator's signature matches `signature`.
+pub fn serve_shards<F: Fn(&HashSet<u32>) -> Vec<ItemSet>>(
+    listener: TcpListener,
+    signature: &Value,
+    itemizer: &Itemizer,
+    grow: F,
+) {
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line)?;
+            let reply = match handle_shard(&line, signature, itemizer, &grow) {
+                Ok(reply) => reply,
+                Err(error) => {
+                    warn!("Refused a shard: {}", error);
+                    json!({ "error": error })
+                }
+            };
+            let mut stream = stream;
+            writeln!(stream, "{}", reply)?;
+            stream.flush()
+        });
+        if let Err(err) = result {
+            warn!("Failed to serve a shard: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_shard_items() {
+        use super::shard_items;
+        use std::collections::{HashMap, HashSet};
+
+        let rare_items: HashSet<u32> = [1, 2, 3, 4, 5].iter().cloned().collect();
+        let item_count: HashMap<u32, u32> = [(1, 10), (2, 8), (3, 5), (4, 4), (5, 1)].iter().cloned().collect();
+        let shards = shard_items(&rare_items, &item_count, 2);
+        assert_eq!(shards, vec![vec![1, 4], vec![2, 3, 5]]);
+        // More workers than items leaves some with empty shards.
+        let shards = shard_items(&rare_items, &item_count, 7);
+        assert_eq!(shards.iter().filter(|shard| shard.is_empty()).count(), 2);
+    }
+
+    #[test]
+    fn test_grow_on_workers() {
+        use super::{grow_on_workers, serve_shards};
+        use fptree::ItemSet;
+        use itemizer::Itemizer;
+        use std::collections::{HashMap, HashSet};
+        use std::net::TcpListener;
+        use std::thread;
+
+        // Workers read the same dataset, so number its items the same.
+        let new_itemizer = || {
+            let mut itemizer = Itemizer::new();
+            for item in ["a", "b", "c"].iter() {
+                itemizer.id_of(item);
+            }
+            itemizer
+        };
+        let itemizer = new_itemizer();
+        let (a, b, c) = (itemizer.get_id("a").unwrap(), itemizer.get_id("b").unwrap(), itemizer.get_id("c").unwrap());
+        let signature = json!({"transactions": 10});
+        let mut workers: Vec<String> = vec![];
+        for _ in 0..2 {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            workers.push(listener.local_addr().unwrap().to_string());
+            let (itemizer, signature) = (new_itemizer(), signature.clone());
+            // Each item grows into itself, and itself with c.
+            thread::spawn(move || {
+                serve_shards(listener, &signature, &itemizer, |items: &HashSet<u32>| {
+                    items
+                        .iter()
+                        .flat_map(|&item| vec![ItemSet::new(vec![item], 2), ItemSet::new(vec![item, c], 1)])
+                        .collect()
+                })
+            });
+        }
+
+        let rare_items: HashSet<u32> = [a, b].iter().cloned().collect();
+        let item_count: HashMap<u32, u32> = [(a, 2), (b, 2), (c, 5)].iter().cloned().collect();
+        let mut itemsets = grow_on_workers(&workers, &rare_items, &item_count, &signature, &itemizer).unwrap();
+        itemsets.sort_by(|x, y| x.items.cmp(&y.items));
+        let itemsets: Vec<(Vec<u32>, u32)> = itemsets.into_iter().map(|i| (i.items, i.count)).collect();
+        assert_eq!(itemsets, vec![(vec![a], 2), (vec![a, c], 1), (vec![b], 2), (vec![b, c], 1)]);
+
+        // A worker with another signature refuses its shard.
+        let result = grow_on_workers(&workers, &rare_items, &item_count, &json!({}), &itemizer);
+        assert!(result.unwrap_err().to_string().contains("different options or data"));
+    }
+}
@@ -0,0 +1,335 @@
+// Measures how the rules found change over time, for --drift-report. The
+// transactions are split by the --timestamp-column into buckets of equal
+// length of time, and each rule's contingency table is counted in each
+// bucket. A chi-square test of homogeneity across the buckets, of the
+// fraction of transactions containing the rule (its support) and of the
+// fraction of those containing its antecedent which also contain its
+// consequent (its confidence), finds the rules whose strength changes by more
+// than chance would explain.
+
+use generate_rules::Rule;
+use index::Index;
+use itemizer::Itemizer;
+use significance::{homogeneity_pval, Contingency};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+
+// The significance level of the drift tests, before the Bonferroni
+// correction for testing the support and confidence of every rule.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+// The number of days from 1970-01-01 to the given date, in the proleptic
+// Gregorian calendar. From Howard Hinnant's "chrono-Compatible Low-Level Date
+// Algorithms".
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of days_from_civil: the (year, month, day) of a day.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+// Parses a date, optionally with a time, as in 2024-03-01 or
+// 2024-03-01T09:30:00, to seconds since 1970-01-01 in UTC.
+fn parse_date(s: &str) -> Option<f64> {
+    let s = s.trim_end_matches('Z');
+    let (date, time) = match s.find(['T', ' ']) {
+        Some(position) => (&s[..position], &s[position + 1..]),
+        None => (s, ""),
+    };
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let year: i64 = parts[0].parse().ok()?;
+    let month: u32 = parts[1].parse().ok()?;
+    let day: u32 = parts[2].parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    if !time.is_empty() {
+        let parts: Vec<f64> = time.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+        if parts.len() > 3 {
+            return None;
+        }
+        seconds = parts.iter().zip([3600.0, 60.0, 1.0].iter()).map(|(part, unit)| part * unit).sum();
+    }
+    Some(days_from_civil(year, month, day) as f64 * SECONDS_PER_DAY + seconds)
+}
+
+// Parses the value of a --timestamp-column: either a number, such as a Unix
+// time, or a date as parse_date accepts. Returns the time, and whether it was
+// a date.
+pub fn parse_timestamp(s: &str) -> io::Result<(f64, bool)> {
+    let s = s.trim();
+    match s.parse::<f64>() {
+        Ok(number) if number.is_finite() => return Ok((number, false)),
+        _ => {}
+    }
+    parse_date(s).map(|seconds| (seconds, true)).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid timestamp '{}'", s))
+    })
+}
+
+// Splits the range of timestamps from start into buckets of equal width.
+#[derive(Debug)]
+pub struct TimeBuckets {
+    start: f64,
+    width: f64,
+    count: usize,
+    // Whether the timestamps were dates, so buckets are labelled by date.
+    dated: bool,
+}
+
+impl TimeBuckets {
+    // Buckets covering the timestamps from first to last. If they're all the
+    // same, every transaction is in the first bucket.
+    pub fn new(first: f64, last: f64, count: usize, dated: bool) -> TimeBuckets {
+        let width = if last > first { (last - first) / count as f64 } else { 1.0 };
+        TimeBuckets {
+            start: first,
+            width,
+            count,
+            dated,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // The bucket of a timestamp; the last timestamp is in the last bucket.
+    pub fn bucket(&self, timestamp: f64) -> usize {
+        let bucket = ((timestamp - self.start) / self.width).floor();
+        (bucket.max(0.0) as usize).min(self.count - 1)
+    }
+
+    // The start of a bucket, as a date if the timestamps were dates.
+    pub fn label(&self, bucket: usize) -> String {
+        let start = self.start + bucket as f64 * self.width;
+        if !self.dated {
+            let label = format!("{:.3}", start);
+            return label.trim_end_matches('0').trim_end_matches('.').to_owned();
+        }
+        let days = (start / SECONDS_PER_DAY).floor();
+        let seconds = (start - days * SECONDS_PER_DAY).round() as u32;
+        let (year, month, day) = civil_from_days(days as i64);
+        if seconds == 0 {
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        } else {
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                year,
+                month,
+                day,
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )
+        }
+    }
+}
+
+// A rule's contingency table in each time bucket, and the p-values of the
+// tests that its support and its confidence are the same in every bucket.
+pub struct RuleDrift {
+    // The rule's position in the rules it was measured from.
+    pub rule: usize,
+    pub tables: Vec<Contingency>,
+    pub support_p_value: f64,
+    pub confidence_p_value: f64,
+}
+
+impl RuleDrift {
+    pub fn p_value(&self) -> f64 {
+        self.support_p_value.min(self.confidence_p_value)
+    }
+}
+
+pub struct DriftReport {
+    pub buckets: TimeBuckets,
+    // The rules, by increasing p-value, so those which drift most come first.
+    pub rules: Vec<RuleDrift>,
+    // The p-value below which a rule is drifting, Bonferroni corrected.
+    pub threshold: f64,
+}
+
+impl DriftReport {
+    pub fn num_drifting(&self) -> usize {
+        self.rules.iter().filter(|drift| drift.p_value() < self.threshold).count()
+    }
+}
+
+// Measures the rules in each time bucket, given an index of the transactions
+// in each.
+pub fn measure_drift(rules: &[Rule], indexes: &[Index], buckets: TimeBuckets) -> DriftReport {
+    let mut drifts: Vec<RuleDrift> = rules
+        .iter()
+        .enumerate()
+        .map(|(position, rule)| {
+            let tables: Vec<Contingency> = indexes.iter().map(|index| rule.contingency(index)).collect();
+            // Transactions with and without the rule, and those with its
+            // antecedent with and without its consequent.
+            let support: Vec<(u32, u32)> = tables.iter().map(|t| (t.ab, t.n - t.ab)).collect();
+            let confidence: Vec<(u32, u32)> = tables.iter().map(|t| (t.ab, t.a_not_b())).collect();
+            RuleDrift {
+                rule: position,
+                tables,
+                support_p_value: homogeneity_pval(&support),
+                confidence_p_value: homogeneity_pval(&confidence),
+            }
+        })
+        .collect();
+    drifts.sort_by(|a, b| {
+        a.p_value()
+            .partial_cmp(&b.p_value())
+            .unwrap_or(Ordering::Equal)
+            .then(a.rule.cmp(&b.rule))
+    });
+    DriftReport {
+        buckets,
+        threshold: SIGNIFICANCE_LEVEL / (2 * rules.len()).max(1) as f64,
+        rules: drifts,
+    }
+}
+
+// Writes the drift report as CSV: a line per rule, with the p-values of its
+// drift tests, whether it's drifting, and its support, confidence and lift in
+// each bucket, which is labelled by the time it starts.
+pub fn write_drift_csv<W: Write>(
+    output: &mut W,
+    report: &DriftReport,
+    rules: &[Rule],
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    write!(
+        output,
+        "Antecedent => Consequent, Support Drift P-Value, Confidence Drift P-Value, Drifting"
+    )?;
+    for bucket in 0..report.buckets.len() {
+        let label = report.buckets.label(bucket);
+        write!(output, ", {0} Support, {0} Confidence, {0} Lift", label)?;
+    }
+    writeln!(output)?;
+    for drift in report.rules.iter() {
+        write!(
+            output,
+            "{}, {}, {}, {}",
+            rules[drift.rule].to_string(itemizer),
+            drift.support_p_value,
+            drift.confidence_p_value,
+            drift.p_value() < report.threshold
+        )?;
+        for table in drift.tables.iter() {
+            write!(output, ", {}, {}, {}", table.support(), table.confidence(), table.lift())?;
+        }
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_timestamp() {
+        use super::{days_from_civil, civil_from_days, parse_timestamp, TimeBuckets};
+
+        assert_eq!(parse_timestamp("1700000000").unwrap(), (1_700_000_000.0, false));
+        assert_eq!(parse_timestamp(" 2.5 ").unwrap(), (2.5, false));
+        assert_eq!(parse_timestamp("1970-01-02").unwrap(), (86400.0, true));
+        assert_eq!(parse_timestamp("2024-03-01").unwrap(), (1_709_251_200.0, true));
+        assert_eq!(parse_timestamp("2024-03-01T09:30:15Z").unwrap(), (1_709_285_415.0, true));
+        assert_eq!(parse_timestamp("2024-03-01 09:30").unwrap(), (1_709_285_400.0, true));
+        for invalid in ["", "yesterday", "2024-13-01", "2024-03", "2024-03-01T09:xx", "NaN"].iter() {
+            assert!(parse_timestamp(invalid).is_err(), "{}", invalid);
+        }
+        for &days in [-800_000, -1, 0, 59, 19_782, 2_000_000].iter() {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+
+        let buckets = TimeBuckets::new(10.0, 20.0, 4, false);
+        assert_eq!(
+            [9.0, 10.0, 12.4, 12.5, 19.9, 20.0, 25.0].iter().map(|&t| buckets.bucket(t)).collect::<Vec<_>>(),
+            vec![0, 0, 0, 1, 3, 3, 3]
+        );
+        assert_eq!(buckets.label(1), "12.5");
+        let day = 86400.0;
+        let buckets = TimeBuckets::new(19_783.0 * day, 19_785.0 * day, 4, true);
+        assert_eq!(buckets.label(0), "2024-03-01");
+        assert_eq!(buckets.label(1), "2024-03-01T12:00:00");
+        // All the same timestamp.
+        assert_eq!(TimeBuckets::new(5.0, 5.0, 3, false).bucket(5.0), 0);
+    }
+
+    #[test]
+    fn test_measure_drift() {
+        use super::{measure_drift, write_drift_csv, TimeBuckets};
+        use generate_rules::Rule;
+        use index::Index;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let (milk, bread, eggs, ham) =
+            (itemizer.id_of("milk"), itemizer.id_of("bread"), itemizer.id_of("eggs"), itemizer.id_of("ham"));
+        // Milk and bread go together throughout, but eggs and ham only
+        // in the later buckets.
+        let mut indexes: Vec<Index> = vec![];
+        for bucket in 0..4 {
+            let mut index = Index::new();
+            index.insert(&[milk, bread], 30);
+            index.insert(&[milk], 10);
+            index.insert(&[eggs], 30);
+            if bucket >= 2 {
+                index.insert(&[eggs, ham], 30);
+            } else {
+                index.insert(&[ham], 30);
+            }
+            indexes.push(index);
+        }
+        let rules = vec![
+            Rule::new(vec![milk], vec![bread], 0.0, 0.0, 0.0),
+            Rule::new(vec![eggs], vec![ham], 0.0, 0.0, 0.0),
+        ];
+        let report = measure_drift(&rules, &indexes, TimeBuckets::new(0.0, 4.0, 4, false));
+        assert_eq!(report.threshold, 0.0125);
+        assert_eq!(report.num_drifting(), 1);
+        assert_eq!(report.rules[0].rule, 1);
+        assert!(report.rules[0].support_p_value < 1e-10 && report.rules[0].confidence_p_value < 1e-6);
+        assert!(report.rules[1].p_value() > 0.99);
+        assert_eq!(report.rules[1].tables[3].ab, 30);
+
+        let mut output: Vec<u8> = vec![];
+        write_drift_csv(&mut output, &report, &rules, &itemizer).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(", 3 Support, 3 Confidence, 3 Lift"));
+        assert!(lines[1].starts_with("eggs ==> ham, ") && lines[1].contains(", true, 0, 0, 0, "));
+        assert!(lines[2].starts_with("milk ==> bread, ") && lines[2].contains(", false, 0.3, 0.75, "));
+    }
+}
@@ -0,0 +1,200 @@
+// C-compatible interface for embedding the miner in other languages. See
+// include/riptree.h for the matching declarations.
+//
+// Rules are mined into an opaque RiptreeRules handle which owns the
+// stringified rules, so the pointers handed out by riptree_rule_get() stay
+// valid until the handle is passed to riptree_free().
+
+use command_line_args::{Arguments, MaxSupportMode};
+use miner::mine;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+
+pub const RIPTREE_MAX_SUPPORT_GAUSSIAN: c_int = 0;
+pub const RIPTREE_MAX_SUPPORT_PARETO: c_int = 1;
+pub const RIPTREE_MAX_SUPPORT_ROBUST: c_int = 2;
+pub const RIPTREE_MAX_SUPPORT_ELBOW: c_int = 3;
+
+struct FfiRule {
+    antecedent: CString,
+    consequent: CString,
+    confidence: f64,
+    lift: f64,
+    support: f64,
+}
+
+pub struct RiptreeRules {
+    rules: Vec<FfiRule>,
+}
+
+#[repr(C)]
+pub struct RiptreeRule {
+    pub antecedent: *const c_char,
+    pub consequent: *const c_char,
+    pub confidence: f64,
+    pub lift: f64,
+    pub support: f64,
+}
+
+fn to_c_string(s: String) -> CString {
+    // Item names come from splitting lines of text, so they can't contain
+    // NULs in practice, but don't let a malformed dataset panic here.
+    CString::new(s.replace('\0', "")).unwrap()
+}
+
+fn mine_file(
+    path: &str,
+    max_support_mode: MaxSupportMode,
+    min_confidence: f64,
+    min_lift: f64,
+) -> Option<RiptreeRules> {
+    let args = Arguments {
+        input_file_paths: vec![path.to_owned()],
+        max_support_mode,
+        min_confidence,
+        min_lift,
+        ..Arguments::default()
+    };
+    let result = mine(&args).ok()?;
+    let rules = result
+        .rules
+        .iter()
+        .map(|rule| FfiRule {
+            antecedent: to_c_string(rule.antecedent_to_string(&result.itemizer)),
+            consequent: to_c_string(rule.consequent_to_string(&result.itemizer)),
+            confidence: rule.confidence(),
+            lift: rule.lift(),
+            support: rule.support(),
+        })
+        .collect();
+    Some(RiptreeRules { rules })
+}
+
+/// Mines the CSV dataset at `path` and returns a handle to the rules found,
+/// or NULL on failure. The handle must be released with riptree_free().
+///
+/// # Safety
+///
+/// `path` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn riptree_mine_file(
+    path: *const c_char,
+    max_support_mode: c_int,
+    min_confidence: f64,
+    min_lift: f64,
+) -> *mut RiptreeRules {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path.to_owned(),
+        Err(_) => return ptr::null_mut(),
+    };
+    let max_support_mode = match max_support_mode {
+        RIPTREE_MAX_SUPPORT_GAUSSIAN => MaxSupportMode::Gaussian,
+        RIPTREE_MAX_SUPPORT_PARETO => MaxSupportMode::Pareto,
+        RIPTREE_MAX_SUPPORT_ROBUST => MaxSupportMode::Robust,
+        RIPTREE_MAX_SUPPORT_ELBOW => MaxSupportMode::Elbow,
+        _ => return ptr::null_mut(),
+    };
+    // Unwinding across the FFI boundary is undefined behaviour, so treat
+    // any panic inside the miner (e.g. an unreadable file) as failure.
+    match panic::catch_unwind(|| mine_file(&path, max_support_mode, min_confidence, min_lift)) {
+        Ok(Some(rules)) => Box::into_raw(Box::new(rules)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Returns the number of rules in `rules`, or 0 if `rules` is NULL.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a handle returned by riptree_mine_file().
+#[no_mangle]
+pub unsafe extern "C" fn riptree_rules_len(rules: *const RiptreeRules) -> usize {
+    match rules.as_ref() {
+        Some(rules) => rules.rules.len(),
+        None => 0,
+    }
+}
+
+/// Copies the rule at `index` into `out`. Returns 0 on success, or -1 if
+/// either pointer is NULL or `index` is out of range. The strings in `out`
+/// are owned by `rules`, and are valid until riptree_free() is called.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a handle returned by riptree_mine_file(), and
+/// `out` must be NULL or point to writable memory for a RiptreeRule.
+#[no_mangle]
+pub unsafe extern "C" fn riptree_rule_get(
+    rules: *const RiptreeRules,
+    index: usize,
+    out: *mut RiptreeRule,
+) -> c_int {
+    let rule = match rules.as_ref().and_then(|rules| rules.rules.get(index)) {
+        Some(rule) => rule,
+        None => return -1,
+    };
+    if out.is_null() {
+        return -1;
+    }
+    *out = RiptreeRule {
+        antecedent: rule.antecedent.as_ptr(),
+        consequent: rule.consequent.as_ptr(),
+        confidence: rule.confidence,
+        lift: rule.lift,
+        support: rule.support,
+    };
+    0
+}
+
+/// Releases a handle returned by riptree_mine_file(). Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `rules` must be NULL or a handle returned by riptree_mine_file() which
+/// hasn't already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn riptree_free(rules: *mut RiptreeRules) {
+    if !rules.is_null() {
+        drop(Box::from_raw(rules));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_mine_file() {
+        use super::*;
+        use std::ffi::CString;
+        use std::mem;
+
+        let path = CString::new("datasets/UCI-zoo.csv").unwrap();
+        unsafe {
+            let rules =
+                riptree_mine_file(path.as_ptr(), RIPTREE_MAX_SUPPORT_PARETO, 0.9, 5.0);
+            assert!(!rules.is_null());
+            let len = riptree_rules_len(rules);
+            assert!(len > 0);
+
+            let mut rule: RiptreeRule = mem::zeroed();
+            assert_eq!(riptree_rule_get(rules, 0, &mut rule), 0);
+            assert!(rule.confidence >= 0.9);
+            assert!(rule.lift >= 5.0);
+            assert!(!CStr::from_ptr(rule.consequent).to_str().unwrap().is_empty());
+            assert_eq!(riptree_rule_get(rules, len, &mut rule), -1);
+
+            riptree_free(rules);
+        }
+
+        let missing = CString::new("datasets/does-not-exist.csv").unwrap();
+        unsafe {
+            assert!(riptree_mine_file(missing.as_ptr(), RIPTREE_MAX_SUPPORT_PARETO, 0.9, 5.0)
+                .is_null());
+            assert!(riptree_mine_file(path.as_ptr(), 7, 0.9, 5.0).is_null());
+        }
+    }
+}
@@ -1,106 +1,92 @@
 use itemizer::Itemizer;
 use index::Index;
+use significance::{Contingency, SignificanceTest};
 use rayon::prelude::*;
 use itertools::Itertools;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
 use std::cmp;
-
-#[derive(Eq, Debug)]
+use top_k::TopK;
+use cancel::is_cancelled;
+use checkpoint::Checkpointer;
+use spill::{MemoryBudget, SpillFile};
+use std::mem;
+use std::sync::mpsc::SyncSender;
+
+// Nodes refer to their parent and children by index into the tree's nodes.
+#[derive(Debug)]
 struct FPNode {
-    id: u32,
     item: u32,
     count: u32,
-    children: Vec<FPNode>,
+    parent: usize,
+    children: Vec<usize>,
 }
 
-impl PartialEq for FPNode {
-    fn eq(&self, other: &FPNode) -> bool {
-        self.id == other.id
-    }
-}
+// The index of the root node, which has no item.
+const ROOT: usize = 0;
 
-impl Hash for FPNode {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.id.hash(state);
-    }
+// The paths from the root to each of an item's nodes, with the node's count,
+// from which the item's conditional tree is built.
+pub type PatternBase = Vec<(Vec<u32>, u32)>;
+
+// Below the top level, the items of conditional trees with fewer nodes than
+// this are grown on the current thread, as handing such small trees to
+// other threads costs more than it saves. Larger trees, such as the one
+// conditional tree which dominates when item counts are very skewed, have
+// their items grown as rayon tasks, which idle threads steal.
+const MIN_PARALLEL_NODES: usize = 1024;
+
+// Conditional trees this many items deep or deeper are always grown on the
+// current thread, as by then the work has long been spread over the threads.
+const MAX_PARALLEL_DEPTH: usize = 4;
+
+// Estimated bytes used by each node of a tree: the node itself, plus its link
+// in the header table.
+const BYTES_PER_NODE: usize = mem::size_of::<FPNode>() + 2 * mem::size_of::<usize>();
+
+// Estimated bytes used by each distinct item in a tree, in its item counts
+// and header table, allowing for hash table overhead.
+const BYTES_PER_ITEM: usize = 8 * mem::size_of::<usize>();
+
+fn estimated_bytes(num_nodes: usize, num_items: usize) -> usize {
+    num_nodes * BYTES_PER_NODE + num_items * BYTES_PER_ITEM
 }
 
 pub struct FPTree {
-    root: FPNode,
+    // The nodes, with the root first.
+    nodes: Vec<FPNode>,
+    // The header table, which links each item to its nodes, so growing from
+    // an item visits only those nodes.
+    header: HashMap<u32, Vec<usize>>,
     num_transactions: u32,
     item_count: HashMap<u32, u32>,
-    node_count: u32,
 }
 
 impl FPNode {
-    fn new(id: u32, item: u32) -> FPNode {
+    fn new(item: u32, parent: usize) -> FPNode {
         FPNode {
-            id: id,
-            item: item,
+            item,
             count: 0,
+            parent,
             children: Vec::with_capacity(1),
         }
     }
+}
 
-    fn insert(&mut self, transaction: &[u32], count: u32, next_node_id: u32) -> u32 {
-        if transaction.len() == 0 {
-            return 0;
-        }
-
-        let item = transaction[0];
-        let mut new_nodes: u32 = 0;
-
-        let index = match self.children
-            .iter()
-            .position(|ref child| child.item == item)
-        {
-            Some(index) => index,
-            None => {
-                self.children.push(FPNode::new(next_node_id, item));
-                new_nodes += 1;
-                self.children.len() - 1
-            }
-        };
-
-        self.children[index].count += count;
-        if transaction.len() > 1 {
-            new_nodes +=
-                self.children[index].insert(&transaction[1..], count, next_node_id + new_nodes);
-        }
-        new_nodes
-    }
-
-    fn is_root(&self) -> bool {
-        self.item == 0
-    }
-
-    #[allow(dead_code)]
-    fn print(&self, itemizer: &Itemizer, item_count: &HashMap<u32, u32>, level: u32) {
-        let mut indicies: Vec<usize> = (0..self.children.len()).collect();
-        indicies.sort_by(|&a, &b| {
-            item_cmp(&self.children[b].item, &self.children[a].item, item_count)
-        });
-        for _ in 0..level {
-            print!("  ");
-        }
-        println!("{}:{}", itemizer.str_of(self.item), self.count);
-        for index in indicies {
-            self.children[index].print(itemizer, item_count, level + 1);
-        }
+impl Default for FPTree {
+    fn default() -> FPTree {
+        FPTree::new()
     }
 }
 
 impl FPTree {
     pub fn new() -> FPTree {
-        let root_node = FPNode::new(0, 0);
-        return FPTree {
-            root: root_node,
+        FPTree {
+            nodes: vec![FPNode::new(0, ROOT)],
+            header: HashMap::new(),
             num_transactions: 0,
             item_count: HashMap::new(),
-            node_count: 1,
-        };
+        }
     }
 
     pub fn insert(&mut self, transaction: &[u32], count: u32) {
@@ -109,21 +95,104 @@ impl FPTree {
         for item in transaction {
             *self.item_count.entry(*item).or_insert(0) += count;
         }
-        self.node_count += self.root.insert(&transaction, count, self.node_count);
+        let mut node = ROOT;
+        for &item in transaction {
+            let existing = self.nodes[node]
+                .children
+                .iter()
+                .cloned()
+                .find(|&child| self.nodes[child].item == item);
+            let child = match existing {
+                Some(child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(FPNode::new(item, node));
+                    self.nodes[node].children.push(child);
+                    self.header.entry(item).or_default().push(child);
+                    child
+                }
+            };
+            self.nodes[child].count += count;
+            node = child;
+        }
         self.num_transactions += count;
     }
 
-    fn root(&self) -> &FPNode {
-        &self.root
-    }
-
     fn item_count(&self) -> &HashMap<u32, u32> {
         &self.item_count
     }
 
+    // The number of nodes in the tree, including the root.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Estimated bytes used by the tree.
+    pub fn estimated_bytes(&self) -> usize {
+        estimated_bytes(self.nodes.len(), self.item_count.len())
+    }
+
+    // The number of nodes at each depth, where the root is at depth 0. The
+    // deepest level is the length of the longest transaction in the tree.
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![];
+        let mut stack = vec![(ROOT, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            if histogram.len() <= depth {
+                histogram.push(0);
+            }
+            histogram[depth] += 1;
+            for &child in self.nodes[node].children.iter() {
+                stack.push((child, depth + 1));
+            }
+        }
+        histogram
+    }
+
+    // The items of the path from the root to `node`, not including the
+    // node's own item.
+    fn path_from_root_to(&self, node: usize) -> Vec<u32> {
+        let mut path = vec![];
+        let mut n = self.nodes[node].parent;
+        while n != ROOT {
+            path.push(self.nodes[n].item);
+            n = self.nodes[n].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    // If the tree is a single path, its items and their counts from the root
+    // down.
+    fn single_path(&self) -> Option<Vec<(u32, u32)>> {
+        let mut path = vec![];
+        let mut node = ROOT;
+        loop {
+            match self.nodes[node].children.len() {
+                0 => return Some(path),
+                1 => node = self.nodes[node].children[0],
+                _ => return None,
+            }
+            path.push((self.nodes[node].item, self.nodes[node].count));
+        }
+    }
+
     #[allow(dead_code)]
     pub fn print(&self, itemizer: &Itemizer) {
-        self.root.print(itemizer, &self.item_count, 0);
+        self.print_node(ROOT, itemizer, 0);
+    }
+
+    #[allow(dead_code)]
+    fn print_node(&self, node: usize, itemizer: &Itemizer, level: u32) {
+        let mut children = self.nodes[node].children.clone();
+        children.sort_by(|&a, &b| item_cmp(&self.nodes[b].item, &self.nodes[a].item, &self.item_count));
+        for _ in 0..level {
+            print!("  ");
+        }
+        println!("{}:{}", itemizer.str_of(self.nodes[node].item), self.nodes[node].count);
+        for child in children {
+            self.print_node(child, itemizer, level + 1);
+        }
     }
 }
 
@@ -155,80 +224,71 @@ pub fn sort_transaction(transaction: &mut [u32], item_count: &HashMap<u32, u32>,
     }
 }
 
-fn add_parents_to_table<'a>(node: &'a FPNode, table: &mut HashMap<&'a FPNode, &'a FPNode>) {
-    for ref child in node.children.iter() {
-        assert!(!table.contains_key(child));
-        table.insert(child, node);
-        add_parents_to_table(child, table)
-    }
+fn conditional_pattern_base(fptree: &FPTree, item: u32) -> PatternBase {
+    fptree.header[&item]
+        .iter()
+        .map(|&node| (fptree.path_from_root_to(node), fptree.nodes[node].count))
+        .collect()
 }
 
-fn make_parent_table<'a>(fptree: &'a FPTree) -> HashMap<&'a FPNode, &'a FPNode> {
-    let mut table = HashMap::new();
-    add_parents_to_table(fptree.root(), &mut table);
-    table
+// An upper bound on the estimated bytes used by the conditional tree built
+// from `pattern_base`, were none of the paths to share nodes.
+fn pattern_base_bytes(pattern_base: &PatternBase) -> usize {
+    let num_nodes = 1 + pattern_base.iter().map(|(path, _)| path.len()).sum::<usize>();
+    estimated_bytes(num_nodes, num_nodes)
 }
 
-fn add_nodes_to_index<'a>(node: &'a FPNode, index: &mut HashMap<u32, Vec<&'a FPNode>>) {
-    for ref child in node.children.iter() {
-        index.entry(child.item).or_insert(vec![]).push(child);
-        add_nodes_to_index(child, index)
+// Builds a conditional tree from `pattern_base`, leaving out the items for
+// which `keep` is false.
+fn tree_from_pattern_base<F: Fn(u32) -> bool>(pattern_base: &PatternBase, keep: F) -> FPTree {
+    let mut tree = FPTree::new();
+    for (path, count) in pattern_base.iter() {
+        let path: Vec<u32> = path.iter().cloned().filter(|&item| keep(item)).collect();
+        tree.insert(&path, *count);
     }
+    tree
 }
 
-fn make_item_index<'a>(fptree: &'a FPTree) -> HashMap<u32, Vec<&'a FPNode>> {
-    let mut index = HashMap::new();
-    add_nodes_to_index(fptree.root(), &mut index);
-    index
-}
-
-fn path_from_root_to<'a>(
-    node: &'a FPNode,
-    parent_table: &HashMap<&'a FPNode, &'a FPNode>,
-) -> Vec<u32> {
-    let mut path = vec![];
-    let mut n = node;
-    loop {
-        match parent_table.get(n) {
-            Some(parent) if !parent.is_root() => {
-                path.push(parent.item);
-                n = parent;
-                continue;
-            }
-            _ => {
-                break;
-            }
-        }
-    }
-    path.reverse();
-    path
+fn itemsets_bytes(itemsets: &[ItemSet]) -> usize {
+    itemsets
+        .iter()
+        .map(|itemset| mem::size_of::<ItemSet>() + itemset.len() * mem::size_of::<u32>())
+        .sum()
 }
 
-fn construct_conditional_tree<'a>(
-    parent_table: &HashMap<&'a FPNode, &'a FPNode>,
-    item_list: &Vec<&'a FPNode>,
-) -> FPTree {
+// Builds the conditional tree of `item`, following its links in the header
+// table to the paths which lead to it. Items for which `keep` is false are
+// left out of it.
+fn construct_conditional_tree<F: Fn(u32) -> bool>(fptree: &FPTree, item: u32, keep: F) -> FPTree {
     let mut conditional_tree = FPTree::new();
 
-    for node in item_list {
-        let path = path_from_root_to(node, parent_table);
-        conditional_tree.insert(&path, node.count);
+    for &node in fptree.header[&item].iter() {
+        let mut path = fptree.path_from_root_to(node);
+        path.retain(|&item| keep(item));
+        conditional_tree.insert(&path, fptree.nodes[node].count);
     }
     conditional_tree
 }
 
-#[derive(Clone, Hash, PartialEq, Eq, Debug, Ord)]
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ItemSet {
     pub items: Vec<u32>,
     pub count: u32,
 }
 
-impl PartialOrd for ItemSet {
-    fn partial_cmp(&self, other: &ItemSet) -> Option<cmp::Ordering> {
+impl Ord for ItemSet {
+    fn cmp(&self, other: &ItemSet) -> cmp::Ordering {
         if other.len() != self.len() {
-            return Some(self.len().cmp(&other.len()));
+            return self.len().cmp(&other.len());
         }
-        Some(self.items.cmp(&other.items))
+        self.items.cmp(&other.items)
+    }
+}
+
+impl PartialOrd for ItemSet {
+    fn partial_cmp(&self, other: &ItemSet) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -237,27 +297,102 @@ impl ItemSet {
         let sorted_items = items.iter().cloned().sorted();
         ItemSet {
             items: sorted_items,
-            count: count,
+            count,
         }
     }
 
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
 }
 
-fn lfactorial(n: u32, ln_table: &[f64]) -> f64 {
-    ln_table[n as usize]
+// Whether `item` is associated with the itemset `path`, so is worth growing
+// `path` by. Every item is worth growing the empty path by.
+fn is_significant(item: u32, path: &[u32], index: &Index, significance_test: &SignificanceTest) -> bool {
+    if path.is_empty() {
+        return true;
+    }
+    let mut itemset: Vec<u32> = Vec::from(path);
+    itemset.push(item);
+    let table = Contingency::new(
+        index.num_transactions() as u32,
+        index.count(&[item]) as u32,
+        index.count(path) as u32,
+        index.count(&itemset) as u32,
+    );
+    significance_test.pval(&table) < 0.05
 }
 
-pub fn pval(ab: u32, a: u32, b: u32, n: u32, ln_table: &[f64]) -> f64 {
-    (lfactorial(b, ln_table) + lfactorial(n - b, ln_table) + lfactorial(a, ln_table) +
-        lfactorial(n - a, ln_table) - lfactorial(ab, ln_table) - lfactorial(b - ab, ln_table) -
-        lfactorial(a - ab, ln_table) - lfactorial(n - a - b + ab, ln_table) -
-        lfactorial(n, ln_table))
-        .exp()
+// rip_growth from a tree which is a single path, given as its items and their
+// counts from the root down. The conditional tree of each item is then the
+// path above it, with every count that item's, so it's enumerated directly.
+// This grows the same itemsets, in the same order, as rip_growth would.
+#[allow(clippy::too_many_arguments)]
+fn grow_single_path(
+    nodes: &[(u32, u32)],
+    path: &[u32],
+    path_count: u32,
+    index: &Index,
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+    top_k: Option<&TopK>,
+) -> Vec<ItemSet> {
+    // In increasing order of count, as rip_growth sorts the items.
+    let mut order: Vec<usize> = (0..nodes.len())
+        .filter(|&i| is_significant(nodes[i].0, path, index, significance_test))
+        .collect();
+    order.sort_by_key(|&i| (nodes[i].1, nodes[i].0));
+
+    let mut result: Vec<ItemSet> = vec![];
+    for i in order {
+        if is_cancelled() {
+            break;
+        }
+        let (item, count) = nodes[i];
+        let mut itemset: Vec<u32> = Vec::from(path);
+        let new_path_count = cmp::min(path_count, count);
+        itemset.push(item);
+
+        if top_k.is_some_and(|top_k| new_path_count < top_k.min_count()) {
+            continue;
+        }
+
+        let has_required_item = required_items.is_empty()
+            || itemset.iter().any(|item| required_items.contains(item));
+
+        let at_max_len = max_itemset_len.is_some_and(|max| itemset.len() >= max);
+        if !at_max_len {
+            let conditional: Vec<(u32, u32)> = nodes[..i].iter().map(|&(item, _)| (item, count)).collect();
+            if !has_required_item && !conditional.iter().any(|(item, _)| required_items.contains(item)) {
+                continue;
+            }
+            result.append(&mut grow_single_path(
+                &conditional,
+                &itemset,
+                new_path_count,
+                index,
+                significance_test,
+                max_itemset_len,
+                required_items,
+                top_k,
+            ));
+        }
+        if has_required_item {
+            if let Some(top_k) = top_k {
+                top_k.insert(&itemset, new_path_count);
+            }
+            result.push(ItemSet::new(itemset, new_path_count));
+        }
+    }
+    result
 }
 
+#[allow(clippy::too_many_arguments, clippy::only_used_in_recursion)]
 pub fn rip_growth(
     initial_tree: &FPTree,
     fptree: &FPTree,
@@ -266,77 +401,345 @@ pub fn rip_growth(
     path_count: u32,
     itemizer: &Itemizer,
     index: &Index,
-    ln_table: &[f64],
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+    top_k: Option<&TopK>,
+    checkpoint: Option<&Checkpointer>,
+    budget: Option<&MemoryBudget>,
+    sink: Option<&SyncSender<Vec<ItemSet>>>,
 ) -> Vec<ItemSet> {
-    let mut itemsets: Vec<ItemSet> = vec![];
-
-    // Maps a node to its parent.
-    let parent_table = make_parent_table(&fptree);
+    // Below the top level, conditional trees are often a single path, whose
+    // itemsets can be enumerated without building any further trees.
+    if let (None, Some(nodes)) = (rare_items, fptree.single_path()) {
+        return grow_single_path(
+            &nodes,
+            path,
+            path_count,
+            index,
+            significance_test,
+            max_itemset_len,
+            required_items,
+            top_k,
+        );
+    }
 
-    // Maps item id to vec of &FPNode's for those items.
-    let item_index = make_item_index(&fptree);
+    let mut itemsets: Vec<ItemSet> = vec![];
 
     // Sort the list in increasing order of frequency. On the first
     // iteration, we also filter out the non-rare items. On subsequent
     // recursions, we recurse on all items (frequent and rare) to ensure
     // we can find associations between rare items with frequent items.
     let mut items: Vec<u32> = match rare_items {
-        Some(rare_items) => item_index
+        Some(rare_items) => fptree
+            .header
             .keys()
-            .map(|item| item.clone())
+            .cloned()
             .filter(|item| rare_items.contains(item))
             .collect(),
-        None => item_index.keys().map(|item| item.clone()).collect(),
+        None => fptree.header.keys().cloned().collect(),
     };
     sort_transaction(&mut items, fptree.item_count(), SortOrder::Increasing);
 
     let items: Vec<u32> = items
         .iter()
-        .map(|x| x.clone())
-        .filter(|item| {
-            if path.len() == 0 {
-                return true;
-            }
-            let a = index.count(&[*item]) as u32;
-            let b = index.count(&path) as u32;
-            let mut itemset: Vec<u32> = Vec::from(path);
-            itemset.push(*item);
-            let ab = index.count(&itemset) as u32;
-            let n = index.num_transactions() as u32;
-            let pv = pval(ab, a, b, n, ln_table);
-            pv < 0.05
-        })
+        .cloned()
+        .filter(|item| is_significant(*item, path, index, significance_test))
         .collect();
 
-    let x: Vec<ItemSet> = items
-        .par_iter()
-        .flat_map(|item| -> Vec<ItemSet> {
-            // The path to here plus this item must be below the maximum
-            // support threshold.
-            let mut itemset: Vec<u32> = Vec::from(path);
-            let new_path_count = cmp::min(path_count, get_item_count(*item, fptree.item_count()));
-            itemset.push(*item);
-
-            let mut result: Vec<ItemSet> = Vec::new();
-            if let Some(item_list) = item_index.get(item) {
-                let conditional_tree = construct_conditional_tree(&parent_table, item_list);
-                let mut y = rip_growth(
-                    initial_tree,
-                    &conditional_tree,
-                    None,
-                    &itemset,
-                    new_path_count,
-                    itemizer,
-                    &index,
-                    ln_table,
-                );
-                result.append(&mut y);
+    // Only the top level spills, as each rare item there is grown
+    // independently of the others.
+    let spill = match budget {
+        Some(_) if path.is_empty() => Some(SpillFile::new()),
+        _ => None,
+    };
+
+    // Grows from `item`, building its conditional tree from `pattern_base`
+    // if given.
+    let grow = |item: &u32, pattern_base: Option<PatternBase>| -> Vec<ItemSet> {
+        if is_cancelled() {
+            return vec![];
+        }
+        // The path to here plus this item must be below the maximum
+        // support threshold.
+        let mut itemset: Vec<u32> = Vec::from(path);
+        let new_path_count = cmp::min(path_count, get_item_count(*item, fptree.item_count()));
+        itemset.push(*item);
+
+        // When mining the top K itemsets, neither this itemset nor any
+        // grown from it can be among them if it's below the threshold.
+        if top_k.is_some_and(|top_k| new_path_count < top_k.min_count()) {
+            return vec![];
+        }
+
+        // If there are required items, only itemsets containing at least
+        // one of them are kept.
+        let has_required_item = required_items.is_empty()
+            || itemset.iter().any(|item| required_items.contains(item));
+
+        let mut result: Vec<ItemSet> = Vec::new();
+        // Itemsets grown from here would be longer than the maximum, so
+        // there's no need to build the conditional tree.
+        let at_max_len = max_itemset_len.is_some_and(|max| itemset.len() >= max);
+        if !at_max_len {
+            // When mining the top K itemsets, an item whose pre-counted
+            // count with this one is below the threshold can't be in any
+            // itemset grown from here which is among them.
+            let min_count = top_k.map_or(0, |top_k| top_k.min_count()) as usize;
+            let keep = |other: u32| index.pair_count(*item, other).is_none_or(|count| count >= min_count);
+            let conditional_tree = match pattern_base {
+                Some(pattern_base) => tree_from_pattern_base(&pattern_base, keep),
+                None => construct_conditional_tree(fptree, *item, keep),
             };
+            // The top level's trees are reserved before they're built.
+            let _reservation = match (budget, &spill) {
+                (Some(budget), &None) => Some(budget.reserve(conditional_tree.estimated_bytes())),
+                _ => None,
+            };
+            // Itemsets grown from here are this itemset plus items from the
+            // conditional tree, so if neither has a required item, none of
+            // them will.
+            if !has_required_item
+                && !conditional_tree
+                    .item_count()
+                    .keys()
+                    .any(|item| required_items.contains(item))
+            {
+                return result;
+            }
+            let mut y = rip_growth(
+                initial_tree,
+                &conditional_tree,
+                None,
+                &itemset,
+                new_path_count,
+                itemizer,
+                index,
+                significance_test,
+                max_itemset_len,
+                required_items,
+                top_k,
+                None,
+                budget,
+                None,
+            );
+            result.append(&mut y);
+        };
+        if has_required_item {
+            if let Some(top_k) = top_k {
+                top_k.insert(&itemset, new_path_count);
+            }
             result.push(ItemSet::new(itemset, new_path_count));
-            result
-        })
-        .collect::<Vec<ItemSet>>();
+        }
+        result
+    };
+
+    // Grows from `item` if its conditional tree fits in the memory budget,
+    // returning None if it was spilled to grow later instead.
+    let grow_or_spill = |item: &u32| -> Option<Vec<ItemSet>> {
+        let (budget, spill) = match (budget, &spill) {
+            (Some(budget), Some(spill)) => (budget, spill),
+            _ => return Some(grow(item, None)),
+        };
+        let pattern_base = conditional_pattern_base(fptree, *item);
+        let bytes = pattern_base_bytes(&pattern_base);
+        let _reservation = match budget.try_reserve(bytes) {
+            Some(reservation) => reservation,
+            None => match spill.write(*item, &pattern_base) {
+                Ok(()) => return None,
+                Err(err) => {
+                    warn!("Failed to spill to disk, so growing in memory: {}", err);
+                    budget.reserve(bytes)
+                }
+            },
+        };
+        let itemsets = grow(item, Some(pattern_base));
+        budget.retain(itemsets_bytes(&itemsets));
+        Some(itemsets)
+    };
+
+    // Records the itemsets grown from a top level item in the checkpoint.
+    let complete = |item: &u32, itemsets: &[ItemSet]| {
+        // If cancelled, the growth may have stopped part way.
+        if let (Some(checkpoint), false) = (checkpoint, is_cancelled()) {
+            checkpoint.complete(*item, itemsets);
+        }
+    };
+
+    // If given a sink, the top level sends each rare item's itemsets to it as
+    // they're grown, rather than returning them all at the end.
+    let emit = |itemsets: Vec<ItemSet>| -> Vec<ItemSet> {
+        match sink {
+            Some(sink) => {
+                // Only fails if the receiver has panicked, which is raised
+                // when it's joined.
+                let _ = sink.send(itemsets);
+                vec![]
+            }
+            None => itemsets,
+        }
+    };
 
+    // Only the top level is checkpointed, as each rare item there is grown
+    // independently of the others.
+    let grow_item = |item: &u32| -> Vec<ItemSet> {
+        let checkpoint = match checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => return emit(grow_or_spill(item).unwrap_or_default()),
+        };
+        if let Some(itemsets) = checkpoint.completed(*item) {
+            // Restore the threshold the resumed itemsets would have set.
+            if let Some(top_k) = top_k {
+                for itemset in itemsets.iter() {
+                    top_k.insert(&itemset.items, itemset.count);
+                }
+            }
+            if let Some(budget) = budget {
+                budget.retain(itemsets_bytes(&itemsets));
+            }
+            return emit(itemsets);
+        }
+        let itemsets = match grow_or_spill(item) {
+            Some(itemsets) => itemsets,
+            None => return vec![],
+        };
+        complete(item, &itemsets);
+        emit(itemsets)
+    };
+    let parallel = path.is_empty()
+        || (path.len() < MAX_PARALLEL_DEPTH && fptree.num_nodes() >= MIN_PARALLEL_NODES);
+    let x: Vec<ItemSet> = if parallel {
+        items.par_iter().flat_map(grow_item).collect()
+    } else {
+        items.iter().flat_map(grow_item).collect()
+    };
     itemsets.extend(x);
+
+    // The spilled items are grown one at a time, each with the others'
+    // memory freed.
+    if let (Some(budget), Some(spill)) = (budget, &spill) {
+        if !spill.is_empty() {
+            info!(
+                "Growing {} of {} rare items which were spilled to disk to stay under the memory cap.",
+                spill.len(),
+                items.len()
+            );
+        }
+        replay_spill(spill, budget, &grow, |item, grown| {
+            complete(&item, &grown);
+            itemsets.extend(emit(grown));
+        });
+    }
     itemsets
 }
+
+// Grows each item in `spill` from its spilled pattern base, one at a time,
+// passing the itemsets to `grown`. If the spill file can't be read, such as
+// when the disk is full or the temporary directory was cleaned, the items
+// not yet grown are grown in memory instead, their conditional trees built
+// from the tree again, so mining still finishes, just over the memory cap.
+fn replay_spill<G, F>(spill: &SpillFile, budget: &MemoryBudget, grow: &G, mut grown: F)
+where
+    G: Fn(&u32, Option<PatternBase>) -> Vec<ItemSet>,
+    F: FnMut(u32, Vec<ItemSet>),
+{
+    let mut done: HashSet<u32> = HashSet::new();
+    let result = spill.read().and_then(|entries| {
+        for entry in entries {
+            if is_cancelled() {
+                break;
+            }
+            let (item, pattern_base) = entry?;
+            let _reservation = budget.reserve(pattern_base_bytes(&pattern_base));
+            grown(item, grow(&item, Some(pattern_base)));
+            done.insert(item);
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        warn!(
+            "Failed to read back the spill file, so growing the rest of the spilled items in memory: {}",
+            err
+        );
+        for item in spill.items() {
+            if is_cancelled() {
+                break;
+            }
+            if !done.contains(&item) {
+                grown(item, grow(&item, None));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use super::ItemSet;
+        use serde_json;
+
+        let itemset = ItemSet::new(vec![7, 1, 4], 3);
+        let json = serde_json::to_string(&itemset).unwrap();
+        assert_eq!(json, r#"{"items":[1,4,7],"count":3}"#);
+        assert_eq!(serde_json::from_str::<ItemSet>(&json).unwrap(), itemset);
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        use super::FPTree;
+
+        let mut tree = FPTree::new();
+        assert_eq!(tree.num_nodes(), 1);
+        assert_eq!(tree.depth_histogram(), vec![1]);
+
+        tree.insert(&[1, 2, 3], 1);
+        tree.insert(&[1, 2], 2);
+        tree.insert(&[1, 4], 1);
+        tree.insert(&[5], 1);
+        // root, 1, 1-2, 1-2-3, 1-4 and 5.
+        assert_eq!(tree.num_nodes(), 6);
+        assert_eq!(tree.depth_histogram(), vec![1, 2, 2, 1]);
+
+        assert_eq!(tree.single_path(), None);
+
+        let mut single = FPTree::new();
+        single.insert(&[1, 2, 3], 2);
+        single.insert(&[1, 2], 1);
+        assert_eq!(single.single_path(), Some(vec![(1, 3), (2, 3), (3, 2)]));
+
+        let bytes = tree.estimated_bytes();
+        tree.insert(&[6, 7], 1);
+        assert!(tree.estimated_bytes() > bytes);
+    }
+
+    #[test]
+    fn test_replay_unreadable_spill() {
+        use super::{replay_spill, ItemSet, PatternBase};
+        use spill::{MemoryBudget, SpillFile};
+        use std::fs;
+
+        let grow = |item: &u32, pattern_base: Option<PatternBase>| {
+            vec![ItemSet::new(vec![*item], if pattern_base.is_some() { 1 } else { 0 })]
+        };
+        let budget = MemoryBudget::new(1000);
+
+        let spill = SpillFile::new();
+        spill.write(3, &vec![(vec![1], 2)]).unwrap();
+        spill.write(4, &vec![]).unwrap();
+        let mut grown = vec![];
+        replay_spill(&spill, &budget, &grow, |item, itemsets| {
+            grown.push((item, itemsets[0].count))
+        });
+        assert_eq!(grown, vec![(3, 1), (4, 1)]);
+
+        // Items whose spilled pattern base is lost are grown in memory.
+        fs::remove_file(spill.path()).unwrap();
+        let mut grown = vec![];
+        replay_spill(&spill, &budget, &grow, |item, itemsets| {
+            grown.push((item, itemsets[0].count))
+        });
+        assert_eq!(grown, vec![(3, 0), (4, 0)]);
+    }
+}
@@ -0,0 +1,199 @@
+// Generates synthetic datasets of transactions in the style of IBM's Quest
+// generator (Agrawal and Srikant, 1994), for `riptree gen-data` and the
+// benchmarks, so performance can be measured on datasets of any size.
+//
+// Transactions are made of patterns, sets of items which tend to occur
+// together. Each pattern's length is Poisson distributed around the average
+// pattern length, and a fraction of its items, exponentially distributed
+// around the correlation, are taken from the pattern before it, so patterns
+// overlap. Patterns are picked with exponentially distributed weights, so
+// some are much more common than others, and each has a corruption level,
+// the chance each of its items is left out when it's put in a transaction.
+// Each transaction's length is Poisson distributed around the average
+// transaction length, and it's filled with patterns until it's that long.
+
+use rand::distributions::{Exp, IndependentSample, Normal};
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::io::{self, Write};
+
+pub struct DataParameters {
+    pub num_transactions: usize,
+    pub num_items: usize,
+    pub avg_transaction_len: f64,
+    pub num_patterns: usize,
+    pub avg_pattern_len: f64,
+    pub correlation: f64,
+    pub seed: u32,
+}
+
+impl Default for DataParameters {
+    fn default() -> DataParameters {
+        DataParameters {
+            num_transactions: 10000,
+            num_items: 1000,
+            avg_transaction_len: 10.0,
+            num_patterns: 2000,
+            avg_pattern_len: 4.0,
+            correlation: 0.5,
+            seed: 1,
+        }
+    }
+}
+
+struct Pattern {
+    items: Vec<u32>,
+    corruption: f64,
+}
+
+// Draws from a Poisson distribution with the given mean, approximated by a
+// normal distribution for large means.
+fn poisson<R: Rng>(rng: &mut R, mean: f64) -> usize {
+    if mean > 30.0 {
+        let draw = Normal::new(mean, mean.sqrt()).ind_sample(rng);
+        return draw.round().max(0.0) as usize;
+    }
+    let limit = (-mean).exp();
+    let mut count = 0;
+    let mut product: f64 = rng.gen();
+    while product > limit {
+        count += 1;
+        product *= rng.gen::<f64>();
+    }
+    count
+}
+
+pub struct Generator {
+    rng: XorShiftRng,
+    patterns: Vec<Pattern>,
+    // The running total of the patterns' weights, which sum to 1.
+    cumulative_weights: Vec<f64>,
+    avg_transaction_len: f64,
+}
+
+impl Generator {
+    pub fn new(parameters: &DataParameters) -> Generator {
+        let seed = parameters.seed;
+        let mut rng = XorShiftRng::from_seed([seed | 1, seed ^ 0x9e37_79b9, 0x85eb_ca6b, 0xc2b2_ae35]);
+        let num_items = parameters.num_items.max(1) as u32;
+        let correlation = Exp::new(1.0 / parameters.correlation.max(f64::EPSILON));
+        let corruption = Normal::new(0.5, 0.1);
+
+        let mut patterns: Vec<Pattern> = vec![];
+        for _ in 0..parameters.num_patterns.max(1) {
+            let len = poisson(&mut rng, parameters.avg_pattern_len).clamp(1, num_items as usize);
+            let mut items: Vec<u32> = vec![];
+            if let Some(previous) = patterns.last() {
+                let fraction = correlation.ind_sample(&mut rng).min(1.0);
+                let shared = ((fraction * len as f64).round() as usize).min(previous.items.len());
+                for _ in 0..shared {
+                    let item = previous.items[rng.gen_range(0, previous.items.len())];
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            while items.len() < len {
+                let item = rng.gen_range(0, num_items) + 1;
+                if !items.contains(&item) {
+                    items.push(item);
+                }
+            }
+            patterns.push(Pattern {
+                items,
+                corruption: corruption.ind_sample(&mut rng).clamp(0.0, 0.9),
+            });
+        }
+
+        let weights = Exp::new(1.0);
+        let mut cumulative_weights: Vec<f64> = vec![];
+        let mut total = 0.0;
+        for _ in 0..patterns.len() {
+            total += weights.ind_sample(&mut rng);
+            cumulative_weights.push(total);
+        }
+        for weight in cumulative_weights.iter_mut() {
+            *weight /= total;
+        }
+
+        Generator {
+            rng,
+            patterns,
+            cumulative_weights,
+            avg_transaction_len: parameters.avg_transaction_len,
+        }
+    }
+
+    fn pick_pattern(&mut self) -> usize {
+        let draw: f64 = self.rng.gen();
+        let index = self.cumulative_weights.partition_point(|&weight| weight < draw);
+        index.min(self.patterns.len() - 1)
+    }
+
+    // Returns the next transaction, with its items in increasing order.
+    pub fn next_transaction(&mut self) -> Vec<u32> {
+        let len = poisson(&mut self.rng, self.avg_transaction_len).max(1);
+        let mut transaction: Vec<u32> = vec![];
+        // Patterns may be corrupted to nothing, so give up eventually.
+        let mut attempts = 0;
+        while transaction.len() < len && attempts < 10 * len {
+            attempts += 1;
+            let pattern = self.pick_pattern();
+            let pattern = &self.patterns[pattern];
+            for &item in pattern.items.iter() {
+                if self.rng.gen::<f64>() >= pattern.corruption && !transaction.contains(&item) {
+                    transaction.push(item);
+                }
+            }
+        }
+        transaction.sort();
+        transaction
+    }
+}
+
+pub fn generate(parameters: &DataParameters) -> Vec<Vec<u32>> {
+    let mut generator = Generator::new(parameters);
+    (0..parameters.num_transactions)
+        .map(|_| generator.next_transaction())
+        .collect()
+}
+
+// Writes a dataset as CSV, with items named by number.
+pub fn write_dataset<W: Write>(output: &mut W, parameters: &DataParameters) -> io::Result<()> {
+    let mut generator = Generator::new(parameters);
+    for _ in 0..parameters.num_transactions {
+        let items: Vec<String> = generator.next_transaction().iter().map(|item| item.to_string()).collect();
+        writeln!(output, "{}", items.join(","))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_generate() {
+        use super::{generate, write_dataset, DataParameters};
+
+        let parameters = DataParameters {
+            num_transactions: 2000,
+            num_items: 100,
+            avg_transaction_len: 8.0,
+            num_patterns: 50,
+            ..DataParameters::default()
+        };
+        let transactions = generate(&parameters);
+        assert_eq!(transactions.len(), 2000);
+        assert!(transactions.iter().all(|t| !t.is_empty() && t.windows(2).all(|w| w[0] < w[1])));
+        assert!(transactions.iter().flatten().all(|&item| (1..=100).contains(&item)));
+        let avg_len = transactions.iter().map(|t| t.len()).sum::<usize>() as f64 / 2000.0;
+        assert!(avg_len > 6.0 && avg_len < 12.0, "average length {}", avg_len);
+
+        // The same seed gives the same dataset, which is what's written.
+        assert_eq!(generate(&parameters), transactions);
+        let mut buffer: Vec<u8> = vec![];
+        write_dataset(&mut buffer, &parameters).unwrap();
+        let first_line = String::from_utf8(buffer).unwrap().lines().next().unwrap().to_owned();
+        let first: Vec<String> = transactions[0].iter().map(|item| item.to_string()).collect();
+        assert_eq!(first_line, first.join(","));
+        assert_ne!(generate(&DataParameters { seed: 2, ..parameters }), transactions);
+    }
+}
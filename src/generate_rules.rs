@@ -1,20 +1,145 @@
+use bitset_counts::BitsetCounts;
+use command_line_args::{Counting, RuleSelection, RuleSortKey};
 use index::Index;
 use itemizer::Itemizer;
 use itertools::Itertools;
 use ordered_float::OrderedFloat;
+use rand::{Rng, SeedableRng, XorShiftRng};
 use rayon::prelude::*;
+use std::cmp;
 use std::collections::HashSet;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use cancel::is_cancelled;
 use fptree::ItemSet;
-use fptree::pval;
+use metrics::Metric;
+use significance::{bootstrap_intervals, odds_ratio, Contingency, PValueCache, SignificanceTest};
+use support_cache::SupportCache;
 
-#[derive(Clone, Hash, Eq, Debug)]
+// A rule's measures on transactions held out from mining.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HoldoutMeasures {
+    confidence: OrderedFloat<f64>,
+    lift: OrderedFloat<f64>,
+    support: OrderedFloat<f64>,
+}
+
+impl HoldoutMeasures {
+    pub fn confidence(&self) -> f64 {
+        self.confidence.into()
+    }
+
+    pub fn lift(&self) -> f64 {
+        self.lift.into()
+    }
+
+    pub fn support(&self) -> f64 {
+        self.support.into()
+    }
+}
+
+// The odds ratio of the association between a rule's antecedent and
+// consequent, and its 95% confidence interval.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OddsRatio {
+    ratio: OrderedFloat<f64>,
+    lower: OrderedFloat<f64>,
+    upper: OrderedFloat<f64>,
+}
+
+impl OddsRatio {
+    pub fn ratio(&self) -> f64 {
+        self.ratio.into()
+    }
+
+    pub fn lower(&self) -> f64 {
+        self.lower.into()
+    }
+
+    pub fn upper(&self) -> f64 {
+        self.upper.into()
+    }
+}
+
+// 95% bootstrap confidence intervals of a rule's confidence and lift.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BootstrapIntervals {
+    confidence: (OrderedFloat<f64>, OrderedFloat<f64>),
+    lift: (OrderedFloat<f64>, OrderedFloat<f64>),
+}
+
+impl BootstrapIntervals {
+    pub fn confidence(&self) -> (f64, f64) {
+        (self.confidence.0.into(), self.confidence.1.into())
+    }
+
+    pub fn lift(&self) -> (f64, f64) {
+        (self.lift.0.into(), self.lift.1.into())
+    }
+}
+
+// How unevenly a rule's antecedent and consequent are supported. The
+// imbalance ratio is |sup(A) - sup(C)| / sup(A ∪ C), in [0,1), where sup(A ∪ C)
+// is the support of transactions containing either, and the cross support
+// ratio is sup(A) / sup(C), which is large when a common antecedent is paired
+// with a rare consequent, inflating the rule's confidence.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Imbalance {
+    ratio: OrderedFloat<f64>,
+    cross_support_ratio: OrderedFloat<f64>,
+}
+
+impl Imbalance {
+    pub fn ratio(&self) -> f64 {
+        self.ratio.into()
+    }
+
+    pub fn cross_support_ratio(&self) -> f64 {
+        self.cross_support_ratio.into()
+    }
+}
+
+// A transaction a rule fires in, for --emit-examples: its id in the index,
+// which numbers the distinct transactions, and its items.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Example {
+    pub transaction: usize,
+    items: Vec<u32>,
+}
+
+impl Example {
+    pub fn item_names<'a>(&self, itemizer: &'a Itemizer) -> Vec<&'a str> {
+        item_names(&self.items, itemizer)
+    }
+}
+
+// With the serde feature, rules serialize with their items as ids, which
+// the itemizer of the run that found them maps to names.
+#[derive(Clone, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rule {
     antecedent: Vec<u32>,
     consequent: Vec<u32>,
     confidence: OrderedFloat<f64>,
     lift: OrderedFloat<f64>,
     support: OrderedFloat<f64>,
+    // The p-value of the association between the antecedent and consequent,
+    // and the threshold it was compared against in family wise filtering.
+    // Both are None if filtering was disabled.
+    p_value: Option<OrderedFloat<f64>>,
+    p_value_threshold: Option<OrderedFloat<f64>>,
+    odds_ratio: Option<OddsRatio>,
+    imbalance: Option<Imbalance>,
+    // The metrics calculated for the rule, in Metric order.
+    metrics: Vec<(Metric, OrderedFloat<f64>)>,
+    bootstrap: Option<BootstrapIntervals>,
+    holdout: Option<HoldoutMeasures>,
+    examples: Option<Vec<Example>>,
 }
 
 impl PartialEq for Rule {
@@ -23,8 +148,15 @@ impl PartialEq for Rule {
     }
 }
 
+impl Hash for Rule {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.antecedent.hash(state);
+        self.consequent.hash(state);
+    }
+}
+
 // Assumes both itemsets are sorted.
-fn union(a: &Vec<u32>, b: &Vec<u32>) -> Vec<u32> {
+fn union(a: &[u32], b: &[u32]) -> Vec<u32> {
     let mut c: Vec<u32> = Vec::new();
     let mut ap = 0;
     let mut bp = 0;
@@ -55,49 +187,52 @@ fn union(a: &Vec<u32>, b: &Vec<u32>) -> Vec<u32> {
 
 // If all items in the itemset convert to an integer, order by that integer,
 // otherwise order lexicographically.
-fn ensure_sorted(a: &mut Vec<String>) {
-    let all_items_convert_to_ints = a.iter().all(|ref x| match x.parse::<u32>() {
-        Ok(_) => true,
-        Err(_) => false,
-    });
+fn ensure_sorted(a: &mut [&str]) {
+    let all_items_convert_to_ints = a.iter().all(|x| x.parse::<u32>().is_ok());
     if all_items_convert_to_ints {
-        a.sort_by(|ref x, ref y| {
-            let _x = match x.parse::<u32>() {
-                Ok(i) => i,
-                Err(_) => 0,
-            };
-            let _y = match y.parse::<u32>() {
-                Ok(i) => i,
-                Err(_) => 0,
-            };
-            _x.cmp(&_y)
-        });
+        a.sort_by_key(|x| x.parse::<u32>().unwrap_or_default());
     } else {
         a.sort();
     }
 }
 
+fn item_names<'a>(items: &[u32], itemizer: &'a Itemizer) -> Vec<&'a str> {
+    let mut names: Vec<&str> = items.iter().map(|&id| itemizer.str_of(id)).collect();
+    ensure_sorted(&mut names);
+    names
+}
+
 impl Rule {
     pub fn to_string(&self, itemizer: &Itemizer) -> String {
-        let mut a: Vec<String> = self.antecedent
-            .iter()
-            .map(|&id| itemizer.str_of(id))
-            .collect();
-        ensure_sorted(&mut a);
-        let mut b: Vec<String> = self.consequent
-            .iter()
-            .map(|&id| itemizer.str_of(id))
-            .collect();
-        ensure_sorted(&mut b);
-        [a.join(" "), " ==> ".to_owned(), b.join(" ")].join("")
+        [
+            self.antecedent_to_string(itemizer),
+            " ==> ".to_owned(),
+            self.consequent_to_string(itemizer),
+        ].join("")
+    }
+
+    pub fn antecedent_to_string(&self, itemizer: &Itemizer) -> String {
+        self.antecedent_names(itemizer).join(" ")
+    }
+
+    pub fn consequent_to_string(&self, itemizer: &Itemizer) -> String {
+        self.consequent_names(itemizer).join(" ")
+    }
+
+    pub fn antecedent_names<'a>(&self, itemizer: &'a Itemizer) -> Vec<&'a str> {
+        item_names(&self.antecedent, itemizer)
+    }
+
+    pub fn consequent_names<'a>(&self, itemizer: &'a Itemizer) -> Vec<&'a str> {
+        item_names(&self.consequent, itemizer)
     }
 
     // Creates a new Rule from (antecedent,consequent) if the rule
     // would be above the min_confidence threshold.
-    fn make(
+    fn make<F: Fn(&Vec<u32>) -> Option<f64>>(
         antecedent: Vec<u32>,
         consequent: Vec<u32>,
-        itemset_support: &HashMap<Vec<u32>, f64>,
+        support_of: &F,
         min_confidence: f64,
         min_lift: f64,
     ) -> Option<Rule> {
@@ -106,39 +241,92 @@ impl Rule {
         }
 
         let ac_vec: Vec<u32> = union(&antecedent, &consequent);
-        let ac_sup = match itemset_support.get(&ac_vec) {
-            Some(support) => support.clone(),
-            None => return None,
-        };
+        let ac_sup = support_of(&ac_vec)?;
 
-        let a_sup = match itemset_support.get(&antecedent) {
-            Some(support) => support.clone(),
-            None => return None,
-        };
+        let a_sup = support_of(&antecedent)?;
 
         let confidence = ac_sup / a_sup;
         if confidence < min_confidence {
             return None;
         }
-        let c_sup = match itemset_support.get(&consequent) {
-            Some(support) => support.clone(),
-            None => return None,
-        };
+        let c_sup = support_of(&consequent)?;
 
         let lift = ac_sup / (a_sup * c_sup);
         if lift < min_lift {
             return None;
         }
 
-        // Note: We sort the antecedent and consequent so that equality
-        // tests are consistent.
-        Some(Rule {
+        Some(Rule::new(antecedent, consequent, confidence, lift, ac_sup))
+    }
+
+    // Creates the rule antecedent ==> consequent from the number of
+    // transactions containing its antecedent, its consequent, and both, out
+    // of `num_transactions`, for analyzing rules found elsewhere. Returns None
+    // if the antecedent or consequent is empty, they share items, or the
+    // counts are inconsistent.
+    pub fn from_counts(
+        antecedent: Vec<u32>,
+        consequent: Vec<u32>,
+        antecedent_count: u32,
+        consequent_count: u32,
+        rule_count: u32,
+        num_transactions: u32,
+    ) -> Option<Rule> {
+        if antecedent.is_empty()
+            || consequent.is_empty()
+            || antecedent.iter().any(|item| consequent.contains(item))
+        {
+            return None;
+        }
+        if antecedent_count == 0
+            || consequent_count == 0
+            || rule_count > cmp::min(antecedent_count, consequent_count)
+            || cmp::max(antecedent_count, consequent_count) > num_transactions
+        {
+            return None;
+        }
+        let (a, c, ac, n) = (
+            antecedent_count as f64,
+            consequent_count as f64,
+            rule_count as f64,
+            num_transactions as f64,
+        );
+        Some(Rule::new(antecedent, consequent, ac / a, ac * n / (a * c), ac / n))
+    }
+
+    // Creates the rule antecedent ==> consequent with the given measures.
+    // Note: We sort the antecedent and consequent so that equality tests are
+    // consistent.
+    pub fn new(
+        antecedent: Vec<u32>,
+        consequent: Vec<u32>,
+        confidence: f64,
+        lift: f64,
+        support: f64,
+    ) -> Rule {
+        Rule {
             antecedent: antecedent.iter().cloned().sorted(),
             consequent: consequent.iter().cloned().sorted(),
             confidence: OrderedFloat::from(confidence),
             lift: OrderedFloat::from(lift),
-            support: OrderedFloat::from(ac_sup),
-        })
+            support: OrderedFloat::from(support),
+            p_value: None,
+            p_value_threshold: None,
+            odds_ratio: None,
+            imbalance: None,
+            metrics: vec![],
+            bootstrap: None,
+            holdout: None,
+            examples: None,
+        }
+    }
+
+    pub fn antecedent(&self) -> &[u32] {
+        &self.antecedent
+    }
+
+    pub fn consequent(&self) -> &[u32] {
+        &self.consequent
     }
 
     pub fn confidence(&self) -> f64 {
@@ -152,49 +340,318 @@ impl Rule {
     pub fn support(&self) -> f64 {
         self.support.into()
     }
+
+    pub fn p_value(&self) -> Option<f64> {
+        self.p_value.map(|p| p.into())
+    }
+
+    pub fn p_value_threshold(&self) -> Option<f64> {
+        self.p_value_threshold.map(|t| t.into())
+    }
+
+    pub fn odds_ratio(&self) -> Option<&OddsRatio> {
+        self.odds_ratio.as_ref()
+    }
+
+    // Returns the contingency table of the rule's antecedent and consequent
+    // in the transactions in `index`.
+    pub fn contingency(&self, index: &Index) -> Contingency {
+        Contingency::new(
+            index.num_transactions() as u32,
+            index.count(&self.antecedent) as u32,
+            index.count(&self.consequent) as u32,
+            index.count(&union(&self.antecedent, &self.consequent)) as u32,
+        )
+    }
+
+    // Calculates the rule's odds ratio from its contingency table.
+    pub fn calculate_odds_ratio(&mut self, table: &Contingency) {
+        let (ratio, lower, upper) = odds_ratio(table);
+        self.odds_ratio = Some(OddsRatio {
+            ratio: OrderedFloat::from(ratio),
+            lower: OrderedFloat::from(lower),
+            upper: OrderedFloat::from(upper),
+        });
+    }
+
+    pub fn imbalance(&self) -> Option<&Imbalance> {
+        self.imbalance.as_ref()
+    }
+
+    // Calculates the rule's imbalance from its contingency table.
+    pub fn calculate_imbalance(&mut self, table: &Contingency) {
+        let (a, c) = (table.a as f64, table.b as f64);
+        let either = table.n - table.neither();
+        let ratio = if either > 0 { (a - c).abs() / either as f64 } else { 0.0 };
+        self.imbalance = Some(Imbalance {
+            ratio: OrderedFloat::from(ratio),
+            cross_support_ratio: OrderedFloat::from(a / c),
+        });
+    }
+
+    pub fn metrics(&self) -> Vec<(Metric, f64)> {
+        self.metrics.iter().map(|&(metric, value)| (metric, value.into())).collect()
+    }
+
+    // The value of `metric`, if it was calculated for the rule.
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        self.metrics
+            .iter()
+            .find(|&&(m, _)| m == metric)
+            .map(|&(_, value)| value.into())
+    }
+
+    // Calculates `metrics`, which must be sorted and distinct, from the rule's
+    // contingency table.
+    pub fn calculate_metrics(&mut self, table: &Contingency, metrics: &[Metric]) {
+        self.metrics = metrics
+            .iter()
+            .map(|&metric| (metric, OrderedFloat::from(metric.compute(table))))
+            .collect();
+    }
+
+    pub fn bootstrap(&self) -> Option<&BootstrapIntervals> {
+        self.bootstrap.as_ref()
+    }
+
+    // Calculates the rule's bootstrap confidence intervals from `resamples`
+    // resamples of the transactions its contingency table counts.
+    pub fn calculate_bootstrap_intervals(&mut self, table: &Contingency, resamples: usize) {
+        let (confidence, lift) = bootstrap_intervals(table, resamples);
+        self.bootstrap = Some(BootstrapIntervals {
+            confidence: (OrderedFloat::from(confidence.0), OrderedFloat::from(confidence.1)),
+            lift: (OrderedFloat::from(lift.0), OrderedFloat::from(lift.1)),
+        });
+    }
+
+    pub fn holdout(&self) -> Option<&HoldoutMeasures> {
+        self.holdout.as_ref()
+    }
+
+    pub fn examples(&self) -> Option<&[Example]> {
+        self.examples.as_deref()
+    }
+
+    // Finds up to `max_examples` of the transactions in `index` containing
+    // all the rule's items, where `transactions` has the items of each
+    // transaction in the index, by id.
+    pub fn find_examples(&mut self, index: &Index, transactions: &[Vec<u32>], max_examples: usize) {
+        let tids = index.tids_of(&union(&self.antecedent, &self.consequent));
+        self.examples = Some(
+            tids.into_iter()
+                .take(max_examples)
+                .map(|tid| Example {
+                    transaction: tid,
+                    items: transactions[tid].clone(),
+                })
+                .collect(),
+        );
+    }
+
+    // Measures the rule on held out transactions in `index`.
+    pub fn evaluate_holdout(&mut self, index: &Index) {
+        let table = self.contingency(index);
+        self.holdout = Some(HoldoutMeasures {
+            confidence: OrderedFloat::from(table.confidence()),
+            lift: OrderedFloat::from(table.lift()),
+            support: OrderedFloat::from(table.support()),
+        });
+    }
+}
+
+// Returns the value by which rules are primarily sorted, in increasing order.
+fn sort_value(rule: &Rule, key: &RuleSortKey) -> f64 {
+    match *key {
+        RuleSortKey::Confidence => -rule.confidence(),
+        RuleSortKey::Lift => -rule.lift(),
+        RuleSortKey::Support => -rule.support(),
+        // Rules without p-values, because filtering was disabled, go last.
+        RuleSortKey::PValue => rule.p_value().unwrap_or(f64::INFINITY),
+        RuleSortKey::Lexicographic => 0.0,
+    }
+}
+
+// Sorts rules into a deterministic order; see RuleSortKey.
+pub fn sort_rules(rules: &mut [Rule], key: &RuleSortKey, itemizer: &Itemizer) {
+    rules.sort_by_cached_key(|rule| {
+        (
+            OrderedFloat::from(sort_value(rule, key)),
+            rule.to_string(itemizer),
+        )
+    });
 }
 
-pub fn split_out_item(items: &Vec<u32>, item: u32) -> (Vec<u32>, Vec<u32>) {
+// Keeps at most `max_rules` of the rules with each consequent, picked by
+// `selection`, and leaves them in order. By coverage, rules are picked
+// greedily, each adding the most transactions containing its antecedent
+// which aren't yet covered, so the rules kept are a compact set covering the
+// transactions the consequent is predicted for. Ties go to the earlier rule,
+// and picking stops when no rule covers any more.
+pub fn select_rules(
+    rules: Vec<Rule>,
+    max_rules: usize,
+    selection: &RuleSelection,
+    index: &Index,
+) -> Vec<Rule> {
+    let mut groups: HashMap<&[u32], Vec<usize>> = HashMap::new();
+    for (position, rule) in rules.iter().enumerate() {
+        groups.entry(&rule.consequent).or_default().push(position);
+    }
+    let selected: HashSet<usize> = groups
+        .into_par_iter()
+        .flat_map_iter(|(_, positions)| match *selection {
+            RuleSelection::Coverage => select_by_coverage(&rules, positions, max_rules, index),
+            RuleSelection::Best(ref key) => {
+                let mut positions = positions;
+                positions.sort_by_key(|&position| {
+                    (OrderedFloat::from(sort_value(&rules[position], key)), position)
+                });
+                positions.truncate(max_rules);
+                positions
+            }
+        })
+        .collect();
+    let selected: Vec<Rule> = rules
+        .into_iter()
+        .enumerate()
+        .filter(|(position, _)| selected.contains(position))
+        .map(|(_, rule)| rule)
+        .collect();
+    debug!("After selecting rules per consequent, {} rules remain", selected.len());
+    selected
+}
+
+fn select_by_coverage(rules: &[Rule], positions: Vec<usize>, max_rules: usize, index: &Index) -> Vec<usize> {
+    let mut candidates: Vec<(usize, Vec<usize>)> = positions
+        .into_iter()
+        .map(|position| (position, index.tids_of(&rules[position].antecedent)))
+        .collect();
+    let mut covered: HashSet<usize> = HashSet::new();
+    let mut selected = vec![];
+    while selected.len() < max_rules {
+        let gain = |tids: &[usize]| -> usize {
+            tids.iter()
+                .filter(|tid| !covered.contains(tid))
+                .map(|&tid| index.weight(tid) as usize)
+                .sum()
+        };
+        let best = candidates
+            .iter()
+            .enumerate()
+            .map(|(candidate, (_, tids))| (gain(tids), cmp::Reverse(candidate)))
+            .max();
+        match best {
+            Some((gain, cmp::Reverse(candidate))) if gain > 0 => {
+                let (position, tids) = candidates.remove(candidate);
+                covered.extend(tids);
+                selected.push(position);
+            }
+            _ => break,
+        }
+    }
+    selected
+}
+
+// A summary of the rules with the same consequent, written ahead of them
+// with --group-by-consequent.
+pub struct ConsequentGroup {
+    pub consequent: Vec<u32>,
+    pub num_rules: usize,
+    pub best_lift: f64,
+    // The fraction of transactions containing some rule's antecedent, and so
+    // which some rule makes a prediction for.
+    pub coverage: f64,
+}
+
+// Moves the rules with the same consequent together, and summarizes each
+// group. Groups are in the order of their first rule, and the rules within
+// them stay in order.
+pub fn group_by_consequent(rules: &mut [Rule], index: &Index) -> Vec<ConsequentGroup> {
+    let mut first: HashMap<Vec<u32>, usize> = HashMap::new();
+    for (position, rule) in rules.iter().enumerate() {
+        first.entry(rule.consequent.clone()).or_insert(position);
+    }
+    rules.sort_by_key(|rule| first[&rule.consequent]);
+
+    rules
+        .chunk_by(|a, b| a.consequent == b.consequent)
+        .map(|group| {
+            let mut tids: Vec<usize> =
+                group.iter().flat_map(|rule| index.tids_of(&rule.antecedent)).collect();
+            tids.sort_unstable();
+            tids.dedup();
+            let covered: usize = tids.iter().map(|&tid| index.weight(tid) as usize).sum();
+            ConsequentGroup {
+                consequent: group[0].consequent.clone(),
+                num_rules: group.len(),
+                best_lift: group.iter().map(Rule::lift).fold(0.0, f64::max),
+                coverage: covered as f64 / index.num_transactions() as f64,
+            }
+        })
+        .collect()
+}
+
+pub fn split_out_item(items: &[u32], item: u32) -> (Vec<u32>, Vec<u32>) {
     let antecedent: Vec<u32> = items.iter().filter(|&&x| x != item).cloned().collect();
     let consequent: Vec<u32> = vec![item];
     (antecedent, consequent)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn generate_rules(
-    itemsets: &Vec<ItemSet>,
+    itemsets: &[ItemSet],
     dataset_size: u32,
     min_confidence: f64,
     min_lift: f64,
     rare_items: &HashSet<u32>,
     index: &Index,
-    ln_table: &[f64],
+    significance_test: &SignificanceTest,
     disable_family_wise_rule_filtering: bool,
+    min_antecedent_len: usize,
+    max_antecedent_len: Option<usize>,
+    required_items: &HashSet<u32>,
 ) -> HashSet<Rule> {
     // Create a lookup of itemset to support, so we can quickly determine
     // an itemset's support during rule generation.
     let mut itemset_support: HashMap<Vec<u32>, f64> = HashMap::with_capacity(itemsets.len());
-    for ref i in itemsets.iter() {
+    for i in itemsets.iter() {
         itemset_support.insert(i.items.clone(), i.count as f64 / dataset_size as f64);
     }
+    let support_of = |items: &Vec<u32>| -> Option<f64> {
+        match itemset_support.get(items) {
+            Some(support) => Some(*support),
+            // When mining only itemsets containing required items, the
+            // antecedent or consequent of a rule may not contain one, and so
+            // won't have been mined. Count those in the index instead.
+            None if !required_items.is_empty() => Some(index.support(items)),
+            None => None,
+        }
+    };
 
     // Rare rules are those with the consequent as a single rare item. Generate
-    // those by splitting out each rare item out from every itemset.
+    // those by splitting out each rare item out from every itemset. So the
+    // antecedent has one item less than the itemset.
     let all_rare_rules: HashSet<Rule> = itemsets
         .par_iter()
-        .filter(|i| i.items.len() > 1)
-        .map(|ref itemset| {
+        .filter(|_| !is_cancelled())
+        .filter(|i| {
+            let antecedent_len = i.items.len() - 1;
+            antecedent_len >= cmp::max(min_antecedent_len, 1)
+                && max_antecedent_len.is_none_or(|max| antecedent_len <= max)
+        })
+        .map(|itemset| {
             let mut rules: HashSet<Rule> = HashSet::new();
             let mut candidates: Vec<Rule> = Vec::new();
             for &item in itemset
                 .items
                 .iter()
-                .filter(|item| rare_items.contains(&item))
+                .filter(|item| rare_items.contains(item))
             {
                 let (antecedent, consequent) = split_out_item(&itemset.items, item);
                 if let Some(rule) = Rule::make(
                     antecedent,
                     consequent,
-                    &itemset_support,
+                    &support_of,
                     min_confidence,
                     min_lift,
                 ) {
@@ -214,59 +671,375 @@ pub fn generate_rules(
             accum
         });
 
-    println!(
-        "Generated {} unfiltered rules, filtering via FW+BC",
-        all_rare_rules.len()
-    );
+    debug!("Generated {} unfiltered rules", all_rare_rules.len());
+
+    if disable_family_wise_rule_filtering {
+        all_rare_rules
+    } else {
+        family_wise_filter(all_rare_rules, index, significance_test, Counting::TidLists)
+    }
+}
+
+// A rule's antecedent and consequent, before its measures are known.
+type Candidate = (Vec<u32>, Vec<u32>);
+
+// Generates rare rules from itemsets as they're mined, so rule generation
+// overlaps mining rather than waiting for every itemset. A rule needs the
+// supports of its antecedent and consequent, which may be mined after the
+// itemset it's split from, so it waits for the first of them missing. Rules
+// still waiting at the end are dropped, as generate_rules drops rules whose
+// antecedent or consequent wasn't mined, so both find the same rules.
+pub struct RuleGenerator<'a> {
+    dataset_size: u32,
+    min_confidence: f64,
+    min_lift: f64,
+    rare_items: &'a HashSet<u32>,
+    index: &'a Index,
+    min_antecedent_len: usize,
+    max_antecedent_len: Option<usize>,
+    required_items: &'a HashSet<u32>,
+    itemset_support: HashMap<Vec<u32>, f64>,
+    // The antecedents and consequents of rules, keyed by the itemset whose
+    // support they're waiting for.
+    waiting: HashMap<Vec<u32>, Vec<Candidate>>,
+    rules: HashSet<Rule>,
+    // When set, rules are handed here as they're made, rather than kept.
+    sink: Option<Box<dyn FnMut(Rule) + Send + 'a>>,
+}
+
+impl<'a> RuleGenerator<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        dataset_size: u32,
+        min_confidence: f64,
+        min_lift: f64,
+        rare_items: &'a HashSet<u32>,
+        index: &'a Index,
+        min_antecedent_len: usize,
+        max_antecedent_len: Option<usize>,
+        required_items: &'a HashSet<u32>,
+    ) -> RuleGenerator<'a> {
+        RuleGenerator {
+            dataset_size,
+            min_confidence,
+            min_lift,
+            rare_items,
+            index,
+            min_antecedent_len,
+            max_antecedent_len,
+            required_items,
+            itemset_support: HashMap::new(),
+            waiting: HashMap::new(),
+            rules: HashSet::new(),
+            sink: None,
+        }
+    }
 
-    let mut rule_p_values: HashMap<Rule, f64> = HashMap::new();
-    let family_wise_filtered_rules: HashSet<Rule>;
-    if !disable_family_wise_rule_filtering {
-        // Family-Wise with Bonfronni correction.
-        // Count number of rules generated with the same consequent.
-        let mut rule_counts: HashMap<u32, u32> = HashMap::new();
-        for rule in all_rare_rules.iter() {
-            assert_eq!(rule.consequent.len(), 1);
-            *rule_counts.entry(rule.consequent[0]).or_insert(0) += 1;
+    // Hands each rule to `sink` as soon as it's made, instead of keeping it
+    // to be returned by finish(), which then returns no rules.
+    pub fn stream_to<F: FnMut(Rule) + Send + 'a>(&mut self, sink: F) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    fn support_of(&self, items: &Vec<u32>) -> Option<f64> {
+        match self.itemset_support.get(items) {
+            Some(support) => Some(*support),
+            // As in generate_rules, itemsets without a required item aren't
+            // mined, so are counted in the index.
+            None if !self.required_items.is_empty() => Some(self.index.support(items)),
+            None => None,
         }
+    }
 
-        // For each rule, calculate the p-value for association between antecedent
-        // and consequent, and keep those with p-value less than significance
-        // divided by the number of rules with the same consequent.
-        family_wise_filtered_rules = all_rare_rules
+    fn make_rule(&mut self, antecedent: Vec<u32>, consequent: Vec<u32>) {
+        let missing = vec![union(&antecedent, &consequent), antecedent.clone(), consequent.clone()]
             .into_iter()
-            .filter(|rule| {
-                let a = index.count(&rule.antecedent) as u32;
-                let b = index.count(&rule.consequent) as u32;
-                let both = union(&rule.antecedent, &rule.consequent);
-                let ab = index.count(&both) as u32;
-                let n = index.num_transactions() as u32;
-                let pv = pval(ab, a, b, n, ln_table);
-                rule_p_values.insert(rule.clone(), pv);
-                let threshold = 0.05 / (rule_counts[&rule.consequent[0]] as f64);
-                pv < threshold
-            })
-            .collect();
+            .find(|items| self.support_of(items).is_none());
+        if let Some(missing) = missing {
+            self.waiting.entry(missing).or_default().push((antecedent, consequent));
+            return;
+        }
+        let support_of = |items: &Vec<u32>| self.support_of(items);
+        if let Some(rule) = Rule::make(antecedent, consequent, &support_of, self.min_confidence, self.min_lift) {
+            match self.sink {
+                Some(ref mut sink) => sink(rule),
+                None => {
+                    self.rules.insert(rule);
+                }
+            }
+        }
+    }
 
-        println!(
-            "After family wise filtering, {} rules remain",
-            family_wise_filtered_rules.len()
-        );
-    } else {
-        family_wise_filtered_rules = all_rare_rules;
+    pub fn add(&mut self, itemset: &ItemSet) {
+        self.itemset_support
+            .insert(itemset.items.clone(), itemset.count as f64 / self.dataset_size as f64);
+        for (antecedent, consequent) in self.waiting.remove(&itemset.items).unwrap_or_default() {
+            self.make_rule(antecedent, consequent);
+        }
+
+        let antecedent_len = itemset.items.len() - 1;
+        if antecedent_len < cmp::max(self.min_antecedent_len, 1)
+            || self.max_antecedent_len.is_some_and(|max| antecedent_len > max)
+        {
+            return;
+        }
+        let rare_items = self.rare_items;
+        for &item in itemset.items.iter().filter(|item| rare_items.contains(item)) {
+            let (antecedent, consequent) = split_out_item(&itemset.items, item);
+            self.make_rule(antecedent, consequent);
+        }
+    }
+
+    pub fn finish(self) -> HashSet<Rule> {
+        debug!("Generated {} unfiltered rules", self.rules.len());
+        self.rules
+    }
+}
+
+// Counts every rule's antecedent, consequent and their union at once in
+// bitsets, keyed by their sorted items.
+fn count_with_bitsets(rules: &HashSet<Rule>, index: &Index) -> HashMap<Vec<u32>, usize> {
+    let mut itemsets: HashSet<Vec<u32>> = HashSet::new();
+    for rule in rules.iter() {
+        let union = union(&rule.antecedent, &rule.consequent);
+        for mut items in [rule.antecedent.clone(), rule.consequent.clone(), union] {
+            items.sort();
+            itemsets.insert(items);
+        }
     }
+    let itemsets: Vec<Vec<u32>> = itemsets.into_iter().collect();
+    let mut items: Vec<u32> = itemsets.iter().flat_map(|itemset| itemset.iter().cloned()).collect();
+    items.sort();
+    items.dedup();
+    let bitsets = BitsetCounts::new(index, &items);
+    debug!(
+        "Counting {} itemsets in bitsets of {} items, using {:.1} MB",
+        itemsets.len(),
+        items.len(),
+        bitsets.bytes() as f64 / (1 << 20) as f64
+    );
+    let counts = bitsets.count_all(&itemsets);
+    itemsets.into_iter().zip(counts).collect()
+}
+
+// Family-Wise with Bonfronni correction: keeps the rules whose antecedent and
+// consequent are significantly associated, correcting for the number of rules
+// with the same consequent.
+pub fn family_wise_filter(
+    rules: HashSet<Rule>,
+    index: &Index,
+    significance_test: &SignificanceTest,
+    counting: Counting,
+) -> HashSet<Rule> {
+    // Count number of rules generated with the same consequent.
+    let mut rule_counts: HashMap<u32, u32> = HashMap::new();
+    for rule in rules.iter() {
+        assert_eq!(rule.consequent.len(), 1);
+        *rule_counts.entry(rule.consequent[0]).or_insert(0) += 1;
+    }
+
+    // For each rule, calculate the p-value for association between antecedent
+    // and consequent, and keep those with p-value less than significance
+    // divided by the number of rules with the same consequent. Record both
+    // on the rule, so they can be written out. Rules are tested in parallel,
+    // sharing the itemset counts and p-values found so far.
+    let cache = SupportCache::new(index);
+    let counts = match counting {
+        Counting::Bitsets => Some(count_with_bitsets(&rules, index)),
+        Counting::TidLists => None,
+    };
+    let count = |items: &[u32]| -> usize {
+        match counts {
+            Some(ref counts) => {
+                let mut key = items.to_vec();
+                key.sort();
+                counts[&key]
+            }
+            None => cache.count(items),
+        }
+    };
+    let pvalues = PValueCache::new(significance_test);
+    let filtered: HashSet<Rule> = rules
+        .into_par_iter()
+        .filter_map(|mut rule| {
+            let table = Contingency::new(
+                index.num_transactions() as u32,
+                count(&rule.antecedent) as u32,
+                count(&rule.consequent) as u32,
+                count(&union(&rule.antecedent, &rule.consequent)) as u32,
+            );
+            let pv = pvalues.pval(&table);
+            let threshold = 0.05 / (rule_counts[&rule.consequent[0]] as f64);
+            rule.p_value = Some(OrderedFloat::from(pv));
+            rule.p_value_threshold = Some(OrderedFloat::from(threshold));
+            if pv < threshold {
+                Some(rule)
+            } else {
+                None
+            }
+        })
+        .collect();
 
-    family_wise_filtered_rules
+    let (counted, hits) = cache.stats();
+    debug!("Family wise filtering counted {} itemsets, and reused counts {} times", counted, hits);
+    let (tested, hits) = pvalues.stats();
+    debug!("Family wise filtering tested {} contingency tables, and reused p-values {} times", tested, hits);
+    debug!("After family wise filtering, {} rules remain", filtered.len());
+    filtered
+}
+
+// How many random swaps are tried to move an item which was shuffled into a
+// transaction already containing it.
+const MAX_SWAP_ATTEMPTS: usize = 1000;
+
+// Returns a copy of the transactions in `index` with their items shuffled
+// between them, so any association between items is by chance. A transaction
+// with weight w is shuffled as w separate transactions. Each keeps its length,
+// and each item its count, except where an item lands in a transaction twice
+// and no swap with another transaction fixes it, when it's only counted once.
+pub fn generate_random_dataset<R: Rng>(index: &Index, rng: &mut R) -> Index {
+    let mut transactions: Vec<Vec<u32>> = vec![vec![]; index.num_distinct_transactions()];
+    for item in 0..index.num_items() {
+        for &tid in index.tids(item as u32) {
+            transactions[tid].push(item as u32);
+        }
+    }
+
+    // The items of every copy of every transaction, and the range of them
+    // each copy has.
+    let mut items: Vec<u32> = vec![];
+    let mut ranges = vec![];
+    for (tid, transaction) in transactions.iter().enumerate() {
+        for _ in 0..index.weight(tid) {
+            let start = items.len();
+            items.extend_from_slice(transaction);
+            ranges.push(start..items.len());
+        }
+    }
+    drop(transactions);
+    let owners: Vec<usize> = ranges
+        .iter()
+        .enumerate()
+        .flat_map(|(copy, range)| std::iter::repeat_n(copy, range.len()))
+        .collect();
+    rng.shuffle(&mut items);
+
+    for position in 0..items.len() {
+        let range = ranges[owners[position]].clone();
+        if !items[range.start..position].contains(&items[position]) {
+            continue;
+        }
+        for _ in 0..MAX_SWAP_ATTEMPTS {
+            let other = rng.gen_range(0, items.len());
+            let other_range = ranges[owners[other]].clone();
+            if other_range != range
+                && !items[range.clone()].contains(&items[other])
+                && !items[other_range].contains(&items[position])
+            {
+                items.swap(position, other);
+                break;
+            }
+        }
+    }
+
+    let mut weights: HashMap<Vec<u32>, u32> = HashMap::new();
+    for range in ranges {
+        let mut transaction = items[range].to_vec();
+        transaction.sort();
+        transaction.dedup();
+        *weights.entry(transaction).or_insert(0) += 1;
+    }
+    // Insert in order, so a seeded rng gives the same index.
+    let mut random = Index::new();
+    for (transaction, weight) in weights.into_iter().sorted() {
+        random.insert(&transaction, weight);
+    }
+    random
+}
+
+// Removes the rules which also pass the confidence and lift thresholds in a
+// random shuffle of the transactions in `index`, as they'd be found even if
+// the items were unrelated. The shuffle is seeded, so the rules kept are the
+// same each run.
+pub fn permutation_filter(
+    rules: HashSet<Rule>,
+    index: &Index,
+    min_confidence: f64,
+    min_lift: f64,
+) -> HashSet<Rule> {
+    let mut rng = XorShiftRng::from_seed([0x2545_f491, 0x4f6c_dd1d, 0x9e37_79b9, 0x85eb_ca6b]);
+    let random = generate_random_dataset(index, &mut rng);
+    let filtered: HashSet<Rule> = rules
+        .into_par_iter()
+        .filter(|rule| {
+            let table = rule.contingency(&random);
+            table.ab == 0 || table.confidence() < min_confidence || table.lift() < min_lift
+        })
+        .collect();
+    debug!("After permutation filtering, {} rules remain", filtered.len());
+    filtered
+}
+
+// Removes redundant rules, which say no more than another rule which is at
+// least as good by `key`'s measure. A rule is redundant if such a rule
+// either:
+//  - has the same consequent and an antecedent which is a proper subset of
+//    its antecedent, so the rule's extra antecedent items add nothing, or
+//  - is made of the same items, with another of them as the consequent.
+//    Among rules of the same items equally good, the one whose consequent
+//    has the lowest id is kept, so which is kept is deterministic.
+// So what's left of each group of related rules is the most general of the
+// best of them. Redundancy is judged against all the rules passed in, so
+// the rules kept don't depend on the order they're checked in.
+pub fn redundancy_filter(rules: HashSet<Rule>, key: &RuleSortKey) -> HashSet<Rule> {
+    // Lower values are better, as when sorting.
+    let value = |rule: &Rule| OrderedFloat::from(sort_value(rule, key));
+    let mut by_antecedent: HashMap<(&[u32], u32), OrderedFloat<f64>> = HashMap::with_capacity(rules.len());
+    let mut best_of_items: HashMap<Vec<u32>, (OrderedFloat<f64>, u32)> = HashMap::new();
+    for rule in rules.iter() {
+        let consequent = rule.consequent[0];
+        by_antecedent.insert((&rule.antecedent, consequent), value(rule));
+        let best = best_of_items
+            .entry(union(&rule.antecedent, &rule.consequent))
+            .or_insert((value(rule), consequent));
+        *best = cmp::min(*best, (value(rule), consequent));
+    }
+
+    let redundant: HashSet<&Rule> = rules
+        .par_iter()
+        .filter(|rule| {
+            let consequent = rule.consequent[0];
+            let value = value(rule);
+            if best_of_items[&union(&rule.antecedent, &rule.consequent)] < (value, consequent) {
+                return true;
+            }
+            // The antecedent is sorted, so its combinations are too.
+            (1..rule.antecedent.len()).any(|len| {
+                rule.antecedent.iter().cloned().combinations(len).any(|subset| {
+                    by_antecedent
+                        .get(&(subset.as_slice(), consequent))
+                        .is_some_and(|&other| other <= value)
+                })
+            })
+        })
+        .collect();
+    let redundant: HashSet<Rule> = redundant.into_iter().cloned().collect();
+    let filtered: HashSet<Rule> = rules.into_iter().filter(|rule| !redundant.contains(rule)).collect();
+    debug!("After redundancy filtering, {} rules remain", filtered.len());
+    filtered
 }
 
 #[cfg(test)]
 mod tests {
     #[test]
     fn test_index() {
+        use command_line_args::RuleSortKey;
+        use super::Rule;
         use index::Index;
+        use significance::SignificanceTest;
         use super::ItemSet;
         use super::Itemizer;
-        use std::collections::HashMap;
+        use std::collections::{HashMap, HashSet};
 
         // HARM's census2.csv test dataset.
 
@@ -288,7 +1061,7 @@ mod tests {
         let mut itemizer: Itemizer = Itemizer::new();
         for line in &transactions {
             let transaction = line.iter().map(|s| itemizer.id_of(s)).collect::<Vec<u32>>();
-            index.insert(&transaction);
+            index.insert(&transaction, 1);
         }
 
         let itemsets = [
@@ -329,50 +1102,53 @@ mod tests {
             })
             .collect::<Vec<ItemSet>>();
 
-        let rules = super::generate_rules(&itemsets, transactions.len() as u32, 0.05, 1.0);
+        // Treat every item as rare, so that each item is tried as a consequent.
+        let rare_items: HashSet<u32> = (1..itemizer.max_item_id() + 1).collect();
+
+        let rules = super::generate_rules(
+            &itemsets,
+            transactions.len() as u32,
+            0.05,
+            1.0,
+            &rare_items,
+            &index,
+            &SignificanceTest::fisher(),
+            true,
+            1,
+            None,
+            &HashSet::new(),
+        );
 
         let mut expected_rules: HashMap<&str, u32> = [
             ("a ==> b", 0),
-            ("a ==> b e", 0),
             ("a ==> e", 0),
             ("a b ==> e", 0),
             ("a c ==> b", 0),
             ("a e ==> b", 0),
             ("b ==> a", 0),
-            ("b ==> a e", 0),
             ("b ==> c", 0),
-            ("b ==> c d", 0),
             ("b c ==> d", 0),
             ("b c ==> f", 0),
             ("b d ==> c", 0),
             ("b e ==> a", 0),
             ("b f ==> c", 0),
             ("c ==> b", 0),
-            ("c ==> b d", 0),
             ("c ==> f", 0),
-            ("c ==> f g", 0),
             ("c d ==> b", 0),
             ("c f ==> g", 0),
             ("c g ==> f", 0),
-            ("d ==> b c", 0),
             ("d ==> e", 0),
-            ("d ==> e g", 0),
             ("d ==> g", 0),
             ("d e ==> g", 0),
             ("d g ==> e", 0),
             ("e ==> a", 0),
-            ("e ==> a b", 0),
             ("e ==> d", 0),
-            ("e ==> d g", 0),
             ("e ==> g", 0),
             ("e g ==> d", 0),
             ("f ==> c", 0),
-            ("f ==> c g", 0),
             ("f ==> g", 0),
             ("f g ==> c", 0),
-            ("g ==> c f", 0),
             ("g ==> d", 0),
-            ("g ==> d e", 0),
             ("g ==> e", 0),
             ("g ==> f", 0),
         ].iter()
@@ -380,7 +1156,7 @@ mod tests {
             .collect();
 
         for rule_str in rules.iter().map(|r| r.to_string(&itemizer)) {
-            assert_eq!(expected_rules.contains_key::<str>(&rule_str), true);
+            assert!(expected_rules.contains_key::<str>(&rule_str));
             if let Some(count) = expected_rules.get_mut::<str>(&rule_str) {
                 *count += 1;
             }
@@ -389,5 +1165,294 @@ mod tests {
         for count in expected_rules.values() {
             assert_eq!(*count, 1);
         }
+
+        // Generating the rules as the itemsets arrive finds the same rules,
+        // whatever order they arrive in.
+        let no_required_items: HashSet<u32> = HashSet::new();
+        let mut generator = super::RuleGenerator::new(
+            transactions.len() as u32,
+            0.05,
+            1.0,
+            &rare_items,
+            &index,
+            1,
+            None,
+            &no_required_items,
+        );
+        for itemset in itemsets.iter().rev() {
+            generator.add(itemset);
+        }
+        assert_eq!(generator.finish(), rules);
+
+        // Restricting the antecedent length should leave only the rules with
+        // two items in the antecedent.
+        let rules = super::generate_rules(
+            &itemsets,
+            transactions.len() as u32,
+            0.05,
+            1.0,
+            &rare_items,
+            &index,
+            &SignificanceTest::fisher(),
+            true,
+            2,
+            Some(2),
+            &HashSet::new(),
+        );
+        let expected_len = expected_rules
+            .keys()
+            .filter(|r| r.split(" ==> ").next().unwrap().split(' ').count() == 2)
+            .count();
+        assert_eq!(rules.len(), expected_len);
+        assert!(rules.iter().all(|r| r.antecedent().len() == 2));
+
+        // Sorting gives a total order, with ties broken by the rule's text.
+        let mut rules: Vec<Rule> = rules.into_iter().collect();
+        super::sort_rules(&mut rules, &RuleSortKey::Lift, &itemizer);
+        for pair in rules.windows(2) {
+            assert!(
+                pair[0].lift() > pair[1].lift()
+                    || (pair[0].lift() == pair[1].lift()
+                        && pair[0].to_string(&itemizer) < pair[1].to_string(&itemizer))
+            );
+        }
+        super::sort_rules(&mut rules, &RuleSortKey::Lexicographic, &itemizer);
+        let strings: Vec<String> = rules.iter().map(|r| r.to_string(&itemizer)).collect();
+        let mut sorted = strings.clone();
+        sorted.sort();
+        assert_eq!(strings, sorted);
+    }
+
+    #[test]
+    fn test_permutation_filter() {
+        use super::{generate_random_dataset, permutation_filter, Rule};
+        use index::Index;
+        use rand::{SeedableRng, XorShiftRng};
+        use std::collections::HashSet;
+
+        // Item 1 is in every transaction, so "2 ==> 1" holds in any shuffle,
+        // while 2 and 3 occur only together.
+        let mut index = Index::new();
+        index.insert(&[1, 2, 3], 1);
+        for i in 0..20 {
+            index.insert(&[1, 4 + i], 1);
+            index.insert(&[1, 4 + i], 2);
+        }
+        index.insert(&[1, 2, 3], 2);
+
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let random = generate_random_dataset(&index, &mut rng);
+        assert_eq!(random.num_transactions(), index.num_transactions());
+        for item in 1..24 {
+            assert_eq!(random.count(&[item]), index.count(&[item]));
+        }
+
+        let rules: HashSet<Rule> = vec![
+            Rule::new(vec![2], vec![1], 1.0, 1.0, 3.0 / 63.0),
+            Rule::new(vec![2], vec![3], 1.0, 21.0, 3.0 / 63.0),
+        ]
+        .into_iter()
+        .collect();
+        let filtered = permutation_filter(rules, &index, 0.5, 1.0);
+        let expected: HashSet<Rule> = vec![Rule::new(vec![2], vec![3], 1.0, 21.0, 3.0 / 63.0)]
+            .into_iter()
+            .collect();
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_redundancy_filter() {
+        use super::{redundancy_filter, Rule};
+        use command_line_args::RuleSortKey;
+        use std::collections::HashSet;
+
+        let rules = || -> HashSet<Rule> {
+            vec![
+                Rule::new(vec![1], vec![3], 0.8, 4.0, 0.1),
+                // No better than "1 ==> 3", which is more general.
+                Rule::new(vec![1, 2], vec![3], 0.8, 5.0, 0.05),
+                // More confident than any more general rule.
+                Rule::new(vec![1, 4], vec![3], 0.9, 4.5, 0.05),
+                // Less confident than "1 ==> 3", of the same items.
+                Rule::new(vec![3], vec![1], 0.6, 4.0, 0.1),
+                // Equally good rules of the same items; the one with the
+                // lower consequent is kept.
+                Rule::new(vec![5], vec![6], 0.7, 2.0, 0.2),
+                Rule::new(vec![6], vec![5], 0.7, 2.0, 0.2),
+            ]
+            .into_iter()
+            .collect()
+        };
+        let to_strings = |rules: HashSet<Rule>| -> Vec<String> {
+            let mut strings: Vec<String> = rules
+                .iter()
+                .map(|rule| format!("{:?} ==> {:?}", rule.antecedent(), rule.consequent()))
+                .collect();
+            strings.sort();
+            strings
+        };
+
+        assert_eq!(
+            to_strings(redundancy_filter(rules(), &RuleSortKey::Confidence)),
+            vec!["[1, 4] ==> [3]", "[1] ==> [3]", "[6] ==> [5]"]
+        );
+        // By lift, "1 2 ==> 3" improves on "1 ==> 3", and "3 ==> 1" is as
+        // good as it, with a lower consequent, so is kept instead.
+        assert_eq!(
+            to_strings(redundancy_filter(rules(), &RuleSortKey::Lift)),
+            vec!["[1, 2] ==> [3]", "[1, 4] ==> [3]", "[3] ==> [1]", "[6] ==> [5]"]
+        );
+    }
+
+    #[test]
+    fn test_group_by_consequent() {
+        use super::{group_by_consequent, Rule};
+        use index::Index;
+
+        let mut index = Index::new();
+        index.insert(&[1, 3], 2);
+        index.insert(&[2, 3], 1);
+        index.insert(&[2, 4], 1);
+        index.insert(&[5], 6);
+
+        let mut rules = vec![
+            Rule::new(vec![1], vec![3], 1.0, 3.33, 0.2),
+            Rule::new(vec![2], vec![4], 0.5, 5.0, 0.1),
+            Rule::new(vec![2], vec![3], 0.5, 1.67, 0.1),
+        ];
+        let groups = group_by_consequent(&mut rules, &index);
+        let order: Vec<(&[u32], &[u32])> =
+            rules.iter().map(|rule| (rule.antecedent(), rule.consequent())).collect();
+        assert_eq!(order, vec![(&[1][..], &[3][..]), (&[2], &[3]), (&[2], &[4])]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].consequent, vec![3]);
+        assert_eq!(groups[0].num_rules, 2);
+        assert_eq!(groups[0].best_lift, 3.33);
+        // Items 1 and 2 are in 4 of the 10 transactions.
+        assert!((groups[0].coverage - 0.4).abs() < 1e-9);
+        assert_eq!(groups[1].consequent, vec![4]);
+        assert_eq!(groups[1].num_rules, 1);
+        assert!((groups[1].coverage - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_select_rules() {
+        use super::{select_rules, Rule};
+        use command_line_args::{RuleSelection, RuleSortKey};
+        use index::Index;
+
+        let mut index = Index::new();
+        index.insert(&[1, 9], 3);
+        index.insert(&[2, 9], 2);
+        index.insert(&[1, 2, 9], 1);
+        index.insert(&[3, 9], 1);
+        index.insert(&[3, 8], 1);
+
+        let rules = vec![
+            Rule::new(vec![1, 2], vec![9], 1.0, 1.1, 0.1),
+            Rule::new(vec![1], vec![9], 1.0, 1.1, 0.5),
+            Rule::new(vec![3], vec![8], 0.5, 4.0, 0.1),
+            Rule::new(vec![2], vec![9], 1.0, 1.1, 0.4),
+            Rule::new(vec![3], vec![9], 0.5, 0.6, 0.1),
+        ];
+        let kept = |max_rules: usize, selection: &RuleSelection| -> Vec<(Vec<u32>, Vec<u32>)> {
+            select_rules(rules.clone(), max_rules, selection, &index)
+                .iter()
+                .map(|rule| (rule.antecedent().to_vec(), rule.consequent().to_vec()))
+                .collect()
+        };
+
+        // Item 1 covers 4 of the transactions with item 9, then item 2 the 2
+        // more, then item 3 the last, and items 1 and 2 together cover none
+        // which aren't covered already.
+        assert_eq!(
+            kept(2, &RuleSelection::Coverage),
+            vec![(vec![1], vec![9]), (vec![3], vec![8]), (vec![2], vec![9])]
+        );
+        assert_eq!(
+            kept(5, &RuleSelection::Coverage),
+            vec![(vec![1], vec![9]), (vec![3], vec![8]), (vec![2], vec![9]), (vec![3], vec![9])]
+        );
+        // By lift, the first of the rules tied for the best are kept.
+        assert_eq!(
+            kept(2, &RuleSelection::Best(RuleSortKey::Lift)),
+            vec![(vec![1, 2], vec![9]), (vec![1], vec![9]), (vec![3], vec![8])]
+        );
+    }
+
+    #[test]
+    fn test_imbalance() {
+        use super::Rule;
+        use index::Index;
+
+        // Item 1 is in 8 of 10 transactions, item 2 in 2, and both together
+        // in 1.
+        let mut index = Index::new();
+        index.insert(&[1, 2], 1);
+        index.insert(&[1], 7);
+        index.insert(&[2], 1);
+        index.insert(&[3], 1);
+
+        let mut rule = Rule::new(vec![1], vec![2], 0.125, 0.625, 0.1);
+        assert!(rule.imbalance().is_none());
+        rule.calculate_imbalance(&rule.contingency(&index));
+        let imbalance = rule.imbalance().unwrap();
+        assert_eq!(imbalance.ratio(), 6.0 / 9.0);
+        assert_eq!(imbalance.cross_support_ratio(), 4.0);
+
+        let mut rule = Rule::new(vec![2], vec![1], 0.5, 0.625, 0.1);
+        rule.calculate_imbalance(&rule.contingency(&index));
+        assert_eq!(rule.imbalance().unwrap().ratio(), 6.0 / 9.0);
+        assert_eq!(rule.imbalance().unwrap().cross_support_ratio(), 0.25);
+    }
+
+    #[test]
+    fn test_rule_from_counts() {
+        use super::Rule;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let ids = itemizer.to_id_vec(&["milk", "bread", "eggs"]);
+        // Milk and bread are in 40 of 100 transactions, eggs in 20, and all
+        // three together in 10.
+        let rule = Rule::from_counts(vec![ids[1], ids[0]], vec![ids[2]], 40, 20, 10, 100).unwrap();
+        assert_eq!(rule.antecedent(), &[ids[0], ids[1]][..]);
+        assert_eq!(rule.consequent(), &[ids[2]][..]);
+        assert_eq!(rule.antecedent_names(&itemizer), vec!["bread", "milk"]);
+        assert_eq!(rule.consequent_names(&itemizer), vec!["eggs"]);
+        assert_eq!(rule.confidence(), 0.25);
+        assert_eq!(rule.lift(), 1.25);
+        assert_eq!(rule.support(), 0.1);
+
+        assert!(Rule::from_counts(vec![], vec![ids[2]], 40, 20, 10, 100).is_none());
+        assert!(Rule::from_counts(vec![ids[0]], vec![ids[0]], 40, 40, 40, 100).is_none());
+        assert!(Rule::from_counts(vec![ids[0]], vec![ids[2]], 40, 20, 30, 100).is_none());
+        assert!(Rule::from_counts(vec![ids[0]], vec![ids[2]], 0, 20, 0, 100).is_none());
+        assert!(Rule::from_counts(vec![ids[0]], vec![ids[2]], 140, 20, 10, 100).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use super::Rule;
+        use metrics::Metric;
+        use serde_json;
+        use significance::Contingency;
+
+        let mut rule = Rule::new(vec![1, 3], vec![2], 0.125, 0.625, 0.1);
+        let table = Contingency::new(10, 2, 8, 1);
+        rule.calculate_odds_ratio(&table);
+        rule.calculate_imbalance(&table);
+        rule.calculate_metrics(&table, &Metric::all());
+        rule.calculate_bootstrap_intervals(&table, 50);
+
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(json["antecedent"], json!([1, 3]));
+        // Metrics are named, rather than numbered by their place in MEASURES.
+        assert_eq!(json["metrics"][0][0], "kulc");
+        let round_trip: Rule = serde_json::from_value(json).unwrap();
+        assert_eq!(format!("{:?}", round_trip), format!("{:?}", rule));
+
+        assert!(serde_json::from_str::<Metric>("\"unknown\"").is_err());
     }
 }
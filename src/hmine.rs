@@ -0,0 +1,158 @@
+// H-mine (Pei et al., 2001) mines itemsets from the transactions themselves,
+// stored as arrays, rather than from a tree. The projected database of an
+// itemset is a queue of links to where its last item occurs in the
+// transactions containing it; the items before that position are the ones
+// the itemset can be extended by. Unlike conditional trees, the projections
+// are only links into the shared arrays, so memory stays small on sparse
+// data, where trees share few prefixes.
+//
+// The search is rip_growth's: items in each transaction are ordered by
+// decreasing count, as in the tree, and an itemset starts with a rare item
+// and is extended by items which come earlier, and which pass the test of
+// association with the itemset so far.
+
+use fptree::ItemSet;
+use index::Index;
+use rayon::prelude::*;
+use significance::{Contingency, SignificanceTest};
+use std::collections::{HashMap, HashSet};
+use transaction_reader::Transaction;
+
+// An occurrence of an itemset's last item: the index of the transaction, and
+// the item's position in it.
+type Link = (usize, usize);
+
+// Maps each item in a projected database to its count, and links to where it
+// occurs.
+type HeaderTable = HashMap<u32, (u32, Vec<Link>)>;
+
+// Builds the header table of the items before each link's position.
+fn make_header_table(transactions: &[Transaction], links: &[Link]) -> HeaderTable {
+    let mut header: HeaderTable = HashMap::new();
+    for &(t, position) in links {
+        let transaction = &transactions[t];
+        for (p, &item) in transaction.items[..position].iter().enumerate() {
+            let entry = header.entry(item).or_insert((0, vec![]));
+            entry.0 += transaction.weight;
+            entry.1.push((t, p));
+        }
+    }
+    header
+}
+
+#[allow(clippy::too_many_arguments)]
+fn grow(
+    itemset: &[u32],
+    itemset_count: u32,
+    links: &[Link],
+    transactions: &[Transaction],
+    index: &Index,
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+    itemsets: &mut Vec<ItemSet>,
+) {
+    let header = make_header_table(transactions, links);
+    // Itemsets grown from here are this itemset plus items from the header
+    // table, so if neither has a required item, none of them will.
+    if !required_items.is_empty()
+        && !itemset.iter().any(|item| required_items.contains(item))
+        && !header.keys().any(|item| required_items.contains(item))
+    {
+        return;
+    }
+
+    let n = index.num_transactions() as u32;
+    for (&item, &(count, ref item_links)) in header.iter() {
+        let a = index.count(&[item]) as u32;
+        if significance_test.pval(&Contingency::new(n, a, itemset_count, count)) >= 0.05 {
+            continue;
+        }
+        let mut grown: Vec<u32> = Vec::from(itemset);
+        grown.push(item);
+        if max_itemset_len.is_none_or(|max| grown.len() < max) {
+            grow(
+                &grown,
+                count,
+                item_links,
+                transactions,
+                index,
+                significance_test,
+                max_itemset_len,
+                required_items,
+                itemsets,
+            );
+        }
+        if required_items.is_empty() || grown.iter().any(|item| required_items.contains(item)) {
+            itemsets.push(ItemSet::new(grown, count));
+        }
+    }
+}
+
+// Mines `transactions`, which must have their items sorted in decreasing
+// order of count. Only transactions containing a rare item are needed.
+pub fn hmine(
+    transactions: &[Transaction],
+    index: &Index,
+    rare_items: &HashSet<u32>,
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+) -> Vec<ItemSet> {
+    let links: Vec<Link> = transactions
+        .iter()
+        .enumerate()
+        .map(|(t, transaction)| (t, transaction.items.len()))
+        .collect();
+    let header = make_header_table(transactions, &links);
+
+    header
+        .par_iter()
+        .filter(|&(item, _)| rare_items.contains(item))
+        .flat_map(|(&item, &(count, ref item_links))| -> Vec<ItemSet> {
+            let mut itemsets: Vec<ItemSet> = vec![];
+            if max_itemset_len.is_none_or(|max| max > 1) {
+                grow(
+                    &[item],
+                    count,
+                    item_links,
+                    transactions,
+                    index,
+                    significance_test,
+                    max_itemset_len,
+                    required_items,
+                    &mut itemsets,
+                );
+            }
+            if required_items.is_empty() || required_items.contains(&item) {
+                itemsets.push(ItemSet::new(vec![item], count));
+            }
+            itemsets
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_header_table() {
+        use super::make_header_table;
+        use transaction_reader::Transaction;
+
+        let transactions = vec![
+            Transaction {
+                items: vec![1, 2, 3],
+                weight: 2,
+            },
+            Transaction {
+                items: vec![1, 3],
+                weight: 1,
+            },
+        ];
+        // The projected database of item 3.
+        let header = make_header_table(&transactions, &[(0, 2), (1, 1)]);
+        assert_eq!(header.len(), 2);
+        assert_eq!(header[&1], (3, vec![(0, 0), (1, 0)]));
+        assert_eq!(header[&2], (2, vec![(0, 1)]));
+    }
+}
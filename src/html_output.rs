@@ -0,0 +1,262 @@
+// Writes a standalone HTML report of a run, for --output-html, which can be
+// opened in a browser and passed around without anything else: summary
+// statistics, bar charts of item frequencies drawn as inline SVG, the top
+// rules for each consequent, and the run's parameters, as in --report.
+
+use command_line_args::Arguments;
+use config::config_table;
+use generate_rules::Rule;
+use itemizer::Itemizer;
+use miner::MiningResult;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use toml;
+
+// The most rules shown for each consequent, and the most bars in a chart.
+const RULES_PER_CONSEQUENT: usize = 10;
+const CHART_BARS: usize = 20;
+
+const STYLE: &str = "
+    body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+    h1, h2, h3 { font-weight: normal; }
+    table { border-collapse: collapse; margin-bottom: 1.5em; }
+    th, td { padding: 0.25em 0.75em; border-bottom: 1px solid #ddd; text-align: left; }
+    td.number { text-align: right; font-variant-numeric: tabular-nums; }
+    .warning { background: #fff3cd; padding: 0.5em 1em; }
+    svg text { font-size: 12px; }
+";
+
+// Escapes text for HTML, in both element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_table<W: Write>(output: &mut W, headings: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    writeln!(output, "<table>")?;
+    write!(output, "<tr>")?;
+    for heading in headings {
+        write!(output, "<th>{}</th>", escape(heading))?;
+    }
+    writeln!(output, "</tr>")?;
+    for row in rows {
+        write!(output, "<tr>")?;
+        for cell in row {
+            // Cells are already escaped, so numbers can be told apart.
+            if cell.parse::<f64>().is_ok() {
+                write!(output, "<td class=\"number\">{}</td>", cell)?;
+            } else {
+                write!(output, "<td>{}</td>", cell)?;
+            }
+        }
+        writeln!(output, "</tr>")?;
+    }
+    writeln!(output, "</table>")
+}
+
+// Writes a horizontal bar chart of items' counts, with each bar labelled by
+// its count and the percentage of the transactions it is.
+fn write_bar_chart<W: Write>(
+    output: &mut W,
+    bars: &[(&str, u32)],
+    num_transactions: usize,
+) -> io::Result<()> {
+    const LABEL_WIDTH: usize = 220;
+    const BAR_WIDTH: usize = 380;
+    const ROW_HEIGHT: usize = 22;
+    let max_count = bars.iter().map(|&(_, count)| count).max().unwrap_or(0).max(1);
+    writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">",
+        LABEL_WIDTH + BAR_WIDTH + 120,
+        bars.len() * ROW_HEIGHT
+    )?;
+    for (row, &(item, count)) in bars.iter().enumerate() {
+        let y = row * ROW_HEIGHT;
+        let width = (count as f64 / max_count as f64 * BAR_WIDTH as f64).round().max(1.0);
+        let percentage = 100.0 * count as f64 / num_transactions.max(1) as f64;
+        writeln!(
+            output,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\
+             <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#4878a8\"><title>{}: {}</title></rect>\
+             <text x=\"{}\" y=\"{}\">{} ({:.1}%)</text>",
+            LABEL_WIDTH - 8,
+            y + 15,
+            escape(item),
+            LABEL_WIDTH,
+            y + 3,
+            width,
+            ROW_HEIGHT - 6,
+            escape(item),
+            count,
+            LABEL_WIDTH as f64 + width + 6.0,
+            y + 15,
+            count,
+            percentage
+        )?;
+    }
+    writeln!(output, "</svg>")
+}
+
+// Returns the items with the highest counts, ties broken by name.
+fn top_items<'a>(
+    items: &[u32],
+    item_count: &HashMap<u32, u32>,
+    itemizer: &'a Itemizer,
+) -> Vec<(&'a str, u32)> {
+    let mut bars: Vec<(&str, u32)> = items
+        .iter()
+        .filter_map(|item| item_count.get(item).map(|&count| (itemizer.str_of(*item), count)))
+        .collect();
+    bars.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    bars.truncate(CHART_BARS);
+    bars
+}
+
+// Returns the rules grouped by consequent, in the order of each
+// consequent's first rule.
+fn group_rules(rules: &[Rule]) -> Vec<(&[u32], Vec<&Rule>)> {
+    let mut positions: HashMap<&[u32], usize> = HashMap::new();
+    let mut groups: Vec<(&[u32], Vec<&Rule>)> = vec![];
+    for rule in rules {
+        let position = *positions.entry(rule.consequent()).or_insert_with(|| {
+            groups.push((rule.consequent(), vec![]));
+            groups.len() - 1
+        });
+        groups[position].1.push(rule);
+    }
+    groups
+}
+
+pub fn write_html<W: Write>(output: &mut W, result: &MiningResult, args: &Arguments) -> io::Result<()> {
+    let report = &result.report;
+    let itemizer = &result.itemizer;
+    writeln!(output, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(output, "<title>RIPTree report</title>\n<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(output, "<h1>RIPTree report</h1>")?;
+    if report.partial {
+        writeln!(
+            output,
+            "<p class=\"warning\">The run was interrupted, so these results are incomplete.</p>"
+        )?;
+    }
+
+    writeln!(output, "<h2>Summary</h2>")?;
+    let mut summary = vec![
+        vec![String::from("Transactions"), report.num_transactions.to_string()],
+        vec![String::from("Distinct transactions"), report.num_distinct_transactions.to_string()],
+        vec![String::from("Items"), report.num_items.to_string()],
+        vec![String::from("Rare items"), report.num_rare_items.to_string()],
+        vec![String::from("Itemsets"), report.num_itemsets.to_string()],
+        vec![String::from("Rules"), result.rules.len().to_string()],
+    ];
+    if let Some(cutoff) = report.rare_item_cutoff {
+        summary.insert(4, vec![String::from("Rare item cutoff"), cutoff.to_string()]);
+    }
+    write_table(output, &["Statistic", "Value"], &summary)?;
+    if !report.rule_counts.is_empty() {
+        writeln!(output, "<h3>Rules after each stage</h3>")?;
+        let rows: Vec<Vec<String>> = report
+            .rule_counts
+            .iter()
+            .map(|&(ref stage, count)| vec![escape(stage), count.to_string()])
+            .collect();
+        write_table(output, &["Stage", "Rules"], &rows)?;
+    }
+
+    writeln!(output, "<h2>Item frequencies</h2>")?;
+    let items: Vec<u32> = result.item_count.keys().cloned().collect();
+    writeln!(output, "<h3>Most frequent items</h3>")?;
+    write_bar_chart(output, &top_items(&items, &result.item_count, itemizer), report.num_transactions)?;
+    let groups = group_rules(&result.rules);
+    let mut consequent_items: Vec<u32> =
+        groups.iter().flat_map(|(consequent, _)| consequent.iter().cloned()).collect();
+    consequent_items.sort();
+    consequent_items.dedup();
+    if !consequent_items.is_empty() {
+        writeln!(output, "<h3>Items the rules predict</h3>")?;
+        let bars = top_items(&consequent_items, &result.item_count, itemizer);
+        write_bar_chart(output, &bars, report.num_transactions)?;
+    }
+
+    writeln!(output, "<h2>Top rules by consequent</h2>")?;
+    if groups.is_empty() {
+        writeln!(output, "<p>No rules were found.</p>")?;
+    }
+    for (consequent, rules) in groups.iter() {
+        let names: Vec<&str> = consequent.iter().map(|&item| itemizer.str_of(item)).collect();
+        writeln!(output, "<h3>{} ({} rules)</h3>", escape(&names.join(" ")), rules.len())?;
+        let rows: Vec<Vec<String>> = rules
+            .iter()
+            .take(RULES_PER_CONSEQUENT)
+            .map(|rule| {
+                vec![
+                    escape(&rule.antecedent_to_string(itemizer)),
+                    format!("{:.4}", rule.confidence()),
+                    format!("{:.4}", rule.lift()),
+                    format!("{:.4}", rule.support()),
+                    rule.p_value().map_or_else(String::new, |p_value| format!("{:.3e}", p_value)),
+                ]
+            })
+            .collect();
+        write_table(output, &["Antecedent", "Confidence", "Lift", "Support", "P-Value"], &rows)?;
+    }
+
+    writeln!(output, "<h2>Parameters</h2>")?;
+    let rows: Vec<Vec<String>> = config_table(args)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                toml::Value::String(s) => s,
+                value => value.to_string(),
+            };
+            vec![escape(&name), escape(&value)]
+        })
+        .collect();
+    write_table(output, &["Option", "Value"], &rows)?;
+    writeln!(output, "</body>\n</html>")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_write_html() {
+        use super::{escape, write_html};
+        use command_line_args::{Arguments, MaxSupportMode};
+        use miner::mine;
+
+        assert_eq!(escape("<a href=\"x\">&'</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;");
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        let mut output: Vec<u8> = vec![];
+        write_html(&mut output, &result, &args).unwrap();
+        let html = String::from_utf8(output).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>") && html.trim_end().ends_with("</html>"));
+        assert!(html.contains("<td>Transactions</td><td class=\"number\">101</td>"));
+        // A chart of the most frequent items, and one of the items predicted.
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(html.contains("<td>min-confidence</td><td class=\"number\">0.9</td>"));
+        // Each consequent has a heading and its first rule.
+        let rule = &result.rules[0];
+        let consequent = rule.consequent_to_string(&result.itemizer);
+        assert!(html.contains(&format!("<h3>{} (", consequent)));
+        assert!(html.contains(&format!("<td>{}</td>", rule.antecedent_to_string(&result.itemizer))));
+    }
+}
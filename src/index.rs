@@ -1,30 +1,344 @@
-#[cfg(test)]
 use itemizer::Itemizer;
+use mining_state::{read_u32, write_u32};
+use pair_counts::PairCounts;
+use sketch::CountMinSketch;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+// Index files, written by --save-index, start with the magic bytes "RIPI" and
+// a format version. Then come the item names, each a length followed by
+// UTF-8 bytes, as in mining state files, so an index can be loaded by a run
+// which numbers the items differently. Then the weight of each transaction,
+// and each item's tid list, as its length and the differences between
+// successive tids. Everything after the names is varint encoded, and as tids
+// are in increasing order the differences are mostly small.
+const MAGIC: &[u8; 4] = b"RIPI";
+const VERSION: u32 = 1;
+
+// Writes `value` 7 bits at a time, low bits first, with the top bit of each
+// byte set if more follow.
+fn write_varint<W: Write>(output: &mut W, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        output.write_all(&[(value as u8) | 0x80])?;
+        value >>= 7;
+    }
+    output.write_all(&[value as u8])
+}
+
+fn read_varint<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("Invalid varint in index file"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// When one tid list is this many times longer than the other, finding each of
+// the shorter list's tids in the longer by galloping search is faster than
+// merging them.
+const GALLOP_RATIO: usize = 32;
+
+// Intersects two sorted lists of transaction ids.
+pub fn intersect(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if short.len() * GALLOP_RATIO < long.len() {
+        return gallop_intersect(short, long);
+    }
+    let mut result = vec![];
+    let mut j = 0;
+    for &tid in short {
+        while j < long.len() && long[j] < tid {
+            j += 1;
+        }
+        if j == long.len() {
+            break;
+        }
+        if long[j] == tid {
+            result.push(tid);
+        }
+    }
+    result
+}
+
+// Intersects two sorted lists of transaction ids, searching `long` for each
+// of `short`'s tids with steps doubling from the last tid found, then a
+// binary search of the last step, so the cost grows with short's length
+// times the log of the gaps between its tids in `long`.
+pub fn gallop_intersect(short: &[usize], long: &[usize]) -> Vec<usize> {
+    let mut result = vec![];
+    let mut start = 0;
+    for &tid in short {
+        let mut step = 1;
+        while start + step < long.len() && long[start + step] < tid {
+            step *= 2;
+        }
+        let end = (start + step + 1).min(long.len());
+        match long[start..end].binary_search(&tid) {
+            Ok(i) => {
+                result.push(tid);
+                start += i + 1;
+            }
+            Err(i) => start += i,
+        }
+        if start == long.len() {
+            break;
+        }
+    }
+    result
+}
+
+// The probability that an approximate count is further above the true count
+// than the sketch's error bound.
+const SKETCH_DELTA: f64 = 0.01;
+
+// An approximate index, which counts itemsets of up to max_len items in a
+// Count-Min sketch rather than keeping tid lists. A longer itemset's count
+// is estimated as the least of its max_len item subsets' estimates, each of
+// which is at least the itemset's count, so counts are only ever
+// overestimated.
+struct Sketch {
+    counts: CountMinSketch,
+    max_len: usize,
+}
+
+// Calls `f` with each subset of `items` of `len` items.
+fn for_each_subset<F: FnMut(&[u32])>(items: &[u32], len: usize, f: &mut F) {
+    fn recurse<F: FnMut(&[u32])>(items: &[u32], len: usize, subset: &mut Vec<u32>, f: &mut F) {
+        if subset.len() == len {
+            f(subset);
+            return;
+        }
+        for i in 0..items.len() {
+            if items.len() - i < len - subset.len() {
+                break;
+            }
+            subset.push(items[i]);
+            recurse(&items[i + 1..], len, subset, f);
+            subset.pop();
+        }
+    }
+    recurse(items, len, &mut Vec::with_capacity(len), f);
+}
+
+// An index of the counts of a fixed set of itemsets, for --partition-size,
+// where they're the candidates found in some partition, along with their
+// subsets of two or more items, which rule generation counts too, e.g. as
+// antecedents. Itemsets are kept sorted, and found by their first item to
+// count those in each transaction inserted.
+struct Counted {
+    itemsets: Vec<Vec<u32>>,
+    counts: Vec<usize>,
+    positions: HashMap<Vec<u32>, usize>,
+    by_first_item: HashMap<u32, Vec<usize>>,
+}
+
+impl Counted {
+    fn new(itemsets: &[Vec<u32>]) -> Counted {
+        let mut counted = Counted {
+            itemsets: vec![],
+            counts: vec![],
+            positions: HashMap::new(),
+            by_first_item: HashMap::new(),
+        };
+        for itemset in itemsets {
+            let mut items = itemset.clone();
+            items.sort();
+            items.dedup();
+            for len in 2..=items.len() {
+                for_each_subset(&items, len, &mut |subset| {
+                    if counted.positions.contains_key(subset) {
+                        return;
+                    }
+                    let position = counted.itemsets.len();
+                    counted.itemsets.push(subset.to_vec());
+                    counted.counts.push(0);
+                    counted.positions.insert(subset.to_vec(), position);
+                    counted.by_first_item.entry(subset[0]).or_default().push(position);
+                });
+            }
+        }
+        counted
+    }
+
+    // Adds the weight of a transaction, sorted, to the itemsets it contains.
+    fn insert(&mut self, transaction: &[u32], weight: u32) {
+        for (i, item) in transaction.iter().enumerate() {
+            let positions = match self.by_first_item.get(item) {
+                Some(positions) => positions,
+                None => continue,
+            };
+            let rest = &transaction[i + 1..];
+            for &position in positions {
+                if self.itemsets[position][1..].iter().all(|item| rest.binary_search(item).is_ok()) {
+                    self.counts[position] += weight as usize;
+                }
+            }
+        }
+    }
+
+    fn count(&self, items: &[u32]) -> Option<usize> {
+        self.positions.get(items).map(|&position| self.counts[position])
+    }
+
+    fn len(&self) -> usize {
+        self.itemsets.len()
+    }
+}
 
 pub struct Index {
+    // Maps item id to the ids of the transactions containing that item.
     index: Vec<Vec<usize>>,
+    // Weight of each transaction, indexed by transaction id.
+    weights: Vec<u32>,
+    // Sum of the weights of the transactions containing each item.
+    item_counts: Vec<usize>,
     transaction_count: usize,
+    // If set, the index is approximate, and has no tid lists.
+    sketch: Option<Sketch>,
+    // If set, the index only counts these itemsets, and has no tid lists.
+    counted: Option<Counted>,
+    // If set, the counts of pairs of the most frequent items, which count
+    // those pairs without intersecting their tid lists.
+    pairs: Option<PairCounts>,
+}
+
+impl Default for Index {
+    fn default() -> Index {
+        Index::new()
+    }
 }
 
 impl Index {
     pub fn new() -> Index {
         Index {
             index: Vec::new(),
+            weights: Vec::new(),
+            item_counts: Vec::new(),
             transaction_count: 0,
+            sketch: None,
+            counted: None,
+            pairs: None,
+        }
+    }
+
+    // An approximate index, whose counts of itemsets are at most `epsilon`
+    // times the total weight of the itemsets of up to `max_len` items in the
+    // transactions inserted too high, with 99% probability. Single items are
+    // still counted exactly. It has no tid lists, so `tids` mustn't be used.
+    pub fn approximate(epsilon: f64, max_len: usize) -> Index {
+        Index {
+            sketch: Some(Sketch {
+                counts: CountMinSketch::new(epsilon, SKETCH_DELTA),
+                max_len,
+            }),
+            ..Index::new()
         }
     }
-    pub fn insert(&mut self, transaction: &[u32]) {
-        let tid = self.transaction_count;
-        self.transaction_count += 1;
+
+    // An index which only counts the given itemsets, and their subsets,
+    // exactly, for --partition-size. Single items are counted as usual, but
+    // there are no tid lists, so `tids` mustn't be used, and the count of an
+    // itemset not counted is estimated as the least count of its items,
+    // which is never too low.
+    pub fn counting(itemsets: &[Vec<u32>]) -> Index {
+        Index {
+            counted: Some(Counted::new(itemsets)),
+            ..Index::new()
+        }
+    }
+
+    // The number of itemsets counted by an index made by `counting`.
+    pub fn num_counted_itemsets(&self) -> Option<usize> {
+        self.counted.as_ref().map(Counted::len)
+    }
+
+    pub fn is_approximate(&self) -> bool {
+        self.sketch.is_some()
+    }
+
+    // The bytes used by the approximate index's sketch, and the bound on how
+    // far above their true values its counts are, if it's approximate.
+    pub fn sketch_stats(&self) -> Option<(usize, f64)> {
+        self.sketch
+            .as_ref()
+            .map(|sketch| (sketch.counts.bytes(), sketch.counts.error_bound()))
+    }
+
+    // Sets the pair counts, which must be of the same transactions as those
+    // inserted.
+    pub fn set_pair_counts(&mut self, pairs: PairCounts) {
+        self.pairs = Some(pairs);
+    }
+
+    // The count of transactions containing both items, if the pair was
+    // pre-counted.
+    pub fn pair_count(&self, a: u32, b: u32) -> Option<usize> {
+        self.pairs
+            .as_ref()
+            .and_then(|pairs| pairs.count(a, b))
+            .map(|count| count as usize)
+    }
+
+    // Inserts a transaction which counts as `weight` transactions.
+    pub fn insert(&mut self, transaction: &[u32], weight: u32) {
+        let tid = self.weights.len();
+        self.weights.push(weight);
+        self.transaction_count += weight as usize;
         for &item_id in transaction {
             let item_index = item_id as usize;
             while self.index.len() <= item_index {
                 self.index.push(vec![]);
+                self.item_counts.push(0);
+            }
+            if self.sketch.is_none() && self.counted.is_none() {
+                self.index[item_index].push(tid);
+            }
+            self.item_counts[item_index] += weight as usize;
+        }
+        if let Some(ref mut counted) = self.counted {
+            let mut items = transaction.to_vec();
+            items.sort();
+            items.dedup();
+            counted.insert(&items, weight);
+        }
+        if let Some(ref mut sketch) = self.sketch {
+            let mut items = transaction.to_vec();
+            items.sort();
+            for len in 2..=sketch.max_len.min(items.len()) {
+                for_each_subset(&items, len, &mut |subset| sketch.counts.add(subset, weight as u64));
             }
-            self.index[item_index].push(tid);
         }
     }
 
+    // Estimates the count of a set of two or more items from the sketch.
+    fn approximate_count(&self, sketch: &Sketch, transaction: &[u32]) -> usize {
+        let mut items = transaction.to_vec();
+        items.sort();
+        items.dedup();
+        let mut count = items
+            .iter()
+            .map(|item| self.item_counts[*item as usize] as u64)
+            .min()
+            .unwrap_or(0);
+        if items.len() > 1 {
+            for_each_subset(&items, sketch.max_len.min(items.len()), &mut |subset| {
+                count = count.min(sketch.counts.estimate(subset));
+            });
+        }
+        count as usize
+    }
+
+    // Returns the sum of the weights of the transactions containing all of
+    // the items in `transaction`.
     pub fn count(&self, transaction: &[u32]) -> usize {
         if transaction.is_empty() {
             return 0;
@@ -35,50 +349,192 @@ impl Index {
             if item_index >= self.index.len() {
                 return 0;
             }
-            return self.index[item_index].len();
+            return self.item_counts[item_index];
         }
 
-        let mut tid_lists: Vec<&Vec<usize>> = vec![];
-        for &item in transaction.iter() {
-            let item_index = item as usize;
-            tid_lists.push(&self.index[item_index]);
+        if transaction.iter().any(|&item| item as usize >= self.index.len()) {
+            return 0;
+        }
+        if transaction.len() == 2 {
+            if let Some(count) = self.pair_count(transaction[0], transaction[1]) {
+                return count;
+            }
+        }
+        if let Some(ref sketch) = self.sketch {
+            return self.approximate_count(sketch, transaction);
+        }
+        if let Some(ref counted) = self.counted {
+            let mut items = transaction.to_vec();
+            items.sort();
+            items.dedup();
+            return counted.count(&items).unwrap_or_else(|| {
+                items.iter().map(|item| self.item_counts[*item as usize]).min().unwrap_or(0)
+            });
         }
 
-        let mut p: Vec<usize> = vec![0; tid_lists.len()];
+        self.tids_of(transaction).iter().map(|&tid| self.weights[tid] as usize).sum()
+    }
 
-        // For each tid in the transaction's first item's list of tids.
-        let mut count = 0;
-        for &tid in tid_lists[0].iter() {
-            // Check whether all the other tid lists contain that tid.
-            let mut tid_in_all_item_tid_lists = true;
-            for i in 1..tid_lists.len() {
-                while p[i] < tid_lists[i].len() && tid_lists[i][p[i]] < tid {
-                    p[i] += 1;
-                }
-                if p[i] == tid_lists[i].len() || tid_lists[i][p[i]] != tid {
-                    // This tidlist doesn't include that tid. So this tid cannot
-                    // have all items in it.
-                    tid_in_all_item_tid_lists = false;
-                    break;
-                }
-            }
-            if tid_in_all_item_tid_lists {
-                count += 1
+    // Returns the ids of the transactions containing all of `items`, in
+    // increasing order. Approximate indexes have no tid lists, so can't.
+    pub fn tids_of(&self, items: &[u32]) -> Vec<usize> {
+        debug_assert!(
+            self.sketch.is_none() && self.counted.is_none(),
+            "An approximate or partitioned index has no tid lists"
+        );
+        if items.is_empty() || items.iter().any(|&item| item as usize >= self.index.len()) {
+            return vec![];
+        }
+        if items.len() == 1 {
+            return self.index[items[0] as usize].clone();
+        }
+        // Intersect the shortest tid lists first, so the running intersection
+        // is as short as it can be, and stop as soon as it's empty.
+        let mut tid_lists: Vec<&[usize]> = items.iter().map(|&item| &self.index[item as usize][..]).collect();
+        tid_lists.sort_by_key(|tids| tids.len());
+        let mut tids = intersect(tid_lists[0], tid_lists[1]);
+        for other in &tid_lists[2..] {
+            if tids.is_empty() {
+                break;
             }
+            tids = intersect(&tids, other);
         }
+        tids
+    }
 
-        count
+    // Returns the ids of the transactions containing `item`, in increasing
+    // order.
+    pub fn tids(&self, item: u32) -> &[usize] {
+        debug_assert!(
+            self.sketch.is_none() && self.counted.is_none(),
+            "An approximate or partitioned index has no tid lists"
+        );
+        match self.index.get(item as usize) {
+            Some(tids) => tids,
+            None => &[],
+        }
+    }
+
+    pub fn weight(&self, tid: usize) -> u32 {
+        self.weights[tid]
     }
 
-    #[allow(dead_code)]
     pub fn support(&self, transaction: &[u32]) -> f64 {
         let count = self.count(transaction);
         (count as f64) / (self.transaction_count as f64)
     }
 
+    // The number of transactions inserted, regardless of their weights.
+    pub fn num_distinct_transactions(&self) -> usize {
+        self.weights.len()
+    }
+
+    // One more than the largest item id inserted.
+    pub fn num_items(&self) -> usize {
+        self.index.len()
+    }
+
+    // Returns the sum of the weights of all transactions.
     pub fn num_transactions(&self) -> usize {
         self.transaction_count
     }
+
+    // Writes the index in the compact format described above, naming its
+    // items with `itemizer`.
+    pub fn write<W: Write>(&self, output: &mut W, itemizer: &Itemizer) -> io::Result<()> {
+        if self.is_approximate() || self.counted.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "An approximate or partitioned index can't be saved",
+            ));
+        }
+        output.write_all(MAGIC)?;
+        write_u32(output, VERSION)?;
+
+        let num_items = self.index.len().saturating_sub(1);
+        write_u32(output, num_items as u32)?;
+        for id in 1..=num_items as u32 {
+            let name = itemizer.str_of(id);
+            write_u32(output, name.len() as u32)?;
+            output.write_all(name.as_bytes())?;
+        }
+
+        write_varint(output, self.weights.len() as u64)?;
+        for &weight in self.weights.iter() {
+            write_varint(output, weight as u64)?;
+        }
+        for tids in self.index.iter().skip(1) {
+            write_varint(output, tids.len() as u64)?;
+            let mut previous = 0;
+            for &tid in tids.iter() {
+                write_varint(output, (tid - previous) as u64)?;
+                previous = tid;
+            }
+        }
+        Ok(())
+    }
+
+    // Reads an index written by `write`, numbering its items with `itemizer`.
+    // Lengths read from the file are only trusted as far as the data behind
+    // them, so a corrupt file is an error rather than a huge allocation.
+    pub fn read<R: Read>(input: &mut R, itemizer: &mut Itemizer) -> io::Result<Index> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a riptree index file"));
+        }
+        if read_u32(input)? != VERSION {
+            return Err(invalid_data("Unsupported riptree index file version"));
+        }
+
+        let num_items = read_u32(input)?;
+        let mut ids = vec![];
+        for _ in 0..num_items {
+            let len = read_u32(input)? as u64;
+            let mut name = vec![];
+            if input.by_ref().take(len).read_to_end(&mut name)? as u64 != len {
+                return Err(invalid_data("Truncated item name in index file"));
+            }
+            let name = String::from_utf8(name).map_err(|_| invalid_data("Invalid item name"))?;
+            ids.push(itemizer.try_id_of(&name)?);
+        }
+
+        let mut index = Index::new();
+        let num_distinct_transactions = read_varint(input)? as usize;
+        for _ in 0..num_distinct_transactions {
+            let weight = read_varint(input)?;
+            if weight > u32::MAX as u64 {
+                return Err(invalid_data("Invalid transaction weight"));
+            }
+            index.weights.push(weight as u32);
+            index.transaction_count += weight as usize;
+        }
+        let max_id = ids.iter().cloned().max().unwrap_or(0) as usize;
+        index.index = vec![vec![]; max_id + 1];
+        index.item_counts = vec![0; max_id + 1];
+        for id in ids {
+            // Tids are distinct, so there can't be more than there are
+            // transactions.
+            let len = read_varint(input)?;
+            if len > num_distinct_transactions as u64 {
+                return Err(invalid_data("Invalid tid list length in index file"));
+            }
+            let mut tids = Vec::with_capacity(len as usize);
+            let mut tid = 0;
+            let mut count = 0;
+            for _ in 0..len {
+                tid += read_varint(input)? as usize;
+                if tid >= num_distinct_transactions || tids.last().is_some_and(|&last| last >= tid) {
+                    return Err(invalid_data("Invalid transaction id in index file"));
+                }
+                tids.push(tid);
+                count += index.weights[tid] as usize;
+            }
+            index.index[id as usize] = tids;
+            index.item_counts[id as usize] = count;
+        }
+        Ok(index)
+    }
 }
 
 #[cfg(test)]
@@ -102,33 +558,200 @@ mod tests {
             let transaction = line.iter()
                 .map(|s| itemizer.id_of(s.trim()))
                 .collect::<Vec<u32>>();
-            index.insert(&transaction);
-        }
-
-        assert_eq!(index.support(&vec![itemizer.id_of("a")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("b")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("c")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("d")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("e")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("f")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("h")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("i")]), 2.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("j")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("k")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("l")]), 1.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("z")]), 4.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("x")]), 4.0 / 6.0);
-        assert_eq!(index.support(&vec![itemizer.id_of("y")]), 2.0 / 6.0);
+            index.insert(&transaction, 1);
+        }
+
+        assert_eq!(index.support(&[itemizer.id_of("a")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("b")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("c")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("d")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("e")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("f")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("h")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("i")]), 2.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("j")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("k")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("l")]), 1.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("z")]), 4.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("x")]), 4.0 / 6.0);
+        assert_eq!(index.support(&[itemizer.id_of("y")]), 2.0 / 6.0);
         assert_eq!(
-            index.support(&vec![itemizer.id_of("x"), itemizer.id_of("z")]),
+            index.support(&[itemizer.id_of("x"), itemizer.id_of("z")]),
             4.0 / 6.0
         );
         assert!(
-            index.support(&vec![
+            index.support(&[
                 itemizer.id_of("x"),
                 itemizer.id_of("y"),
                 itemizer.id_of("z"),
             ]) == 2.0 / 6.0
         );
     }
+
+    #[test]
+    fn test_weighted_index() {
+        use super::Index;
+
+        let mut index = Index::new();
+        index.insert(&[1, 2], 3);
+        index.insert(&[1], 2);
+        index.insert(&[2, 3], 1);
+
+        assert_eq!(index.num_transactions(), 6);
+        assert_eq!(index.count(&[1]), 5);
+        assert_eq!(index.count(&[2]), 4);
+        assert_eq!(index.count(&[1, 2]), 3);
+        assert_eq!(index.count(&[2, 3]), 1);
+        assert_eq!(index.support(&[1, 2]), 0.5);
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        use super::Index;
+        use super::Itemizer;
+        use std::io;
+
+        let mut itemizer = Itemizer::new();
+        let (a, b, c) = (itemizer.id_of("a"), itemizer.id_of("b"), itemizer.id_of("c"));
+        let mut index = Index::new();
+        for _ in 0..200 {
+            index.insert(&[a, b], 1);
+        }
+        index.insert(&[b, c], 300);
+        let mut buffer: Vec<u8> = vec![];
+        index.write(&mut buffer, &itemizer).unwrap();
+
+        // Read by a run which numbers the items differently.
+        let mut other = Itemizer::new();
+        let (c, b) = (other.id_of("c"), other.id_of("b"));
+        let read = Index::read(&mut &buffer[..], &mut other).unwrap();
+        let a = other.get_id("a").unwrap();
+        assert_eq!(read.num_transactions(), 500);
+        assert_eq!(read.num_distinct_transactions(), 201);
+        assert_eq!(read.count(&[a, b]), 200);
+        assert_eq!(read.count(&[b]), 500);
+        assert_eq!(read.count(&[c]), 300);
+        assert_eq!(read.tids(c), &[200]);
+
+        assert!(Index::read(&mut &buffer[..buffer.len() - 1], &mut Itemizer::new()).is_err());
+        assert!(Index::read(&mut &b"RIPS"[..], &mut Itemizer::new()).is_err());
+
+        // Huge lengths in a corrupt file are errors, not allocations. The
+        // names start after the magic, version and number of items.
+        let mut huge_name = buffer.clone();
+        huge_name[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = Index::read(&mut &huge_name[..], &mut Itemizer::new()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut many_names = buffer[..8].to_vec();
+        many_names.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Index::read(&mut &many_names[..], &mut Itemizer::new()).is_err());
+        // One transaction, and then a tid list claiming 2^62 tids.
+        let mut huge_tids = buffer[..8].to_vec();
+        huge_tids.extend_from_slice(&1u32.to_le_bytes());
+        huge_tids.extend_from_slice(&1u32.to_le_bytes());
+        huge_tids.extend_from_slice(b"a");
+        huge_tids.extend_from_slice(&[1, 1]);
+        super::write_varint(&mut huge_tids, 1 << 62).unwrap();
+        let err = Index::read(&mut &huge_tids[..], &mut Itemizer::new()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_intersect() {
+        use super::{gallop_intersect, intersect};
+
+        let long: Vec<usize> = (0..1000).filter(|tid| tid % 3 == 0).collect();
+        let short = vec![0, 1, 3, 298, 300, 301, 999, 1200];
+        let expected = vec![0, 3, 300, 999];
+        assert_eq!(gallop_intersect(&short, &long), expected);
+        assert_eq!(intersect(&short, &long), expected);
+        assert_eq!(intersect(&long, &short), expected);
+        assert_eq!(intersect(&[2, 3, 4, 5], &[1, 3, 5, 7]), vec![3, 5]);
+        assert!(gallop_intersect(&[], &long).is_empty());
+        assert!(gallop_intersect(&short, &[]).is_empty());
+
+        let evens: Vec<usize> = (0..1000).filter(|tid| tid % 2 == 0).collect();
+        let merged = intersect(&evens, &long);
+        assert_eq!(merged, (0..1000).filter(|tid| tid % 6 == 0).collect::<Vec<usize>>());
+        assert_eq!(gallop_intersect(&evens, &long), merged);
+    }
+
+    #[test]
+    fn test_pair_counts() {
+        use super::Index;
+        use pair_counts::PairCounts;
+        use std::collections::HashMap;
+
+        let transactions: Vec<Vec<u32>> = vec![vec![1, 2, 3], vec![2, 3], vec![1, 3, 4], vec![3]];
+        let mut index = Index::new();
+        let mut item_count: HashMap<u32, u32> = HashMap::new();
+        for transaction in transactions.iter() {
+            index.insert(transaction, 2);
+            for &item in transaction {
+                *item_count.entry(item).or_insert(0) += 2;
+            }
+        }
+        let pairs_of = [[1, 2], [1, 3], [2, 3], [3, 4]];
+        let expected: Vec<usize> = pairs_of.iter().map(|pair| index.count(pair)).collect();
+        let mut pairs = PairCounts::new(&item_count, 3);
+        for transaction in transactions.iter() {
+            pairs.insert(transaction, 2);
+        }
+        index.set_pair_counts(pairs);
+        assert_eq!(index.pair_count(3, 1), Some(4));
+        assert_eq!(index.pair_count(3, 4), None);
+        let counted: Vec<usize> = pairs_of.iter().map(|pair| index.count(pair)).collect();
+        assert_eq!(counted, expected);
+    }
+
+    #[test]
+    fn test_approximate_index() {
+        use super::Index;
+
+        let mut exact = Index::new();
+        let mut approximate = Index::approximate(0.001, 2);
+        for i in 0..100u32 {
+            let transaction = [i % 10 + 1, i % 7 + 20, i % 3 + 40];
+            exact.insert(&transaction, i % 4 + 1);
+            approximate.insert(&transaction, i % 4 + 1);
+        }
+        assert!(approximate.is_approximate());
+        let (_, bound) = approximate.sketch_stats().unwrap();
+        for itemset in [vec![1], vec![1, 20], vec![20, 40], vec![1, 2], vec![3, 41]] {
+            let (count, estimate) = (exact.count(&itemset), approximate.count(&itemset));
+            assert!(estimate >= count);
+            assert!((estimate - count) as f64 <= bound);
+        }
+        // Longer itemsets are only bounded by their subsets' counts.
+        for itemset in [vec![2, 21, 41], vec![3, 22, 40]] {
+            assert!(approximate.count(&itemset) >= exact.count(&itemset));
+        }
+        assert_eq!(approximate.count(&[1]), exact.count(&[1]));
+        assert_eq!(approximate.num_transactions(), exact.num_transactions());
+    }
+
+    #[test]
+    fn test_counting_index() {
+        use super::Index;
+
+        let mut exact = Index::new();
+        let mut counting = Index::counting(&[vec![20, 1, 40], vec![2, 21]]);
+        for i in 0..100u32 {
+            let transaction = [i % 10 + 1, i % 7 + 20, i % 3 + 40];
+            exact.insert(&transaction, i % 4 + 1);
+            counting.insert(&transaction, i % 4 + 1);
+        }
+        // The itemsets and their subsets of two or more items.
+        assert_eq!(counting.num_counted_itemsets(), Some(5));
+        assert_eq!(exact.num_counted_itemsets(), None);
+        for itemset in [vec![1], vec![1, 20, 40], vec![40, 1], vec![20, 40], vec![21, 2]] {
+            assert_eq!(counting.count(&itemset), exact.count(&itemset));
+        }
+        // Others are bounded by their items' counts.
+        for itemset in [vec![1, 2], vec![3, 22, 40]] {
+            assert!(counting.count(&itemset) >= exact.count(&itemset));
+        }
+        assert_eq!(counting.count(&[1, 2]), exact.count(&[1]).min(exact.count(&[2])));
+        assert_eq!(counting.num_transactions(), exact.num_transactions());
+    }
 }
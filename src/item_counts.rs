@@ -0,0 +1,112 @@
+// Saves and loads the item counts found by the first pass over a dataset, so
+// repeat runs over the same dataset can skip that pass, and so the counts
+// feeding rare item detection can be inspected or adjusted by hand.
+//
+// The format is CSV, with a comment line giving the number of transactions,
+// then a line of `item,count` for each item, in decreasing order of count.
+// Counts are of the items after --exclude and --taxonomy are applied, so
+// should be loaded with the same options as they were saved with.
+
+use itemizer::Itemizer;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+const TRANSACTIONS_PREFIX: &str = "# transactions,";
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn write_item_counts<W: Write>(
+    output: &mut W,
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    let mut counts: Vec<(&str, u32)> = item_count
+        .iter()
+        .map(|(&item, &count)| (itemizer.str_of(item), count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    writeln!(output, "{}{}", TRANSACTIONS_PREFIX, num_transactions)?;
+    for (item, count) in counts {
+        writeln!(output, "{},{}", item, count)?;
+    }
+    Ok(())
+}
+
+// Returns the count of each item, and the number of transactions. Excluded
+// items are skipped.
+pub fn read_item_counts<R: BufRead>(
+    input: R,
+    itemizer: &mut Itemizer,
+) -> io::Result<(HashMap<u32, u32>, usize)> {
+    let mut item_count: HashMap<u32, u32> = HashMap::new();
+    let mut num_transactions = None;
+    for line in input.lines() {
+        let line = line?;
+        if let Some(count) = line.strip_prefix(TRANSACTIONS_PREFIX) {
+            let count = count.trim();
+            num_transactions = Some(
+                count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("Invalid number of transactions '{}'", count)))?,
+            );
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (item, count) = match line.rfind(',') {
+            Some(comma) => (line[..comma].trim(), line[comma + 1..].trim()),
+            None => return Err(invalid_data(format!("Expected 'item,count', found '{}'", line))),
+        };
+        let count: u32 = count
+            .parse()
+            .map_err(|_| invalid_data(format!("Invalid count '{}' for item '{}'", count, item)))?;
+        if itemizer.is_excluded(item) {
+            continue;
+        }
+        *item_count.entry(itemizer.try_id_of(item)?).or_insert(0) += count;
+    }
+    match num_transactions {
+        Some(num_transactions) => Ok((item_count, num_transactions)),
+        None => Err(invalid_data(String::from(
+            "Item counts are missing the number of transactions",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_item_counts() {
+        use super::{read_item_counts, write_item_counts};
+        use itemizer::Itemizer;
+        use std::collections::HashMap;
+
+        let mut itemizer = Itemizer::new();
+        let mut item_count = HashMap::new();
+        item_count.insert(itemizer.id_of("milk"), 5);
+        item_count.insert(itemizer.id_of("bread"), 7);
+        item_count.insert(itemizer.id_of("eggs"), 5);
+
+        let mut buffer: Vec<u8> = vec![];
+        write_item_counts(&mut buffer, &item_count, 10, &itemizer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            "# transactions,10\nbread,7\neggs,5\nmilk,5\n"
+        );
+
+        let mut itemizer = Itemizer::new();
+        itemizer.exclude("eggs");
+        let (counts, num_transactions) = read_item_counts(&buffer[..], &mut itemizer).unwrap();
+        assert_eq!(num_transactions, 10);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&itemizer.id_of("bread")], 7);
+        assert_eq!(counts[&itemizer.id_of("milk")], 5);
+
+        assert!(read_item_counts(&b"milk,5\n"[..], &mut Itemizer::new()).is_err());
+        assert!(read_item_counts(&b"# transactions,1\nmilk\n"[..], &mut Itemizer::new()).is_err());
+    }
+}
@@ -1,40 +1,298 @@
-use std::collections::HashMap;
+use discretize::Discretizer;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use taxonomy::Taxonomy;
 
+// Maps item names as read to the names they're counted under.
+pub type Normalizer = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+// Returned when a dataset has more distinct items than fit in a u32 id.
+#[derive(Debug)]
+pub struct TooManyItems;
+
+impl fmt::Display for TooManyItems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dataset has more than {} distinct items", u32::MAX - 1)
+    }
+}
+
+impl Error for TooManyItems {}
+
+impl From<TooManyItems> for io::Error {
+    fn from(err: TooManyItems) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+// Item names are interned in one arena: the name of item id is
+// names[ends[id - 2]..ends[id - 1]], so looking up names hands out slices of
+// it rather than allocating a String for each.
 pub struct Itemizer {
-    next_item_id: u32,
+    names: String,
+    ends: Vec<usize>,
     item_str_to_id: HashMap<String, u32>,
-    item_id_to_str: HashMap<u32, String>,
+    excluded_items: HashSet<String>,
+    normalizer: Option<Normalizer>,
+    // Maps raw item names to the canonical names they're counted under.
+    aliases: HashMap<String, String>,
+    stop_items: HashSet<String>,
+    // The number of occurrences of stop items removed so far.
+    num_stopped: usize,
+    taxonomy: Option<Taxonomy>,
+    taxonomy_level: Option<usize>,
+    discretizer: Option<Discretizer>,
+}
+
+// Reads a list of stop items, one per line, ignoring blank lines.
+pub fn read_stop_items<R: BufRead>(reader: R) -> io::Result<HashSet<String>> {
+    let mut items = HashSet::new();
+    for line in reader.lines() {
+        let line = line?;
+        let item = line.trim();
+        if !item.is_empty() {
+            items.insert(String::from(item));
+        }
+    }
+    Ok(items)
+}
+
+// Reads a list of aliases, one per line as "raw_name,canonical_name",
+// ignoring blank lines.
+pub fn read_aliases<R: BufRead>(reader: R) -> io::Result<HashMap<String, String>> {
+    let mut aliases = HashMap::new();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let names: Vec<&str> = line.split(',').map(|name| name.trim()).collect();
+        if names.len() != 2 || names.iter().any(|name| name.is_empty()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Line {} of the alias file must be 'raw_name,canonical_name'",
+                    line_number + 1
+                ),
+            ));
+        }
+        aliases.insert(String::from(names[0]), String::from(names[1]));
+    }
+    Ok(aliases)
+}
+
+// Lower cases `item` if `lowercase`, and replaces each run of whitespace in
+// it with a single space if `collapse_whitespace`, so that e.g. "Milk " and
+// "milk" are the same item.
+pub fn normalize_item(item: &str, lowercase: bool, collapse_whitespace: bool) -> String {
+    let item = if collapse_whitespace {
+        item.split_whitespace().collect::<Vec<&str>>().join(" ")
+    } else {
+        String::from(item)
+    };
+    if lowercase {
+        item.to_lowercase()
+    } else {
+        item
+    }
+}
+
+impl Default for Itemizer {
+    fn default() -> Itemizer {
+        Itemizer::new()
+    }
 }
 
 impl Itemizer {
     pub fn new() -> Itemizer {
         Itemizer {
-            next_item_id: 1,
+            names: String::new(),
+            ends: vec![],
             item_str_to_id: HashMap::new(),
-            item_id_to_str: HashMap::new(),
+            excluded_items: HashSet::new(),
+            normalizer: None,
+            aliases: HashMap::new(),
+            stop_items: HashSet::new(),
+            num_stopped: 0,
+            taxonomy: None,
+            taxonomy_level: None,
+            discretizer: None,
         }
     }
-    pub fn id_of(&mut self, item: &str) -> u32 {
+    // Returns the id of `item`, giving it the next id if it's new. Ids start
+    // at 1, and fail once there are no more u32 ids left.
+    pub fn try_id_of(&mut self, item: &str) -> Result<u32, TooManyItems> {
         if let Some(id) = self.item_str_to_id.get(item) {
-            return *id;
+            return Ok(*id);
+        }
+        if self.ends.len() >= (u32::MAX - 1) as usize {
+            return Err(TooManyItems);
         }
-        let id = self.next_item_id;
-        self.next_item_id += 1;
+        self.names.push_str(item);
+        self.ends.push(self.names.len());
+        let id = self.ends.len() as u32;
         self.item_str_to_id.insert(String::from(item), id);
-        self.item_id_to_str.insert(id, String::from(item));
-        id
+        Ok(id)
+    }
+    // As try_id_of, for when the items are known to fit, such as those
+    // already in memory; panics if they don't.
+    pub fn id_of(&mut self, item: &str) -> u32 {
+        self.try_id_of(item).unwrap_or_else(|err| panic!("{}", err))
+    }
+    // Excluded items are dropped from transactions as they're read, so they
+    // never get an id.
+    pub fn exclude(&mut self, item: &str) {
+        self.excluded_items.insert(String::from(item));
     }
-    pub fn str_of(&self, id: u32) -> String {
-        match self.item_id_to_str.get(&id) {
-            Some(s) => s.clone(),
-            _ => String::from("Unknown"),
+    pub fn is_excluded(&self, item: &str) -> bool {
+        self.excluded_items.contains(item)
+    }
+    // With a normalizer, item names are normalized as transactions are
+    // read, before anything else is done with them.
+    pub fn set_normalizer<F>(&mut self, normalizer: F)
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.normalizer = Some(Box::new(normalizer));
+    }
+    pub fn normalize<'s>(&self, item: &'s str) -> Cow<'s, str> {
+        match self.normalizer {
+            Some(ref normalizer) => Cow::Owned(normalizer(item)),
+            None => Cow::Borrowed(item),
+        }
+    }
+    // With aliases, items are renamed to their canonical names as
+    // transactions are read, after normalization and before anything else.
+    pub fn set_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.aliases = aliases;
+    }
+    pub fn alias<'s>(&self, item: Cow<'s, str>) -> Cow<'s, str> {
+        match self.aliases.get(item.as_ref()) {
+            Some(canonical) => Cow::Owned(canonical.clone()),
+            None => item,
+        }
+    }
+    // Stop items are stripped from transactions as they're read, before
+    // discretization and generalization, counting each occurrence removed.
+    pub fn set_stop_items(&mut self, items: HashSet<String>) {
+        self.stop_items = items;
+    }
+    pub fn stop(&mut self, item: &str) -> bool {
+        let stopped = self.stop_items.contains(item);
+        if stopped {
+            self.num_stopped += 1;
+        }
+        stopped
+    }
+    // Returns the number of occurrences of stop items removed since last
+    // called.
+    pub fn take_num_stopped(&mut self) -> usize {
+        let num_stopped = self.num_stopped;
+        self.num_stopped = 0;
+        num_stopped
+    }
+    // With a taxonomy, transactions are generalized as they're read. If a
+    // level is given, items are replaced by their ancestor at that level,
+    // otherwise all of their ancestors are added to the transaction.
+    pub fn set_taxonomy(&mut self, taxonomy: Taxonomy, level: Option<usize>) {
+        self.taxonomy = Some(taxonomy);
+        self.taxonomy_level = level;
+    }
+    pub fn taxonomy(&self) -> Option<&Taxonomy> {
+        self.taxonomy.as_ref()
+    }
+    // With a discretizer, numeric values are replaced by their ranges as
+    // transactions are read, before exclusion and generalization.
+    pub fn set_discretizer(&mut self, discretizer: Discretizer) {
+        self.discretizer = Some(discretizer);
+    }
+    pub fn discretize<'s>(&self, item: &'s str) -> Cow<'s, str> {
+        match self.discretizer {
+            Some(ref discretizer) => discretizer.discretize(item),
+            None => Cow::Borrowed(item),
         }
     }
+    // Returns the names of the items which `item` contributes to a
+    // transaction.
+    pub fn generalize(&self, item: &str) -> Vec<String> {
+        let taxonomy = match self.taxonomy {
+            Some(ref taxonomy) => taxonomy,
+            None => return vec![String::from(item)],
+        };
+        match self.taxonomy_level {
+            Some(level) => vec![String::from(taxonomy.ancestor_at_level(item, level))],
+            None => {
+                let mut items = vec![String::from(item)];
+                items.extend(taxonomy.ancestors(item).into_iter().map(String::from));
+                items
+            }
+        }
+    }
+    // Returns the id of an item which has already been seen.
+    pub fn get_id(&self, item: &str) -> Option<u32> {
+        self.item_str_to_id.get(item).cloned()
+    }
+    pub fn str_of(&self, id: u32) -> &str {
+        if id == 0 || id as usize > self.ends.len() {
+            return "Unknown";
+        }
+        let index = id as usize - 1;
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.names[start..self.ends[index]]
+    }
+    // Returns the names of all the items, in order of id.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        (1..=self.max_item_id()).map(move |id| self.str_of(id))
+    }
     pub fn max_item_id(&self) -> u32 {
-        self.next_item_id - 1
+        self.ends.len() as u32
     }
     #[cfg(test)]
     pub fn to_id_vec(&mut self, vec_of_str: &[&str]) -> Vec<u32> {
         vec_of_str.iter().map(|s| self.id_of(s)).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_itemizer() {
+        use super::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        assert_eq!(itemizer.id_of("milk"), 1);
+        assert_eq!(itemizer.id_of("skim milk"), 2);
+        assert_eq!(itemizer.try_id_of("milk").unwrap(), 1);
+        assert_eq!(itemizer.id_of(""), 3);
+        assert_eq!(itemizer.max_item_id(), 3);
+        assert_eq!(itemizer.str_of(1), "milk");
+        assert_eq!(itemizer.str_of(2), "skim milk");
+        assert_eq!(itemizer.str_of(3), "");
+        assert_eq!(itemizer.str_of(0), "Unknown");
+        assert_eq!(itemizer.str_of(4), "Unknown");
+        assert_eq!(itemizer.names().collect::<Vec<&str>>(), vec!["milk", "skim milk", ""]);
+    }
+
+    #[test]
+    fn test_aliases() {
+        use super::{read_aliases, Itemizer};
+        use std::borrow::Cow;
+        use transaction_reader::itemize;
+
+        let aliases = read_aliases("Coke 330ml,coca-cola\n\ncoca-cola can, coca-cola\n".as_bytes()).unwrap();
+        assert_eq!(aliases.len(), 2);
+        assert!(read_aliases("coke\n".as_bytes()).is_err());
+
+        let mut itemizer = Itemizer::new();
+        itemizer.set_aliases(aliases);
+        assert_eq!(itemizer.alias(Cow::Borrowed("coca-cola can")), "coca-cola");
+        assert_eq!(itemizer.alias(Cow::Borrowed("pepsi")), "pepsi");
+        // Both aliases are the one item.
+        let items = itemize(["Coke 330ml", "coca-cola can", "pepsi"].iter().cloned(), &mut itemizer).unwrap();
+        let mut names: Vec<&str> = items.iter().map(|&id| itemizer.str_of(id)).collect();
+        names.sort();
+        assert_eq!(names, vec!["coca-cola", "pepsi"]);
+    }
+}
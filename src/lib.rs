@@ -0,0 +1,88 @@
+#![cfg_attr(feature = "simd", feature(portable_simd))]
+#[cfg(feature = "parquet")]
+extern crate arrow_array;
+#[cfg(feature = "parquet")]
+extern crate arrow_schema;
+extern crate argparse;
+extern crate flate2;
+extern crate itertools;
+#[macro_use]
+extern crate log;
+extern crate ordered_float;
+#[cfg(feature = "postgres")]
+extern crate postgres;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+extern crate rand;
+extern crate rayon;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+extern crate zstd;
+
+pub mod itemizer;
+pub mod item_counts;
+pub mod taxonomy;
+pub mod discretize;
+pub mod arff;
+pub mod transaction_reader;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod db_input;
+#[cfg(feature = "parquet")]
+pub mod parquet_input;
+pub mod fptree;
+pub mod eclat;
+pub mod apriori;
+pub mod hmine;
+pub mod top_k;
+pub mod checkpoint;
+pub mod utility;
+pub mod sequences;
+pub mod classifier;
+pub mod generate_rules;
+pub mod command_line_args;
+pub mod config;
+pub mod index;
+pub mod sketch;
+pub mod pair_counts;
+pub mod support_cache;
+pub mod significance;
+pub mod metrics;
+pub mod miner;
+pub mod mining_state;
+pub mod ffi;
+pub mod clock;
+#[cfg(feature = "mem-stats")]
+pub mod mem_stats;
+pub mod cancel;
+pub mod spill;
+pub mod output;
+pub mod rule_writer;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_output;
+pub mod html_output;
+pub mod drift;
+pub mod diff;
+pub mod class_rules;
+pub mod distributed;
+pub mod bitset_counts;
+pub mod work_estimate;
+pub mod run_metadata;
+#[cfg(feature = "parquet")]
+pub mod parquet_output;
+pub mod rule_reader;
+pub mod report;
+pub mod gen_data;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,316 +1,528 @@
-extern crate argparse;
-extern crate itertools;
-extern crate ordered_float;
-extern crate rand;
-extern crate rayon;
-
-mod itemizer;
-mod transaction_reader;
-mod fptree;
-mod generate_rules;
-mod command_line_args;
-mod index;
-
-use index::Index;
-use itemizer::Itemizer;
-use transaction_reader::TransactionReader;
-use fptree::FPTree;
-use fptree::sort_transaction;
-use fptree::rip_growth;
-use fptree::SortOrder;
-use fptree::ItemSet;
-use generate_rules::generate_rules;
-use generate_rules::Rule;
-use command_line_args::Arguments;
-use command_line_args::parse_args_or_exit;
-use command_line_args::MaxSupportMode;
-use rand::Rng;
-use rayon::prelude::*;
-use std::cmp::{max, min};
-use std::collections::{HashMap, HashSet};
+extern crate ctrlc;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate ratatui;
+extern crate riptree;
+#[macro_use]
+extern crate serde_json;
+extern crate tiny_http;
+
+mod browse;
+mod classify;
+mod query;
+mod recommend;
+mod server;
+
+use riptree::command_line_args::Arguments;
+use riptree::command_line_args::parse_args_or_exit;
+use riptree::command_line_args::parse_browse_args_or_exit;
+use riptree::command_line_args::MaxSupportMode;
+use riptree::command_line_args::OutputFormat;
+use riptree::command_line_args::parse_classify_predict_args_or_exit;
+use riptree::command_line_args::parse_config_dump_args_or_exit;
+use riptree::command_line_args::parse_diff_args_or_exit;
+use riptree::command_line_args::parse_gen_data_args_or_exit;
+use riptree::command_line_args::parse_classify_train_args_or_exit;
+use riptree::command_line_args::parse_query_args_or_exit;
+use riptree::command_line_args::parse_recommend_args_or_exit;
+use riptree::command_line_args::parse_serve_args_or_exit;
+use riptree::command_line_args::parse_update_args_or_exit;
+use riptree::command_line_args::parse_sequence_args_or_exit;
+use riptree::command_line_args::parse_utility_args_or_exit;
+use riptree::command_line_args::parse_worker_args_or_exit;
+use riptree::command_line_args::DiffArguments;
+use riptree::command_line_args::GenDataArguments;
+use riptree::command_line_args::LogOptions;
+use riptree::command_line_args::SequenceArguments;
+use riptree::command_line_args::UpdateArguments;
+use riptree::command_line_args::UtilityArguments;
+use riptree::config::{config_table, dump_config, utility_config_table};
+use riptree::gen_data::write_dataset;
+use riptree::output::Output;
+use riptree::run_metadata::RunMetadata;
+use riptree::transaction_reader::expand_input_paths;
+use riptree::miner::{mine, mine_diff, mine_high_utility, mine_sequences, update, MiningResult};
+#[cfg(feature = "parquet")]
+use riptree::parquet_output::write_parquet;
+#[cfg(feature = "sqlite")]
+use riptree::sqlite_output::write_sqlite;
+use riptree::html_output::write_html;
+use riptree::drift::write_drift_csv;
+use riptree::diff::write_diff_csv;
+use riptree::class_rules::write_class_report_csv;
+use riptree::rule_writer::{write_itemsets_csv, write_rules_csv, write_rules_delimited, write_rules_dot,
+                           write_rules_json, write_sequential_rules_csv, DelimitedFormat};
+use env_logger::fmt::Formatter;
+use log::{Level, LevelFilter, Record};
+use riptree::cancel::cancel;
+use riptree::clock::Phase;
+use std::env;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 use std::process;
-use std::time::Instant;
-
-fn count_item_frequencies(
-    reader: TransactionReader,
-) -> Result<(HashMap<u32, u32>, usize), Box<Error>> {
-    let mut item_count: HashMap<u32, u32> = HashMap::new();
-    let mut num_transactions = 0;
-    for transaction in reader {
-        num_transactions += 1;
-        for item in transaction {
-            let counter = item_count.entry(item).or_insert(0);
-            *counter += 1;
-        }
-    }
-    Ok((item_count, num_transactions))
-}
+use std::time::{Instant, SystemTime};
+
+// Process exit codes, so shell pipelines and workflow engines can branch on
+// how a run ended. Invalid arguments exit with
+// command_line_args::EXIT_BAD_ARGS, and pressing Ctrl-C twice with 130.
+const EXIT_ERROR: i32 = 1;
+const EXIT_IO_ERROR: i32 = 3;
+const EXIT_NO_RARE_ITEMS: i32 = 4;
+const EXIT_NO_RULES: i32 = 5;
 
-// Returns true if transaction contains at least one rate item.
-fn contains_rare_item(transaction: &Vec<u32>, rare_items: &HashSet<u32>) -> bool {
-    transaction.iter().any(|item| rare_items.contains(item))
+// A mining run which wrote its outputs, but found nothing in them.
+#[derive(Debug)]
+enum EmptyResult {
+    NoRareItems,
+    NoRules,
 }
 
-fn find_gaussian_rare_items(
-    item_count: &HashMap<u32, u32>,
-    num_transactions: usize,
-    max_item_id: u32,
-) -> HashSet<u32> {
-    let avg_transaction_len = (item_count.iter().fold(0, |acc, (_, count)| acc + count) as f64 /
-        num_transactions as f64)
-        .ceil() as u32;
-
-    let max_item_count = item_count
-        .iter()
-        .fold(0, |acc, (_, count)| max(acc, *count));
-
-    let delta = 0.05;
-    let epsilon = ((max_item_count as f64).powi(2) * (1.0_f64 / delta).ln() /
-        (2.0 * num_transactions as f64))
-        .sqrt();
-
-    // Generate 100 randomly distributed datasets in parallel,
-    // and reduce into the minimum count of each item over all datasets.
-    let min_count: HashMap<u32, u32> = (0..1000)
-        .into_par_iter()
-        .map(|_| {
-            let mut rng = rand::thread_rng();
-            let mut random_dataset = HashMap::new();
-            for _ in 0..num_transactions {
-                for _ in 0..avg_transaction_len {
-                    let random_item = rng.gen_range(0, max_item_id + 1);
-                    *random_dataset.entry(random_item).or_insert(0) += 1;
-                }
-            }
-            random_dataset
+impl fmt::Display for EmptyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            EmptyResult::NoRareItems => "No items are rare, so no rules were generated",
+            EmptyResult::NoRules => "No rules were generated",
         })
-        .reduce(HashMap::new, |mut min_count, random_dataset| {
-            for (item, count) in random_dataset.iter() {
-                let p = min_count.entry(*item).or_insert(*count);
-                *p = min(*p, *count);
-            }
-            min_count
-        });
+    }
+}
 
-    // See if the count in the actual dataset is significantly different from
-    // the random datasets.
-    let mut rare_items: HashSet<u32> = HashSet::new();
-    for (item, count) in item_count.iter() {
-        let random_min_count = min_count[item] as f64;
-        if (random_min_count - (*count as f64)) > epsilon {
-            rare_items.insert(*item);
-        }
+impl Error for EmptyResult {}
+
+// Fails with an EmptyResult if mining found no rare items, or no rules.
+fn check_results(args: &Arguments, result: &MiningResult) -> Result<(), Box<dyn Error>> {
+    if result.report.num_rare_items == 0 {
+        Err(Box::new(EmptyResult::NoRareItems))
+    } else if result.rules.is_empty() && !args.dry_run {
+        Err(Box::new(EmptyResult::NoRules))
+    } else {
+        Ok(())
     }
+}
 
-    rare_items
+// Returns the exit code for a run which failed with `err`, and the status
+// --machine reports.
+fn exit_status(err: &(dyn Error + 'static)) -> (i32, &'static str) {
+    if let Some(empty) = err.downcast_ref::<EmptyResult>() {
+        match *empty {
+            EmptyResult::NoRareItems => (EXIT_NO_RARE_ITEMS, "no_rare_items"),
+            EmptyResult::NoRules => (EXIT_NO_RULES, "no_rules"),
+        }
+    } else if err.downcast_ref::<io::Error>().is_some() {
+        (EXIT_IO_ERROR, "io_error")
+    } else {
+        (EXIT_ERROR, "error")
+    }
 }
 
-fn find_pareto_rare_items(item_count: &HashMap<u32, u32>) -> HashSet<u32> {
-    // Sort (item, count) pairs by increasing frequency, and accumulate the
-    // total sum of the counts of all items.
-    let mut item_count_sum = 0;
-    let mut items = Vec::with_capacity(item_count.len());
-    for (&item, &count) in item_count.iter() {
-        item_count_sum += count;
-        items.push((item, count));
+// Starts logging at the level `log` asks for: by default progress messages,
+// with -q or --quiet only warnings and errors, and with -v or --verbose, or
+// -vv, more detail. RUST_LOG, if set, overrides the flags, e.g.
+// RUST_LOG=riptree::fptree=trace. Returns whether --machine was given, so a
+// line of JSON describing how the run ended is written to stdout.
+fn init_logging(log: &LogOptions) -> bool {
+    let level = match log.verbose {
+        0 if log.quiet || log.machine => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(level)
+        .format(|buf: &mut Formatter, record: &Record| match record.level() {
+            Level::Info => writeln!(buf, "{}", record.args()),
+            level => writeln!(buf, "{}: {}", level, record.args()),
+        });
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
     }
-    items.sort_by(|&(_, a), &(_, b)| a.cmp(&b));
-
-    let threshold = (0.01 * item_count_sum as f64) as u32;
-    let mut rare_items: HashSet<u32> = HashSet::new();
-    let mut sum = 0;
-    let mut prev_count = 0;
-    for (item, count) in items {
-        sum += count;
-        // If this item as the same count as the previous, include it.
-        // This ensures that all items of the same count are included
-        // if any are included, otherwise, the order in which items are
-        // iterated here is significant in the results, i.e. they're
-        // non-deterministic.
-        if sum < threshold || prev_count == count {
-            rare_items.insert(item);
-        }
-        if sum > threshold && prev_count != count {
-            break;
+    builder.init();
+    log.machine
+}
+
+// On Ctrl-C, stops the current phase of mining, so the results found so far
+// can be written out. Ctrl-C again before the phase stops exits at once.
+fn handle_interrupts() {
+    let result = ctrlc::set_handler(|| {
+        if cancel() {
+            warn!("Interrupted; stopping to write the results found so far. Press Ctrl-C again to exit.");
+        } else {
+            process::exit(130);
         }
-        prev_count = count;
+    });
+    if let Err(err) = result {
+        warn!("Can't handle Ctrl-C: {}", err);
     }
-
-    rare_items
 }
 
-fn mine_rip_tree(args: &Arguments) -> Result<(), Box<Error>> {
-    println!("Mining data set: {}", args.input_file_path);
-    println!("Output rules file: {}", args.output_rules_path);
-    println!(
+fn print_parameters(args: &Arguments) {
+    if !args.dry_run && !args.output_rules_path.is_empty() {
+        info!("Output rules file: {}", args.output_rules_path);
+    }
+    if !args.dry_run && !args.output_sqlite_path.is_empty() {
+        info!("Output SQLite database: {}", args.output_sqlite_path);
+    }
+    if !args.dry_run && !args.output_parquet_path.is_empty() {
+        info!("Output Parquet directory: {}", args.output_parquet_path);
+    }
+    if !args.dry_run && !args.output_html_path.is_empty() {
+        info!("Output HTML report: {}", args.output_html_path);
+    }
+    if !args.dry_run && !args.drift_report_path.is_empty() {
+        info!("Output drift report: {}", args.drift_report_path);
+    }
+    if !args.dry_run && !args.class_report_path.is_empty() {
+        info!("Output class report: {}", args.class_report_path);
+    }
+    info!(
         "Max support mode: {}",
         match args.max_support_mode {
             MaxSupportMode::Pareto => "Pareto",
             MaxSupportMode::Gaussian => "Gaussian",
+            MaxSupportMode::Robust => "Robust",
+            MaxSupportMode::Elbow => "Elbow",
         }
     );
-    println!("Minimum confidence: {}", args.min_confidence);
-    println!("Minimum lift: {}", args.min_lift);
-    println!(
+    info!("Minimum confidence: {}", args.min_confidence);
+    info!("Minimum lift: {}", args.min_lift);
+    info!(
         "Family Wise with Bonferonni correction rule filtering: {}",
-        match args.disable_family_wise_rule_filtering {
-            true => "Disabled",
-            false => "Enabled",
+        if args.disable_family_wise_rule_filtering {
+            "Disabled"
+        } else {
+            "Enabled"
         }
     );
-
-    println!("Making first pass of dataset to count item frequencies...");
-    // Make one pass of the dataset to calculate the item frequencies
-    // for the initial tree.
-    let start = Instant::now();
-    let timer = Instant::now();
-    let mut itemizer: Itemizer = Itemizer::new();
-    let (item_count, num_transactions) = count_item_frequencies(
-        TransactionReader::new(&args.input_file_path, &mut itemizer),
-    ).unwrap();
-    println!(
-        "First pass took {} seconds, num_transactions={}.",
-        timer.elapsed().as_secs(),
-        num_transactions
+    info!(
+        "Permutation rule filtering: {}",
+        if args.disable_permutation_rule_filtering {
+            "Disabled"
+        } else {
+            "Enabled"
+        }
     );
+}
+
+// The metadata of a run which started at `started`, unless
+// --disable-output-metadata.
+fn run_metadata(args: &Arguments, started: SystemTime) -> Result<Option<RunMetadata>, Box<dyn Error>> {
+    if args.disable_output_metadata {
+        return Ok(None);
+    }
+    let paths = expand_input_paths(&args.input_file_paths)?;
+    Ok(Some(RunMetadata::new(config_table(args), &paths, started)?))
+}
 
-    // Load the initial tree, by re-reading the data set and inserting
-    // each transaction into the tree sorted by item frequency.
-    let timer = Instant::now();
-    println!("Determining which items are rare...");
-    let rare_items = match args.max_support_mode {
-        MaxSupportMode::Gaussian => {
-            find_gaussian_rare_items(&item_count, num_transactions, itemizer.max_item_id())
+fn write_rules(args: &Arguments, result: &mut MiningResult, started: SystemTime) -> Result<(), Box<dyn Error>> {
+    let phase = Phase::start("Writing rules to disk");
+    if !args.output_rules_path.is_empty() {
+        let metadata = run_metadata(args, started)?;
+        let mut output = Output::create(&args.output_rules_path, args.compress_output.as_ref())?;
+        let (rules, groups, itemizer) = (&result.rules, &result.consequent_groups, &result.itemizer);
+        let partial = result.report.partial;
+        let format = |default_delimiter, comments| DelimitedFormat {
+            columns: &args.output_columns,
+            delimiter: args.output_delimiter.as_ref().map_or(default_delimiter, String::as_str),
+            item_separator: &args.item_separator,
+            comments,
+        };
+        let mut sidecar = false;
+        match args.output_format {
+            OutputFormat::Legacy => {
+                if let Some(ref metadata) = metadata {
+                    metadata.write_comments(&mut output)?;
+                }
+                if args.output_columns.is_empty() && args.output_delimiter.is_none() && args.item_separator == " " {
+                    write_rules_csv(&mut output, rules, groups, itemizer, partial)?
+                } else {
+                    write_rules_delimited(&mut output, rules, groups, itemizer, partial, &format(", ", true))?
+                }
+            }
+            OutputFormat::Csv => {
+                write_rules_delimited(&mut output, rules, groups, itemizer, partial, &format(",", false))?;
+                sidecar = true;
+            }
+            OutputFormat::Json => {
+                write_rules_json(&mut output, rules, groups, itemizer, partial)?;
+                sidecar = true;
+            }
         }
-        MaxSupportMode::Pareto => find_pareto_rare_items(&item_count),
-    };
-    println!(
-        "{} of {} items are considered rare, took {} seconds.",
-        rare_items.len(),
-        item_count.len(),
-        timer.elapsed().as_secs(),
-    );
+        if let (true, Some(metadata)) = (sidecar, metadata) {
+            let path = format!("{}.meta.json", args.output_rules_path);
+            let mut sidecar = BufWriter::new(File::create(path)?);
+            metadata.write_json(&mut sidecar)?;
+        }
+        output.finish()?;
+    }
+    if !args.output_dot_path.is_empty() {
+        let mut output = BufWriter::new(File::create(&args.output_dot_path)?);
+        write_rules_dot(&mut output, &result.rules, &result.itemizer)?;
+    }
+    // Without the sqlite feature, --output-sqlite is rejected up front.
+    #[cfg(feature = "sqlite")]
+    {
+        if !args.output_sqlite_path.is_empty() {
+            write_sqlite(&args.output_sqlite_path, result, args)?;
+        }
+    }
+    if !args.output_html_path.is_empty() {
+        let mut output = BufWriter::new(File::create(&args.output_html_path)?);
+        write_html(&mut output, result, args)?;
+    }
+    if let Some(ref drift) = result.drift {
+        let mut output = BufWriter::new(File::create(&args.drift_report_path)?);
+        write_drift_csv(&mut output, drift, &result.rules, &result.itemizer)?;
+    }
+    if let Some(ref classes) = result.classes {
+        let mut output = BufWriter::new(File::create(&args.class_report_path)?);
+        write_class_report_csv(&mut output, classes, &result.itemizer)?;
+    }
+    // Without the parquet feature, --output-parquet is rejected up front.
+    #[cfg(feature = "parquet")]
+    {
+        if !args.output_parquet_path.is_empty() {
+            write_parquet(
+                &args.output_parquet_path,
+                &result.rules,
+                &result.itemsets,
+                result.report.num_transactions,
+                &result.itemizer,
+            )?;
+        }
+    }
+    result.report.end_phase(phase);
+    write_report(args, result)
+}
 
-    if rare_items.len() == 0 {
-        println!("Since 0 items are considered rare, giving up.");
-        return Ok(());
+fn write_report(args: &Arguments, result: &MiningResult) -> Result<(), Box<dyn Error>> {
+    if !args.report_path.is_empty() {
+        let mut output = BufWriter::new(File::create(&args.report_path)?);
+        result.report.write_json(&mut output, args)?;
     }
+    Ok(())
+}
 
-    if rare_items.len() > 500 {
-        println!("Since there are more than 500 items considered rare, giving up!");
-        return Ok(());
+fn mine_rip_tree(args: &Arguments) -> Result<(), Box<dyn Error>> {
+    if args.input_db_url.is_empty() {
+        info!("Mining data set: {}", args.input_file_paths.join(", "));
+    } else {
+        info!("Mining query: {}", args.input_query);
     }
+    print_parameters(args);
+    handle_interrupts();
 
-    if args.log_rare_items {
-        println!("Rare items:");
-        for &item in rare_items.iter() {
-            println!("{}", itemizer.str_of(item));
-        }
+    let start = Instant::now();
+    let started = SystemTime::now();
+    let mut result = mine(args)?;
+    if args.dry_run {
+        write_report(args, &result)?;
+    } else {
+        write_rules(args, &mut result, started)?;
     }
 
-    let timer = Instant::now();
-    let mut fptree = FPTree::new();
-    println!("Building initial RIPTree based on item frequencies...");
-    let mut index: Index = Index::new();
-    for mut transaction in TransactionReader::new(&args.input_file_path, &mut itemizer) {
-        index.insert(&transaction);
-        // Only include transactions which contain at least one rate item.
-        if !contains_rare_item(&transaction, &rare_items) {
-            continue;
-        }
+    info!("Total runtime: {:.3} seconds", start.elapsed().as_secs_f64());
+
+    check_results(args, &result)
+}
+
+fn update_rip_tree(args: &UpdateArguments) -> Result<(), Box<dyn Error>> {
+    info!("Updating state: {}", args.state_path);
+    info!("Appending data set: {}", args.mining.input_file_paths.join(", "));
+    print_parameters(&args.mining);
+    handle_interrupts();
 
-        sort_transaction(&mut transaction, &item_count, SortOrder::Decreasing);
-        fptree.insert(&transaction, 1);
+    let start = Instant::now();
+    let started = SystemTime::now();
+    let mut result = update(&args.state_path, &args.mining)?;
+    if args.mining.dry_run {
+        write_report(&args.mining, &result)?;
+    } else {
+        write_rules(&args.mining, &mut result, started)?;
     }
-    println!(
-        "Building initial FPTree took {} seconds.",
-        timer.elapsed().as_secs()
-    );
 
-    println!("Building lookup table for natural log/factorial...");
-    let mut ln_table = vec![];
-    ln_table.push(0.0);
-    ln_table.push(0.0);
-    for i in 2..num_transactions + 1 {
-        let prev = ln_table[i - 1];
-        ln_table.push(prev + (i as f64).ln());
+    info!("Total runtime: {:.3} seconds", start.elapsed().as_secs_f64());
+
+    check_results(&args.mining, &result)
+}
+
+fn diff_datasets(args: &DiffArguments) -> Result<(), Box<dyn Error>> {
+    info!("Dataset A: {}", args.mining.input_file_paths.join(", "));
+    info!("Dataset B: {}", args.input_b_paths.join(", "));
+    print_parameters(&args.mining);
+
+    let start = Instant::now();
+    let result = mine_diff(&args.mining, &args.input_b_paths)?;
+    {
+        let mining = &args.mining;
+        let mut output = Output::create(&mining.output_rules_path, mining.compress_output.as_ref())?;
+        write_diff_csv(&mut output, &result.diff, &result.itemizer)?;
+        output.finish()?;
     }
 
-    println!("Starting recursive FPGrowth...");
-    let timer = Instant::now();
-    let patterns: Vec<ItemSet> = rip_growth(
-        &fptree,
-        &fptree,
-        Some(&rare_items),
-        &vec![],
-        num_transactions as u32,
-        &itemizer,
-        &index,
-        &ln_table,
-    );
+    info!("Total runtime: {:.3} seconds", start.elapsed().as_secs_f64());
 
-    println!(
-        "FPGrowth generated {} frequent itemsets in {} seconds.",
-        patterns.len(),
-        timer.elapsed().as_secs()
-    );
+    Ok(())
+}
 
-    println!("Generating rules...");
-    let timer = Instant::now();
-    let rules: Vec<Rule> = generate_rules(
-        &patterns,
-        num_transactions as u32,
-        args.min_confidence,
-        args.min_lift,
-        &rare_items,
-        &index,
-        &ln_table,
-        args.disable_family_wise_rule_filtering,
-    ).iter()
-        .cloned()
-        .collect();
-    println!(
-        "Generated {} rules in {} seconds, writing to disk.",
-        rules.len(),
-        timer.elapsed().as_secs()
-    );
+// Builds the tree and index, and then serves shards of growth to a
+// coordinator until killed.
+fn run_worker(args: &Arguments) -> Result<(), Box<dyn Error>> {
+    info!("Worker data set: {}", args.input_file_paths.join(", "));
+    print_parameters(args);
+    handle_interrupts();
+    mine(args)?;
+    Ok(())
+}
+
+fn mine_high_utility_itemsets(args: &UtilityArguments) -> Result<(), Box<dyn Error>> {
+    info!("Mining data set: {}", args.input_file_path);
+    info!("Utilities: {}", args.utilities_path);
+    info!("Minimum utility: {}", args.min_utility);
 
-    let timer = Instant::now();
+    let start = Instant::now();
+    let started = SystemTime::now();
+    let result = mine_high_utility(args)?;
     {
-        let mut output = BufWriter::new(File::create(&args.output_rules_path).unwrap());
-        writeln!(
-            output,
-            "Antecedent => Consequent, Confidence, Lift, Support"
-        )?;
-        for rule in rules {
-            writeln!(
-                output,
-                "{}, {}, {}, {}",
-                rule.to_string(&itemizer),
-                rule.confidence(),
-                rule.lift(),
-                rule.support(),
-            )?;
+        let mut output = Output::create(&args.output_path, args.compress_output.as_ref())?;
+        if !args.disable_output_metadata {
+            let paths = [args.input_file_path.clone()];
+            RunMetadata::new(utility_config_table(args), &paths, started)?.write_comments(&mut output)?;
         }
+        write_itemsets_csv(&mut output, &result.itemsets, &result.itemizer)?;
+        output.finish()?;
+    }
+
+    info!("Total runtime: {:.3} seconds", start.elapsed().as_secs_f64());
+
+    Ok(())
+}
+
+fn mine_sequential_rules(args: &SequenceArguments) -> Result<(), Box<dyn Error>> {
+    info!("Mining data set: {}", args.input_file_path);
+    info!("Minimum confidence: {}", args.min_confidence);
+    info!("Minimum lift: {}", args.min_lift);
+
+    let start = Instant::now();
+    let result = mine_sequences(args)?;
+    {
+        let mut output = Output::create(&args.output_path, args.compress_output.as_ref())?;
+        write_sequential_rules_csv(&mut output, &result.rules, &result.itemizer)?;
+        output.finish()?;
     }
-    println!(
-        "Wrote rules to disk in {} seconds.",
-        timer.elapsed().as_secs()
-    );
 
-    println!("Total runtime: {} seconds", start.elapsed().as_secs());
+    info!("Total runtime: {:.3} seconds", start.elapsed().as_secs_f64());
 
     Ok(())
 }
 
+fn generate_dataset(args: &GenDataArguments) -> Result<(), Box<dyn Error>> {
+    info!(
+        "Generating {} transactions of {} items into {}",
+        args.parameters.num_transactions, args.parameters.num_items, args.output_path
+    );
+    let mut output = Output::create(&args.output_path, args.compress_output.as_ref())?;
+    write_dataset(&mut output, &args.parameters)?;
+    output.finish()?;
+    Ok(())
+}
+
+// Each subcommand's options are parsed, including any config file, before
+// logging starts, as they include the log level.
 fn main() {
-    let arguments = parse_args_or_exit();
+    let args: Vec<String> = env::args().collect();
+    let machine;
+    let result = match args.get(1).map(|arg| arg.as_str()) {
+        Some("serve") => {
+            let args = parse_serve_args_or_exit(args);
+            machine = init_logging(&args.log);
+            server::serve(&args)
+        }
+        Some("query") => {
+            let args = parse_query_args_or_exit(args);
+            machine = init_logging(&args.log);
+            query::query(&args)
+        }
+        Some("browse") => {
+            let args = parse_browse_args_or_exit(args);
+            machine = init_logging(&args.log);
+            browse::browse(&args)
+        }
+        Some("recommend") => {
+            let args = parse_recommend_args_or_exit(args);
+            machine = init_logging(&args.log);
+            recommend::recommend(&args)
+        }
+        Some("config-dump") => {
+            let args = parse_config_dump_args_or_exit(args);
+            machine = init_logging(&args.log);
+            print!("{}", dump_config(&args));
+            Ok(())
+        }
+        Some("update") => {
+            let args = parse_update_args_or_exit(args);
+            machine = init_logging(&args.mining.log);
+            update_rip_tree(&args)
+        }
+        Some("diff") => {
+            let args = parse_diff_args_or_exit(args);
+            machine = init_logging(&args.mining.log);
+            diff_datasets(&args)
+        }
+        Some("worker") => {
+            let args = parse_worker_args_or_exit(args);
+            machine = init_logging(&args.log);
+            run_worker(&args)
+        }
+        Some("classify-train") => {
+            let args = parse_classify_train_args_or_exit(args);
+            machine = init_logging(&args.log);
+            classify::classify_train(&args)
+        }
+        Some("classify-predict") => {
+            let args = parse_classify_predict_args_or_exit(args);
+            machine = init_logging(&args.log);
+            classify::classify_predict(&args)
+        }
+        Some("sequences") => {
+            let args = parse_sequence_args_or_exit(args);
+            machine = init_logging(&args.log);
+            mine_sequential_rules(&args)
+        }
+        Some("utility") => {
+            let args = parse_utility_args_or_exit(args);
+            machine = init_logging(&args.log);
+            mine_high_utility_itemsets(&args)
+        }
+        Some("gen-data") => {
+            let args = parse_gen_data_args_or_exit(args);
+            machine = init_logging(&args.log);
+            generate_dataset(&args)
+        }
+        _ => {
+            let args = parse_args_or_exit(args);
+            machine = init_logging(&args.log);
+            mine_rip_tree(&args)
+        }
+    };
 
-    if let Err(err) = mine_rip_tree(&arguments) {
-        println!("Error: {}", err);
-        process::exit(1);
+    let (code, status) = match result {
+        Ok(()) => (0, "ok"),
+        Err(ref err) => exit_status(&**err),
+    };
+    match result {
+        Err(ref err) if err.downcast_ref::<EmptyResult>().is_some() => warn!("{}.", err),
+        Err(ref err) => error!("{}", err),
+        Ok(()) => {}
+    }
+    if machine {
+        let message = result.as_ref().err().map(|err| err.to_string());
+        println!("{}", json!({"status": status, "exit_code": code, "message": message}));
+    }
+    if code != 0 {
+        process::exit(code);
     }
 }
@@ -0,0 +1,90 @@
+// Counts the bytes allocated, for the `mem-stats` feature, so the report can
+// give the most memory allocated at once during each phase of a run, e.g.
+// counting, building the tree, growth and rule generation. The allocator
+// wraps the system allocator, and costs a couple of atomic operations per
+// allocation, so is only built when asked for.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn record_alloc(bytes: usize) {
+    let allocated = ALLOCATED.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+fn record_dealloc(bytes: usize) {
+    ALLOCATED.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                record_alloc(new_size - layout.size());
+            } else {
+                record_dealloc(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+pub fn allocated_bytes() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+// Starts a new peak from the bytes allocated now, as at the start of a phase.
+pub fn reset_peak() {
+    PEAK.store(allocated_bytes(), Ordering::Relaxed);
+}
+
+// The most bytes allocated at once since the peak was last reset.
+pub fn peak_bytes() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_peak_bytes() {
+        use super::{allocated_bytes, peak_bytes, reset_peak};
+
+        reset_peak();
+        let buffer: Vec<u8> = vec![1; 1 << 20];
+        // Other tests allocate, and start phases, at the same time, but the
+        // buffer is allocated throughout.
+        assert!(allocated_bytes() >= buffer.len());
+        assert!(peak_bytes() >= buffer.len());
+        drop(buffer);
+    }
+}
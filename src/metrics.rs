@@ -0,0 +1,284 @@
+// Measures of how interesting a rule A ==> B is, computed from its
+// contingency table. Kulczynski and cosine are null-invariant: unlike lift,
+// they don't depend on the number of transactions containing neither A nor B,
+// which dominates in datasets of rare items.
+//
+// To add a measure, implement Measure for it and add it to MEASURES; the
+// --metrics option and its --min-<name> (or --max-<name>) threshold option
+// are generated from there.
+
+use significance::Contingency;
+use std::fmt;
+use std::str::FromStr;
+
+pub trait Measure: Sync {
+    // The name used in --metrics and the threshold option.
+    fn name(&self) -> &'static str;
+    // The heading of the measure's column in CSV output.
+    fn column(&self) -> &'static str;
+    // Completes "Minimum rule ..." in the threshold option's help.
+    fn description(&self) -> &'static str;
+    // The smallest and largest values the measure takes.
+    fn range(&self) -> (f64, f64);
+    fn higher_is_better(&self) -> bool;
+    fn compute(&self, table: &Contingency) -> f64;
+}
+
+// The mean of the confidences of A ==> B and B ==> A.
+pub struct Kulczynski;
+
+impl Measure for Kulczynski {
+    fn name(&self) -> &'static str {
+        "kulc"
+    }
+
+    fn column(&self) -> &'static str {
+        "Kulczynski"
+    }
+
+    fn description(&self) -> &'static str {
+        "Kulczynski measure, the mean of the confidences of the rule and its reverse"
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, table: &Contingency) -> f64 {
+        (table.confidence() + table.ab as f64 / table.b as f64) / 2.0
+    }
+}
+
+// The cosine (IS) measure: the geometric mean of the confidences of A ==> B
+// and B ==> A.
+pub struct Cosine;
+
+impl Measure for Cosine {
+    fn name(&self) -> &'static str {
+        "cosine"
+    }
+
+    fn column(&self) -> &'static str {
+        "Cosine"
+    }
+
+    fn description(&self) -> &'static str {
+        "cosine (IS) measure, the geometric mean of the confidences of the rule and its reverse"
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, table: &Contingency) -> f64 {
+        table.ab as f64 / (table.a as f64 * table.b as f64).sqrt()
+    }
+}
+
+// How much A raises the confidence in B above its support, as a fraction of
+// how far it could, with 0 meaning A and B are independent.
+pub struct CertaintyFactor;
+
+impl Measure for CertaintyFactor {
+    fn name(&self) -> &'static str {
+        "certainty-factor"
+    }
+
+    fn column(&self) -> &'static str {
+        "Certainty Factor"
+    }
+
+    fn description(&self) -> &'static str {
+        "certainty factor, the gain in confidence over the consequent's support, \
+         relative to the largest possible gain"
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (-1.0, 1.0)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, table: &Contingency) -> f64 {
+        let confidence = table.confidence();
+        let b_sup = table.b as f64 / table.n as f64;
+        if confidence > b_sup {
+            (confidence - b_sup) / (1.0 - b_sup)
+        } else if b_sup > 0.0 {
+            (confidence - b_sup) / b_sup
+        } else {
+            0.0
+        }
+    }
+}
+
+// The confidence of A ==> B less the support of B.
+pub struct AddedValue;
+
+impl Measure for AddedValue {
+    fn name(&self) -> &'static str {
+        "added-value"
+    }
+
+    fn column(&self) -> &'static str {
+        "Added Value"
+    }
+
+    fn description(&self) -> &'static str {
+        "added value, the confidence less the consequent's support"
+    }
+
+    fn range(&self) -> (f64, f64) {
+        (-1.0, 1.0)
+    }
+
+    fn higher_is_better(&self) -> bool {
+        true
+    }
+
+    fn compute(&self, table: &Contingency) -> f64 {
+        table.confidence() - table.b as f64 / table.n as f64
+    }
+}
+
+// Every measure, in the order their columns appear.
+pub static MEASURES: &[&dyn Measure] = &[&Kulczynski, &Cosine, &CertaintyFactor, &AddedValue];
+
+// A measure in MEASURES, by its position there, so rules can store which
+// measures they have cheaply, and in column order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Metric(usize);
+
+impl Metric {
+    pub fn all() -> Vec<Metric> {
+        (0..MEASURES.len()).map(Metric).collect()
+    }
+
+    pub fn measure(&self) -> &'static dyn Measure {
+        MEASURES[self.0]
+    }
+
+    pub fn compute(&self, table: &Contingency) -> f64 {
+        self.measure().compute(table)
+    }
+
+    pub fn column(&self) -> &'static str {
+        self.measure().column()
+    }
+
+    // The option giving the metric's threshold, e.g. "--min-kulc".
+    pub fn threshold_option(&self) -> String {
+        let measure = self.measure();
+        let bound = if measure.higher_is_better() { "min" } else { "max" };
+        format!("--{}-{}", bound, measure.name())
+    }
+
+    // Whether `value` passes `threshold`, given as the metric's threshold
+    // option.
+    pub fn passes(&self, value: f64, threshold: f64) -> bool {
+        if self.measure().higher_is_better() {
+            value >= threshold
+        } else {
+            value <= threshold
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Metric, String> {
+        match MEASURES.iter().position(|measure| measure.name() == s) {
+            Some(i) => Ok(Metric(i)),
+            None => Err(format!(
+                "Unknown metric '{}'; --metrics must be a list of {}",
+                s,
+                names()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.measure().name())
+    }
+}
+
+// With the serde feature, metrics serialize as their names, so serialized
+// rules don't depend on the order of MEASURES.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Metric {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.measure().name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Metric {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Metric, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(::serde::de::Error::custom)
+    }
+}
+
+// The names of the measures, quoted, e.g. "'kulc', 'cosine' or 'lift'".
+pub fn names() -> String {
+    let names: Vec<String> = MEASURES.iter().map(|measure| format!("'{}'", measure.name())).collect();
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_null_invariant_measures() {
+        use super::{Contingency, Cosine, Kulczynski, Measure};
+
+        let table = |ab, a, b| Contingency::new(1000, a, b, ab);
+        assert_eq!(Kulczynski.compute(&table(2, 4, 8)), 0.375);
+        assert_eq!(Cosine.compute(&table(2, 4, 16)), 0.25);
+        assert_eq!(Kulczynski.compute(&table(3, 3, 3)), 1.0);
+        assert_eq!(Cosine.compute(&table(3, 3, 3)), 1.0);
+        // A common antecedent and a rare consequent, which always occur
+        // together, still score highly on Kulczynski but not on cosine.
+        assert_eq!(Kulczynski.compute(&table(1, 100, 1)), 0.505);
+        assert_eq!(Cosine.compute(&table(1, 100, 1)), 0.1);
+    }
+
+    #[test]
+    fn test_metrics() {
+        use super::{names, Contingency, Metric};
+
+        let certainty_factor: Metric = "certainty-factor".parse().unwrap();
+        let added_value: Metric = "added-value".parse().unwrap();
+        // B is in a quarter of transactions, and half of those containing A.
+        let table = Contingency::new(40, 4, 10, 2);
+        assert_eq!(certainty_factor.compute(&table), 1.0 / 3.0);
+        assert_eq!(added_value.compute(&table), 0.25);
+        // A lowers the confidence in B.
+        let table = Contingency::new(40, 8, 10, 1);
+        assert_eq!(certainty_factor.compute(&table), -0.5);
+        assert_eq!(added_value.compute(&table), -0.125);
+
+        for metric in Metric::all() {
+            assert_eq!(metric.to_string().parse::<Metric>(), Ok(metric));
+        }
+        assert!("lift".parse::<Metric>().is_err());
+        assert_eq!(added_value.threshold_option(), "--min-added-value");
+        assert!(added_value.passes(0.5, 0.25) && !added_value.passes(0.0, 0.25));
+        assert_eq!(names(), "'kulc', 'cosine', 'certainty-factor' or 'added-value'");
+    }
+}
@@ -0,0 +1,3464 @@
+use index::Index;
+use pair_counts::PairCounts;
+use itemizer::{normalize_item, read_aliases, read_stop_items, Itemizer};
+use transaction_reader::{expand_input_paths, AppendedCsvFile, CsvFile, InputOptions, Transaction, TransactionSource};
+use transaction_reader::TransactionReader;
+use drift::{measure_drift, DriftReport, TimeBuckets};
+use diff::{compare_rules, DiffReport};
+use class_rules::{class_specific_rules, ClassReport};
+use distributed::{grow_on_workers, serve_shards};
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use db_input::Database;
+use discretize::Discretizer;
+use mining_state::{read_state, write_state};
+use apriori::apriori;
+use eclat::eclat;
+use hmine::hmine;
+use fptree::FPTree;
+use fptree::sort_transaction;
+use fptree::rip_growth;
+use fptree::SortOrder;
+use fptree::ItemSet;
+use generate_rules::{family_wise_filter, generate_rules, permutation_filter, redundancy_filter, RuleGenerator};
+use generate_rules::{group_by_consequent, select_rules, sort_rules, ConsequentGroup, Rule};
+use command_line_args::{Algorithm, Arguments, SequenceArguments, UtilityArguments};
+use command_line_args::{FallbackMaxSupport, LongTransactions, MaxSupportMode, RuleColumn};
+use command_line_args::{GaussianParameters, NullModel, RarityParameters};
+use command_line_args::SignificanceTestMode;
+use significance::{Contingency, SignificanceTest};
+use metrics::Metric;
+use taxonomy::Taxonomy;
+use top_k::{select_top_k, TopK};
+use sequences::{mine_sequential_rules, read_sequences, SequentialRule, SequentialRuleParameters};
+use utility::{hui_miner, read_utility_transactions, HighUtilityItemset, UtilityTable};
+use report::Report;
+use item_counts::{read_item_counts, write_item_counts};
+use ordered_float::OrderedFloat;
+use rand;
+use rand::Rng;
+use rayon::prelude::*;
+use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::mem;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::TcpListener;
+use std::ops::Range;
+use std::path::Path;
+use cancel::{current_token, is_cancelled, take_cancelled, with_token};
+use checkpoint::Checkpointer;
+use spill::MemoryBudget;
+use clock::Phase;
+use serde_json::Value;
+use std::time::Duration;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread;
+use work_estimate::estimate_work;
+
+// The rules found by a mining run, along with the itemizer needed to map
+// their item ids back to item names.
+pub struct MiningResult {
+    pub itemizer: Itemizer,
+    pub rules: Vec<Rule>,
+    // The frequent itemsets the rules were generated from, kept only when
+    // they're written out, by --output-sqlite or --output-parquet.
+    pub itemsets: Vec<ItemSet>,
+    // With --group-by-consequent, a summary of each group of rules with the
+    // same consequent, which the rules are in the order of.
+    pub consequent_groups: Vec<ConsequentGroup>,
+    // The count of each item, after --min-item-count and --max-item-count
+    // have removed any.
+    pub item_count: HashMap<u32, u32>,
+    // With --drift-report, how the rules change across time buckets.
+    pub drift: Option<DriftReport>,
+    // With --class-report, the rules found in only one class.
+    pub classes: Option<ClassReport>,
+    pub report: Report,
+}
+
+// Returns the count of each item, and the number of transactions, taking
+// the transactions' weights into account.
+fn count_item_frequencies<I: Iterator<Item = io::Result<Transaction>>>(
+    reader: I,
+) -> Result<(HashMap<u32, u32>, usize), Box<dyn Error>> {
+    let mut item_count: HashMap<u32, u32> = HashMap::new();
+    let mut num_transactions = 0;
+    for transaction in reader {
+        let transaction = transaction?;
+        num_transactions += transaction.weight as usize;
+        for item in transaction.items {
+            let counter = item_count.entry(item).or_insert(0);
+            *counter += transaction.weight;
+        }
+    }
+    Ok((item_count, num_transactions))
+}
+
+// Collapses repeated transactions into one, with the sum of their weights.
+// Retail datasets in particular have many identical baskets, and inserting
+// each distinct transaction once makes building the tree and index faster.
+// Items are sorted by decreasing count, ready for insertion into the tree.
+// The result is sorted so that trees are built in a consistent order.
+fn aggregate_transactions<I: Iterator<Item = io::Result<Transaction>>>(
+    reader: I,
+    item_count: &HashMap<u32, u32>,
+) -> Result<Vec<Transaction>, Box<dyn Error>> {
+    let mut weights: HashMap<Vec<u32>, u32> = HashMap::new();
+    for transaction in reader {
+        let Transaction { mut items, weight } = transaction?;
+        sort_transaction(&mut items, item_count, SortOrder::Decreasing);
+        *weights.entry(items).or_insert(0) += weight;
+    }
+    let mut transactions: Vec<Transaction> = weights
+        .into_iter()
+        .map(|(items, weight)| Transaction { items, weight })
+        .collect();
+    transactions.sort_by(|a, b| a.items.cmp(&b.items));
+    Ok(transactions)
+}
+
+fn log_stop_items_removed(report: &Report) {
+    if report.num_stop_items_removed > 0 {
+        info!("Removed {} occurrences of stop items.", report.num_stop_items_removed);
+    }
+}
+
+// Removes the items with counts outside --min-item-count and
+// --max-item-count from `item_count`, returning them.
+fn filter_item_counts(item_count: &mut HashMap<u32, u32>, args: &Arguments) -> HashSet<u32> {
+    let min_count = args.min_item_count.unwrap_or(0);
+    let max_count = args.max_item_count.unwrap_or(u32::MAX);
+    let filtered: HashSet<u32> = item_count
+        .iter()
+        .filter(|&(_, &count)| count < min_count || count > max_count)
+        .map(|(&item, _)| item)
+        .collect();
+    item_count.retain(|item, _| !filtered.contains(item));
+    filtered
+}
+
+// Returns the rare items which may be rules' consequents, those within
+// --min-consequent-count and --max-consequent-support.
+fn find_consequent_items(
+    rare_items: &HashSet<u32>,
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    args: &Arguments,
+) -> HashSet<u32> {
+    let min_count = args.min_consequent_count.unwrap_or(0);
+    let max_support = args.max_consequent_support.unwrap_or(1.0);
+    rare_items
+        .iter()
+        .filter(|item| {
+            let count = item_count.get(item).cloned().unwrap_or(0);
+            count >= min_count && count as f64 / num_transactions as f64 <= max_support
+        })
+        .cloned()
+        .collect()
+}
+
+// Removes `items` from each transaction, skipping transactions left empty.
+fn remove_items<'a, I: Iterator<Item = io::Result<Transaction>> + 'a>(
+    reader: I,
+    items: &'a HashSet<u32>,
+) -> impl Iterator<Item = io::Result<Transaction>> + 'a {
+    reader
+        .map(move |transaction| {
+            transaction.map(|mut transaction| {
+                transaction.items.retain(|item| !items.contains(item));
+                transaction
+            })
+        })
+        .filter(|transaction| transaction.as_ref().map_or(true, |t| !t.items.is_empty()))
+}
+
+// Drops transactions with fewer items than --min-transaction-len, and drops
+// or truncates, per --long-transactions, those with more than
+// --max-transaction-len. Truncation keeps the most frequent or rarest items
+// by `item_count`, so without counts, as in the pass which finds them,
+// transactions which would be truncated are kept whole.
+fn limit_transaction_lens<'a, I: Iterator<Item = io::Result<Transaction>> + 'a>(
+    reader: I,
+    item_count: Option<&'a HashMap<u32, u32>>,
+    args: &'a Arguments,
+) -> impl Iterator<Item = io::Result<Transaction>> + 'a {
+    let min_len = args.min_transaction_len.unwrap_or(0);
+    let max_len = args.max_transaction_len.unwrap_or(usize::MAX);
+    reader.filter_map(move |transaction| {
+        let mut transaction = match transaction {
+            Ok(transaction) => transaction,
+            Err(err) => return Some(Err(err)),
+        };
+        if transaction.items.len() < min_len {
+            return None;
+        }
+        if transaction.items.len() > max_len {
+            let order = match args.long_transactions {
+                LongTransactions::Drop => return None,
+                LongTransactions::KeepFrequent => SortOrder::Decreasing,
+                LongTransactions::KeepRare => SortOrder::Increasing,
+            };
+            if let Some(item_count) = item_count {
+                sort_transaction(&mut transaction.items, item_count, order);
+                transaction.items.truncate(max_len);
+            }
+        }
+        Some(Ok(transaction))
+    })
+}
+
+// The transactions to mine: those read from `source`, less the items
+// removed by --min-item-count and --max-item-count, with their lengths
+// limited.
+fn mined_transactions<'a, S: TransactionSource + ?Sized>(
+    source: &'a S,
+    itemizer: &'a mut Itemizer,
+    filtered_items: &'a HashSet<u32>,
+    item_count: &'a HashMap<u32, u32>,
+    args: &'a Arguments,
+) -> impl Iterator<Item = io::Result<Transaction>> + 'a {
+    limit_transaction_lens(remove_items(source.read(itemizer), filtered_items), Some(item_count), args)
+}
+
+// Returns true if the rule contains both an item and one of its categories.
+// Every transaction containing the item also contains its category, so such
+// rules are redundant, e.g. "skim milk milk ==> x" says no more than
+// "skim milk ==> x", and "skim milk ==> milk" always holds.
+fn contains_ancestor_and_descendant(rule: &Rule, itemizer: &Itemizer) -> bool {
+    let taxonomy = match itemizer.taxonomy() {
+        Some(taxonomy) => taxonomy,
+        None => return false,
+    };
+    let items: Vec<&str> = rule
+        .antecedent()
+        .iter()
+        .chain(rule.consequent().iter())
+        .map(|&id| itemizer.str_of(id))
+        .collect();
+    items
+        .iter()
+        .any(|item| items.iter().any(|other| taxonomy.is_ancestor(other, item)))
+}
+
+// Returns the middle of `values`, which must be sorted.
+fn median(values: &[f64]) -> f64 {
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+// Returns the items whose log count is more than `k` median absolute
+// deviations below the median log count. The deviation is scaled to estimate
+// the standard deviation, as for a robust z-score. If most items have the
+// same count, so the deviation is 0, every item below the median is rare.
+fn find_robust_rare_items(item_count: &HashMap<u32, u32>, k: f64) -> HashSet<u32> {
+    if item_count.is_empty() {
+        return HashSet::new();
+    }
+    let mut log_counts: Vec<f64> = item_count.values().map(|&count| (count as f64).ln()).collect();
+    log_counts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median_log_count = median(&log_counts);
+    let mut deviations: Vec<f64> = log_counts
+        .iter()
+        .map(|log_count| (log_count - median_log_count).abs())
+        .collect();
+    deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let deviation = 1.4826 * median(&deviations);
+
+    item_count
+        .iter()
+        .filter(|&(_, &count)| median_log_count - (count as f64).ln() > k * deviation)
+        .map(|(&item, _)| item)
+        .collect()
+}
+
+// Finds the knee of the item counts sorted in increasing order, using the
+// Kneedle algorithm: with both axes scaled to [0,1], the curve of counts is
+// furthest below the line between its ends at the knee, where the counts
+// start rising steeply. Returns the items with counts below the knee's, and
+// the knee's count, if the counts have a knee.
+fn find_elbow_rare_items(item_count: &HashMap<u32, u32>) -> (HashSet<u32>, Option<u32>) {
+    let mut counts: Vec<u32> = item_count.values().cloned().collect();
+    counts.sort();
+    let (min_count, max_count) = match (counts.first(), counts.last()) {
+        (Some(&min_count), Some(&max_count)) if min_count < max_count => (min_count, max_count),
+        _ => {
+            info!("Item counts have no elbow, as they're all the same.");
+            return (HashSet::new(), None);
+        }
+    };
+
+    let last = (counts.len() - 1) as f64;
+    let range = (max_count - min_count) as f64;
+    let mut knee = 0;
+    let mut max_difference = f64::MIN;
+    for (i, &count) in counts.iter().enumerate() {
+        let difference = i as f64 / last - (count - min_count) as f64 / range;
+        if difference > max_difference {
+            max_difference = difference;
+            knee = i;
+        }
+    }
+    let cutoff = counts[knee];
+    info!("Elbow of the item counts is at a count of {}.", cutoff);
+
+    let rare_items = item_count
+        .iter()
+        .filter(|&(_, &count)| count < cutoff)
+        .map(|(&item, _)| item)
+        .collect();
+    (rare_items, Some(cutoff))
+}
+
+// Returns the items with counts at or below the count at the `percentile`th
+// percentile of item counts, and that count.
+fn find_percentile_rare_items(item_count: &HashMap<u32, u32>, percentile: f64) -> (HashSet<u32>, Option<u32>) {
+    let mut counts: Vec<u32> = item_count.values().cloned().collect();
+    if counts.is_empty() {
+        return (HashSet::new(), None);
+    }
+    counts.sort();
+    let rank = (percentile / 100.0 * counts.len() as f64).ceil() as usize;
+    let cutoff = counts[rank.max(1).min(counts.len()) - 1];
+    let rare_items = item_count
+        .iter()
+        .filter(|&(_, &count)| count <= cutoff)
+        .map(|(&item, _)| item)
+        .collect();
+    (rare_items, Some(cutoff))
+}
+
+// Returns the rare items among those in `item_count`, using the max support
+// mode in `args`, and the count below which items are rare, if the mode
+// finds one.
+fn find_rare_items(
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    itemizer: &Itemizer,
+    args: &Arguments,
+) -> (HashSet<u32>, Option<u32>) {
+    find_rare_items_by(&args.max_support_mode, item_count, num_transactions, itemizer, &args.rarity)
+}
+
+// As find_rare_items, but using `mode` and `rarity` rather than args, so
+// --fallback-max-support can try another mode with the same parameters, and
+// the utility and sequences subcommands can find rare items the same way.
+fn find_rare_items_by(
+    mode: &MaxSupportMode,
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    itemizer: &Itemizer,
+    rarity: &RarityParameters,
+) -> (HashSet<u32>, Option<u32>) {
+    match *mode {
+        MaxSupportMode::Gaussian => (
+            find_gaussian_rare_items(item_count, num_transactions, itemizer.max_item_id(), &rarity.gaussian),
+            None,
+        ),
+        MaxSupportMode::Pareto => (find_pareto_rare_items(item_count, rarity.pareto_fraction), None),
+        MaxSupportMode::Robust => (find_robust_rare_items(item_count, rarity.robust_k), None),
+        MaxSupportMode::Elbow => find_elbow_rare_items(item_count),
+    }
+}
+
+// Splits the item counts by the items' most general category in the
+// itemizer's taxonomy. Items outside the taxonomy are their own top level
+// category, so are grouped with the categories themselves.
+fn group_by_category(
+    item_count: &HashMap<u32, u32>,
+    itemizer: &Itemizer,
+) -> HashMap<String, HashMap<u32, u32>> {
+    let taxonomy = itemizer.taxonomy().expect("Grouping by category requires a taxonomy");
+    let mut groups: HashMap<String, HashMap<u32, u32>> = HashMap::new();
+    for (&item, &count) in item_count.iter() {
+        let name = itemizer.str_of(item);
+        let category = if taxonomy.ancestors(name).is_empty() {
+            String::new()
+        } else {
+            String::from(taxonomy.ancestor_at_level(name, 0))
+        };
+        groups.entry(category).or_default().insert(item, count);
+    }
+    groups
+}
+
+// Logs the rare items and their counts, rarest first.
+fn log_rare_items(rare_items: &HashSet<u32>, item_count: &HashMap<u32, u32>, itemizer: &Itemizer) {
+    let mut items: Vec<(&str, u32)> = rare_items
+        .iter()
+        .map(|item| (itemizer.str_of(*item), item_count[item]))
+        .collect();
+    items.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+    info!("Rare items:");
+    for (item, count) in items {
+        info!("{},{}", item, count);
+    }
+}
+
+// Returns true if transaction contains at least one rate item.
+fn contains_rare_item(transaction: &[u32], rare_items: &HashSet<u32>) -> bool {
+    transaction.iter().any(|item| rare_items.contains(item))
+}
+
+fn find_gaussian_rare_items(
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    max_item_id: u32,
+    parameters: &GaussianParameters,
+) -> HashSet<u32> {
+    let avg_transaction_len = (item_count.iter().fold(0, |acc, (_, count)| acc + count) as f64 /
+        num_transactions as f64)
+        .ceil() as u32;
+
+    let max_item_count = item_count
+        .iter()
+        .fold(0, |acc, (_, count)| max(acc, *count));
+
+    let delta = parameters.delta;
+    let epsilon = ((max_item_count as f64).powi(2) * (1.0_f64 / delta).ln() /
+        (2.0 * num_transactions as f64))
+        .sqrt();
+
+    // For the frequency null model, items are drawn by where a random
+    // number falls among the items' cumulative counts.
+    let mut items: Vec<u32> = item_count.keys().cloned().collect();
+    items.sort();
+    let cumulative_counts: Vec<u64> = items
+        .iter()
+        .scan(0, |sum, item| {
+            *sum += item_count[item] as u64;
+            Some(*sum)
+        })
+        .collect();
+    let total_count = cumulative_counts.last().cloned().unwrap_or(0);
+
+    // Generate randomly distributed datasets in parallel,
+    // and reduce into the minimum count of each item over all datasets.
+    let min_count: HashMap<u32, u32> = (0..parameters.simulations)
+        .into_par_iter()
+        .map(|_| {
+            let mut rng = rand::thread_rng();
+            let mut random_dataset = HashMap::new();
+            for _ in 0..num_transactions {
+                for _ in 0..avg_transaction_len {
+                    let random_item = match parameters.null_model {
+                        NullModel::Uniform => rng.gen_range(0, max_item_id + 1),
+                        NullModel::Frequency => {
+                            let draw = rng.gen_range(0, total_count);
+                            items[cumulative_counts.partition_point(|&sum| sum <= draw)]
+                        }
+                    };
+                    *random_dataset.entry(random_item).or_insert(0) += 1;
+                }
+            }
+            random_dataset
+        })
+        .reduce(HashMap::new, |mut min_count, random_dataset| {
+            for (item, count) in random_dataset.iter() {
+                let p = min_count.entry(*item).or_insert(*count);
+                *p = min(*p, *count);
+            }
+            min_count
+        });
+
+    // See if the count in the actual dataset is significantly different from
+    // the random datasets.
+    let mut rare_items: HashSet<u32> = HashSet::new();
+    for (item, count) in item_count.iter() {
+        // Items never drawn have a minimum count of 0.
+        let random_min_count = min_count.get(item).cloned().unwrap_or(0) as f64;
+        if (random_min_count - (*count as f64)) > epsilon {
+            rare_items.insert(*item);
+        }
+    }
+
+    rare_items
+}
+
+// Returns the rarest items which together account for `fraction` of all item
+// occurrences.
+fn find_pareto_rare_items(item_count: &HashMap<u32, u32>, fraction: f64) -> HashSet<u32> {
+    // Sort (item, count) pairs by increasing frequency, and accumulate the
+    // total sum of the counts of all items.
+    let mut item_count_sum = 0;
+    let mut items = Vec::with_capacity(item_count.len());
+    for (&item, &count) in item_count.iter() {
+        item_count_sum += count;
+        items.push((item, count));
+    }
+    items.sort_by_key(|&(_, count)| count);
+
+    let threshold = (fraction * item_count_sum as f64) as u32;
+    let mut rare_items: HashSet<u32> = HashSet::new();
+    let mut sum = 0;
+    let mut prev_count = 0;
+    for (item, count) in items {
+        sum += count;
+        // If this item has the same count as the previous rare item, include
+        // it. This ensures that all items of the same count are included
+        // if any are included, otherwise, the order in which items are
+        // iterated here is significant in the results, i.e. they're
+        // non-deterministic.
+        if sum >= threshold && prev_count != count {
+            break;
+        }
+        rare_items.insert(item);
+        prev_count = count;
+    }
+
+    rare_items
+}
+
+// Returns epsilon such that an itemset's support in a random sample of
+// num_transactions transactions is within epsilon of its support in the full
+// dataset with probability 1 - delta, by Hoeffding's inequality.
+fn sampling_error(num_transactions: usize, delta: f64) -> f64 {
+    ((2.0 / delta).ln() / (2.0 * num_transactions as f64)).sqrt()
+}
+
+// When mining a sample, an item which is rare in the full dataset may appear
+// slightly more often in the sample than the rare items' support cutoff.
+// Raise the cutoff by the sampling error, so such items aren't missed.
+fn add_possibly_rare_items(
+    rare_items: &mut HashSet<u32>,
+    item_count: &HashMap<u32, u32>,
+    num_transactions: usize,
+    epsilon: f64,
+) {
+    let max_rare_count = match rare_items.iter().map(|item| item_count[item]).max() {
+        Some(count) => count,
+        None => return,
+    };
+    let cutoff = max_rare_count as f64 / num_transactions as f64 + epsilon;
+    for (&item, &count) in item_count.iter() {
+        if count as f64 / num_transactions as f64 <= cutoff {
+            rare_items.insert(item);
+        }
+    }
+}
+
+// Returns the range of random draws for which lines of the input are mined;
+// see TransactionReader. Lines with draws below the holdout fraction are held
+// out, and a sample is taken from the rest.
+fn mining_line_range(args: &Arguments) -> Option<Range<f64>> {
+    if args.holdout_fraction.is_none() && args.sample_fraction.is_none() {
+        return None;
+    }
+    let start = args.holdout_fraction.unwrap_or(0.0);
+    Some(start..start + (1.0 - start) * args.sample_fraction.unwrap_or(1.0))
+}
+
+// Returns the files to mine, with any directories expanded.
+fn input_files(args: &Arguments) -> Result<Vec<String>, Box<dyn Error>> {
+    let files = expand_input_paths(&args.input_file_paths)?;
+    if files.is_empty() {
+        return Err(From::from("No input files to mine"));
+    }
+    if files.len() > 1 {
+        info!("Reading {} input files.", files.len());
+    }
+    Ok(files)
+}
+
+fn input_options(args: &Arguments) -> InputOptions {
+    InputOptions {
+        format: args.input_format,
+        weight_column: args.weight_column,
+        timestamp_column: args.timestamp_column,
+        class_column: args.class_column,
+        class: None,
+        items_column: args.items_column.clone(),
+        tabular: args.tabular,
+        null_values: args.null_values.clone(),
+        missing_value: args.missing_value.clone(),
+        encoding: args.encoding,
+        on_bad_line: args.on_bad_line,
+    }
+}
+
+// Measures the rules on the test file, or the lines held out from mining.
+fn evaluate_holdout(result: &mut MiningResult, args: &Arguments) -> Result<(), Box<dyn Error>> {
+    let source = if !args.test_file_path.is_empty() {
+        CsvFile::new(vec![args.test_file_path.clone()], input_options(args), None)
+    } else if let Some(fraction) = args.holdout_fraction {
+        CsvFile::new(
+            expand_input_paths(&args.input_file_paths)?,
+            input_options(args),
+            Some(0.0..fraction),
+        )
+    } else {
+        return Ok(());
+    };
+
+    let mut index = Index::new();
+    for transaction in source.read(&mut result.itemizer) {
+        let transaction = transaction?;
+        index.insert(&transaction.items, transaction.weight);
+    }
+    info!(
+        "Evaluating rules on {} held out transactions.",
+        index.num_transactions()
+    );
+    for rule in result.rules.iter_mut() {
+        rule.evaluate_holdout(&index);
+    }
+    Ok(())
+}
+
+// Splits the transactions mined into args.drift_buckets buckets of time by
+// their timestamps, and measures the rules in each, for --drift-report. The
+// first pass over the input finds the range of the timestamps, and the
+// second indexes the transactions in each bucket.
+fn analyze_drift(result: &mut MiningResult, args: &Arguments) -> Result<(), Box<dyn Error>> {
+    if args.drift_report_path.is_empty() {
+        return Ok(());
+    }
+    let paths = input_files(args)?;
+    let options = input_options(args);
+    let (mut first, mut last) = (f64::INFINITY, f64::NEG_INFINITY);
+    let dated = {
+        let line_range = mining_line_range(args);
+        let mut reader = TransactionReader::new(&paths, &mut result.itemizer, &options, line_range, None);
+        while let Some(transaction) = reader.next() {
+            transaction?;
+            if let Some(timestamp) = reader.timestamp() {
+                first = first.min(timestamp);
+                last = last.max(timestamp);
+            }
+        }
+        reader.timestamps_are_dates()
+    };
+    if first > last {
+        return Err(From::from("--drift-report found no transactions to split by time"));
+    }
+    let buckets = TimeBuckets::new(first, last, args.drift_buckets, dated);
+    let mut indexes: Vec<Index> = (0..buckets.len()).map(|_| Index::new()).collect();
+    {
+        let line_range = mining_line_range(args);
+        let mut reader = TransactionReader::new(&paths, &mut result.itemizer, &options, line_range, None);
+        while let Some(transaction) = reader.next() {
+            let transaction = transaction?;
+            let bucket = buckets.bucket(reader.timestamp().unwrap_or(first));
+            indexes[bucket].insert(&transaction.items, transaction.weight);
+        }
+    }
+    for (bucket, index) in indexes.iter().enumerate() {
+        info!(
+            "Drift bucket from {} has {} transactions.",
+            buckets.label(bucket),
+            index.num_transactions()
+        );
+    }
+    let report = measure_drift(&result.rules, &indexes, buckets);
+    info!(
+        "{} of {} rules drift significantly over time (p < {:e}).",
+        report.num_drifting(),
+        result.rules.len(),
+        report.threshold
+    );
+    result.drift = Some(report);
+    Ok(())
+}
+
+// Mines the transactions of each class of the --class-column separately,
+// for --class-report, and keeps the rules found in only one class. The first
+// pass over the input finds the classes and indexes the transactions of
+// each, and then each class is mined as a dataset of its own, sharing the
+// itemizer.
+fn analyze_classes(result: &mut MiningResult, args: &Arguments) -> Result<(), Box<dyn Error>> {
+    if args.class_report_path.is_empty() {
+        return Ok(());
+    }
+    let paths = input_files(args)?;
+    let options = input_options(args);
+    let mut classes: Vec<String> = vec![];
+    let mut indexes: Vec<Index> = vec![];
+    {
+        let line_range = mining_line_range(args);
+        let mut reader = TransactionReader::new(&paths, &mut result.itemizer, &options, line_range, None);
+        while let Some(transaction) = reader.next() {
+            let transaction = transaction?;
+            let class = reader.class().unwrap_or_default();
+            let position = match classes.iter().position(|c| c == class) {
+                Some(position) => position,
+                None => {
+                    classes.push(class.to_owned());
+                    indexes.push(Index::new());
+                    classes.len() - 1
+                }
+            };
+            indexes[position].insert(&transaction.items, transaction.weight);
+        }
+    }
+    if classes.len() < 2 {
+        return Err(From::from("--class-report needs transactions of at least two classes"));
+    }
+
+    let mut itemizer = mem::take(&mut result.itemizer);
+    let mut class_rules: Vec<Vec<Rule>> = vec![];
+    for (class, index) in classes.iter().zip(indexes.iter()) {
+        info!(
+            "Mining the {} transactions of class {}.",
+            index.num_transactions(),
+            class
+        );
+        let source = CsvFile::new(
+            paths.clone(),
+            InputOptions {
+                class: Some(class.clone()),
+                ..input_options(args)
+            },
+            mining_line_range(args),
+        );
+        let class_result = mine_source(&source, itemizer, args)?;
+        itemizer = class_result.itemizer;
+        class_rules.push(class_result.rules);
+    }
+    result.itemizer = itemizer;
+
+    let report = class_specific_rules(classes, class_rules, &indexes);
+    info!(
+        "Found {} rules in only one of {} classes.",
+        report.rules.len(),
+        report.classes.len()
+    );
+    result.classes = Some(report);
+    Ok(())
+}
+
+// Sets up the itemizer to discretize the numeric columns in
+// args.numeric_columns and args.cut_points, if there are any. Cut points not
+// given are found by binning the values in `source`, which takes an extra
+// pass over it.
+fn discretize_numeric_columns<S: TransactionSource + ?Sized>(
+    source: &S,
+    itemizer: &mut Itemizer,
+    args: &Arguments,
+) -> Result<(), Box<dyn Error>> {
+    if args.numeric_columns.is_empty() && args.cut_points.is_empty() {
+        return Ok(());
+    }
+    let needs_values = args
+        .numeric_columns
+        .iter()
+        .any(|column| !args.cut_points.iter().any(|(c, _)| c == column));
+    let mut item_counts: Vec<(String, u32)> = vec![];
+    if needs_values {
+        info!("Reading values of numeric columns.");
+        let mut values = Itemizer::new();
+        let (counts, _) = count_item_frequencies(source.read(&mut values))?;
+        item_counts = counts.iter().map(|(&id, &count)| (values.str_of(id).to_owned(), count)).collect();
+    }
+    let discretizer = Discretizer::new(
+        &args.numeric_columns,
+        &args.binning,
+        args.bins,
+        &args.cut_points,
+        item_counts.iter().map(|&(ref item, count)| (item.as_str(), count)),
+    );
+    for column in args.numeric_columns.iter().chain(args.cut_points.iter().map(|(c, _)| c)) {
+        if let Some(cut_points) = discretizer.cut_points(column) {
+            info!("Cut points for {}: {:?}", column, cut_points);
+        }
+    }
+    itemizer.set_discretizer(discretizer);
+    Ok(())
+}
+
+// Mines the input named by `args`, as mine() and mine_with() do, handing
+// the rules to `rule_sink` as they're generated, if there is one.
+fn mine_input(args: &Arguments, rule_sink: Option<&mut RuleSink>) -> Result<MiningResult, Box<dyn Error>> {
+    let mut itemizer = Itemizer::new();
+    // Without the sqlite or postgres features, --input-db is rejected up
+    // front.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    if !args.input_db_url.is_empty() {
+        let source = Database {
+            url: args.input_db_url.clone(),
+            query: args.input_query.clone(),
+        };
+        discretize_numeric_columns(&source, &mut itemizer, args)?;
+        return mine_source_with(&source, itemizer, args, rule_sink);
+    }
+    let source = CsvFile::new(
+        input_files(args)?,
+        input_options(args),
+        mining_line_range(args),
+    );
+    discretize_numeric_columns(&source, &mut itemizer, args)?;
+    mine_source_with(&source, itemizer, args, rule_sink)
+}
+
+// Mines the CSV or Parquet files in args.input_file_paths, or the results of
+// args.input_query on the database at args.input_db_url.
+pub fn mine(args: &Arguments) -> Result<MiningResult, Box<dyn Error>> {
+    let mut result = mine_input(args, None)?;
+    evaluate_holdout(&mut result, args)?;
+    if args.input_db_url.is_empty() {
+        analyze_drift(&mut result, args)?;
+        analyze_classes(&mut result, args)?;
+    }
+    Ok(result)
+}
+
+// Mines like mine(), but hands each rule to `callback` as soon as rule
+// generation makes it and the checks which need only that rule pass, so
+// embedders can send rules elsewhere, such as to a message queue or a
+// database, without them all being held in memory. The rules arrive in no
+// particular order, and the result has none, but its itemizer maps their
+// item ids to item names. The options which need every rule at once, such
+// as family-wise filtering, are refused.
+pub fn mine_with<F: FnMut(&Rule) + Send>(args: &Arguments, mut callback: F) -> Result<MiningResult, Box<dyn Error>> {
+    let needs_all_rules = [
+        (!args.disable_family_wise_rule_filtering, "Family-wise rule filtering"),
+        (!args.disable_permutation_rule_filtering, "Permutation rule filtering"),
+        (args.filter_redundant_rules.is_some(), "--filter-redundant-rules"),
+        (args.max_rules_per_consequent.is_some(), "--max-rules-per-consequent"),
+        (args.group_by_consequent, "--group-by-consequent"),
+        (!args.test_file_path.is_empty() || args.holdout_fraction.is_some(), "Holdout evaluation"),
+        (!args.drift_report_path.is_empty(), "--drift-report"),
+        (!args.class_report_path.is_empty(), "--class-report"),
+    ];
+    if let Some(&(_, option)) = needs_all_rules.iter().find(|&&(used, _)| used) {
+        return Err(From::from(format!(
+            "{} needs all the rules at once, so can't be used with mine_with",
+            option
+        )));
+    }
+    mine_input(args, Some(&mut callback))
+}
+
+// Mines transactions held in memory; args.input_file_paths is ignored. This
+// path does no file IO, so it can run in the browser.
+pub fn mine_transactions(
+    transactions: &[Vec<String>],
+    args: &Arguments,
+) -> Result<MiningResult, Box<dyn Error>> {
+    let mut itemizer = Itemizer::new();
+    discretize_numeric_columns(transactions, &mut itemizer, args)?;
+    mine_source(transactions, itemizer, args)
+}
+
+// Mines the transactions saved in the state file at state_path along with the
+// new transactions in args.input_file_paths, and then saves them all back to
+// the state file for next time. The state file is created if it doesn't
+// exist yet.
+pub fn update(state_path: &str, args: &Arguments) -> Result<MiningResult, Box<dyn Error>> {
+    if !args.drift_report_path.is_empty() {
+        // The saved transactions have no timestamps.
+        return Err(From::from("--drift-report can't be used with riptree update"));
+    }
+    if !args.class_report_path.is_empty() {
+        return Err(From::from("--class-report can't be used with riptree update"));
+    }
+    let (mut itemizer, previous) = if Path::new(state_path).exists() {
+        read_state(&mut BufReader::new(File::open(state_path)?))?
+    } else {
+        (Itemizer::new(), vec![])
+    };
+    info!(
+        "Loaded {} distinct transactions from {}.",
+        previous.len(),
+        state_path
+    );
+
+    let source = AppendedCsvFile {
+        previous: &previous,
+        appended: CsvFile::new(
+            input_files(args)?,
+            input_options(args),
+            mining_line_range(args),
+        ),
+    };
+    // The saved transactions are already discretized, so cut points are
+    // found from the new transactions only.
+    discretize_numeric_columns(&source.appended, &mut itemizer, args)?;
+    let mut result = mine_source(&source, itemizer, args)?;
+    evaluate_holdout(&mut result, args)?;
+
+    let transactions = aggregate_transactions(source.read(&mut result.itemizer), &HashMap::new())?;
+    info!(
+        "Saving {} distinct transactions to {}.",
+        transactions.len(),
+        state_path
+    );
+    // Write to a temporary file first, so that a failed write doesn't lose
+    // the existing state.
+    let temp_path = format!("{}.tmp", state_path);
+    {
+        let mut output = BufWriter::new(File::create(&temp_path)?);
+        write_state(&mut output, &result.itemizer, &transactions)?;
+        output.flush()?;
+    }
+    fs::rename(&temp_path, state_path)?;
+
+    Ok(result)
+}
+
+pub struct DiffResult {
+    pub itemizer: Itemizer,
+    pub diff: DiffReport,
+}
+
+// Reads a dataset into an index, with the lines mined.
+fn index_dataset(source: &CsvFile, itemizer: &mut Itemizer) -> Result<Index, Box<dyn Error>> {
+    let mut index = Index::new();
+    for transaction in source.read(itemizer) {
+        let transaction = transaction?;
+        index.insert(&transaction.items, transaction.weight);
+    }
+    Ok(index)
+}
+
+// Mines the datasets in args.input_file_paths (A) and input_b_paths (B),
+// sharing an itemizer so their rules can be compared, and finds the rules
+// from either whose support or confidence differs significantly between
+// them. Numeric columns are discretized by the values in A, so both datasets
+// are cut the same way.
+pub fn mine_diff(args: &Arguments, input_b_paths: &[String]) -> Result<DiffResult, Box<dyn Error>> {
+    if !args.drift_report_path.is_empty() {
+        return Err(From::from("--drift-report can't be used with riptree diff"));
+    }
+    if !args.class_report_path.is_empty() {
+        return Err(From::from("--class-report can't be used with riptree diff"));
+    }
+    let source_a = CsvFile::new(input_files(args)?, input_options(args), mining_line_range(args));
+    let source_b = CsvFile::new(
+        expand_input_paths(input_b_paths)?,
+        input_options(args),
+        mining_line_range(args),
+    );
+    let mut itemizer = Itemizer::new();
+    discretize_numeric_columns(&source_a, &mut itemizer, args)?;
+    info!("Mining dataset A: {}", args.input_file_paths.join(", "));
+    let result_a = mine_source(&source_a, itemizer, args)?;
+    info!("Mining dataset B: {}", input_b_paths.join(", "));
+    let mut result_b = mine_source(&source_b, result_a.itemizer, args)?;
+    info!(
+        "Found {} rules in A and {} in B.",
+        result_a.rules.len(),
+        result_b.rules.len()
+    );
+
+    let index_a = index_dataset(&source_a, &mut result_b.itemizer)?;
+    let index_b = index_dataset(&source_b, &mut result_b.itemizer)?;
+    let diff = compare_rules(result_a.rules, result_b.rules, &index_a, &index_b);
+    info!(
+        "{} of {} rules differ significantly between A and B (p < {:e}).",
+        diff.rules.len(),
+        diff.num_rules_tested,
+        diff.threshold
+    );
+    Ok(DiffResult {
+        itemizer: result_b.itemizer,
+        diff,
+    })
+}
+
+pub struct HighUtilityResult {
+    pub itemizer: Itemizer,
+    pub itemsets: Vec<HighUtilityItemset>,
+}
+
+// Finds the high utility itemsets containing rare items in
+// args.input_file_path. The itemsets are sorted by decreasing utility.
+pub fn mine_high_utility(args: &UtilityArguments) -> Result<HighUtilityResult, Box<dyn Error>> {
+    let table = UtilityTable::from_file(&args.utilities_path)?;
+    let mut itemizer = Itemizer::new();
+    let transactions = read_utility_transactions(
+        BufReader::new(File::open(&args.input_file_path)?),
+        &mut itemizer,
+        &table,
+    )?;
+    info!("Read {} transactions.", transactions.len());
+
+    let mut item_count: HashMap<u32, u32> = HashMap::new();
+    for transaction in transactions.iter() {
+        for &(item, _) in transaction {
+            *item_count.entry(item).or_insert(0) += 1;
+        }
+    }
+    let (rare_items, _) = find_rare_items_by(
+        &args.max_support_mode,
+        &item_count,
+        transactions.len(),
+        &itemizer,
+        &args.rarity,
+    );
+    info!(
+        "{} of {} items are considered rare.",
+        rare_items.len(),
+        item_count.len()
+    );
+
+    let phase = Phase::start("Mining high utility itemsets");
+    let mut itemsets = hui_miner(&transactions, args.min_utility, &rare_items);
+    drop(phase);
+    info!("Found {} high utility itemsets.", itemsets.len());
+    itemsets.sort_by_cached_key(|itemset| {
+        (
+            OrderedFloat::from(-itemset.utility),
+            itemset.to_string(&itemizer),
+        )
+    });
+    Ok(HighUtilityResult { itemizer, itemsets })
+}
+
+pub struct SequentialResult {
+    pub itemizer: Itemizer,
+    pub rules: Vec<SequentialRule>,
+}
+
+// Finds the sequential rules with rare consequents in args.input_file_path.
+// An item is rare if it occurs in few sequences. The rules are sorted by
+// decreasing confidence.
+pub fn mine_sequences(args: &SequenceArguments) -> Result<SequentialResult, Box<dyn Error>> {
+    let mut itemizer = Itemizer::new();
+    let sequences = read_sequences(
+        BufReader::new(File::open(&args.input_file_path)?),
+        &mut itemizer,
+    )?;
+    info!("Read {} sequences.", sequences.len());
+
+    let mut item_count: HashMap<u32, u32> = HashMap::new();
+    for sequence in sequences.iter() {
+        let items: HashSet<u32> = sequence.iter().flat_map(|event| event.iter().cloned()).collect();
+        for item in items {
+            *item_count.entry(item).or_insert(0) += 1;
+        }
+    }
+    let (rare_items, _) = find_rare_items_by(
+        &args.max_support_mode,
+        &item_count,
+        sequences.len(),
+        &itemizer,
+        &args.rarity,
+    );
+    info!(
+        "{} of {} items are considered rare.",
+        rare_items.len(),
+        item_count.len()
+    );
+
+    let phase = Phase::start("Mining sequential rules");
+    let parameters = SequentialRuleParameters {
+        min_count: args.min_count,
+        max_antecedent_len: args.max_antecedent_len,
+        min_confidence: args.min_confidence,
+        min_lift: args.min_lift,
+    };
+    let mut rules = mine_sequential_rules(&sequences, &rare_items, &parameters);
+    drop(phase);
+    info!("Generated {} rules.", rules.len());
+    rules.sort_by_cached_key(|rule| {
+        (
+            OrderedFloat::from(-rule.confidence),
+            rule.to_string(&itemizer),
+        )
+    });
+    Ok(SequentialResult { itemizer, rules })
+}
+
+// The number of batches of itemsets, one per rare item, which may be waiting
+// for rule generation while mining, which bounds the memory they use when
+// rules are generated more slowly than itemsets are mined.
+const ITEMSET_BATCHES: usize = 64;
+
+// Runs `mine`, generating rules from the batches of itemsets it sends as
+// they arrive, on another thread. Returns the itemsets, if `keep_itemsets`,
+// how many there were, and the rules.
+fn generate_rules_while_mining<F: FnOnce(&SyncSender<Vec<ItemSet>>)>(
+    mine: F,
+    mut generator: RuleGenerator,
+    keep_itemsets: bool,
+) -> (Vec<ItemSet>, usize, HashSet<Rule>) {
+    // Rule generation is cancelled along with the run it's part of.
+    let token = current_token();
+    thread::scope(|scope| {
+        let (sender, receiver) = sync_channel::<Vec<ItemSet>>(ITEMSET_BATCHES);
+        let rules = scope.spawn(move || {
+            with_token(token, || {
+                let mut itemsets: Vec<ItemSet> = vec![];
+                let mut num_itemsets = 0;
+                for batch in receiver {
+                    num_itemsets += batch.len();
+                    if !is_cancelled() {
+                        for itemset in batch.iter() {
+                            generator.add(itemset);
+                        }
+                    }
+                    if keep_itemsets {
+                        itemsets.extend(batch);
+                    }
+                }
+                (itemsets, num_itemsets, generator.finish())
+            })
+        });
+        mine(&sender);
+        drop(sender);
+        rules.join().expect("Rule generation panicked")
+    })
+}
+
+// Mines `source` in partitions of `partition_size` transactions, for
+// --partition-size, so only one partition's tree and index are in memory at
+// a time, in the style of Savasere, Omiecinski and Navathe (1995). Each
+// partition is mined on its own, and the union of the itemsets found in any
+// of them are the candidates. A second pass over the dataset then counts the
+// candidates, and their subsets, exactly, in an index which keeps only those
+// counts. Returns that index, and the candidates with their counts.
+#[allow(clippy::too_many_arguments)]
+fn mine_partitions<S: TransactionSource + ?Sized>(
+    source: &S,
+    itemizer: &mut Itemizer,
+    partition_size: usize,
+    filtered_items: &HashSet<u32>,
+    args: &Arguments,
+    item_count: &HashMap<u32, u32>,
+    rare_items: &HashSet<u32>,
+    significance_test: &SignificanceTest,
+    max_itemset_len: Option<usize>,
+    required_items: &HashSet<u32>,
+    budget: Option<&MemoryBudget>,
+) -> Result<(Index, Vec<ItemSet>), Box<dyn Error>> {
+    let mut candidates: HashSet<Vec<u32>> = HashSet::new();
+    let mut num_partitions = 0;
+    {
+        let mut reader = mined_transactions(source, itemizer, filtered_items, item_count, args).peekable();
+        // The reader borrows the itemizer, and rip_growth only passes its
+        // itemizer down, so each partition is mined with an empty one.
+        let itemizer = Itemizer::default();
+        while reader.peek().is_some() {
+            let transactions = aggregate_transactions(reader.by_ref().take(partition_size), item_count)?;
+            let mut fptree = FPTree::new();
+            let mut index = Index::new();
+            for transaction in transactions.iter() {
+                index.insert(&transaction.items, transaction.weight);
+                if contains_rare_item(&transaction.items, rare_items) {
+                    fptree.insert(&transaction.items, transaction.weight);
+                }
+            }
+            let itemsets = rip_growth(
+                &fptree,
+                &fptree,
+                Some(rare_items),
+                &[],
+                index.num_transactions() as u32,
+                &itemizer,
+                &index,
+                significance_test,
+                max_itemset_len,
+                required_items,
+                None,
+                None,
+                budget,
+                None,
+            );
+            num_partitions += 1;
+            debug!(
+                "Partition {} has {} distinct transactions, and {} itemsets.",
+                num_partitions,
+                transactions.len(),
+                itemsets.len()
+            );
+            for itemset in itemsets {
+                let mut items = itemset.items;
+                items.sort();
+                candidates.insert(items);
+            }
+        }
+    }
+    let mut candidates: Vec<Vec<u32>> = candidates.into_iter().collect();
+    candidates.sort();
+    info!(
+        "Mined {} partitions, finding {} candidate itemsets.",
+        num_partitions,
+        candidates.len()
+    );
+
+    let mut index = Index::counting(&candidates);
+    for transaction in mined_transactions(source, itemizer, filtered_items, item_count, args) {
+        let transaction = transaction?;
+        index.insert(&transaction.items, transaction.weight);
+    }
+    info!(
+        "Counted {} itemsets of two or more items in a second pass.",
+        index.num_counted_itemsets().unwrap_or(0)
+    );
+    let itemsets = candidates
+        .into_iter()
+        .map(|items| {
+            let count = index.count(&items) as u32;
+            ItemSet { items, count }
+        })
+        .collect();
+    Ok((index, itemsets))
+}
+
+// The options and data which a checkpoint's itemsets depend on, so a run is
+// only resumed from a checkpoint which would have found the same itemsets.
+fn checkpoint_signature(
+    args: &Arguments,
+    num_transactions: usize,
+    rare_items: &HashSet<u32>,
+    itemizer: &Itemizer,
+) -> Value {
+    let mut rare_items: Vec<&str> = rare_items.iter().map(|&id| itemizer.str_of(id)).collect();
+    rare_items.sort();
+    json!({
+        "transactions": num_transactions,
+        "rare_items": rare_items,
+        "significance_test": args.significance_test.to_string(),
+        "yates_correction": args.yates_correction,
+        "max_antecedent_len": args.max_antecedent_len,
+        "must_contain": args.must_contain_items,
+        "exclude": args.exclude_items,
+        "stop_items": args.stop_items_path,
+        "aliases": args.alias_path,
+        "normalize_case": args.normalize_case,
+        "trim_internal_whitespace": args.trim_internal_whitespace,
+        "min_item_count": args.min_item_count,
+        "max_item_count": args.max_item_count,
+        "taxonomy": args.taxonomy_path,
+        "taxonomy_level": args.taxonomy_level,
+        "tabular": args.tabular,
+        "null_values": args.null_values,
+        "missing_value": args.missing_value,
+        "numeric_columns": args.numeric_columns,
+        "binning": args.binning.to_string(),
+        "bins": args.bins,
+        "cut_points": args.cut_points,
+        "top_k": args.top_k,
+        "approx_index": args.approx_index,
+        "approx_itemset_len": args.approx_itemset_len,
+    })
+}
+
+// Mines `source`. Items are numbered by `itemizer`, which may already know
+// some items, as when updating.
+pub fn mine_source<S: TransactionSource + ?Sized>(
+    source: &S,
+    itemizer: Itemizer,
+    args: &Arguments,
+) -> Result<MiningResult, Box<dyn Error>> {
+    mine_source_with(source, itemizer, args, None)
+}
+
+// Receives the rules as they're generated, for mine_with.
+type RuleSink<'a> = dyn FnMut(&Rule) + Send + 'a;
+
+// The metrics calculated for each rule: those asked for with --metrics, and
+// those with thresholds or output columns, which are so output even if they
+// weren't asked for.
+fn metrics_to_calculate(args: &Arguments) -> Vec<Metric> {
+    let mut metrics: Vec<Metric> = args.metrics.clone();
+    metrics.extend(args.metric_thresholds.iter().map(|&(metric, _)| metric));
+    metrics.extend(args.output_columns.iter().filter_map(|column| match *column {
+        RuleColumn::Metric(metric) => Some(metric),
+        _ => None,
+    }));
+    metrics.sort();
+    metrics.dedup();
+    metrics
+}
+
+// Finishes the rules from rule generation one at a time, for mine_with,
+// with the measures and filters which need only that rule, and hands those
+// kept to the sink.
+struct RuleStream<'a, 's> {
+    sink: &'a mut RuleSink<'s>,
+    args: &'a Arguments,
+    index: &'a Index,
+    itemizer: &'a Itemizer,
+    metrics: &'a [Metric],
+    example_transactions: &'a [Vec<u32>],
+    num_generated: usize,
+    num_sent: usize,
+}
+
+impl<'a, 's> RuleStream<'a, 's> {
+    fn send(&mut self, mut rule: Rule) {
+        self.num_generated += 1;
+        let args = self.args;
+        if contains_ancestor_and_descendant(&rule, self.itemizer) {
+            return;
+        }
+        let table = rule.contingency(self.index);
+        rule.calculate_odds_ratio(&table);
+        if rule.odds_ratio().unwrap().ratio() < args.min_odds_ratio {
+            return;
+        }
+        if let Some(max_ratio) = args.max_cross_support_ratio {
+            rule.calculate_imbalance(&table);
+            if rule.imbalance().unwrap().cross_support_ratio() > max_ratio {
+                return;
+            }
+        }
+        if !self.metrics.is_empty() {
+            rule.calculate_metrics(&table, self.metrics);
+        }
+        let passes = |&(metric, threshold): &(Metric, f64)| metric.passes(rule.metric(metric).unwrap(), threshold);
+        if !args.metric_thresholds.iter().all(passes) {
+            return;
+        }
+        if let Some(resamples) = args.bootstrap_resamples {
+            rule.calculate_bootstrap_intervals(&table, resamples);
+        }
+        if let Some(max_examples) = args.emit_examples {
+            rule.find_examples(self.index, self.example_transactions, max_examples);
+        }
+        self.num_sent += 1;
+        (self.sink)(&rule);
+    }
+}
+
+fn mine_source_with<S: TransactionSource + ?Sized>(
+    source: &S,
+    mut itemizer: Itemizer,
+    args: &Arguments,
+    rule_sink: Option<&mut RuleSink>,
+) -> Result<MiningResult, Box<dyn Error>> {
+    let mut report = Report::default();
+    if args.normalize_case || args.trim_internal_whitespace {
+        let (lowercase, collapse_whitespace) = (args.normalize_case, args.trim_internal_whitespace);
+        itemizer.set_normalizer(move |item| normalize_item(item, lowercase, collapse_whitespace));
+    }
+    // Items named in the options are normalized to match those read.
+    for item in args.exclude_items.iter() {
+        let item = itemizer.normalize(item).into_owned();
+        itemizer.exclude(&item);
+    }
+    if !args.alias_path.is_empty() {
+        let aliases = read_aliases(BufReader::new(File::open(&args.alias_path)?))?;
+        let aliases = aliases
+            .iter()
+            .map(|(raw, canonical)| {
+                (itemizer.normalize(raw).into_owned(), itemizer.normalize(canonical).into_owned())
+            })
+            .collect();
+        itemizer.set_aliases(aliases);
+    }
+    if !args.stop_items_path.is_empty() {
+        let stop_items = read_stop_items(BufReader::new(File::open(&args.stop_items_path)?))?;
+        let stop_items = stop_items.iter().map(|item| itemizer.normalize(item).into_owned()).collect();
+        itemizer.set_stop_items(stop_items);
+    }
+    if !args.taxonomy_path.is_empty() {
+        itemizer.set_taxonomy(Taxonomy::from_file(&args.taxonomy_path)?, args.taxonomy_level);
+    }
+    // Make one pass of the dataset to calculate the item frequencies
+    // for the initial tree, unless they were saved by an earlier run.
+    let (mut item_count, num_transactions) = if args.load_item_counts_path.is_empty() {
+        let phase = Phase::start("Counting item frequencies");
+        let counts = count_item_frequencies(limit_transaction_lens(source.read(&mut itemizer), None, args))?;
+        report.end_phase(phase);
+        report.num_stop_items_removed = itemizer.take_num_stopped();
+        log_stop_items_removed(&report);
+        counts
+    } else {
+        info!("Loading item counts from {}.", args.load_item_counts_path);
+        let input = BufReader::new(File::open(&args.load_item_counts_path)?);
+        read_item_counts(input, &mut itemizer)?
+    };
+    if !args.save_item_counts_path.is_empty() {
+        let mut output = BufWriter::new(File::create(&args.save_item_counts_path)?);
+        write_item_counts(&mut output, &item_count, num_transactions, &itemizer)?;
+        output.flush()?;
+    }
+    report.num_transactions = num_transactions;
+    report.num_items = item_count.len();
+    info!("Dataset has {} transactions.", num_transactions);
+    let filtered_items = filter_item_counts(&mut item_count, args);
+    if !filtered_items.is_empty() {
+        info!(
+            "Removed {} items with counts outside --min-item-count and --max-item-count.",
+            filtered_items.len()
+        );
+    }
+    report.num_filtered_items = filtered_items.len();
+
+    let phase = Phase::start("Finding rare items");
+    let mut rare_items = if args.rare_items_by_category {
+        let mut rare_items = HashSet::new();
+        for (category, counts) in group_by_category(&item_count, &itemizer) {
+            let (category_rare_items, _) = find_rare_items(&counts, num_transactions, &itemizer, args);
+            debug!(
+                "{} of {} items in category '{}' are considered rare.",
+                category_rare_items.len(),
+                counts.len(),
+                category
+            );
+            rare_items.extend(category_rare_items);
+        }
+        rare_items
+    } else {
+        let (rare_items, cutoff) = find_rare_items(&item_count, num_transactions, &itemizer, args);
+        report.rare_item_cutoff = cutoff;
+        rare_items
+    };
+    if rare_items.is_empty() && !item_count.is_empty() {
+        if let Some(ref fallback) = args.fallback_max_support {
+            let (fallback_rare_items, cutoff) = match *fallback {
+                FallbackMaxSupport::Mode(ref mode) => {
+                    find_rare_items_by(mode, &item_count, num_transactions, &itemizer, &args.rarity)
+                }
+                FallbackMaxSupport::Percentile(percentile) => find_percentile_rare_items(&item_count, percentile),
+            };
+            report.warn(format!(
+                "--max-support {} found no rare items, so --fallback-max-support {} was used, \
+                 which found {}.",
+                args.max_support_mode,
+                fallback,
+                fallback_rare_items.len()
+            ));
+            rare_items = fallback_rare_items;
+            report.rare_item_cutoff = cutoff;
+        }
+    }
+    if args.sample_fraction.is_some() && num_transactions > 0 {
+        let epsilon = sampling_error(num_transactions, 0.05);
+        info!(
+            "Sampled {} transactions. Supports are within {} of their value in the \
+             full dataset with 95% probability.",
+            num_transactions, epsilon
+        );
+        add_possibly_rare_items(&mut rare_items, &item_count, num_transactions, epsilon);
+    }
+    report.end_phase(phase);
+    report.num_rare_items = rare_items.len();
+    info!(
+        "{} of {} items are considered rare.",
+        rare_items.len(),
+        item_count.len()
+    );
+
+    if args.dry_run {
+        log_rare_items(&rare_items, &item_count, &itemizer);
+        let phase = Phase::start("Estimating work");
+        let estimate = estimate_work(
+            mined_transactions(source, &mut itemizer, &filtered_items, &item_count, args),
+            &item_count,
+            &rare_items,
+        )?;
+        report.end_phase(phase);
+        info!(
+            "The tree would hold {} transactions in about {:.0} nodes, and growth would build \
+             about {} conditional trees at the top two levels.",
+            estimate.num_tree_transactions, estimate.num_tree_nodes, estimate.num_conditional_trees
+        );
+        info!("Mining would take {} (roughly {:.1}s).", estimate.describe_runtime(), estimate.seconds());
+        report.estimate = Some(estimate);
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+
+    if rare_items.is_empty() {
+        report.warn(String::from(
+            "Since 0 items are considered rare, giving up. Try another --max-support, \
+             or --fallback-max-support.",
+        ));
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+
+    if rare_items.len() > 500 {
+        report.warn(String::from(
+            "Since there are more than 500 items considered rare, giving up!",
+        ));
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+
+    let mut required_items: HashSet<u32> = HashSet::new();
+    for item in args.must_contain_items.iter() {
+        match itemizer.get_id(&itemizer.normalize(item)) {
+            Some(id) => {
+                required_items.insert(id);
+            }
+            None => warn!("Required item '{}' isn't in the dataset.", item),
+        }
+    }
+    if !args.must_contain_items.is_empty() && required_items.is_empty() {
+        report.warn(String::from(
+            "Since none of the required items are in the dataset, giving up.",
+        ));
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+
+    if args.log_rare_items {
+        log_rare_items(&rare_items, &item_count, &itemizer);
+    }
+
+    let consequent_items = find_consequent_items(&rare_items, &item_count, num_transactions, args);
+    if consequent_items.len() < rare_items.len() {
+        info!(
+            "{} of {} rare items are outside --min-consequent-count and --max-consequent-support, \
+             so won't be rules' consequents.",
+            rare_items.len() - consequent_items.len(),
+            rare_items.len()
+        );
+    }
+    if consequent_items.is_empty() {
+        report.warn(String::from(
+            "Since no rare items are within --min-consequent-count and --max-consequent-support, \
+             giving up.",
+        ));
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+
+    let significance_test = match args.significance_test {
+        SignificanceTestMode::Fisher | SignificanceTestMode::Permutation => SignificanceTest::fisher(),
+        SignificanceTestMode::ChiSquare => SignificanceTest::chi_square(args.yates_correction),
+    };
+    // The permutation test is too slow to prune itemsets with, so is only
+    // used to filter rules.
+    let permutation_test = match args.significance_test {
+        SignificanceTestMode::Permutation => Some(SignificanceTest::permutation(args.permutations)),
+        _ => None,
+    };
+
+    let max_itemset_len = args.max_antecedent_len.map(|len| len + 1);
+    let budget = args
+        .max_memory
+        .map(|gigabytes| MemoryBudget::new((gigabytes * (1u64 << 30) as f64) as usize));
+    // With --partition-size, the itemsets are mined a partition at a time,
+    // and only their counts are kept, so the whole dataset is never in the
+    // tree or index at once.
+    let (partition_index, mut partition_patterns) = match args.partition_size {
+        Some(partition_size) => {
+            let phase = Phase::start("Mining partitions");
+            let (index, patterns) = mine_partitions(
+                source,
+                &mut itemizer,
+                partition_size,
+                &filtered_items,
+                args,
+                &item_count,
+                &rare_items,
+                &significance_test,
+                max_itemset_len,
+                &required_items,
+                budget.as_ref(),
+            )?;
+            report.end_phase(phase);
+            (Some(index), Some(patterns))
+        }
+        None => (None, None),
+    };
+
+    // Load the initial tree, by re-reading the data set and inserting
+    // each transaction into the tree sorted by item frequency.
+    let phase = Phase::start(match args.algorithm {
+        Algorithm::RipTree => "Building initial RIPTree",
+        _ => "Building index",
+    });
+    let mut fptree = FPTree::new();
+    // The transactions containing rare items, for H-mine.
+    let mut rare_transactions: Vec<Transaction> = vec![];
+    let transactions = if partition_index.is_some() {
+        vec![]
+    } else {
+        aggregate_transactions(
+            mined_transactions(source, &mut itemizer, &filtered_items, &item_count, args),
+            &item_count,
+        )?
+    };
+    if partition_index.is_none() {
+        info!("Dataset has {} distinct transactions.", transactions.len());
+        report.num_distinct_transactions = transactions.len();
+    }
+    let mut index: Index = if let Some(index) = partition_index {
+        index
+    } else if let Some(epsilon) = args.approx_index {
+        Index::approximate(epsilon, args.approx_itemset_len)
+    } else if args.load_index_path.is_empty() {
+        Index::new()
+    } else {
+        info!("Loading index from {}.", args.load_index_path);
+        let index = Index::read(&mut BufReader::new(File::open(&args.load_index_path)?), &mut itemizer)?;
+        if index.num_distinct_transactions() != transactions.len() {
+            return Err(From::from(format!(
+                "Index in {} has {} distinct transactions, but the dataset has {}",
+                args.load_index_path,
+                index.num_distinct_transactions(),
+                transactions.len()
+            )));
+        }
+        index
+    };
+    // When the item counts are loaded, this is the first pass which strips
+    // stop items.
+    if !args.load_item_counts_path.is_empty() {
+        report.num_stop_items_removed = itemizer.take_num_stopped();
+        log_stop_items_removed(&report);
+    }
+    report.input_files = source.file_counts();
+    report.num_bad_lines = source.num_bad_lines();
+    if report.num_bad_lines > 0 {
+        warn!("Skipped {} bad lines of input.", report.num_bad_lines);
+    }
+    let build_index = args.load_index_path.is_empty();
+    let mut pairs = args.count_pairs.map(|items| PairCounts::new(&item_count, items));
+    // The items of each transaction in the index, by id, for --emit-examples.
+    let mut example_transactions: Vec<Vec<u32>> = vec![];
+    for transaction in transactions {
+        if build_index {
+            index.insert(&transaction.items, transaction.weight);
+        }
+        if args.emit_examples.is_some() {
+            example_transactions.push(transaction.items.clone());
+        }
+        if let Some(ref mut pairs) = pairs {
+            pairs.insert(&transaction.items, transaction.weight);
+        }
+        // Only include transactions which contain at least one rate item.
+        if !contains_rare_item(&transaction.items, &rare_items) {
+            continue;
+        }
+
+        match args.algorithm {
+            // With --distributed, the workers build the trees.
+            Algorithm::RipTree if !args.distributed_workers.is_empty() => {}
+            Algorithm::RipTree => fptree.insert(&transaction.items, transaction.weight),
+            Algorithm::HMine => rare_transactions.push(transaction),
+            Algorithm::Eclat | Algorithm::Apriori => {}
+        }
+    }
+    report.end_phase(phase);
+    if let Some(pairs) = pairs {
+        info!(
+            "Counted the pairs of the {} most frequent items, using {:.1} MB.",
+            pairs.num_items(),
+            pairs.bytes() as f64 / (1 << 20) as f64
+        );
+        index.set_pair_counts(pairs);
+    }
+    if let Some((bytes, error_bound)) = index.sketch_stats() {
+        info!(
+            "Approximate index uses {:.1} MB, and overestimates counts by at most {:.0} \
+             with 99% probability.",
+            bytes as f64 / (1 << 20) as f64,
+            error_bound
+        );
+    }
+    if !args.save_index_path.is_empty() {
+        let mut output = BufWriter::new(File::create(&args.save_index_path)?);
+        index.write(&mut output, &itemizer)?;
+        output.flush()?;
+    }
+    if matches!(args.algorithm, Algorithm::RipTree)
+        && args.partition_size.is_none()
+        && args.distributed_workers.is_empty()
+    {
+        info!(
+            "Initial tree has {} nodes, estimated to use {:.1} MB while mining.",
+            fptree.num_nodes(),
+            fptree.estimated_bytes() as f64 / (1 << 20) as f64
+        );
+        debug!("Nodes at each depth of the initial tree: {:?}", fptree.depth_histogram());
+    }
+
+    let top_k = args.top_k.map(|k| TopK::new(k, &rare_items));
+    // A worker serves shards of the rare items to grow, until it's killed.
+    if !args.listen_address.is_empty() {
+        let listener = TcpListener::bind(&args.listen_address)?;
+        info!("Listening for shards to grow on {}.", args.listen_address);
+        let signature = checkpoint_signature(args, num_transactions, &rare_items, &itemizer);
+        serve_shards(listener, &signature, &itemizer, |items| {
+            rip_growth(
+                &fptree,
+                &fptree,
+                Some(items),
+                &[],
+                num_transactions as u32,
+                &itemizer,
+                &index,
+                &significance_test,
+                max_itemset_len,
+                &required_items,
+                None,
+                None,
+                budget.as_ref(),
+                None,
+            )
+        });
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+    let phase = Phase::start(match args.algorithm {
+        Algorithm::RipTree => "Recursive FPGrowth",
+        Algorithm::Eclat => "Eclat",
+        Algorithm::HMine => "H-mine",
+        Algorithm::Apriori => "Apriori",
+    });
+    let checkpoint_path = if args.checkpoint_path.is_empty() {
+        &args.resume_path
+    } else {
+        &args.checkpoint_path
+    };
+    let checkpointer = if checkpoint_path.is_empty() {
+        None
+    } else {
+        let checkpointer = Checkpointer::new(
+            checkpoint_path,
+            Duration::from_secs(args.checkpoint_interval),
+            checkpoint_signature(args, num_transactions, &rare_items, &itemizer),
+            &itemizer,
+        );
+        if !args.resume_path.is_empty() {
+            checkpointer.resume(&args.resume_path)?;
+        }
+        Some(checkpointer)
+    };
+    // Without --top-k, which picks from all the itemsets, RIPTree sends each
+    // rare item's itemsets to rule generation as they're grown, so the two
+    // overlap. The itemsets are only kept if they're output.
+    let keep_itemsets = !args.output_sqlite_path.is_empty() || !args.output_parquet_path.is_empty();
+    let grow = |sink: Option<&SyncSender<Vec<ItemSet>>>| {
+        rip_growth(
+            &fptree,
+            &fptree,
+            Some(&rare_items),
+            &[],
+            num_transactions as u32,
+            &itemizer,
+            &index,
+            &significance_test,
+            max_itemset_len,
+            &required_items,
+            top_k.as_ref(),
+            checkpointer.as_ref(),
+            budget.as_ref(),
+            sink,
+        )
+    };
+    let metrics = metrics_to_calculate(args);
+    let mut rule_stream = rule_sink.map(|sink| RuleStream {
+        sink,
+        args,
+        index: &index,
+        itemizer: &itemizer,
+        metrics: &metrics,
+        example_transactions: &example_transactions,
+        num_generated: 0,
+        num_sent: 0,
+    });
+    let new_generator = || {
+        RuleGenerator::new(
+            num_transactions as u32,
+            args.min_confidence,
+            args.min_lift,
+            &consequent_items,
+            &index,
+            args.min_antecedent_len,
+            args.max_antecedent_len,
+            &required_items,
+        )
+    };
+    let mut streamed: Option<(usize, HashSet<Rule>)> = None;
+    let mut patterns: Vec<ItemSet> = match args.algorithm {
+        _ if partition_patterns.is_some() => partition_patterns.take().unwrap_or_default(),
+        _ if !args.distributed_workers.is_empty() => grow_on_workers(
+            &args.distributed_workers,
+            &rare_items,
+            &item_count,
+            &checkpoint_signature(args, num_transactions, &rare_items, &itemizer),
+            &itemizer,
+        )?,
+        Algorithm::RipTree if top_k.is_none() => {
+            let mut generator = new_generator();
+            if let Some(ref mut rule_stream) = rule_stream {
+                generator.stream_to(move |rule| rule_stream.send(rule));
+            }
+            let (itemsets, num_itemsets, rules) = generate_rules_while_mining(
+                |sink| {
+                    grow(Some(sink));
+                },
+                generator,
+                keep_itemsets,
+            );
+            streamed = Some((num_itemsets, rules));
+            itemsets
+        }
+        Algorithm::RipTree => grow(None),
+        Algorithm::Eclat => {
+            eclat(
+                &index,
+                &item_count,
+                &rare_items,
+                &significance_test,
+                max_itemset_len,
+                &required_items,
+            )
+        }
+        Algorithm::HMine => {
+            hmine(
+                &rare_transactions,
+                &index,
+                &rare_items,
+                &significance_test,
+                max_itemset_len,
+                &required_items,
+            )
+        }
+        Algorithm::Apriori => {
+            apriori(
+                &index,
+                &item_count,
+                &rare_items,
+                &significance_test,
+                max_itemset_len,
+                &required_items,
+            )
+        }
+    };
+
+    // Save once growth is done, so an interruption while generating rules
+    // doesn't lose it.
+    if let Some(ref checkpointer) = checkpointer {
+        checkpointer.save();
+    }
+    report.end_phase(phase);
+    let num_itemsets = streamed.as_ref().map_or(patterns.len(), |&(num_itemsets, _)| num_itemsets);
+    info!("Mining generated {} frequent itemsets.", num_itemsets);
+    if take_cancelled() {
+        warn!("Mining was interrupted, so the itemsets found are incomplete.");
+        report.partial = true;
+    }
+
+    // Only rip_growth raises its threshold as it goes; the other algorithms
+    // find every itemset, and the top K are picked from those.
+    if let Some(k) = args.top_k {
+        patterns = select_top_k(patterns, k, &rare_items);
+        info!("Kept the top {} itemsets.", patterns.len());
+    }
+    report.num_itemsets = match streamed {
+        Some((num_itemsets, _)) => num_itemsets,
+        None => patterns.len(),
+    };
+
+    let phase = Phase::start("Generating rules");
+    // For mine_with, the rules have been handed over as they were generated,
+    // or are now, from the itemsets.
+    if let Some(mut rule_stream) = rule_stream {
+        if streamed.is_none() {
+            let mut generator = new_generator();
+            generator.stream_to(|rule| rule_stream.send(rule));
+            for itemset in patterns.iter() {
+                generator.add(itemset);
+            }
+            generator.finish();
+        }
+        report.count_rules("confidence_and_lift", rule_stream.num_generated);
+        report.count_rules("streamed", rule_stream.num_sent);
+        report.end_phase(phase);
+        if take_cancelled() {
+            warn!("Rule generation was interrupted, so the rules found are incomplete.");
+            report.partial = true;
+        }
+        info!("Generated {} rules.", rule_stream.num_sent);
+        return Ok(MiningResult {
+            itemizer,
+            rules: vec![],
+            itemsets: vec![],
+            consequent_groups: vec![],
+            item_count,
+            drift: None,
+            classes: None,
+            report,
+        });
+    }
+    // Family-wise filtering is applied here rather than by generate_rules,
+    // so the rules it removes can be counted.
+    let mut rules = match streamed {
+        Some((_, rules)) => rules,
+        None => generate_rules(
+            &patterns,
+            num_transactions as u32,
+            args.min_confidence,
+            args.min_lift,
+            &consequent_items,
+            &index,
+            &significance_test,
+            true,
+            args.min_antecedent_len,
+            args.max_antecedent_len,
+            &required_items,
+        ),
+    };
+    report.count_rules("confidence_and_lift", rules.len());
+    if !args.disable_family_wise_rule_filtering {
+        rules = family_wise_filter(
+            rules,
+            &index,
+            permutation_test.as_ref().unwrap_or(&significance_test),
+            args.counting,
+        );
+        report.count_rules("family_wise", rules.len());
+    }
+    if !args.disable_permutation_rule_filtering {
+        rules = permutation_filter(rules, &index, args.min_confidence, args.min_lift);
+        report.count_rules("permutation", rules.len());
+    }
+    if let Some(ref key) = args.filter_redundant_rules {
+        rules = redundancy_filter(rules, key);
+        report.count_rules("redundancy", rules.len());
+    }
+    let rules: Vec<Rule> = rules
+        .into_iter()
+        .filter(|rule| !contains_ancestor_and_descendant(rule, &itemizer))
+        .collect();
+    if itemizer.taxonomy().is_some() {
+        report.count_rules("taxonomy", rules.len());
+    }
+    // Each rule's contingency table is counted once, for all the measures
+    // below.
+    let mut rules: Vec<(Rule, Contingency)> = rules
+        .into_par_iter()
+        .map(|mut rule| {
+            let table = rule.contingency(&index);
+            rule.calculate_odds_ratio(&table);
+            (rule, table)
+        })
+        .collect();
+    rules.retain(|(rule, _)| rule.odds_ratio().unwrap().ratio() >= args.min_odds_ratio);
+    report.count_rules("odds_ratio", rules.len());
+    if let Some(max_ratio) = args.max_cross_support_ratio {
+        for (rule, table) in rules.iter_mut() {
+            rule.calculate_imbalance(table);
+        }
+        rules.retain(|(rule, _)| rule.imbalance().unwrap().cross_support_ratio() <= max_ratio);
+        report.count_rules("cross_support", rules.len());
+    }
+    let thresholds = &args.metric_thresholds;
+    if !metrics.is_empty() {
+        rules
+            .par_iter_mut()
+            .for_each(|(rule, table)| rule.calculate_metrics(table, &metrics));
+    }
+    if !thresholds.is_empty() {
+        rules.retain(|(rule, _)| {
+            thresholds
+                .iter()
+                .all(|&(metric, threshold)| metric.passes(rule.metric(metric).unwrap(), threshold))
+        });
+        report.count_rules("metrics", rules.len());
+    }
+    if let Some(resamples) = args.bootstrap_resamples {
+        rules
+            .par_iter_mut()
+            .for_each(|(rule, table)| rule.calculate_bootstrap_intervals(table, resamples));
+    }
+    let mut rules: Vec<Rule> = rules.into_iter().map(|(rule, _)| rule).collect();
+    report.end_phase(phase);
+    if take_cancelled() {
+        warn!("Rule generation was interrupted, so the rules found are incomplete.");
+        report.partial = true;
+    }
+    info!("Generated {} rules.", rules.len());
+    if rules.is_empty() {
+        report.warn(format!(
+            "No rules were found among {} itemsets; the report's rule counts show which \
+             filter removed them.",
+            report.num_itemsets
+        ));
+    }
+
+    sort_rules(&mut rules, &args.sort_rules_by, &itemizer);
+    if let Some(max_rules) = args.max_rules_per_consequent {
+        rules = select_rules(rules, max_rules, &args.select_by, &index);
+        report.count_rules("per_consequent", rules.len());
+    }
+    if let Some(max_examples) = args.emit_examples {
+        rules
+            .par_iter_mut()
+            .for_each(|rule| rule.find_examples(&index, &example_transactions, max_examples));
+    }
+    let consequent_groups = if args.group_by_consequent {
+        group_by_consequent(&mut rules, &index)
+    } else {
+        vec![]
+    };
+    if args.output_sqlite_path.is_empty() && args.output_parquet_path.is_empty() {
+        patterns = vec![];
+    }
+
+    Ok(MiningResult {
+        itemizer,
+        rules,
+        itemsets: patterns,
+        consequent_groups,
+        item_count,
+        drift: None,
+        classes: None,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_mine_transactions() {
+        use super::{mine, mine_transactions};
+        use command_line_args::{Arguments, MaxSupportMode};
+        use std::collections::HashSet;
+        use std::fs::File;
+        use std::io::{BufRead, BufReader};
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let transactions: Vec<Vec<String>> = BufReader::new(File::open("datasets/UCI-zoo.csv")
+            .unwrap())
+            .lines()
+            .map(|line| line.unwrap().split(',').map(String::from).collect())
+            .collect();
+
+        let from_file = mine(&args).unwrap();
+        let from_memory = mine_transactions(&transactions, &args).unwrap();
+        let to_strings = |result: &super::MiningResult| -> HashSet<String> {
+            result
+                .rules
+                .iter()
+                .map(|rule| rule.to_string(&result.itemizer))
+                .collect()
+        };
+        assert!(!from_file.rules.is_empty());
+        assert_eq!(to_strings(&from_file), to_strings(&from_memory));
+    }
+
+    #[test]
+    fn test_mine_with() {
+        use super::{mine, mine_with};
+        use command_line_args::{Algorithm, Arguments, MaxSupportMode};
+        use itemizer::Itemizer;
+        use metrics::Metric;
+
+        let zoo_args = || Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        // Family-wise filtering needs every rule with the same consequent.
+        assert!(mine_with(&zoo_args(), |_| {}).is_err());
+
+        let to_strings = |rules: &[super::Rule], itemizer: &Itemizer| -> Vec<String> {
+            let mut strings: Vec<String> = rules.iter().map(|rule| rule.to_string(itemizer)).collect();
+            strings.sort();
+            strings
+        };
+        let kulc: Metric = "kulc".parse().unwrap();
+        for algorithm in [Algorithm::RipTree, Algorithm::Eclat] {
+            let args = Arguments {
+                algorithm,
+                disable_family_wise_rule_filtering: true,
+                disable_permutation_rule_filtering: true,
+                metric_thresholds: vec![(kulc, 0.1)],
+                ..zoo_args()
+            };
+            let expected = mine(&args).unwrap();
+            let mut rules = vec![];
+            let result = mine_with(&args, |rule| rules.push(rule.clone())).unwrap();
+            assert!(result.rules.is_empty());
+            assert!(!rules.is_empty());
+            assert!(rules.iter().all(|rule| rule.metric(kulc).is_some()));
+            assert_eq!(
+                to_strings(&rules, &result.itemizer),
+                to_strings(&expected.rules, &expected.itemizer)
+            );
+            assert_eq!(result.report.rule_counts.last().unwrap().1, rules.len());
+        }
+    }
+
+    #[test]
+    fn test_algorithms() {
+        use super::mine;
+        use command_line_args::{Algorithm, Arguments, MaxSupportMode};
+        use std::collections::HashSet;
+
+        let foodmart_args = |algorithm: Algorithm| Arguments {
+            input_file_paths: vec![String::from("datasets/foodmartFIM.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            algorithm,
+            min_confidence: 0.1,
+            min_lift: 1.5,
+            ..Arguments::default()
+        };
+        let rule_strings = |args: &Arguments| -> HashSet<String> {
+            let result = mine(args).unwrap();
+            result
+                .rules
+                .iter()
+                .map(|rule| rule.to_string(&result.itemizer))
+                .collect()
+        };
+
+        let riptree = rule_strings(&foodmart_args(Algorithm::RipTree));
+        assert!(!riptree.is_empty());
+        assert_eq!(riptree, rule_strings(&foodmart_args(Algorithm::Eclat)));
+        assert_eq!(riptree, rule_strings(&foodmart_args(Algorithm::Apriori)));
+        assert_eq!(riptree, rule_strings(&foodmart_args(Algorithm::HMine)));
+
+        let constrained = |algorithm: Algorithm| Arguments {
+            must_contain_items: vec![String::from("1037")],
+            max_antecedent_len: Some(2),
+            disable_family_wise_rule_filtering: true,
+            ..foodmart_args(algorithm)
+        };
+        let riptree = rule_strings(&constrained(Algorithm::RipTree));
+        assert_eq!(riptree, rule_strings(&constrained(Algorithm::Eclat)));
+        assert_eq!(riptree, rule_strings(&constrained(Algorithm::Apriori)));
+        assert_eq!(riptree, rule_strings(&constrained(Algorithm::HMine)));
+    }
+
+    #[test]
+    fn test_top_k() {
+        use super::mine_transactions;
+        use command_line_args::{Algorithm, Arguments, MaxSupportMode};
+
+        // Pairs of rare items occurring together 6, 3 and 2 times, among many
+        // transactions of frequent items.
+        let mut transactions: Vec<Vec<String>> = vec![];
+        for _ in 0..800 {
+            transactions.push(vec!["a", "b", "c", "d"].into_iter().map(String::from).collect());
+        }
+        for &(pair, count) in [(["x", "y"], 6), (["p", "q"], 3), (["u", "v"], 2)].iter() {
+            for _ in 0..count {
+                transactions.push(pair.iter().map(|s| String::from(*s)).collect());
+            }
+        }
+        let rule_strings = |top_k: Option<usize>, algorithm: Algorithm| -> Vec<String> {
+            let args = Arguments {
+                max_support_mode: MaxSupportMode::Pareto,
+                algorithm,
+                min_confidence: 0.5,
+                min_lift: 1.0,
+                top_k,
+                ..Arguments::default()
+            };
+            let result = mine_transactions(&transactions, &args).unwrap();
+            result
+                .rules
+                .iter()
+                .map(|rule| rule.to_string(&result.itemizer))
+                .collect()
+        };
+
+        assert_eq!(rule_strings(None, Algorithm::RipTree).len(), 6);
+        assert_eq!(rule_strings(Some(1), Algorithm::RipTree), vec!["x ==> y", "y ==> x"]);
+        assert_eq!(rule_strings(Some(2), Algorithm::RipTree).len(), 4);
+        assert_eq!(rule_strings(Some(2), Algorithm::Eclat).len(), 4);
+    }
+
+    #[test]
+    fn test_count_pairs() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let rules = |top_k: Option<usize>, count_pairs: Option<usize>| -> Vec<String> {
+            let result = mine(&Arguments {
+                input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                top_k,
+                count_pairs,
+                ..Arguments::default()
+            }).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        // Pair counts only save work, so the rules found are the same.
+        let all = rules(None, None);
+        assert!(!all.is_empty());
+        assert_eq!(rules(None, Some(30)), all);
+        assert_eq!(rules(Some(5), Some(30)), rules(Some(5), None));
+    }
+
+    #[test]
+    fn test_item_constraints() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let zoo_args = || Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let rule_strings = |args: &Arguments| -> Vec<String> {
+            let result = mine(args).unwrap();
+            result
+                .rules
+                .iter()
+                .map(|rule| rule.to_string(&result.itemizer))
+                .collect()
+        };
+
+        let required = rule_strings(&Arguments {
+            must_contain_items: vec![String::from("legs=5")],
+            ..zoo_args()
+        });
+        assert!(!required.is_empty());
+        assert!(required.iter().all(|rule| rule.contains("legs=5")));
+
+        let excluded = rule_strings(&Arguments {
+            exclude_items: vec![String::from("legs=5")],
+            ..zoo_args()
+        });
+        assert!(excluded.iter().all(|rule| !rule.contains("legs=5")));
+    }
+
+    #[test]
+    fn test_weighted_transactions() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode, WeightColumn};
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::{BufRead, BufReader, Write};
+
+        // Giving each transaction a weight of 2 should be the same as
+        // repeating each transaction.
+        let dir = env::temp_dir();
+        let weighted_path = dir.join("riptree-test-weighted.csv");
+        let repeated_path = dir.join("riptree-test-repeated.csv");
+        {
+            let mut weighted = File::create(&weighted_path).unwrap();
+            let mut repeated = File::create(&repeated_path).unwrap();
+            for line in BufReader::new(File::open("datasets/UCI-zoo.csv").unwrap()).lines() {
+                let line = line.unwrap();
+                writeln!(weighted, "2,{}", line).unwrap();
+                writeln!(repeated, "{}\n{}", line, line).unwrap();
+            }
+        }
+
+        let rule_strings = |path: &::std::path::Path, weight_column| -> Vec<String> {
+            let args = Arguments {
+                input_file_paths: vec![path.to_string_lossy().into_owned()],
+                weight_column,
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..Arguments::default()
+            };
+            let result = mine(&args).unwrap();
+            let mut rules: Vec<String> = result
+                .rules
+                .iter()
+                .map(|rule| {
+                    format!(
+                        "{} {} {} {}",
+                        rule.to_string(&result.itemizer),
+                        rule.confidence(),
+                        rule.lift(),
+                        rule.support()
+                    )
+                })
+                .collect();
+            rules.sort();
+            rules
+        };
+        let weighted = rule_strings(&weighted_path, Some(WeightColumn::First));
+        let repeated = rule_strings(&repeated_path, None);
+        fs::remove_file(&weighted_path).unwrap();
+        fs::remove_file(&repeated_path).unwrap();
+
+        assert!(!weighted.is_empty());
+        assert_eq!(weighted, repeated);
+    }
+
+    #[test]
+    fn test_multiple_input_files() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::{BufRead, BufReader, Write};
+
+        // Mining a dataset split across the files in a directory should be
+        // the same as mining it whole.
+        let dir = env::temp_dir().join("riptree-test-input-dir");
+        fs::create_dir_all(&dir).unwrap();
+        {
+            let mut first = File::create(dir.join("1.csv")).unwrap();
+            let mut second = File::create(dir.join("2.csv")).unwrap();
+            let lines = BufReader::new(File::open("datasets/UCI-zoo.csv").unwrap()).lines();
+            for (i, line) in lines.enumerate() {
+                let output = if i < 40 { &mut first } else { &mut second };
+                writeln!(output, "{}", line.unwrap()).unwrap();
+            }
+        }
+
+        let mine_rules = |paths: Vec<String>| {
+            let args = Arguments {
+                input_file_paths: paths,
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..Arguments::default()
+            };
+            let result = mine(&args).unwrap();
+            let rules: Vec<String> = result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect();
+            (rules, result.report.input_files)
+        };
+        let (whole, _) = mine_rules(vec![String::from("datasets/UCI-zoo.csv")]);
+        let (split, files) = mine_rules(vec![dir.to_string_lossy().into_owned()]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!whole.is_empty());
+        assert_eq!(whole, split);
+        assert_eq!(files.len(), 2);
+        assert!(files[0].0.ends_with("1.csv"));
+        assert_eq!(files[0].1, 40);
+        assert_eq!(files[1].1, 61);
+    }
+
+    #[test]
+    fn test_numeric_columns() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 2.0,
+            numeric_columns: vec![String::from("legs")],
+            ..Arguments::default()
+        };
+        let legs_items = |args: &Arguments| -> Vec<String> {
+            let result = mine(args).unwrap();
+            let mut items: Vec<String> = result
+                .itemizer
+                .names()
+                .filter(|item| item.starts_with("legs"))
+                .map(String::from)
+                .collect();
+            items.sort();
+            items
+        };
+        // Half the animals have 0 or 2 legs, and most of the rest have 4.
+        assert_eq!(legs_items(&args), vec!["legs<4", "legs=4-5", "legs>=5"]);
+        let args = Arguments {
+            cut_points: vec![(String::from("legs"), vec![1.0, 3.0])],
+            ..args
+        };
+        assert_eq!(legs_items(&args), vec!["legs<1", "legs=1-3", "legs>=3"]);
+    }
+
+    #[test]
+    fn test_tabular_input() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use std::env;
+        use std::fs;
+
+        // The zoo dataset as a table, with the column names in a header.
+        let path = env::temp_dir().join(format!("riptree-tabular-test-{}.csv", std::process::id()));
+        let csv = fs::read_to_string("datasets/UCI-zoo.csv").unwrap();
+        let cells = |line: &str, i: usize| -> String {
+            let cells: Vec<&str> = line.split(',').map(|cell| cell.split('=').nth(i).unwrap()).collect();
+            cells.join(",")
+        };
+        let mut table = cells(csv.lines().next().unwrap(), 0) + "\n";
+        for line in csv.lines() {
+            table += &(cells(line, 1) + "\n");
+        }
+        fs::write(&path, table + "\n").unwrap();
+
+        let zoo_args = |path: String| Arguments {
+            input_file_paths: vec![path],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let table_path = path.to_string_lossy().into_owned();
+        let rules = |args: &Arguments| -> Vec<String> {
+            let result = mine(args).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        let csv_rules = rules(&zoo_args(String::from("datasets/UCI-zoo.csv")));
+        let tabular_rules = rules(&Arguments {
+            tabular: true,
+            ..zoo_args(table_path.clone())
+        });
+        assert!(!csv_rules.is_empty());
+        assert_eq!(csv_rules, tabular_rules);
+
+        // Treating 0 as missing drops those cells, or makes them e.g. "hair=?".
+        let items = |args: &Arguments| -> Vec<String> {
+            let result = mine(args).unwrap();
+            result.itemizer.names().map(String::from).collect()
+        };
+        let dropped = items(&Arguments {
+            tabular: true,
+            null_values: vec![String::from("0")],
+            ..zoo_args(table_path.clone())
+        });
+        assert!(dropped.iter().all(|item| !item.ends_with("=0")));
+        let missing = items(&Arguments {
+            tabular: true,
+            null_values: vec![String::from("0")],
+            missing_value: String::from("?"),
+            ..zoo_args(table_path)
+        });
+        fs::remove_file(&path).unwrap();
+        assert!(missing.iter().any(|item| item == "hair=?"));
+    }
+
+    #[test]
+    fn test_fimi_input() {
+        use super::mine;
+        use command_line_args::{Arguments, InputFormat, MaxSupportMode};
+        use std::env;
+        use std::fs;
+
+        // The zoo dataset with its items space separated, and blank lines.
+        let path = env::temp_dir().join(format!("riptree-fimi-test-{}.dat", std::process::id()));
+        let csv = fs::read_to_string("datasets/UCI-zoo.csv").unwrap();
+        fs::write(&path, csv.replace(',', "  ").replace('\n', "\n\n")).unwrap();
+
+        let mine_rules = |path: String, input_format| {
+            let args = Arguments {
+                input_file_paths: vec![path],
+                input_format,
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..Arguments::default()
+            };
+            let result = mine(&args).unwrap();
+            let rules: Vec<String> = result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect();
+            (rules, result.report.num_transactions)
+        };
+        let (csv_rules, _) = mine_rules(String::from("datasets/UCI-zoo.csv"), None);
+        let (fimi_rules, num_transactions) = mine_rules(path.to_string_lossy().into_owned(), Some(InputFormat::Fimi));
+        fs::remove_file(&path).unwrap();
+
+        assert!(!csv_rules.is_empty());
+        assert_eq!(csv_rules, fimi_rules);
+        assert_eq!(num_transactions, 101);
+    }
+
+    #[test]
+    fn test_bad_lines() {
+        use super::mine;
+        use command_line_args::{Arguments, BadLinePolicy, MaxSupportMode};
+        use std::env;
+        use std::fs;
+
+        // The zoo dataset with a line which isn't UTF-8, and one with a NUL.
+        let path = env::temp_dir().join(format!("riptree-bad-lines-test-{}.csv", std::process::id()));
+        let mut csv = fs::read("datasets/UCI-zoo.csv").unwrap();
+        csv.extend_from_slice(b"hair,\xff\xfe\nfeathers,\0\n");
+        fs::write(&path, csv).unwrap();
+
+        let mine_rules = |path: String, on_bad_line| {
+            let args = Arguments {
+                input_file_paths: vec![path],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                on_bad_line,
+                ..Arguments::default()
+            };
+            mine(&args).map(|result| {
+                let rules: Vec<String> = result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect();
+                (rules, result.report.num_bad_lines)
+            })
+        };
+        let path = path.to_string_lossy().into_owned();
+        let error = mine_rules(path.clone(), BadLinePolicy::Error).unwrap_err();
+        let (rules, num_bad_lines) = mine_rules(path.clone(), BadLinePolicy::Skip).unwrap();
+        let (logged_rules, _) = mine_rules(path.clone(), BadLinePolicy::Log).unwrap();
+        let (zoo_rules, _) = mine_rules(String::from("datasets/UCI-zoo.csv"), BadLinePolicy::Error).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(error.to_string().starts_with("Bad line 102 of "));
+        assert!(error.to_string().ends_with("it isn't valid UTF-8"));
+        assert_eq!(num_bad_lines, 2);
+        assert_eq!(rules, zoo_rules);
+        assert_eq!(logged_rules, zoo_rules);
+    }
+
+    #[test]
+    fn test_windows_input() {
+        use super::mine;
+        use command_line_args::{Arguments, Encoding, MaxSupportMode};
+        use std::env;
+        use std::fs;
+
+        // The zoo dataset as Excel would export it, with a byte order mark and
+        // Windows line endings.
+        let path = env::temp_dir().join(format!("riptree-windows-test-{}.csv", std::process::id()));
+        let csv = fs::read_to_string("datasets/UCI-zoo.csv").unwrap();
+        fs::write(&path, format!("\u{feff}{}", csv.replace('\n', "\r\n"))).unwrap();
+
+        let mine_rules = |path: String, encoding| {
+            let args = Arguments {
+                input_file_paths: vec![path],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                encoding,
+                ..Arguments::default()
+            };
+            let result = mine(&args).unwrap();
+            let rules: Vec<String> = result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect();
+            let mut names: Vec<String> = result.itemizer.names().map(String::from).collect();
+            names.sort();
+            (rules, names)
+        };
+        let (zoo_rules, zoo_names) = mine_rules(String::from("datasets/UCI-zoo.csv"), Encoding::Utf8);
+        let windows_path = path.to_string_lossy().into_owned();
+        let (rules, names) = mine_rules(windows_path.clone(), Encoding::Utf8);
+        let (latin1_rules, latin1_names) = mine_rules(windows_path, Encoding::Latin1);
+        fs::remove_file(&path).unwrap();
+
+        assert!(!zoo_rules.is_empty());
+        assert_eq!(rules, zoo_rules);
+        assert_eq!(names, zoo_names);
+        assert_eq!(latin1_rules, zoo_rules);
+        assert_eq!(latin1_names, zoo_names);
+    }
+
+    #[test]
+    fn test_aggregate_transactions() {
+        use super::{aggregate_transactions, count_item_frequencies};
+        use itemizer::Itemizer;
+        use transaction_reader::TransactionSource;
+
+        let transactions: Vec<Vec<String>> = [
+            vec!["a", "b"],
+            vec!["c"],
+            vec!["b", "a"],
+            vec!["a", "b", "b"],
+        ].iter()
+            .map(|t| t.iter().map(|s| String::from(*s)).collect())
+            .collect();
+        let mut itemizer = Itemizer::new();
+        let (item_count, _) = count_item_frequencies(transactions.read(&mut itemizer)).unwrap();
+        let aggregated = aggregate_transactions(transactions.read(&mut itemizer), &item_count)
+            .unwrap();
+
+        let a = itemizer.get_id("a").unwrap();
+        let b = itemizer.get_id("b").unwrap();
+        let c = itemizer.get_id("c").unwrap();
+        let mut ab = vec![a, b];
+        ab.sort();
+        assert_eq!(aggregated.len(), 2);
+        for mut transaction in aggregated {
+            transaction.items.sort();
+            if transaction.items == vec![c] {
+                assert_eq!(transaction.weight, 1);
+            } else {
+                assert_eq!(transaction.items, ab);
+                assert_eq!(transaction.weight, 3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_items() {
+        use itemizer::{normalize_item, Itemizer};
+        use transaction_reader::itemize;
+
+        let mut itemizer = Itemizer::new();
+        itemizer.set_normalizer(|item| normalize_item(item, true, true));
+        let items = itemize(["Milk", "milk ", "Skim \t Milk", "skim milk"].iter().cloned(), &mut itemizer).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(itemizer.get_id("milk").is_some());
+        assert!(itemizer.get_id("skim milk").is_some());
+
+        assert_eq!(normalize_item("Skim  Milk", false, true), "Skim Milk");
+        assert_eq!(normalize_item("Skim  Milk", true, false), "skim  milk");
+    }
+
+    #[test]
+    fn test_stop_items() {
+        use super::mine_source;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use itemizer::Itemizer;
+        use std::env;
+        use std::fs;
+
+        let path = env::temp_dir().join(format!("riptree-stop-items-test-{}.txt", std::process::id()));
+        fs::write(&path, "plastic bag\n\nloyalty card scan\n").unwrap();
+        let transactions: Vec<Vec<String>> = [
+            vec!["milk", "plastic bag"],
+            vec!["bread", "plastic bag", "loyalty card scan"],
+            vec!["milk", "bread"],
+        ].iter()
+            .map(|t| t.iter().map(|s| String::from(*s)).collect())
+            .collect();
+        let args = Arguments {
+            stop_items_path: path.to_string_lossy().into_owned(),
+            max_support_mode: MaxSupportMode::Pareto,
+            ..Arguments::default()
+        };
+        let result = mine_source(&transactions[..], Itemizer::new(), &args).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.report.num_stop_items_removed, 3);
+        assert_eq!(result.itemizer.get_id("plastic bag"), None);
+        assert_eq!(result.itemizer.get_id("loyalty card scan"), None);
+    }
+
+    #[test]
+    fn test_save_and_load_index() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use std::env;
+        use std::fs;
+
+        let path = env::temp_dir().join(format!("riptree-index-test-{}.idx", std::process::id()));
+        let index_path = path.to_string_lossy().into_owned();
+        let rules = |args: Arguments| -> Vec<String> {
+            let result = mine(&Arguments {
+                input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..args
+            }).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        let saved = rules(Arguments {
+            save_index_path: index_path.clone(),
+            ..Arguments::default()
+        });
+        let loaded = rules(Arguments {
+            load_index_path: index_path,
+            ..Arguments::default()
+        });
+        fs::remove_file(&path).unwrap();
+        assert!(!saved.is_empty());
+        assert_eq!(saved, loaded);
+    }
+
+    #[test]
+    fn test_approx_index() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let rules = |approx_index: Option<f64>| -> Vec<String> {
+            let result = mine(&Arguments {
+                input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                disable_permutation_rule_filtering: true,
+                approx_index,
+                ..Arguments::default()
+            }).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        // With a small enough error, the sketch's counts are exact here.
+        let exact = rules(None);
+        assert!(!exact.is_empty());
+        assert_eq!(rules(Some(0.0001)), exact);
+    }
+
+    #[test]
+    fn test_bitset_counting() {
+        use super::mine;
+        use command_line_args::{Arguments, Counting, MaxSupportMode};
+
+        let rules = |counting: Counting| -> Vec<String> {
+            let result = mine(&Arguments {
+                input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                counting,
+                ..Arguments::default()
+            }).unwrap();
+            result
+                .rules
+                .iter()
+                .map(|rule| format!("{} {:?}", rule.to_string(&result.itemizer), rule.p_value()))
+                .collect()
+        };
+        let exact = rules(Counting::TidLists);
+        assert!(!exact.is_empty());
+        assert_eq!(rules(Counting::Bitsets), exact);
+    }
+
+    #[test]
+    fn test_partition_size() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let rules = |partition_size: Option<usize>| -> Vec<String> {
+            let result = mine(&Arguments {
+                input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                disable_permutation_rule_filtering: true,
+                partition_size,
+                ..Arguments::default()
+            }).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        let exact = rules(None);
+        assert!(!exact.is_empty());
+        // One partition holding the whole dataset finds the same rules, and
+        // here so do partitions of half of it.
+        assert_eq!(rules(Some(1000)), exact);
+        assert_eq!(rules(Some(50)), exact);
+        // Small partitions miss rules, but every rule found is counted
+        // exactly, so is one of the rules of the whole dataset.
+        assert!(rules(Some(20)).iter().all(|rule| exact.contains(rule)));
+    }
+
+    #[test]
+    fn test_filter_item_counts() {
+        use super::{filter_item_counts, remove_items};
+        use command_line_args::Arguments;
+        use std::collections::{HashMap, HashSet};
+        use transaction_reader::Transaction;
+
+        let mut item_count: HashMap<u32, u32> = [(1, 1), (2, 5), (3, 9), (4, 10)]
+            .iter()
+            .cloned()
+            .collect();
+        let args = Arguments {
+            min_item_count: Some(2),
+            max_item_count: Some(9),
+            ..Arguments::default()
+        };
+        let filtered = filter_item_counts(&mut item_count, &args);
+        let expected: HashSet<u32> = [1, 4].iter().cloned().collect();
+        assert_eq!(filtered, expected);
+        assert_eq!(item_count.len(), 2);
+
+        let transactions = vec![
+            Ok(Transaction { items: vec![1, 2, 4], weight: 1 }),
+            Ok(Transaction { items: vec![1, 4], weight: 3 }),
+            Ok(Transaction { items: vec![3], weight: 2 }),
+        ];
+        let remaining: Vec<Vec<u32>> = remove_items(transactions.into_iter(), &filtered)
+            .map(|transaction| transaction.unwrap().items)
+            .collect();
+        assert_eq!(remaining, vec![vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_limit_transaction_lens() {
+        use super::limit_transaction_lens;
+        use command_line_args::{Arguments, LongTransactions};
+        use std::collections::HashMap;
+        use transaction_reader::Transaction;
+
+        let item_count: HashMap<u32, u32> = [(1, 10), (2, 5), (3, 7), (4, 1)].iter().cloned().collect();
+        let limit = |long_transactions, item_count| {
+            let args = Arguments {
+                min_transaction_len: Some(2),
+                max_transaction_len: Some(2),
+                long_transactions,
+                ..Arguments::default()
+            };
+            let transactions = vec![
+                Ok(Transaction { items: vec![1], weight: 1 }),
+                Ok(Transaction { items: vec![1, 2], weight: 1 }),
+                Ok(Transaction { items: vec![4, 2, 1, 3], weight: 1 }),
+            ];
+            let limited: Vec<Vec<u32>> = limit_transaction_lens(transactions.into_iter(), item_count, &args)
+                .map(|transaction| transaction.unwrap().items)
+                .collect();
+            limited
+        };
+        assert_eq!(limit(LongTransactions::Drop, Some(&item_count)), vec![vec![1, 2]]);
+        assert_eq!(
+            limit(LongTransactions::KeepFrequent, Some(&item_count)),
+            vec![vec![1, 2], vec![1, 3]]
+        );
+        assert_eq!(
+            limit(LongTransactions::KeepRare, Some(&item_count)),
+            vec![vec![1, 2], vec![4, 2]]
+        );
+        // Without counts, long transactions are kept whole.
+        assert_eq!(
+            limit(LongTransactions::KeepRare, None),
+            vec![vec![1, 2], vec![4, 2, 1, 3]]
+        );
+    }
+
+    #[test]
+    fn test_consequent_bounds() {
+        use super::{find_consequent_items, mine};
+        use command_line_args::{Arguments, MaxSupportMode};
+        use std::collections::{HashMap, HashSet};
+
+        let rare_items: HashSet<u32> = [1, 2, 3].iter().cloned().collect();
+        let item_count: HashMap<u32, u32> = [(1, 1), (2, 5), (3, 20), (4, 50)].iter().cloned().collect();
+        let args = Arguments {
+            min_consequent_count: Some(2),
+            max_consequent_support: Some(0.1),
+            ..Arguments::default()
+        };
+        let expected: HashSet<u32> = [2].iter().cloned().collect();
+        assert_eq!(find_consequent_items(&rare_items, &item_count, 100, &args), expected);
+        assert_eq!(
+            find_consequent_items(&rare_items, &item_count, 100, &Arguments::default()),
+            rare_items
+        );
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        // Every rule's consequent occurs once, so no consequent is left.
+        assert!(!result.rules.is_empty());
+        assert!(result.rules.iter().all(|rule| result.item_count[&rule.consequent()[0]] == 1));
+        let bounded = mine(&Arguments {
+            min_consequent_count: Some(2),
+            ..args
+        })
+        .unwrap();
+        assert!(bounded.rules.is_empty());
+        assert!(bounded.report.notes[0].contains("--min-consequent-count"));
+    }
+
+    #[test]
+    fn test_add_possibly_rare_items() {
+        use super::{add_possibly_rare_items, sampling_error};
+        use std::collections::{HashMap, HashSet};
+
+        let item_count: HashMap<u32, u32> = [(1, 10), (2, 20), (3, 40), (4, 900)]
+            .iter()
+            .cloned()
+            .collect();
+        let mut rare_items: HashSet<u32> = [1].iter().cloned().collect();
+        // The cutoff is item 1's support of 0.01, plus 0.02.
+        add_possibly_rare_items(&mut rare_items, &item_count, 1000, 0.02);
+        let expected: HashSet<u32> = [1, 2].iter().cloned().collect();
+        assert_eq!(rare_items, expected);
+
+        assert!((sampling_error(1000, 0.05) - 0.042947).abs() < 1e-6);
+        assert!(sampling_error(1_000_000, 0.05) < sampling_error(1000, 0.05));
+    }
+
+    #[test]
+    fn test_pareto_fraction() {
+        use super::find_pareto_rare_items;
+        use std::collections::{HashMap, HashSet};
+
+        let item_count: HashMap<u32, u32> = [(1, 1), (2, 2), (3, 3), (4, 94)]
+            .iter()
+            .cloned()
+            .collect();
+        let expected: HashSet<u32> = [1, 2].iter().cloned().collect();
+        assert_eq!(find_pareto_rare_items(&item_count, 0.04), expected);
+        let expected: HashSet<u32> = [1, 2, 3].iter().cloned().collect();
+        assert_eq!(find_pareto_rare_items(&item_count, 0.1), expected);
+    }
+
+    #[test]
+    fn test_pareto_ties_at_threshold() {
+        use super::find_pareto_rare_items;
+        use std::collections::{HashMap, HashSet};
+
+        // Items 2 and 3 are tied, and adding the first of them brings the
+        // sum to exactly the threshold of 5. Neither is rare, whichever
+        // order they're visited in; previously the second of them was.
+        let item_count: HashMap<u32, u32> = [(1, 2), (2, 3), (3, 3), (4, 12)].iter().cloned().collect();
+        let expected: HashSet<u32> = [1].iter().cloned().collect();
+        for _ in 0..10 {
+            // Each map has its own hash keys, so its own iteration order.
+            let item_count: HashMap<u32, u32> = item_count.iter().map(|(&item, &count)| (item, count)).collect();
+            assert_eq!(find_pareto_rare_items(&item_count, 0.25), expected);
+        }
+    }
+
+    #[test]
+    fn test_robust_rare_items() {
+        use super::find_robust_rare_items;
+        use std::collections::{HashMap, HashSet};
+
+        // Log counts 0, ln 100 ± a little, and ln 10000: the median is about
+        // ln 100, and the deviation small, so only item 1 is far below it.
+        let item_count: HashMap<u32, u32> = [(1, 1), (2, 90), (3, 100), (4, 110), (5, 10000)]
+            .iter()
+            .cloned()
+            .collect();
+        let expected: HashSet<u32> = [1].iter().cloned().collect();
+        assert_eq!(find_robust_rare_items(&item_count, 3.0), expected);
+        assert!(find_robust_rare_items(&item_count, 100.0).is_empty());
+        assert!(find_robust_rare_items(&HashMap::new(), 3.0).is_empty());
+
+        // With no deviation, items below the median are rare.
+        let item_count: HashMap<u32, u32> = [(1, 5), (2, 5), (3, 5), (4, 2)].iter().cloned().collect();
+        let expected: HashSet<u32> = [4].iter().cloned().collect();
+        assert_eq!(find_robust_rare_items(&item_count, 3.0), expected);
+    }
+
+    #[test]
+    fn test_elbow_rare_items() {
+        use super::find_elbow_rare_items;
+        use std::collections::{HashMap, HashSet};
+
+        // Counts rise slowly, then steeply from 10.
+        let item_count: HashMap<u32, u32> = [(1, 1), (2, 2), (3, 3), (4, 4), (5, 10), (6, 50), (7, 100)]
+            .iter()
+            .cloned()
+            .collect();
+        let (rare_items, cutoff) = find_elbow_rare_items(&item_count);
+        let expected: HashSet<u32> = [1, 2, 3, 4].iter().cloned().collect();
+        assert_eq!(rare_items, expected);
+        assert_eq!(cutoff, Some(10));
+
+        let item_count: HashMap<u32, u32> = [(1, 5), (2, 5)].iter().cloned().collect();
+        assert_eq!(find_elbow_rare_items(&item_count), (HashSet::new(), None));
+    }
+
+    #[test]
+    fn test_fallback_max_support() {
+        use super::{find_percentile_rare_items, mine_transactions};
+        use command_line_args::{Arguments, FallbackMaxSupport, MaxSupportMode};
+        use std::collections::{HashMap, HashSet};
+
+        let item_count: HashMap<u32, u32> = [(1, 1), (2, 2), (3, 3), (4, 4), (5, 10)].iter().cloned().collect();
+        let expected: HashSet<u32> = [1, 2].iter().cloned().collect();
+        assert_eq!(find_percentile_rare_items(&item_count, 40.0), (expected, Some(2)));
+        assert_eq!(find_percentile_rare_items(&item_count, 1.0).1, Some(1));
+        assert_eq!(find_percentile_rare_items(&item_count, 100.0).0.len(), 5);
+        assert_eq!(find_percentile_rare_items(&HashMap::new(), 50.0), (HashSet::new(), None));
+
+        // Every item has the same count, so the counts have no elbow.
+        let transactions: Vec<Vec<String>> = vec![vec!["a", "b"], vec!["c", "d"], vec!["a", "b"], vec!["c", "d"]]
+            .into_iter()
+            .map(|items| items.into_iter().map(String::from).collect())
+            .collect();
+        let args = Arguments {
+            max_support_mode: MaxSupportMode::Elbow,
+            min_confidence: 0.9,
+            min_lift: 1.0,
+            ..Arguments::default()
+        };
+        let result = mine_transactions(&transactions, &args).unwrap();
+        assert!(result.rules.is_empty());
+        assert_eq!(result.report.num_rare_items, 0);
+        assert_eq!(result.report.notes.len(), 1);
+
+        let args = Arguments {
+            fallback_max_support: Some(FallbackMaxSupport::Percentile(50.0)),
+            ..args
+        };
+        let result = mine_transactions(&transactions, &args).unwrap();
+        assert_eq!(result.report.num_rare_items, 4);
+        assert_eq!(result.report.rare_item_cutoff, Some(2));
+        assert!(result.report.notes[0].contains("--fallback-max-support percentile:50"));
+
+        assert!("percentile:0".parse::<FallbackMaxSupport>().is_err());
+        assert!("pareto".parse::<FallbackMaxSupport>().is_ok());
+    }
+
+    #[test]
+    fn test_rare_items_by_category() {
+        use super::{find_rare_items, group_by_category};
+        use command_line_args::{Arguments, MaxSupportMode, RarityParameters};
+        use itemizer::Itemizer;
+        use std::collections::{HashMap, HashSet};
+        use taxonomy::Taxonomy;
+
+        let mut itemizer = Itemizer::new();
+        let taxonomy = Taxonomy::read(
+            "aspirin,pharmacy\nbandages,pharmacy\nbread,groceries\nmilk,groceries\n".as_bytes(),
+        )
+        .unwrap();
+        itemizer.set_taxonomy(taxonomy, None);
+        // Pharmacy items are all rarer than groceries, but aspirin isn't
+        // rare among pharmacy items, and batteries have no peers.
+        let item_count: HashMap<u32, u32> = [
+            ("aspirin", 40),
+            ("bandages", 3),
+            ("bread", 900),
+            ("milk", 1000),
+            ("batteries", 8),
+        ]
+        .iter()
+        .map(|&(item, count)| (itemizer.id_of(item), count))
+        .collect();
+
+        let groups = group_by_category(&item_count, &itemizer);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups["pharmacy"].len(), 2);
+        assert_eq!(groups[""].len(), 1);
+
+        let args = Arguments {
+            max_support_mode: MaxSupportMode::Pareto,
+            rarity: RarityParameters {
+                pareto_fraction: 0.1,
+                ..RarityParameters::default()
+            },
+            ..Arguments::default()
+        };
+        let (rare_items, _) = find_rare_items(&item_count, 100, &itemizer, &args);
+        let expected: HashSet<u32> = ["aspirin", "bandages", "batteries"]
+            .iter()
+            .map(|item| itemizer.id_of(item))
+            .collect();
+        assert_eq!(rare_items, expected);
+
+        let rare_items: HashSet<u32> = groups
+            .values()
+            .flat_map(|counts| find_rare_items(counts, 100, &itemizer, &args).0)
+            .collect();
+        let expected: HashSet<u32> = [itemizer.id_of("bandages")].iter().cloned().collect();
+        assert_eq!(rare_items, expected);
+    }
+
+    #[test]
+    fn test_sequences_rarity_parameters() {
+        use super::mine_sequences;
+        use command_line_args::{LogOptions, MaxSupportMode, RarityParameters, SequenceArguments};
+        use std::env;
+        use std::fs;
+
+        let name = format!("riptree-sequences-rarity-test-{}.csv", std::process::id());
+        let path = env::temp_dir().join(name);
+        let mut lines = String::new();
+        for id in 0..10 {
+            lines += &format!("{},1,a\n{},2,b\n", id, id);
+            if id < 3 {
+                lines += &format!("{},3,c\n", id);
+            }
+        }
+        fs::write(&path, lines).unwrap();
+
+        let rules = |pareto_fraction: f64| -> Vec<String> {
+            let args = SequenceArguments {
+                input_file_path: path.to_string_lossy().into_owned(),
+                max_support_mode: MaxSupportMode::Pareto,
+                rarity: RarityParameters {
+                    pareto_fraction,
+                    ..RarityParameters::default()
+                },
+                min_confidence: 0.0,
+                min_lift: 0.0,
+                min_count: 2,
+                max_antecedent_len: 2,
+                output_path: String::new(),
+                compress_output: None,
+                log: LogOptions::default(),
+            };
+            let result = mine_sequences(&args).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        // --pareto-fraction reaches the sequences subcommand: with all
+        // occurrences counted, the common item b is rare too.
+        assert!(!rules(0.01).contains(&String::from("a ==> b")));
+        assert!(rules(1.0).contains(&String::from("a ==> b")));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sequences_robust_k() {
+        use super::mine_sequences;
+        use command_line_args::parse_sequence_args_or_exit;
+        use std::env;
+        use std::fs;
+
+        // a and b occur in all 10 sequences, c in 8 and d in 4, so c's log
+        // count is 0.67 deviations below the median, and d's 4.8.
+        let name = format!("riptree-sequences-robust-test-{}.csv", std::process::id());
+        let path = env::temp_dir().join(name);
+        let mut lines = String::new();
+        for id in 0..10 {
+            lines += &format!("{},1,a\n{},2,b\n", id, id);
+            if id < 8 {
+                lines += &format!("{},3,c\n", id);
+            }
+            if id < 4 {
+                lines += &format!("{},4,d\n", id);
+            }
+        }
+        fs::write(&path, lines).unwrap();
+
+        let rules = |robust_k: &str| -> Vec<String> {
+            let args = parse_sequence_args_or_exit(
+                vec![
+                    "riptree",
+                    "sequences",
+                    "--input",
+                    &path.to_string_lossy(),
+                    "--max-support",
+                    "robust",
+                    "--robust-k",
+                    robust_k,
+                    "--min-confidence",
+                    "0",
+                    "--output",
+                    "unused.csv",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            );
+            let result = mine_sequences(&args).unwrap();
+            result.rules.iter().map(|rule| rule.to_string(&result.itemizer)).collect()
+        };
+        // --robust-k reaches the sequences subcommand: only with fewer
+        // deviations is c rare.
+        assert!(!rules("3").contains(&String::from("a ==> c")));
+        assert!(rules("0.5").contains(&String::from("a ==> c")));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_gaussian_null_models() {
+        use super::find_gaussian_rare_items;
+        use command_line_args::{GaussianParameters, NullModel};
+        use std::collections::{HashMap, HashSet};
+
+        let item_count: HashMap<u32, u32> = [(0, 2), (1, 2), (2, 2), (3, 994)]
+            .iter()
+            .cloned()
+            .collect();
+        let mut parameters = GaussianParameters {
+            simulations: 20,
+            ..GaussianParameters::default()
+        };
+        // Drawn uniformly, every item is about as common as item 3, so the
+        // others are far rarer than chance.
+        let expected: HashSet<u32> = [0, 1, 2].iter().cloned().collect();
+        assert_eq!(find_gaussian_rare_items(&item_count, 1000, 3, &parameters), expected);
+        // Drawn by frequency, every item's count is what chance would give.
+        parameters.null_model = NullModel::Frequency;
+        assert!(find_gaussian_rare_items(&item_count, 1000, 3, &parameters).is_empty());
+    }
+
+    #[test]
+    fn test_utility_gaussian_parameters() {
+        use super::mine_high_utility;
+        use command_line_args::parse_utility_args_or_exit;
+        use std::env;
+        use std::fs;
+
+        // Item counts much as in test_gaussian_null_models: a, b and c are
+        // each in 2 of 1000 transactions, and d in the other 998.
+        let name = format!("riptree-utility-gaussian-test-{}", std::process::id());
+        let input = env::temp_dir().join(format!("{}.csv", name));
+        let utilities = env::temp_dir().join(format!("{}.utilities", name));
+        let mut lines = String::from("a,b\na,b\nc,d\nc,d\n");
+        for _ in 0..996 {
+            lines += "d\n";
+        }
+        fs::write(&input, lines).unwrap();
+        fs::write(&utilities, "a,1\nb,1\nc,1\nd,1\n").unwrap();
+
+        let num_itemsets = |null_model: &str| -> usize {
+            let args = parse_utility_args_or_exit(
+                vec![
+                    "riptree",
+                    "utility",
+                    "--input",
+                    &input.to_string_lossy(),
+                    "--utilities",
+                    &utilities.to_string_lossy(),
+                    "--min-utility",
+                    "1",
+                    "--max-support",
+                    "gaussian",
+                    "--gaussian-simulations",
+                    "20",
+                    "--gaussian-delta",
+                    "0.05",
+                    "--gaussian-null-model",
+                    null_model,
+                    "--output",
+                    "unused.csv",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            );
+            mine_high_utility(&args).unwrap().itemsets.len()
+        };
+        // The gaussian options reach the utility subcommand. Drawn
+        // uniformly, a, b and c are rare, so both pairs are found; drawn by
+        // frequency, with two items per random transaction, only d is.
+        assert_eq!(num_itemsets("uniform"), 2);
+        assert_eq!(num_itemsets("frequency"), 1);
+
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&utilities).unwrap();
+    }
+
+    #[test]
+    fn test_holdout_evaluation() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        // Evaluating on the dataset mined should give the same measures.
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            test_file_path: String::from("datasets/UCI-zoo.csv"),
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        assert!(!result.rules.is_empty());
+        for rule in result.rules.iter() {
+            let holdout = rule.holdout().unwrap();
+            assert!((holdout.confidence() - rule.confidence()).abs() < 1e-9);
+            assert!((holdout.lift() - rule.lift()).abs() < 1e-9);
+            assert!((holdout.support() - rule.support()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_emit_examples() {
+        use super::mine;
+        use command_line_args::{Arguments, MaxSupportMode};
+
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            emit_examples: Some(2),
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        assert!(!result.rules.is_empty());
+        for rule in result.rules.iter() {
+            // Each example contains all the rule's items, and there are as
+            // many as there are transactions which do, up to 2.
+            let examples = rule.examples().unwrap();
+            let num_supporting = (rule.support() * result.report.num_transactions as f64).round() as usize;
+            assert_eq!(examples.len(), num_supporting.min(2));
+            for example in examples {
+                let items = example.item_names(&result.itemizer);
+                let rule_items = rule.antecedent_names(&result.itemizer);
+                for item in rule_items.into_iter().chain(rule.consequent_names(&result.itemizer)) {
+                    assert!(items.contains(&item), "{:?} doesn't contain {}", items, item);
+                }
+            }
+        }
+    }
+
+    use proptest::strategy::Strategy;
+
+    // Transactions each made of one of a few overlapping patterns, some
+    // common and some rare, so some rare items are correlated, plus random
+    // noise items.
+    fn random_transactions() -> impl Strategy<Value = Vec<Vec<String>>> {
+        use proptest::collection::{btree_set, vec};
+
+        let common = [vec!["a", "b", "c"], vec!["c", "d"], vec!["e", "f", "g"], vec!["a", "e"]];
+        let rare = [vec!["x", "y"], vec!["x", "y", "z"], vec!["w", "a", "b"], vec!["z", "f"]];
+        let patterns: Vec<Vec<&str>> = common.iter().cycle().take(12).chain(rare.iter()).cloned().collect();
+        vec((0..patterns.len(), btree_set(0..20u8, 0..2)), 50..200).prop_map(move |transactions| {
+            transactions
+                .into_iter()
+                .map(|(pattern, noise)| {
+                    let mut items: Vec<String> =
+                        patterns[pattern].iter().map(|item| item.to_string()).collect();
+                    items.extend(noise.into_iter().map(|item| format!("n{}", item)));
+                    items
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #![proptest_config(::proptest::test_runner::Config::with_cases(48))]
+
+        // Every algorithm should find the same itemsets with the same counts,
+        // and so the same rules with the same measures.
+        #[test]
+        fn test_algorithms_agree(transactions in random_transactions()) {
+            use super::{find_pareto_rare_items, mine_transactions};
+            use apriori::apriori;
+            use command_line_args::{Algorithm, Arguments, MaxSupportMode, RarityParameters};
+            use eclat::eclat;
+            use fptree::{rip_growth, sort_transaction, FPTree, ItemSet, SortOrder};
+            use hmine::hmine;
+            use index::Index;
+            use itemizer::Itemizer;
+            use significance::SignificanceTest;
+            use std::collections::{HashMap, HashSet};
+            use transaction_reader::Transaction;
+
+            let mut itemizer = Itemizer::new();
+            let mut item_count: HashMap<u32, u32> = HashMap::new();
+            let mut itemized: Vec<Vec<u32>> = vec![];
+            for transaction in transactions.iter() {
+                let items: Vec<u32> = transaction.iter().map(|item| itemizer.id_of(item)).collect();
+                for &item in items.iter() {
+                    *item_count.entry(item).or_insert(0) += 1;
+                }
+                itemized.push(items);
+            }
+            // More than the default fraction of items are rare, so the rare
+            // patterns' items are.
+            let pareto_fraction = 0.2;
+            let rare_items = find_pareto_rare_items(&item_count, pareto_fraction);
+            let mut index = Index::new();
+            let mut fptree = FPTree::new();
+            let mut rare_transactions: Vec<Transaction> = vec![];
+            for items in itemized.iter_mut() {
+                sort_transaction(items, &item_count, SortOrder::Decreasing);
+                index.insert(items, 1);
+                if items.iter().any(|item| rare_items.contains(item)) {
+                    fptree.insert(items, 1);
+                    rare_transactions.push(Transaction { items: items.clone(), weight: 1 });
+                }
+            }
+
+            let significance_test = SignificanceTest::fisher();
+            let required_items = HashSet::new();
+            let normalize = |itemsets: Vec<ItemSet>| -> Vec<(Vec<u32>, u32)> {
+                let mut itemsets: Vec<(Vec<u32>, u32)> = itemsets
+                    .into_iter()
+                    .map(|mut itemset| {
+                        itemset.items.sort();
+                        (itemset.items, itemset.count)
+                    })
+                    .collect();
+                itemsets.sort();
+                itemsets
+            };
+            let riptree = normalize(rip_growth(
+                &fptree,
+                &fptree,
+                Some(&rare_items),
+                &[],
+                transactions.len() as u32,
+                &itemizer,
+                &index,
+                &significance_test,
+                None,
+                &required_items,
+                None,
+                None,
+                None,
+                None,
+            ));
+            let test = &significance_test;
+            let eclat = normalize(eclat(&index, &item_count, &rare_items, test, None, &required_items));
+            prop_assert_eq!(&riptree, &eclat);
+            let apriori = normalize(apriori(&index, &item_count, &rare_items, test, None, &required_items));
+            prop_assert_eq!(&riptree, &apriori);
+            let hmine = normalize(hmine(
+                &rare_transactions,
+                &index,
+                &rare_items,
+                &significance_test,
+                None,
+                &required_items,
+            ));
+            prop_assert_eq!(&riptree, &hmine);
+            for (items, count) in riptree.iter() {
+                prop_assert_eq!(index.count(items), *count as usize);
+            }
+
+            let rules = |algorithm: Algorithm| -> Vec<String> {
+                let args = Arguments {
+                    max_support_mode: MaxSupportMode::Pareto,
+                    rarity: RarityParameters {
+                        pareto_fraction,
+                        ..RarityParameters::default()
+                    },
+                    algorithm,
+                    min_confidence: 0.3,
+                    min_lift: 1.0,
+                    disable_family_wise_rule_filtering: true,
+                    disable_permutation_rule_filtering: true,
+                    ..Arguments::default()
+                };
+                let result = mine_transactions(&transactions, &args).unwrap();
+                let mut rules: Vec<String> = result
+                    .rules
+                    .iter()
+                    .map(|rule| {
+                        format!(
+                            "{} {:.9} {:.9} {:.9}",
+                            rule.to_string(&result.itemizer),
+                            rule.support(),
+                            rule.confidence(),
+                            rule.lift()
+                        )
+                    })
+                    .collect();
+                rules.sort();
+                rules
+            };
+            let riptree = rules(Algorithm::RipTree);
+            prop_assert_eq!(&riptree, &rules(Algorithm::Eclat));
+            prop_assert_eq!(&riptree, &rules(Algorithm::Apriori));
+            prop_assert_eq!(&riptree, &rules(Algorithm::HMine));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Persists a dataset in itemized form, so that `riptree update` can mine it
+// again along with newly arrived transactions without re-reading the full
+// history. Only the itemizer and the distinct transactions with their
+// weights are stored; the tree is rebuilt on each update, since which items
+// are rare, and so the tree's shape, changes as new transactions arrive.
+//
+// The format is the magic bytes "RIPS", a format version, the item names in
+// id order, then the transactions. All integers are little endian u32s, and
+// strings are a length followed by UTF-8 bytes.
+
+use itemizer::Itemizer;
+use std::io::{self, Read, Write};
+use transaction_reader::Transaction;
+
+const MAGIC: &[u8; 4] = b"RIPS";
+const VERSION: u32 = 1;
+
+pub fn write_u32<W: Write>(output: &mut W, value: u32) -> io::Result<()> {
+    output.write_all(&value.to_le_bytes())
+}
+
+pub fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn write_state<W: Write>(
+    output: &mut W,
+    itemizer: &Itemizer,
+    transactions: &[Transaction],
+) -> io::Result<()> {
+    output.write_all(MAGIC)?;
+    write_u32(output, VERSION)?;
+
+    write_u32(output, itemizer.max_item_id())?;
+    for id in 1..itemizer.max_item_id() + 1 {
+        let name = itemizer.str_of(id);
+        write_u32(output, name.len() as u32)?;
+        output.write_all(name.as_bytes())?;
+    }
+
+    write_u32(output, transactions.len() as u32)?;
+    for transaction in transactions {
+        write_u32(output, transaction.weight)?;
+        write_u32(output, transaction.items.len() as u32)?;
+        for &item in transaction.items.iter() {
+            write_u32(output, item)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_state<R: Read>(input: &mut R) -> io::Result<(Itemizer, Vec<Transaction>)> {
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a riptree state file"));
+    }
+    if read_u32(input)? != VERSION {
+        return Err(invalid_data("Unsupported riptree state file version"));
+    }
+
+    // Item ids are allocated in order, so re-adding the names in the same
+    // order gives each the id it had before.
+    let mut itemizer = Itemizer::new();
+    let num_items = read_u32(input)?;
+    for _ in 0..num_items {
+        let mut name = vec![0; read_u32(input)? as usize];
+        input.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid_data("Invalid item name"))?;
+        itemizer.try_id_of(&name)?;
+    }
+    if itemizer.max_item_id() != num_items {
+        return Err(invalid_data("Duplicate item name"));
+    }
+
+    let num_transactions = read_u32(input)?;
+    let mut transactions = Vec::with_capacity(num_transactions as usize);
+    for _ in 0..num_transactions {
+        let weight = read_u32(input)?;
+        let len = read_u32(input)?;
+        let mut items = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let item = read_u32(input)?;
+            if item == 0 || item > num_items {
+                return Err(invalid_data("Invalid item id"));
+            }
+            items.push(item);
+        }
+        transactions.push(Transaction { items, weight });
+    }
+    Ok((itemizer, transactions))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_round_trip() {
+        use super::{read_state, write_state};
+        use itemizer::Itemizer;
+        use transaction_reader::Transaction;
+
+        let mut itemizer = Itemizer::new();
+        let a = itemizer.id_of("a");
+        let b = itemizer.id_of("skim milk");
+        let transactions = vec![
+            Transaction {
+                items: vec![a, b],
+                weight: 3,
+            },
+            Transaction {
+                items: vec![b],
+                weight: 1,
+            },
+        ];
+
+        let mut buffer: Vec<u8> = vec![];
+        write_state(&mut buffer, &itemizer, &transactions).unwrap();
+        let (itemizer, read_transactions) = read_state(&mut &buffer[..]).unwrap();
+
+        assert_eq!(itemizer.get_id("a"), Some(a));
+        assert_eq!(itemizer.get_id("skim milk"), Some(b));
+        assert_eq!(read_transactions.len(), 2);
+        assert_eq!(read_transactions[0].items, vec![a, b]);
+        assert_eq!(read_transactions[0].weight, 3);
+        assert_eq!(read_transactions[1].items, vec![b]);
+        assert_eq!(read_transactions[1].weight, 1);
+
+        assert!(read_state(&mut &buffer[..10]).is_err());
+        assert!(read_state(&mut &b"nope"[..]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+// Creates the files rules and itemsets are written to, optionally compressed
+// with --compress-output, as rule files for big runs can reach tens of
+// gigabytes. Writes are buffered in large chunks, as rules are written a few
+// bytes at a time.
+
+use command_line_args::Compression;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use zstd;
+
+const BUFFER_SIZE: usize = 1 << 20;
+const ZSTD_LEVEL: i32 = 3;
+
+enum Encoder {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::Plain(ref mut file) => file.write(buf),
+            Encoder::Gzip(ref mut encoder) => encoder.write(buf),
+            Encoder::Zstd(ref mut encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::Plain(ref mut file) => file.flush(),
+            Encoder::Gzip(ref mut encoder) => encoder.flush(),
+            Encoder::Zstd(ref mut encoder) => encoder.flush(),
+        }
+    }
+}
+
+// An output file. finish() must be called once everything is written, so the
+// end of a compressed stream is written, and any errors doing so reported.
+pub struct Output {
+    writer: BufWriter<Encoder>,
+}
+
+impl Output {
+    pub fn create(path: &str, compression: Option<&Compression>) -> io::Result<Output> {
+        let file = File::create(path)?;
+        let encoder = match compression {
+            None => Encoder::Plain(file),
+            Some(&Compression::Gzip) => Encoder::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Some(&Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(file, ZSTD_LEVEL)?),
+        };
+        Ok(Output {
+            writer: BufWriter::with_capacity(BUFFER_SIZE, encoder),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.writer.into_inner().map_err(|err| err.into_error())? {
+            Encoder::Plain(mut file) => file.flush(),
+            Encoder::Gzip(encoder) => encoder.finish().map(|_| ()),
+            Encoder::Zstd(encoder) => encoder.finish().map(|_| ()),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_output() {
+        use super::Output;
+        use command_line_args::Compression;
+        use flate2::read::GzDecoder;
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::{Read, Write};
+        use zstd;
+
+        let text = "a ==> b, 1, 2, 0.5\n".repeat(1000);
+        let dir = env::temp_dir().join(format!("riptree-output-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, compression: Option<&Compression>| {
+            let path = dir.join(name).to_string_lossy().into_owned();
+            let mut output = Output::create(&path, compression).unwrap();
+            output.write_all(text.as_bytes()).unwrap();
+            output.finish().unwrap();
+            path
+        };
+
+        let plain = write("rules.csv", None);
+        assert_eq!(fs::read_to_string(&plain).unwrap(), text);
+
+        let gzip = write("rules.csv.gz", Some(&Compression::Gzip));
+        let mut decoded = String::new();
+        GzDecoder::new(File::open(&gzip).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+        assert!(fs::metadata(&gzip).unwrap().len() < text.len() as u64 / 10);
+
+        let zstd_path = write("rules.csv.zst", Some(&Compression::Zstd));
+        let decoded = zstd::decode_all(File::open(&zstd_path).unwrap()).unwrap();
+        assert_eq!(String::from_utf8(decoded).unwrap(), text);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
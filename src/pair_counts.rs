@@ -0,0 +1,101 @@
+// Counts of how often pairs of the most frequent items occur together, for
+// --count-pairs. The pairs are counted while the index is built, in a
+// triangular matrix with a row for each of the items counted, so looking up
+// a pair's count is as cheap as looking up an item's. Pairs with an item
+// outside the matrix aren't counted; their count is at most that item's,
+// which is rarer than any in the matrix.
+//
+// Any itemset's count is at most the count of each pair of items in it, so
+// RIPTree leaves items out of a conditional tree when their count with the
+// tree's item is already below the top K threshold, before building it.
+
+use std::collections::HashMap;
+
+pub struct PairCounts {
+    // The row of each item counted.
+    rows: HashMap<u32, usize>,
+    // The count of the pair of the items in rows i < j is at
+    // j * (j - 1) / 2 + i.
+    counts: Vec<u32>,
+}
+
+fn position(i: usize, j: usize) -> usize {
+    let (i, j) = if i < j { (i, j) } else { (j, i) };
+    j * (j - 1) / 2 + i
+}
+
+impl PairCounts {
+    // Counts the pairs of the `max_items` items with the highest counts.
+    pub fn new(item_count: &HashMap<u32, u32>, max_items: usize) -> PairCounts {
+        let mut items: Vec<(u32, u32)> = item_count.iter().map(|(&item, &count)| (item, count)).collect();
+        items.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        items.truncate(max_items);
+        let rows: HashMap<u32, usize> = items.iter().enumerate().map(|(row, &(item, _))| (item, row)).collect();
+        let num_pairs = rows.len() * rows.len().saturating_sub(1) / 2;
+        PairCounts {
+            rows,
+            counts: vec![0; num_pairs],
+        }
+    }
+
+    pub fn insert(&mut self, transaction: &[u32], weight: u32) {
+        let rows: Vec<usize> = transaction.iter().filter_map(|item| self.rows.get(item).cloned()).collect();
+        for (n, &i) in rows.iter().enumerate() {
+            for &j in rows[n + 1..].iter() {
+                if i != j {
+                    self.counts[position(i, j)] += weight;
+                }
+            }
+        }
+    }
+
+    // The count of transactions containing both items, if both are counted.
+    pub fn count(&self, a: u32, b: u32) -> Option<u32> {
+        match (self.rows.get(&a), self.rows.get(&b)) {
+            (Some(&i), Some(&j)) if i != j => Some(self.counts[position(i, j)]),
+            _ => None,
+        }
+    }
+
+    pub fn num_items(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.counts.len() * std::mem::size_of::<u32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_pair_counts() {
+        use super::PairCounts;
+        use std::collections::HashMap;
+
+        let transactions: Vec<(Vec<u32>, u32)> = vec![
+            (vec![1, 2, 3], 1),
+            (vec![2, 1], 2),
+            (vec![3, 4], 1),
+            (vec![1, 3, 5], 1),
+        ];
+        let mut item_count: HashMap<u32, u32> = HashMap::new();
+        for (transaction, weight) in transactions.iter() {
+            for &item in transaction {
+                *item_count.entry(item).or_insert(0) += weight;
+            }
+        }
+        let mut pairs = PairCounts::new(&item_count, 3);
+        for (transaction, weight) in transactions.iter() {
+            pairs.insert(transaction, *weight);
+        }
+        assert_eq!(pairs.num_items(), 3);
+        assert_eq!(pairs.count(1, 2), Some(3));
+        assert_eq!(pairs.count(2, 1), Some(3));
+        assert_eq!(pairs.count(1, 3), Some(2));
+        assert_eq!(pairs.count(2, 3), Some(1));
+        // Items 4 and 5 are the least frequent, so aren't counted.
+        assert_eq!(pairs.count(3, 4), None);
+        assert_eq!(pairs.count(1, 1), None);
+    }
+}
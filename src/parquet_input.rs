@@ -0,0 +1,173 @@
+// Reads baskets from Parquet files, where each row holds a transaction as a
+// list of strings in the column given by --items-column. Only that column is
+// decoded. Build with:
+//   cargo build --release --features parquet
+
+use arrow_array::cast::AsArray;
+use arrow_array::{Array, ArrayRef};
+use arrow_schema::DataType;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ProjectionMask;
+use std::fs::File;
+use std::io;
+
+fn invalid_data(path: &str, message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Can't read {}: {}", path, message))
+}
+
+fn is_string(data_type: &DataType) -> bool {
+    matches!(*data_type, DataType::Utf8 | DataType::LargeUtf8)
+}
+
+// The baskets in a Parquet file, one per row, in order.
+pub struct Baskets {
+    path: String,
+    reader: ParquetRecordBatchReader,
+    // The items column of the batch being read, and the next row in it.
+    column: Option<ArrayRef>,
+    row: usize,
+}
+
+impl Baskets {
+    pub fn open(path: &str, items_column: &str) -> io::Result<Baskets> {
+        let file = File::open(path).map_err(|err| io::Error::new(err.kind(), format!("Can't open {}: {}", path, err)))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|err| invalid_data(path, err.to_string()))?;
+        let index = match builder.schema().index_of(items_column) {
+            Ok(index) => index,
+            Err(_) => {
+                return Err(invalid_data(
+                    path,
+                    format!("there's no column '{}'; pass the column of baskets with --items-column", items_column),
+                ))
+            }
+        };
+        let is_list_of_strings = match *builder.schema().field(index).data_type() {
+            DataType::List(ref item) | DataType::LargeList(ref item) => is_string(item.data_type()),
+            _ => false,
+        };
+        if !is_list_of_strings {
+            return Err(invalid_data(
+                path,
+                format!(
+                    "column '{}' is {}, not a list of strings",
+                    items_column,
+                    builder.schema().field(index).data_type()
+                ),
+            ));
+        }
+        let mask = ProjectionMask::roots(builder.parquet_schema(), [index]);
+        let reader = builder
+            .with_projection(mask)
+            .build()
+            .map_err(|err| invalid_data(path, err.to_string()))?;
+        Ok(Baskets {
+            path: path.to_owned(),
+            reader,
+            column: None,
+            row: 0,
+        })
+    }
+}
+
+// The strings in a list, skipping nulls.
+fn strings(items: &dyn Array) -> Vec<String> {
+    match items.as_string_opt::<i32>() {
+        Some(items) => items.iter().flatten().map(String::from).collect(),
+        None => items.as_string::<i64>().iter().flatten().map(String::from).collect(),
+    }
+}
+
+impl Iterator for Baskets {
+    type Item = io::Result<Vec<String>>;
+    fn next(&mut self) -> Option<io::Result<Vec<String>>> {
+        loop {
+            if let Some(ref column) = self.column {
+                if self.row < column.len() {
+                    let row = self.row;
+                    self.row += 1;
+                    // A null basket is read as an empty one.
+                    if column.is_null(row) {
+                        return Some(Ok(vec![]));
+                    }
+                    let items = match column.as_list_opt::<i32>() {
+                        Some(list) => list.value(row),
+                        None => column.as_list::<i64>().value(row),
+                    };
+                    return Some(Ok(strings(&items)));
+                }
+            }
+            match self.reader.next()? {
+                Ok(batch) => {
+                    self.column = Some(batch.column(0).clone());
+                    self.row = 0;
+                }
+                Err(err) => return Some(Err(invalid_data(&self.path, err.to_string()))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parquet_input() {
+        use arrow_array::builder::{ListBuilder, StringBuilder};
+        use arrow_array::{ArrayRef, RecordBatch, UInt32Array};
+        use command_line_args::{Arguments, MaxSupportMode};
+        use miner::mine;
+        use parquet::arrow::ArrowWriter;
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::{BufRead, BufReader};
+        use std::sync::Arc;
+
+        // The zoo dataset, with each line as a basket, alongside another
+        // column which should be ignored.
+        let mut baskets = ListBuilder::new(StringBuilder::new());
+        let mut ids = vec![];
+        for (id, line) in BufReader::new(File::open("datasets/UCI-zoo.csv").unwrap()).lines().enumerate() {
+            for item in line.unwrap().split(',') {
+                baskets.values().append_value(item);
+            }
+            baskets.append(true);
+            ids.push(id as u32);
+        }
+        let batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(UInt32Array::from(ids)) as ArrayRef),
+            ("basket", Arc::new(baskets.finish()) as ArrayRef),
+        ])
+        .unwrap();
+        let dir = env::temp_dir().join(format!("riptree-parquet-input-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("zoo.parquet").to_string_lossy().into_owned();
+        let mut writer = ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let rule_strings = |path: &str, items_column: &str| -> Result<Vec<String>, String> {
+            let args = Arguments {
+                input_file_paths: vec![String::from(path)],
+                items_column: String::from(items_column),
+                max_support_mode: MaxSupportMode::Pareto,
+                min_confidence: 0.9,
+                min_lift: 5.0,
+                ..Arguments::default()
+            };
+            let result = mine(&args).map_err(|err| err.to_string())?;
+            let mut rules: Vec<String> = result
+                .rules
+                .iter()
+                .map(|rule| format!("{} {}", rule.to_string(&result.itemizer), rule.lift()))
+                .collect();
+            rules.sort();
+            Ok(rules)
+        };
+        let from_parquet = rule_strings(&path, "basket").unwrap();
+        assert!(!from_parquet.is_empty());
+        assert_eq!(from_parquet, rule_strings("datasets/UCI-zoo.csv", "items").unwrap());
+        assert!(rule_strings(&path, "items").unwrap_err().contains("no column 'items'"));
+        assert!(rule_strings(&path, "id").unwrap_err().contains("not a list of strings"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
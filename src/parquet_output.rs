@@ -0,0 +1,221 @@
+// Writes rules and itemsets as Parquet files, for --output-parquet, with
+// typed columns, so they can be loaded straight into Spark, pandas or DuckDB.
+// Antecedents, consequents and itemsets are lists of item names, and measures
+// are doubles. Columns for measures not every run has, e.g. p-values or
+// metrics, are only present if the rules have them, as in the CSV output.
+// Build with:
+//   cargo build --release --features parquet
+
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt32Array};
+use arrow_schema::ArrowError;
+use fptree::ItemSet;
+use generate_rules::Rule;
+use itemizer::Itemizer;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+
+// Rows are converted to Arrow, and written, this many at a time, so big
+// outputs needn't be converted all at once.
+const BATCH_SIZE: usize = 65536;
+
+// A column's name, values, and whether it may have nulls.
+type Column = (String, ArrayRef, bool);
+
+fn item_lists<'a, T, F: Fn(&T) -> Vec<&'a str>>(name: &str, rows: &[T], items: F) -> Column {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for row in rows {
+        for item in items(row) {
+            builder.values().append_value(item);
+        }
+        builder.append(true);
+    }
+    (name.to_owned(), Arc::new(builder.finish()), false)
+}
+
+fn doubles<T, F: Fn(&T) -> f64>(name: &str, rows: &[T], value: F) -> Column {
+    let values: Vec<f64> = rows.iter().map(value).collect();
+    (name.to_owned(), Arc::new(Float64Array::from(values)), false)
+}
+
+fn optional_doubles<T, F: Fn(&T) -> Option<f64>>(name: &str, rows: &[T], value: F) -> Column {
+    let values: Vec<Option<f64>> = rows.iter().map(value).collect();
+    (name.to_owned(), Arc::new(Float64Array::from(values)), true)
+}
+
+// `template` is any one of the rules, which says which measures they have.
+fn rule_batch(
+    rules: &[Rule],
+    template: Option<&Rule>,
+    itemizer: &Itemizer,
+) -> Result<RecordBatch, ArrowError> {
+    let has = |measure: fn(&Rule) -> bool| template.is_some_and(measure);
+    let mut columns = vec![
+        item_lists("antecedent", rules, |rule| rule.antecedent_names(itemizer)),
+        item_lists("consequent", rules, |rule| rule.consequent_names(itemizer)),
+        doubles("confidence", rules, Rule::confidence),
+        doubles("lift", rules, Rule::lift),
+        doubles("support", rules, Rule::support),
+    ];
+    if has(|rule| rule.p_value().is_some()) {
+        columns.push(optional_doubles("p_value", rules, Rule::p_value));
+        columns.push(optional_doubles("p_value_threshold", rules, Rule::p_value_threshold));
+    }
+    if has(|rule| rule.odds_ratio().is_some()) {
+        columns.push(optional_doubles("odds_ratio", rules, |rule| rule.odds_ratio().map(|o| o.ratio())));
+        columns.push(optional_doubles("odds_ratio_lower", rules, |rule| rule.odds_ratio().map(|o| o.lower())));
+        columns.push(optional_doubles("odds_ratio_upper", rules, |rule| rule.odds_ratio().map(|o| o.upper())));
+    }
+    if has(|rule| rule.imbalance().is_some()) {
+        columns.push(optional_doubles("imbalance_ratio", rules, |rule| rule.imbalance().map(|i| i.ratio())));
+        columns.push(optional_doubles("cross_support_ratio", rules, |rule| {
+            rule.imbalance().map(|i| i.cross_support_ratio())
+        }));
+    }
+    for (metric, _) in template.map(Rule::metrics).unwrap_or_default() {
+        let name = metric.to_string().replace('-', "_");
+        columns.push(optional_doubles(&name, rules, |rule| rule.metric(metric)));
+    }
+    if has(|rule| rule.bootstrap().is_some()) {
+        columns.push(optional_doubles("confidence_lower", rules, |rule| rule.bootstrap().map(|b| b.confidence().0)));
+        columns.push(optional_doubles("confidence_upper", rules, |rule| rule.bootstrap().map(|b| b.confidence().1)));
+        columns.push(optional_doubles("lift_lower", rules, |rule| rule.bootstrap().map(|b| b.lift().0)));
+        columns.push(optional_doubles("lift_upper", rules, |rule| rule.bootstrap().map(|b| b.lift().1)));
+    }
+    if has(|rule| rule.holdout().is_some()) {
+        columns.push(optional_doubles("holdout_confidence", rules, |rule| rule.holdout().map(|h| h.confidence())));
+        columns.push(optional_doubles("holdout_lift", rules, |rule| rule.holdout().map(|h| h.lift())));
+        columns.push(optional_doubles("holdout_support", rules, |rule| rule.holdout().map(|h| h.support())));
+    }
+    RecordBatch::try_from_iter_with_nullable(columns)
+}
+
+fn itemset_batch(itemsets: &[ItemSet], num_transactions: usize, itemizer: &Itemizer) -> Result<RecordBatch, ArrowError> {
+    let counts: Vec<u32> = itemsets.iter().map(|itemset| itemset.count).collect();
+    RecordBatch::try_from_iter_with_nullable(vec![
+        item_lists("items", itemsets, |itemset| {
+            itemset.items.iter().map(|&item| itemizer.str_of(item)).collect()
+        }),
+        ("count".to_owned(), Arc::new(UInt32Array::from(counts)) as ArrayRef, false),
+        doubles("support", itemsets, |itemset| itemset.count as f64 / num_transactions as f64),
+    ])
+}
+
+// Writes `rows` to a Parquet file at `path`, converting them to Arrow a
+// batch at a time with `batch`.
+fn write_batches<T, F>(path: &Path, rows: &[T], batch: F) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(&[T]) -> Result<RecordBatch, ArrowError>,
+{
+    let mut chunks = rows.chunks(BATCH_SIZE);
+    // The first batch gives the file's schema, so there's one even with no
+    // rows.
+    let first = batch(chunks.next().unwrap_or(&[]))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, first.schema(), Some(properties))?;
+    writer.write(&first)?;
+    for chunk in chunks {
+        writer.write(&batch(chunk)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+// Writes rules.parquet and itemsets.parquet to the directory `path`, creating
+// it if need be.
+pub fn write_parquet(
+    path: &str,
+    rules: &[Rule],
+    itemsets: &[ItemSet],
+    num_transactions: usize,
+    itemizer: &Itemizer,
+) -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(path);
+    fs::create_dir_all(dir)?;
+    // Either all rules have each measure or none do, so the first rule says
+    // which columns there are.
+    write_batches(&dir.join("rules.parquet"), rules, |chunk| {
+        rule_batch(chunk, rules.first(), itemizer)
+    })?;
+    write_batches(&dir.join("itemsets.parquet"), itemsets, |chunk| {
+        itemset_batch(chunk, num_transactions, itemizer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_write_parquet() {
+        use super::write_parquet;
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::Float64Type;
+        use arrow_array::RecordBatch;
+        use arrow_schema::DataType;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use miner::mine;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use std::env;
+        use std::fs::{self, File};
+        use std::path::Path;
+
+        let dir = env::temp_dir().join(format!("riptree-parquet-test-{}", std::process::id()));
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            output_parquet_path: dir.to_string_lossy().into_owned(),
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            metrics: vec!["kulc".parse().unwrap()],
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        assert!(!result.rules.is_empty() && !result.itemsets.is_empty());
+        write_parquet(
+            &args.output_parquet_path,
+            &result.rules,
+            &result.itemsets,
+            result.report.num_transactions,
+            &result.itemizer,
+        )
+        .unwrap();
+
+        let read = |path: &Path| -> RecordBatch {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                .unwrap()
+                .with_batch_size(1 << 20)
+                .build()
+                .unwrap();
+            let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+            assert_eq!(batches.len(), 1);
+            batches[0].clone()
+        };
+
+        let rules = read(&dir.join("rules.parquet"));
+        assert_eq!(rules.num_rows(), result.rules.len());
+        let rule = &result.rules[0];
+        let antecedent = rules.column_by_name("antecedent").unwrap().as_list::<i32>().value(0);
+        let antecedent: Vec<&str> = antecedent.as_string::<i32>().iter().map(|item| item.unwrap()).collect();
+        assert_eq!(antecedent, rule.antecedent_names(&result.itemizer));
+        let lift = rules.column_by_name("lift").unwrap().as_primitive::<Float64Type>();
+        assert_eq!(lift.value(0), rule.lift());
+        let kulc = rules.column_by_name("kulc").unwrap().as_primitive::<Float64Type>();
+        assert_eq!(kulc.value(0), rule.metric(args.metrics[0]).unwrap());
+        assert!(rules.column_by_name("p_value").is_some());
+
+        let itemsets = read(&dir.join("itemsets.parquet"));
+        assert_eq!(itemsets.num_rows(), result.itemsets.len());
+        match *itemsets.schema().field_with_name("items").unwrap().data_type() {
+            DataType::List(ref item) => assert_eq!(*item.data_type(), DataType::Utf8),
+            ref other => panic!("Expected a list of strings, found {}", other),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
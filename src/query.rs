@@ -0,0 +1,131 @@
+// `riptree query`: filters and sorts a rules file previously written with
+// `--output-format json`.
+
+use riptree::command_line_args::{QueryArguments, RuleSortKey};
+use riptree::rule_reader::{read_rules_json, SavedRule};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+fn matches(rule: &SavedRule, args: &QueryArguments) -> bool {
+    args.contains.iter().all(|item| rule.contains(item))
+        && args.antecedent_contains
+            .iter()
+            .all(|item| rule.antecedent.contains(item))
+        && args.consequent_contains
+            .iter()
+            .all(|item| rule.consequent.contains(item))
+        && rule.confidence >= args.min_confidence
+        && rule.lift >= args.min_lift
+        && rule.support >= args.min_support
+}
+
+// Returns the value by which rules are primarily sorted, in increasing order.
+fn sort_value(rule: &SavedRule, key: &RuleSortKey) -> f64 {
+    match *key {
+        RuleSortKey::Confidence => -rule.confidence,
+        RuleSortKey::Lift => -rule.lift,
+        RuleSortKey::Support => -rule.support,
+        RuleSortKey::PValue => rule.p_value.unwrap_or(f64::INFINITY),
+        RuleSortKey::Lexicographic => 0.0,
+    }
+}
+
+fn filter_rules(rules: Vec<SavedRule>, args: &QueryArguments) -> Vec<SavedRule> {
+    let mut rules: Vec<SavedRule> = rules
+        .into_iter()
+        .filter(|rule| matches(rule, args))
+        .collect();
+    if let Some(ref key) = args.sort_by {
+        // Break ties by the rule's text so the output is stable.
+        rules.sort_by(|a, b| {
+            sort_value(a, key)
+                .partial_cmp(&sort_value(b, key))
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.to_string().cmp(&b.to_string()))
+        });
+    }
+    if args.limit > 0 {
+        rules.truncate(args.limit);
+    }
+    rules
+}
+
+pub fn query(args: &QueryArguments) -> Result<(), Box<dyn Error>> {
+    let rules = read_rules_json(BufReader::new(File::open(&args.rules_path)?))?;
+    let rules = filter_rules(rules, args);
+
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    writeln!(
+        output,
+        "Antecedent => Consequent, Confidence, Lift, Support"
+    )?;
+    for rule in rules {
+        writeln!(
+            output,
+            "{}, {}, {}, {}",
+            rule,
+            rule.confidence,
+            rule.lift,
+            rule.support
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_filter_rules() {
+        use super::filter_rules;
+        use riptree::command_line_args::{LogOptions, QueryArguments, RuleSortKey};
+        use riptree::rule_reader::SavedRule;
+
+        let rule = |antecedent: &[&str], consequent: &str, lift: f64| SavedRule {
+            antecedent: antecedent.iter().map(|s| String::from(*s)).collect(),
+            consequent: vec![String::from(consequent)],
+            confidence: 1.0,
+            lift,
+            support: 0.1,
+            p_value: None,
+        };
+        let rules = vec![
+            rule(&["milk"], "bread", 2.0),
+            rule(&["eggs", "milk"], "caviar", 8.0),
+            rule(&["bread"], "milk", 1.5),
+            rule(&["eggs"], "caviar", 4.0),
+        ];
+        let args = QueryArguments {
+            rules_path: String::new(),
+            contains: vec![String::from("milk")],
+            antecedent_contains: vec![],
+            consequent_contains: vec![],
+            min_confidence: 0.0,
+            min_lift: 1.8,
+            min_support: 0.0,
+            sort_by: Some(RuleSortKey::Lift),
+            limit: 0,
+            log: LogOptions::default(),
+        };
+        let found: Vec<String> = filter_rules(rules.clone(), &args)
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        assert_eq!(found, vec!["eggs milk ==> caviar", "milk ==> bread"]);
+
+        let args = QueryArguments {
+            contains: vec![],
+            consequent_contains: vec![String::from("caviar")],
+            min_lift: 0.0,
+            limit: 1,
+            ..args
+        };
+        let found: Vec<String> = filter_rules(rules, &args)
+            .iter()
+            .map(|r| r.to_string())
+            .collect();
+        assert_eq!(found, vec!["eggs milk ==> caviar"]);
+    }
+}
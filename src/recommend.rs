@@ -0,0 +1,123 @@
+// `riptree recommend`: suggests items to add to a basket, using a rules file
+// previously written with `--output-format json`. Each rule whose antecedent
+// is in the basket suggests the items in its consequent which aren't.
+
+use riptree::command_line_args::RecommendArguments;
+use riptree::rule_reader::{read_rules_json, SavedRule};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+struct Suggestion {
+    item: String,
+    // The best confidence and lift of the rules suggesting the item.
+    confidence: f64,
+    lift: f64,
+    // The number of rules suggesting the item.
+    rules: usize,
+}
+
+// Returns the suggested items, in decreasing order of confidence, then lift.
+fn suggest(rules: &[SavedRule], basket: &[String]) -> Vec<Suggestion> {
+    let basket: HashSet<&str> = basket.iter().map(|s| s.as_str()).collect();
+    let mut suggestions: HashMap<&str, Suggestion> = HashMap::new();
+    for rule in rules {
+        if !rule.antecedent.iter().all(|item| basket.contains(item.as_str())) {
+            continue;
+        }
+        for item in rule.consequent.iter().filter(|item| !basket.contains(item.as_str())) {
+            let suggestion = suggestions.entry(item).or_insert_with(|| Suggestion {
+                item: item.clone(),
+                confidence: 0.0,
+                lift: 0.0,
+                rules: 0,
+            });
+            suggestion.confidence = suggestion.confidence.max(rule.confidence);
+            suggestion.lift = suggestion.lift.max(rule.lift);
+            suggestion.rules += 1;
+        }
+    }
+    let mut suggestions: Vec<Suggestion> = suggestions.into_values().collect();
+    suggestions.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.lift.partial_cmp(&a.lift).unwrap_or(Ordering::Equal))
+            .then_with(|| a.item.cmp(&b.item))
+    });
+    suggestions
+}
+
+pub fn recommend(args: &RecommendArguments) -> Result<(), Box<dyn Error>> {
+    let rules = read_rules_json(BufReader::new(File::open(&args.rules_path)?))?;
+    let mut suggestions = suggest(&rules, &args.basket);
+    if args.limit > 0 {
+        suggestions.truncate(args.limit);
+    }
+
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    writeln!(output, "Item, Confidence, Lift, Rules")?;
+    for suggestion in suggestions {
+        writeln!(
+            output,
+            "{}, {}, {}, {}",
+            suggestion.item,
+            suggestion.confidence,
+            suggestion.lift,
+            suggestion.rules
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_suggest() {
+        use super::suggest;
+        use riptree::rule_reader::SavedRule;
+
+        let rule = |antecedent: &[&str], consequent: &str, confidence: f64, lift: f64| {
+            SavedRule {
+                antecedent: antecedent.iter().map(|s| String::from(*s)).collect(),
+                consequent: vec![String::from(consequent)],
+                confidence,
+                lift,
+                support: 0.1,
+                p_value: None,
+            }
+        };
+        let rules = vec![
+            rule(&["milk"], "bread", 0.5, 2.0),
+            rule(&["eggs", "milk"], "caviar", 0.9, 8.0),
+            rule(&["bread"], "milk", 0.8, 1.5),
+            rule(&["milk", "tea"], "caviar", 0.7, 9.0),
+            rule(&["eggs"], "bacon", 0.9, 3.0),
+        ];
+        let basket = vec![String::from("milk"), String::from("eggs")];
+        let found: Vec<(String, f64, f64, usize)> = suggest(&rules, &basket)
+            .into_iter()
+            .map(|s| (s.item, s.confidence, s.lift, s.rules))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (String::from("caviar"), 0.9, 8.0, 1),
+                (String::from("bacon"), 0.9, 3.0, 1),
+                (String::from("bread"), 0.5, 2.0, 1),
+            ]
+        );
+
+        // Items already in the basket aren't suggested, and all rules
+        // suggesting an item count towards it.
+        let basket = vec![String::from("milk"), String::from("eggs"), String::from("tea")];
+        let found = suggest(&rules, &basket);
+        assert_eq!(found[0].item, "caviar");
+        assert_eq!(found[0].lift, 9.0);
+        assert_eq!(found[0].rules, 2);
+        assert!(suggest(&rules, &[String::from("bread")]).iter().all(|s| s.item == "milk"));
+    }
+}
@@ -0,0 +1,191 @@
+// A summary of a mining run, for tracking experiments: the size of the
+// dataset, how many itemsets were found and how many rules survived each
+// filter, and how long each phase took. It's written as JSON by --report,
+// along with the options used and the peak memory use.
+
+use clock::Phase;
+use command_line_args::Arguments;
+use config::config_table;
+use serde_json::{self, Value};
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Write};
+use work_estimate::WorkEstimate;
+
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Report {
+    pub num_transactions: usize,
+    pub num_distinct_transactions: usize,
+    pub num_items: usize,
+    // Items removed by --min-item-count and --max-item-count.
+    pub num_filtered_items: usize,
+    // Occurrences of items in --stop-items-file removed from transactions.
+    pub num_stop_items_removed: usize,
+    pub num_rare_items: usize,
+    // The count below which items are rare, where the max support mode finds
+    // one, i.e. elbow.
+    pub rare_item_cutoff: Option<u32>,
+    pub num_itemsets: usize,
+    // The weight of the transactions read from each input file.
+    pub input_files: Vec<(String, usize)>,
+    // Lines of input skipped by --on-bad-line.
+    pub num_bad_lines: usize,
+    // The number of rules left after each filter, in the order applied.
+    pub rule_counts: Vec<(Cow<'static, str>, usize)>,
+    // How long each phase took, in seconds, and with the mem-stats feature,
+    // the most bytes allocated at once during it.
+    pub phases: Vec<(Cow<'static, str>, f64, Option<usize>)>,
+    // Whether the run was cancelled, so the itemsets and rules are only
+    // those found before then.
+    pub partial: bool,
+    // With --dry-run, the estimate of the work mining would be.
+    pub estimate: Option<WorkEstimate>,
+    // Why the run found less than it might have, such as no rare items or
+    // no rules, so empty output comes with an explanation.
+    pub notes: Vec<String>,
+}
+
+impl Report {
+    // Records how long `phase` took, and its peak allocation, and ends it.
+    pub fn end_phase(&mut self, phase: Phase) {
+        self.phases
+            .push((Cow::Borrowed(phase.name()), phase.seconds(), phase.peak_allocated_bytes()));
+    }
+
+    pub fn count_rules(&mut self, stage: &'static str, count: usize) {
+        self.rule_counts.push((Cow::Borrowed(stage), count));
+    }
+
+    // Logs `message` as a warning, and keeps it as a note in the report.
+    pub fn warn(&mut self, message: String) {
+        warn!("{}", message);
+        self.notes.push(message);
+    }
+
+    pub fn write_json<W: Write>(&self, output: &mut W, args: &Arguments) -> io::Result<()> {
+        let report = json!({
+            "dataset": {
+                "transactions": self.num_transactions,
+                "distinct_transactions": self.num_distinct_transactions,
+                "items": self.num_items,
+                "filtered_items": self.num_filtered_items,
+                "stop_items_removed": self.num_stop_items_removed,
+                "rare_items": self.num_rare_items,
+                "rare_item_cutoff": self.rare_item_cutoff,
+                "files": self
+                    .input_files
+                    .iter()
+                    .map(|&(ref path, transactions)| json!({"path": path, "transactions": transactions}))
+                    .collect::<Vec<Value>>(),
+                "bad_lines": self.num_bad_lines,
+            },
+            "itemsets": self.num_itemsets,
+            "partial": self.partial,
+            "rules": self
+                .rule_counts
+                .iter()
+                .map(|&(ref stage, count)| json!({"stage": stage, "count": count}))
+                .collect::<Vec<Value>>(),
+            "phases": self
+                .phases
+                .iter()
+                .map(|&(ref name, seconds, peak_bytes)| {
+                    json!({"name": name, "seconds": seconds, "peak_allocated_bytes": peak_bytes})
+                })
+                .collect::<Vec<Value>>(),
+            "estimate": self.estimate.as_ref().map(|estimate| json!({
+                "tree_transactions": estimate.num_tree_transactions,
+                "tree_nodes": estimate.num_tree_nodes,
+                "conditional_trees": estimate.num_conditional_trees,
+                "work": estimate.work,
+                "seconds": estimate.seconds(),
+                "runtime": estimate.describe_runtime(),
+            })),
+            "notes": self.notes,
+            "peak_memory_bytes": peak_memory_bytes(),
+            "parameters": serde_json::to_value(config_table(args))?,
+        });
+        serde_json::to_writer_pretty(&mut *output, &report)?;
+        writeln!(output)
+    }
+}
+
+// Returns the peak resident memory of this process, where the OS reports it.
+fn peak_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line["VmHWM:".len()..].trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_report() {
+        use super::Report;
+        use clock::Phase;
+        use command_line_args::Arguments;
+        use serde_json::{self, Value};
+
+        let mut report = Report {
+            num_transactions: 10,
+            num_rare_items: 2,
+            ..Report::default()
+        };
+        report.end_phase(Phase::start("Mining"));
+        report.count_rules("confidence_and_lift", 5);
+        report.count_rules("family_wise", 3);
+        report.warn(String::from("Since 0 items are considered rare, giving up."));
+
+        let args = Arguments {
+            min_confidence: 0.5,
+            ..Arguments::default()
+        };
+        let mut buffer: Vec<u8> = vec![];
+        report.write_json(&mut buffer, &args).unwrap();
+        let json: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["dataset"]["transactions"], 10);
+        assert_eq!(json["dataset"]["rare_items"], 2);
+        assert_eq!(json["rules"][1]["stage"], "family_wise");
+        assert_eq!(json["rules"][1]["count"], 3);
+        assert_eq!(json["phases"][0]["name"], "Mining");
+        assert_eq!(
+            json["phases"][0]["peak_allocated_bytes"].is_u64(),
+            cfg!(feature = "mem-stats")
+        );
+        assert_eq!(json["notes"][0], "Since 0 items are considered rare, giving up.");
+        assert_eq!(json["parameters"]["min-confidence"], 0.5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use super::Report;
+        use clock::Phase;
+        use serde_json;
+        use work_estimate::WorkEstimate;
+
+        let mut report = Report {
+            num_transactions: 10,
+            rare_item_cutoff: Some(3),
+            input_files: vec![(String::from("a.csv"), 10)],
+            estimate: Some(WorkEstimate {
+                num_tree_transactions: 4,
+                work: 1e6,
+                ..WorkEstimate::default()
+            }),
+            ..Report::default()
+        };
+        report.end_phase(Phase::start("Mining"));
+        report.count_rules("family_wise", 3);
+        report.notes.push(String::from("No rules were found."));
+
+        let json = serde_json::to_string(&report).unwrap();
+        let round_trip: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+        assert_eq!(round_trip.rule_counts, vec![("family_wise".into(), 3)]);
+        assert_eq!(round_trip.phases[0].0, "Mining");
+        assert_eq!(round_trip.estimate.unwrap().num_tree_transactions, 4);
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::{self, Value};
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+// A rule read back from a rules file. Unlike generate_rules::Rule, items are
+// stored by name, as there's no Itemizer to map them back to ids.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedRule {
+    pub antecedent: Vec<String>,
+    pub consequent: Vec<String>,
+    pub confidence: f64,
+    pub lift: f64,
+    pub support: f64,
+    // Only rules which went through family wise filtering have a p-value.
+    pub p_value: Option<f64>,
+}
+
+impl SavedRule {
+    pub fn contains(&self, item: &str) -> bool {
+        self.antecedent.iter().any(|x| x == item) || self.consequent.iter().any(|x| x == item)
+    }
+}
+
+impl fmt::Display for SavedRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ==> {}",
+            self.antecedent.join(" "),
+            self.consequent.join(" ")
+        )
+    }
+}
+
+fn parse_items(rule: &Value, key: &str) -> Result<Vec<String>, String> {
+    match rule.get(key).and_then(Value::as_array) {
+        Some(items) => items
+            .iter()
+            .map(|item| match item.as_str() {
+                Some(item) => Ok(String::from(item)),
+                None => Err(format!("Rule {} contains a non-string item", key)),
+            })
+            .collect(),
+        None => Err(format!("Rule is missing its {}", key)),
+    }
+}
+
+fn parse_metric(rule: &Value, key: &str) -> Result<f64, String> {
+    rule.get(key)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| format!("Rule is missing its {}", key))
+}
+
+// Reads rules in the format written by rule_writer::write_rules_json(),
+// including the partial results of a cancelled run, and rules grouped by
+// consequent.
+pub fn read_rules_json<R: Read>(reader: R) -> Result<Vec<SavedRule>, Box<dyn Error>> {
+    let json: Value = serde_json::from_reader(reader)?;
+    let rules: Vec<&Value> = match json.get("groups").and_then(Value::as_array) {
+        Some(groups) => {
+            let mut rules = vec![];
+            for group in groups {
+                match group.get("rules").and_then(Value::as_array) {
+                    Some(group_rules) => rules.extend(group_rules),
+                    None => return Err("Rules file group should contain a JSON array of rules".into()),
+                }
+            }
+            rules
+        }
+        None => match json.get("rules").unwrap_or(&json).as_array() {
+            Some(rules) => rules.iter().collect(),
+            None => return Err("Rules file should contain a JSON array of rules".into()),
+        },
+    };
+    let mut saved_rules = Vec::with_capacity(rules.len());
+    for rule in rules {
+        saved_rules.push(SavedRule {
+            antecedent: parse_items(rule, "antecedent")?,
+            consequent: parse_items(rule, "consequent")?,
+            confidence: parse_metric(rule, "confidence")?,
+            lift: parse_metric(rule, "lift")?,
+            support: parse_metric(rule, "support")?,
+            p_value: rule.get("p_value").and_then(Value::as_f64),
+        });
+    }
+    Ok(saved_rules)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_read_rules_json() {
+        use super::{read_rules_json, SavedRule};
+
+        let json = br#"[{"antecedent": ["a", "b"], "consequent": ["c"],
+                         "confidence": 0.5, "lift": 2, "support": 0.2}]"#;
+        let rules = read_rules_json(&json[..]).unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                SavedRule {
+                    antecedent: vec![String::from("a"), String::from("b")],
+                    consequent: vec![String::from("c")],
+                    confidence: 0.5,
+                    lift: 2.0,
+                    support: 0.2,
+                    p_value: None,
+                },
+            ]
+        );
+        assert!(rules[0].contains("b"));
+        assert!(!rules[0].contains("d"));
+        assert_eq!(rules[0].to_string(), "a b ==> c");
+
+        // Partial results are wrapped in an object.
+        let partial = read_rules_json(&br#"{"partial": true, "rules": []}"#[..]).unwrap();
+        assert!(partial.is_empty());
+
+        // Rules grouped by consequent are read in order, ignoring the groups.
+        let grouped = br#"{"groups": [
+            {"consequent": ["c"], "num_rules": 1, "best_lift": 2, "coverage": 0.4,
+             "rules": [{"antecedent": ["a", "b"], "consequent": ["c"],
+                        "confidence": 0.5, "lift": 2, "support": 0.2}]},
+            {"consequent": ["d"], "num_rules": 1, "best_lift": 3, "coverage": 0.1,
+             "rules": [{"antecedent": ["a"], "consequent": ["d"],
+                        "confidence": 1, "lift": 3, "support": 0.1}]}]}"#;
+        let grouped = read_rules_json(&grouped[..]).unwrap();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0], rules[0]);
+        assert_eq!(grouped[1].to_string(), "a ==> d");
+
+        assert!(read_rules_json(&b"{}"[..]).is_err());
+        assert!(read_rules_json(&br#"[{"antecedent": ["a"]}]"#[..]).is_err());
+    }
+}
@@ -0,0 +1,616 @@
+use command_line_args::RuleColumn;
+use generate_rules::{ConsequentGroup, Rule};
+use itemizer::Itemizer;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use serde_json::{self, Value};
+use sequences::SequentialRule;
+use utility::HighUtilityItemset;
+use std::io::{self, Write};
+
+pub fn write_itemsets_csv<W: Write>(
+    output: &mut W,
+    itemsets: &[HighUtilityItemset],
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    writeln!(output, "Itemset, Utility, Support")?;
+    for itemset in itemsets {
+        writeln!(
+            output,
+            "{}, {}, {}",
+            itemset.to_string(itemizer),
+            itemset.utility,
+            itemset.support
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_sequential_rules_csv<W: Write>(
+    output: &mut W,
+    rules: &[SequentialRule],
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    writeln!(output, "Antecedent => Consequent, Confidence, Lift, Support")?;
+    for rule in rules {
+        writeln!(
+            output,
+            "{}, {}, {}, {}",
+            rule.to_string(itemizer),
+            rule.confidence,
+            rule.lift,
+            rule.support
+        )?;
+    }
+    Ok(())
+}
+
+// If `groups` isn't empty, the rules are in the order of the groups, each of
+// which is summarized in a comment ahead of its rules. If `partial`, the run
+// was cancelled, which is noted in a comment at the end.
+pub fn write_rules_csv<W: Write>(
+    output: &mut W,
+    rules: &[Rule],
+    groups: &[ConsequentGroup],
+    itemizer: &Itemizer,
+    partial: bool,
+) -> io::Result<()> {
+    // Either all rules have p-values or none do, and likewise for odds ratios,
+    // imbalances, metrics, bootstrap intervals, holdout measures and examples, so
+    // there's a column for each only if the rules have them.
+    let has_p_value = rules.iter().any(|rule| rule.p_value().is_some());
+    let has_odds_ratio = rules.iter().any(|rule| rule.odds_ratio().is_some());
+    let has_imbalance = rules.iter().any(|rule| rule.imbalance().is_some());
+    let metrics = rules.first().map(Rule::metrics).unwrap_or_default();
+    let has_bootstrap = rules.iter().any(|rule| rule.bootstrap().is_some());
+    let has_holdout = rules.iter().any(|rule| rule.holdout().is_some());
+    let has_examples = rules.iter().any(|rule| rule.examples().is_some());
+    write!(
+        output,
+        "Antecedent => Consequent, Confidence, Lift, Support"
+    )?;
+    if has_p_value {
+        write!(output, ", P-Value, P-Value Threshold")?;
+    }
+    if has_odds_ratio {
+        write!(output, ", Odds Ratio, Odds Ratio 95% CI Lower, Odds Ratio 95% CI Upper")?;
+    }
+    if has_imbalance {
+        write!(output, ", Imbalance Ratio, Cross Support Ratio")?;
+    }
+    for &(metric, _) in &metrics {
+        write!(output, ", {}", metric.column())?;
+    }
+    if has_bootstrap {
+        write!(
+            output,
+            ", Confidence 95% CI Lower, Confidence 95% CI Upper, Lift 95% CI Lower, Lift 95% CI Upper"
+        )?;
+    }
+    if has_holdout {
+        write!(
+            output,
+            ", Holdout Confidence, Holdout Lift, Holdout Support"
+        )?;
+    }
+    if has_examples {
+        write!(output, ", Examples")?;
+    }
+    writeln!(output)?;
+    if groups.is_empty() {
+        for rule in rules {
+            write_rule_csv(output, rule, itemizer)?;
+        }
+    }
+    let mut rest = rules;
+    for group in groups {
+        writeln!(
+            output,
+            "# {}: rules {}, best lift {}, coverage {}",
+            consequent_names(group, itemizer).join(" "),
+            group.num_rules,
+            group.best_lift,
+            group.coverage
+        )?;
+        let (group_rules, others) = rest.split_at(group.num_rules);
+        for rule in group_rules {
+            write_rule_csv(output, rule, itemizer)?;
+        }
+        rest = others;
+    }
+    if partial {
+        writeln!(output, "# Partial results: the run was cancelled before it finished.")?;
+    }
+    Ok(())
+}
+
+// Writes a rule's line of write_rules_csv's output.
+fn write_rule_csv<W: Write>(output: &mut W, rule: &Rule, itemizer: &Itemizer) -> io::Result<()> {
+    write!(
+        output,
+        "{}, {}, {}, {}",
+        rule.to_string(itemizer),
+        rule.confidence(),
+        rule.lift(),
+        rule.support(),
+    )?;
+    if let (Some(p_value), Some(threshold)) = (rule.p_value(), rule.p_value_threshold()) {
+        write!(output, ", {}, {}", p_value, threshold)?;
+    }
+    if let Some(odds_ratio) = rule.odds_ratio() {
+        write!(
+            output,
+            ", {}, {}, {}",
+            odds_ratio.ratio(),
+            odds_ratio.lower(),
+            odds_ratio.upper()
+        )?;
+    }
+    if let Some(imbalance) = rule.imbalance() {
+        write!(output, ", {}, {}", imbalance.ratio(), imbalance.cross_support_ratio())?;
+    }
+    for (_, value) in rule.metrics() {
+        write!(output, ", {}", value)?;
+    }
+    if let Some(bootstrap) = rule.bootstrap() {
+        let (confidence, lift) = (bootstrap.confidence(), bootstrap.lift());
+        write!(output, ", {}, {}, {}, {}", confidence.0, confidence.1, lift.0, lift.1)?;
+    }
+    if let Some(holdout) = rule.holdout() {
+        write!(
+            output,
+            ", {}, {}, {}",
+            holdout.confidence(),
+            holdout.lift(),
+            holdout.support()
+        )?;
+    }
+    // Each example is its transaction's id and items, and they're separated
+    // by semicolons, so the column has no commas.
+    if let Some(examples) = rule.examples() {
+        let examples: Vec<String> = examples
+            .iter()
+            .map(|example| format!("{}: {}", example.transaction, example.item_names(itemizer).join(" ")))
+            .collect();
+        write!(output, ", {}", examples.join("; "))?;
+    }
+    writeln!(output)?;
+    Ok(())
+}
+
+// How write_rules_delimited lays out rules.
+pub struct DelimitedFormat<'a> {
+    // The columns to write, or if empty, every column the rules have values
+    // for.
+    pub columns: &'a [RuleColumn],
+    pub delimiter: &'a str,
+    // What separates the items of antecedents and consequents.
+    pub item_separator: &'a str,
+    // Whether to summarize groups and note cancelled runs in "#" comment
+    // lines, which strict CSV parsers reject.
+    pub comments: bool,
+}
+
+// The columns rules have values for, in the order of write_rules_csv's.
+// Either all rules have each measure or none do, as there.
+fn available_columns(rules: &[Rule]) -> Vec<RuleColumn> {
+    let mut columns = vec![
+        RuleColumn::Antecedent,
+        RuleColumn::Consequent,
+        RuleColumn::Confidence,
+        RuleColumn::Lift,
+        RuleColumn::Support,
+    ];
+    if rules.iter().any(|rule| rule.p_value().is_some()) {
+        columns.extend(&[RuleColumn::PValue, RuleColumn::PValueThreshold]);
+    }
+    if rules.iter().any(|rule| rule.odds_ratio().is_some()) {
+        columns.extend(&[RuleColumn::OddsRatio, RuleColumn::OddsRatioLower, RuleColumn::OddsRatioUpper]);
+    }
+    if rules.iter().any(|rule| rule.imbalance().is_some()) {
+        columns.extend(&[RuleColumn::ImbalanceRatio, RuleColumn::CrossSupportRatio]);
+    }
+    let metrics = rules.first().map(Rule::metrics).unwrap_or_default();
+    columns.extend(metrics.iter().map(|&(metric, _)| RuleColumn::Metric(metric)));
+    if rules.iter().any(|rule| rule.bootstrap().is_some()) {
+        columns.extend(&[
+            RuleColumn::ConfidenceLower,
+            RuleColumn::ConfidenceUpper,
+            RuleColumn::LiftLower,
+            RuleColumn::LiftUpper,
+        ]);
+    }
+    if rules.iter().any(|rule| rule.holdout().is_some()) {
+        columns.extend(&[
+            RuleColumn::HoldoutConfidence,
+            RuleColumn::HoldoutLift,
+            RuleColumn::HoldoutSupport,
+        ]);
+    }
+    if rules.iter().any(|rule| rule.examples().is_some()) {
+        columns.push(RuleColumn::Examples);
+    }
+    columns
+}
+
+// Quotes `field` if it contains `delimiter`, a quote or a line break, so
+// item names can't split a column, doubling any quotes inside it.
+fn delimited_field<'a>(field: &'a str, delimiter: &str) -> Cow<'a, str> {
+    if field.contains(delimiter) || field.contains(['"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// A rule's value in `column`, empty if it doesn't have one.
+fn column_value(rule: &Rule, column: RuleColumn, itemizer: &Itemizer, item_separator: &str) -> String {
+    let value = match column {
+        RuleColumn::Antecedent => return rule.antecedent_names(itemizer).join(item_separator),
+        RuleColumn::Consequent => return rule.consequent_names(itemizer).join(item_separator),
+        RuleColumn::Examples => {
+            let examples: Vec<String> = rule
+                .examples()
+                .unwrap_or_default()
+                .iter()
+                .map(|example| format!("{}: {}", example.transaction, example.item_names(itemizer).join(item_separator)))
+                .collect();
+            return examples.join("; ");
+        }
+        RuleColumn::Confidence => Some(rule.confidence()),
+        RuleColumn::Lift => Some(rule.lift()),
+        RuleColumn::Support => Some(rule.support()),
+        RuleColumn::PValue => rule.p_value(),
+        RuleColumn::PValueThreshold => rule.p_value_threshold(),
+        RuleColumn::OddsRatio => rule.odds_ratio().map(|odds_ratio| odds_ratio.ratio()),
+        RuleColumn::OddsRatioLower => rule.odds_ratio().map(|odds_ratio| odds_ratio.lower()),
+        RuleColumn::OddsRatioUpper => rule.odds_ratio().map(|odds_ratio| odds_ratio.upper()),
+        RuleColumn::ImbalanceRatio => rule.imbalance().map(|imbalance| imbalance.ratio()),
+        RuleColumn::CrossSupportRatio => rule.imbalance().map(|imbalance| imbalance.cross_support_ratio()),
+        RuleColumn::HoldoutConfidence => rule.holdout().map(|holdout| holdout.confidence()),
+        RuleColumn::HoldoutLift => rule.holdout().map(|holdout| holdout.lift()),
+        RuleColumn::HoldoutSupport => rule.holdout().map(|holdout| holdout.support()),
+        RuleColumn::ConfidenceLower => rule.bootstrap().map(|bootstrap| bootstrap.confidence().0),
+        RuleColumn::ConfidenceUpper => rule.bootstrap().map(|bootstrap| bootstrap.confidence().1),
+        RuleColumn::LiftLower => rule.bootstrap().map(|bootstrap| bootstrap.lift().0),
+        RuleColumn::LiftUpper => rule.bootstrap().map(|bootstrap| bootstrap.lift().1),
+        RuleColumn::Metric(metric) => rule
+            .metrics()
+            .iter()
+            .find(|&&(m, _)| m == metric)
+            .map(|&(_, value)| value),
+    };
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+// Writes the rules in `format`'s columns, separated by its delimiter, after a
+// header of the columns' names. With comments, like write_rules_csv, groups
+// are summarized and cancelled runs noted in "#" lines; otherwise the rules
+// are still in the order of the groups.
+pub fn write_rules_delimited<W: Write>(
+    output: &mut W,
+    rules: &[Rule],
+    groups: &[ConsequentGroup],
+    itemizer: &Itemizer,
+    partial: bool,
+    format: &DelimitedFormat,
+) -> io::Result<()> {
+    let available;
+    let columns = if format.columns.is_empty() {
+        available = available_columns(rules);
+        &available
+    } else {
+        format.columns
+    };
+    let delimiter = format.delimiter;
+    let header: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+    writeln!(output, "{}", header.join(delimiter))?;
+    let write_rule = |output: &mut W, rule: &Rule| -> io::Result<()> {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|&column| {
+                let value = column_value(rule, column, itemizer, format.item_separator);
+                delimited_field(&value, delimiter).into_owned()
+            })
+            .collect();
+        writeln!(output, "{}", fields.join(delimiter))
+    };
+    if groups.is_empty() || !format.comments {
+        for rule in rules {
+            write_rule(output, rule)?;
+        }
+        if partial && format.comments {
+            writeln!(output, "# Partial results: the run was cancelled before it finished.")?;
+        }
+        return Ok(());
+    }
+    let mut rest = rules;
+    for group in groups {
+        writeln!(
+            output,
+            "# {}: rules {}, best lift {}, coverage {}",
+            consequent_names(group, itemizer).join(" "),
+            group.num_rules,
+            group.best_lift,
+            group.coverage
+        )?;
+        let (group_rules, others) = rest.split_at(group.num_rules);
+        for rule in group_rules {
+            write_rule(output, rule)?;
+        }
+        rest = others;
+    }
+    if partial {
+        writeln!(output, "# Partial results: the run was cancelled before it finished.")?;
+    }
+    Ok(())
+}
+
+// Writes the rules as a JSON array of objects, with the antecedent and
+// consequent as arrays of item names. Rules which went through family wise
+// filtering also have their p-value and the threshold it was compared
+// against, rules may have an "odds_ratio" object with the ratio and its 95%
+// confidence interval, an "imbalance" object with the imbalance and cross
+// support ratios, a "metrics" object keyed by metric name, and a "bootstrap"
+// object with the 95% confidence intervals of their confidence and lift,
+// rules evaluated on a holdout set have a "holdout" object with the measures
+// on that set, and with --emit-examples, rules have "examples", each with its
+// "transaction" id and "items". If `partial`, the run was cancelled, and the
+// array is instead the "rules" of an object with "partial" set. If `groups`
+// isn't empty, the object instead has "groups", each with its consequent,
+// summary, and "rules".
+pub fn write_rules_json<W: Write>(
+    output: &mut W,
+    rules: &[Rule],
+    groups: &[ConsequentGroup],
+    itemizer: &Itemizer,
+    partial: bool,
+) -> io::Result<()> {
+    let rules: Vec<Value> = rules
+        .iter()
+        .map(|rule| {
+            let mut value = json!({
+                "antecedent": rule.antecedent_names(itemizer),
+                "consequent": rule.consequent_names(itemizer),
+                "confidence": rule.confidence(),
+                "lift": rule.lift(),
+                "support": rule.support(),
+            });
+            if let (Some(p_value), Some(threshold)) = (rule.p_value(), rule.p_value_threshold()) {
+                value["p_value"] = json!(p_value);
+                value["p_value_threshold"] = json!(threshold);
+            }
+            if let Some(odds_ratio) = rule.odds_ratio() {
+                value["odds_ratio"] = json!({
+                    "ratio": odds_ratio.ratio(),
+                    "lower": odds_ratio.lower(),
+                    "upper": odds_ratio.upper(),
+                });
+            }
+            if let Some(imbalance) = rule.imbalance() {
+                value["imbalance"] = json!({
+                    "ratio": imbalance.ratio(),
+                    "cross_support_ratio": imbalance.cross_support_ratio(),
+                });
+            }
+            let metrics = rule.metrics();
+            if !metrics.is_empty() {
+                value["metrics"] = metrics
+                    .iter()
+                    .map(|&(metric, value)| (metric.to_string(), json!(value)))
+                    .collect::<serde_json::Map<String, Value>>()
+                    .into();
+            }
+            if let Some(bootstrap) = rule.bootstrap() {
+                let (confidence, lift) = (bootstrap.confidence(), bootstrap.lift());
+                value["bootstrap"] = json!({
+                    "confidence": {"lower": confidence.0, "upper": confidence.1},
+                    "lift": {"lower": lift.0, "upper": lift.1},
+                });
+            }
+            if let Some(holdout) = rule.holdout() {
+                value["holdout"] = json!({
+                    "confidence": holdout.confidence(),
+                    "lift": holdout.lift(),
+                    "support": holdout.support(),
+                });
+            }
+            if let Some(examples) = rule.examples() {
+                value["examples"] = examples
+                    .iter()
+                    .map(|example| {
+                        json!({
+                            "transaction": example.transaction,
+                            "items": example.item_names(itemizer),
+                        })
+                    })
+                    .collect();
+            }
+            value
+        })
+        .collect();
+    if !groups.is_empty() {
+        let mut rules = rules.into_iter();
+        let groups: Vec<Value> = groups
+            .iter()
+            .map(|group| {
+                json!({
+                    "consequent": consequent_names(group, itemizer),
+                    "num_rules": group.num_rules,
+                    "best_lift": group.best_lift,
+                    "coverage": group.coverage,
+                    "rules": rules.by_ref().take(group.num_rules).collect::<Vec<Value>>(),
+                })
+            })
+            .collect();
+        let mut value = json!({ "groups": groups });
+        if partial {
+            value["partial"] = json!(true);
+        }
+        serde_json::to_writer(&mut *output, &value)?;
+    } else if partial {
+        serde_json::to_writer(&mut *output, &json!({"partial": true, "rules": rules}))?;
+    } else {
+        serde_json::to_writer(&mut *output, &rules)?;
+    }
+    writeln!(output)
+}
+
+fn consequent_names<'a>(group: &ConsequentGroup, itemizer: &'a Itemizer) -> Vec<&'a str> {
+    group.consequent.iter().map(|&item| itemizer.str_of(item)).collect()
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Writes a Graphviz DOT digraph with a node per item, and an edge from each
+// antecedent item to the consequent item of each rule. Where several rules
+// link the same pair of items, the edge describes the rule with the highest
+// lift, and its `rules` attribute counts how many rules link the pair. Its
+// `weight` is the lift rounded to an integer, as dot only takes integer
+// weights; the `lift` attribute has the exact value.
+pub fn write_rules_dot<W: Write>(
+    output: &mut W,
+    rules: &[Rule],
+    itemizer: &Itemizer,
+) -> io::Result<()> {
+    // Maps (from item, to item) to the (lift, confidence, rule count) of the
+    // strongest rule linking them. Use a BTreeMap so the output is stable.
+    let mut edges: BTreeMap<(&str, &str), (f64, f64, u32)> = BTreeMap::new();
+    for rule in rules {
+        for &from in rule.antecedent() {
+            for &to in rule.consequent() {
+                let edge = edges
+                    .entry((itemizer.str_of(from), itemizer.str_of(to)))
+                    .or_insert((rule.lift(), rule.confidence(), 0));
+                if rule.lift() > edge.0 {
+                    edge.0 = rule.lift();
+                    edge.1 = rule.confidence();
+                }
+                edge.2 += 1;
+            }
+        }
+    }
+
+    writeln!(output, "digraph rules {{")?;
+    for ((from, to), &(lift, confidence, count)) in edges.iter() {
+        writeln!(
+            output,
+            "  {} -> {} [weight={}, lift={}, confidence={}, rules={}, label=\"lift {:.2}, conf {:.2}\"];",
+            dot_quote(from),
+            dot_quote(to),
+            lift.round() as u64,
+            lift,
+            confidence,
+            count,
+            lift,
+            confidence,
+        )?;
+    }
+    writeln!(output, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_write_rules_dot() {
+        use super::write_rules_dot;
+        use generate_rules::Rule;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let ids = itemizer.to_id_vec(&["a", "b", "\"c\""]);
+        let rules = vec![
+            Rule::new(vec![ids[0], ids[1]], vec![ids[2]], 0.5, 2.0, 0.1),
+            Rule::new(vec![ids[0]], vec![ids[2]], 0.25, 3.0, 0.1),
+        ];
+        let mut dot = vec![];
+        write_rules_dot(&mut dot, &rules, &itemizer).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert_eq!(
+            dot.lines().collect::<Vec<&str>>(),
+            vec![
+                r#"digraph rules {"#,
+                r#"  "a" -> "\"c\"" [weight=3, lift=3, confidence=0.25, rules=2, label="lift 3.00, conf 0.25"];"#,
+                r#"  "b" -> "\"c\"" [weight=2, lift=2, confidence=0.5, rules=1, label="lift 2.00, conf 0.50"];"#,
+                r#"}"#,
+            ]
+        );
+
+        // Edge weights are integers even when lifts aren't.
+        let rules = vec![Rule::new(vec![ids[0]], vec![ids[1]], 0.5, 1.25, 0.1)];
+        let mut dot = vec![];
+        write_rules_dot(&mut dot, &rules, &itemizer).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert_eq!(
+            dot.lines().nth(1).unwrap(),
+            r#"  "a" -> "b" [weight=1, lift=1.25, confidence=0.5, rules=1, label="lift 1.25, conf 0.50"];"#
+        );
+    }
+
+    #[test]
+    fn test_write_rules_delimited() {
+        use super::{write_rules_delimited, DelimitedFormat};
+        use command_line_args::RuleColumn;
+        use generate_rules::Rule;
+        use itemizer::Itemizer;
+
+        let mut itemizer = Itemizer::new();
+        let ids = itemizer.to_id_vec(&["a;b", "c", "say \"hi\""]);
+        let rules = vec![Rule::new(vec![ids[0], ids[1]], vec![ids[2]], 0.5, 2.0, 0.1)];
+        let write = |columns: &[RuleColumn], delimiter: &str| -> Vec<String> {
+            let format = DelimitedFormat {
+                columns,
+                delimiter,
+                item_separator: " ",
+                comments: true,
+            };
+            let mut output = vec![];
+            write_rules_delimited(&mut output, &rules, &[], &itemizer, false, &format).unwrap();
+            String::from_utf8(output).unwrap().lines().map(String::from).collect()
+        };
+
+        let columns = [
+            RuleColumn::Consequent,
+            RuleColumn::Antecedent,
+            RuleColumn::Lift,
+            RuleColumn::PValue,
+        ];
+        assert_eq!(
+            write(&columns, ";"),
+            vec!["consequent;antecedent;lift;pvalue", r#""say ""hi""";"a;b c";2;"#]
+        );
+        assert_eq!(
+            write(&[], "\t"),
+            vec![
+                "antecedent\tconsequent\tconfidence\tlift\tsupport",
+                "a;b c\t\"say \"\"hi\"\"\"\t0.5\t2\t0.1",
+            ]
+        );
+
+        // CSV output has the antecedent and consequent in separate fields,
+        // and quotes those containing commas.
+        let ids = itemizer.to_id_vec(&["milk, full cream", "bread", "say \"hi\""]);
+        let rules = vec![Rule::new(vec![ids[0], ids[1]], vec![ids[2]], 0.5, 2.0, 0.1)];
+        let format = DelimitedFormat {
+            columns: &[],
+            delimiter: ",",
+            item_separator: "|",
+            comments: false,
+        };
+        let mut output = vec![];
+        write_rules_delimited(&mut output, &rules, &[], &itemizer, true, &format).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "antecedent,consequent,confidence,lift,support\n",
+                r#""bread|milk, full cream","say ""hi""",0.5,2,0.1"#,
+                "\n"
+            )
+        );
+
+        assert_eq!("kulc".parse::<RuleColumn>().unwrap().to_string(), "kulc");
+        assert!("rule".parse::<RuleColumn>().is_err());
+    }
+}
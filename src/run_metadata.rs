@@ -0,0 +1,198 @@
+// Metadata written along with rules and itemsets, so results are
+// self-describing and can be reproduced months later: the riptree version,
+// the options used, a hash of the input files, and when the run started and
+// finished. CSV output starts with it as comment lines, and JSON output has
+// it in a sidecar file, the output path with ".meta.json" appended.
+
+use drift::civil_from_days;
+use serde_json::{self, Value};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RunMetadata {
+    pub version: Cow<'static, str>,
+    // The options, keyed as in a config file, so they can be passed back to
+    // --config.
+    pub parameters: toml::value::Table,
+    // The FNV-1a hash of the input files' bytes, read in order, if the input
+    // was files.
+    pub dataset_hash: Option<String>,
+    // Seconds since the Unix epoch.
+    pub started: u64,
+    pub finished: u64,
+}
+
+// Returns the 64 bit FNV-1a hash of the bytes of `paths`, read one after the
+// other, in hex. Unlike the standard library's hashers, it's stable across
+// Rust versions, so hashes from different builds can be compared.
+pub fn hash_files(paths: &[String]) -> io::Result<String> {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut buffer = vec![0u8; 1 << 16];
+    for path in paths {
+        let mut input = BufReader::new(File::open(path)?);
+        loop {
+            let len = input.read(&mut buffer)?;
+            if len == 0 {
+                break;
+            }
+            for &byte in &buffer[..len] {
+                hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        }
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+// Formats seconds since the Unix epoch as an ISO 8601 UTC time, e.g.
+// 2024-03-01T09:30:00Z.
+fn format_time(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let time_of_day = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
+
+impl RunMetadata {
+    // Describes a run which started at `started` and has just finished,
+    // hashing `input_paths`, which are empty if the input wasn't files.
+    pub fn new(
+        parameters: toml::value::Table,
+        input_paths: &[String],
+        started: SystemTime,
+    ) -> io::Result<RunMetadata> {
+        let dataset_hash = if input_paths.is_empty() {
+            None
+        } else {
+            Some(hash_files(input_paths)?)
+        };
+        Ok(RunMetadata {
+            version: Cow::Borrowed(env!("CARGO_PKG_VERSION")),
+            parameters,
+            dataset_hash,
+            started: unix_seconds(started),
+            finished: unix_seconds(SystemTime::now()),
+        })
+    }
+
+    // Writes the metadata as lines starting with "# ", ending with the
+    // options as a TOML config file.
+    pub fn write_comments<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "# riptree {}", self.version)?;
+        writeln!(
+            output,
+            "# Started {}, finished {}",
+            format_time(self.started),
+            format_time(self.finished)
+        )?;
+        if let Some(ref hash) = self.dataset_hash {
+            writeln!(output, "# Dataset FNV-1a hash: {}", hash)?;
+        }
+        writeln!(output, "# Options:")?;
+        let parameters = toml::to_string(&toml::Value::Table(self.parameters.clone())).unwrap_or_default();
+        for line in parameters.lines() {
+            writeln!(output, "# {}", line)?;
+        }
+        Ok(())
+    }
+
+    // Writes the metadata as a JSON object, for the sidecar of JSON output.
+    pub fn write_json<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *output, &self.to_json())?;
+        writeln!(output)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": self.version,
+            "started": format_time(self.started),
+            "finished": format_time(self.finished),
+            "dataset_hash": self.dataset_hash,
+            "parameters": serde_json::to_value(&self.parameters).unwrap_or(Value::Null),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_run_metadata() {
+        use super::{format_time, hash_files, RunMetadata};
+        use std::env;
+        use std::fs;
+        use std::time::{Duration, UNIX_EPOCH};
+        use toml;
+
+        assert_eq!(format_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_time(1_709_285_400), "2024-03-01T09:30:00Z");
+
+        // The hash is of the files' bytes, as if concatenated.
+        let dir = env::temp_dir().join(format!("riptree-metadata-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, ab) = (dir.join("a.csv"), dir.join("b.csv"), dir.join("ab.csv"));
+        fs::write(&a, "milk,bread\n").unwrap();
+        fs::write(&b, "milk\n").unwrap();
+        fs::write(&ab, "milk,bread\nmilk\n").unwrap();
+        let path = |path: &::std::path::Path| path.to_string_lossy().into_owned();
+        let split_hash = hash_files(&[path(&a), path(&b)]).unwrap();
+        assert_eq!(split_hash, hash_files(&[path(&ab)]).unwrap());
+        assert_ne!(split_hash, hash_files(&[path(&a)]).unwrap());
+        // The FNV-1a hash of nothing is its offset basis.
+        assert_eq!(hash_files(&[]).unwrap(), "cbf29ce484222325");
+
+        let mut parameters = toml::value::Table::new();
+        parameters.insert(String::from("min-confidence"), toml::Value::Float(0.9));
+        let started = UNIX_EPOCH + Duration::from_secs(1_709_285_400);
+        let metadata = RunMetadata::new(parameters, &[path(&ab)], started).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut output = vec![];
+        metadata.write_comments(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], format!("# riptree {}", env!("CARGO_PKG_VERSION")));
+        assert!(lines[1].starts_with("# Started 2024-03-01T09:30:00Z, finished "));
+        assert_eq!(lines[2], format!("# Dataset FNV-1a hash: {}", split_hash));
+        assert_eq!(&lines[3..], &["# Options:", "# min-confidence = 0.9"]);
+
+        let json = metadata.to_json();
+        assert_eq!(json["dataset_hash"], split_hash.as_str());
+        assert_eq!(json["parameters"]["min-confidence"], 0.9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use super::RunMetadata;
+        use serde_json;
+        use std::time::SystemTime;
+        use toml;
+
+        let mut parameters = toml::value::Table::new();
+        parameters.insert(String::from("max-support"), toml::Value::String(String::from("pareto")));
+        let metadata = RunMetadata::new(parameters, &[], SystemTime::now()).unwrap();
+        let json = serde_json::to_string(&metadata).unwrap();
+        let round_trip: RunMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip.version, metadata.version);
+        assert_eq!(round_trip.parameters, metadata.parameters);
+        assert_eq!(round_trip.dataset_hash, None);
+        assert_eq!((round_trip.started, round_trip.finished), (metadata.started, metadata.finished));
+    }
+}
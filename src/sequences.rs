@@ -0,0 +1,284 @@
+// Sequential rules say that items happening in an order are followed by a
+// rare item, e.g. "a then b ==> c". The input has one event per line, of the
+// form "sequence id,time,item,item,...". Lines with the same sequence id make
+// up a sequence, ordered by time, and lines with the same id and time are the
+// same event.
+//
+// Rules are mined per rare consequent with PrefixSpan (Pei et al., 2001): the
+// antecedents of rules with consequent c are the patterns occurring before the
+// last c in the sequences containing c. Patterns are grown an item at a time,
+// and each pattern's projected database is, for each sequence containing it,
+// the position just after its earliest occurrence, from which the next item
+// must be found.
+
+use itemizer::Itemizer;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead};
+
+// A sequence's events, in order of time, each holding the items in it.
+pub type Sequence = Vec<Vec<u32>>;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn read_sequences<R: BufRead>(reader: R, itemizer: &mut Itemizer) -> io::Result<Vec<Sequence>> {
+    // The events of each sequence, keyed by time, in order of first line.
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut events: Vec<Vec<(f64, Vec<u32>)>> = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        let mut fields = line.split(',').map(|s| s.trim());
+        let id = match fields.next() {
+            Some(id) if !id.is_empty() => id,
+            _ => continue,
+        };
+        let time = fields.next().unwrap_or("");
+        // Times must be finite, so events can be ordered by them; "nan" and
+        // "inf" parse as f64s.
+        let time: f64 = match time.parse::<f64>() {
+            Ok(parsed) if parsed.is_finite() => parsed,
+            _ => return Err(invalid_data(format!("Invalid time '{}'", time))),
+        };
+        let items: Vec<u32> = fields
+            .filter(|s| !s.is_empty())
+            .map(|item| itemizer.try_id_of(item))
+            .collect::<Result<_, _>>()?;
+
+        let next_index = events.len();
+        let index = *ids.entry(String::from(id)).or_insert(next_index);
+        if index == events.len() {
+            events.push(vec![]);
+        }
+        events[index].push((time, items));
+    }
+
+    Ok(events
+        .into_iter()
+        .map(|mut events| {
+            // Stable, so events keep their line order until merged.
+            events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let mut sequence: Sequence = vec![];
+            let mut last_time: Option<f64> = None;
+            for (time, items) in events {
+                if last_time != Some(time) {
+                    sequence.push(vec![]);
+                    last_time = Some(time);
+                }
+                let event = sequence.last_mut().unwrap();
+                for item in items {
+                    if !event.contains(&item) {
+                        event.push(item);
+                    }
+                }
+            }
+            sequence
+        })
+        .collect())
+}
+
+pub struct SequentialRule {
+    pub antecedent: Vec<u32>,
+    pub consequent: u32,
+    pub confidence: f64,
+    pub lift: f64,
+    pub support: f64,
+}
+
+impl SequentialRule {
+    pub fn to_string(&self, itemizer: &Itemizer) -> String {
+        let antecedent: Vec<&str> = self.antecedent.iter().map(|&id| itemizer.str_of(id)).collect();
+        format!("{} ==> {}", antecedent.join(" then "), itemizer.str_of(self.consequent))
+    }
+}
+
+// Returns the number of sequences in which the pattern's items occur in
+// order, each in a later event than the one before.
+fn count_occurrences(pattern: &[u32], sequences: &[Sequence]) -> u32 {
+    sequences
+        .iter()
+        .filter(|sequence| {
+            let mut position = 0;
+            for item in pattern {
+                match sequence[position..].iter().position(|event| event.contains(item)) {
+                    Some(offset) => position += offset + 1,
+                    None => return false,
+                }
+            }
+            true
+        })
+        .count() as u32
+}
+
+pub struct SequentialRuleParameters {
+    pub min_count: u32,
+    pub max_antecedent_len: usize,
+    pub min_confidence: f64,
+    pub min_lift: f64,
+}
+
+struct Search<'a> {
+    sequences: &'a [Sequence],
+    consequent: u32,
+    // The event of the consequent's last occurrence in each sequence.
+    end: &'a HashMap<usize, usize>,
+    consequent_support: f64,
+    parameters: &'a SequentialRuleParameters,
+    // The number of sequences each antecedent occurs in, shared across
+    // consequents.
+    counts: &'a mut HashMap<Vec<u32>, u32>,
+    rules: Vec<SequentialRule>,
+}
+
+impl<'a> Search<'a> {
+    // Grows `pattern`, whose projected database is `projection`, pairs of
+    // sequence index and the event after the pattern's earliest occurrence.
+    fn grow(&mut self, pattern: &[u32], projection: &[(usize, usize)]) {
+        let mut item_sequences: HashMap<u32, u32> = HashMap::new();
+        for &(s, start) in projection {
+            let mut seen: HashSet<u32> = HashSet::new();
+            for event in self.sequences[s][start..self.end[&s]].iter() {
+                seen.extend(event.iter().cloned());
+            }
+            for item in seen {
+                *item_sequences.entry(item).or_insert(0) += 1;
+            }
+        }
+
+        let mut items: Vec<(u32, u32)> = item_sequences
+            .into_iter()
+            .filter(|&(item, count)| count >= self.parameters.min_count && item != self.consequent)
+            .collect();
+        items.sort();
+        for (item, count) in items {
+            let mut antecedent: Vec<u32> = Vec::from(pattern);
+            antecedent.push(item);
+            let next_projection: Vec<(usize, usize)> = projection
+                .iter()
+                .filter_map(|&(s, start)| {
+                    self.sequences[s][start..self.end[&s]]
+                        .iter()
+                        .position(|event| event.contains(&item))
+                        .map(|offset| (s, start + offset + 1))
+                })
+                .collect();
+
+            let sequences = self.sequences;
+            let antecedent_count = *self
+                .counts
+                .entry(antecedent.clone())
+                .or_insert_with(|| count_occurrences(&antecedent, sequences));
+            let confidence = count as f64 / antecedent_count as f64;
+            let lift = confidence / self.consequent_support;
+            if confidence >= self.parameters.min_confidence && lift >= self.parameters.min_lift {
+                self.rules.push(SequentialRule {
+                    antecedent: antecedent.clone(),
+                    consequent: self.consequent,
+                    confidence,
+                    lift,
+                    support: count as f64 / self.sequences.len() as f64,
+                });
+            }
+
+            if antecedent.len() < self.parameters.max_antecedent_len {
+                self.grow(&antecedent, &next_projection);
+            }
+        }
+    }
+}
+
+// Returns the rules whose consequent is a rare item, and whose antecedent
+// and consequent occur together in order in at least min_count sequences.
+pub fn mine_sequential_rules(
+    sequences: &[Sequence],
+    rare_items: &HashSet<u32>,
+    parameters: &SequentialRuleParameters,
+) -> Vec<SequentialRule> {
+    let mut rare_items: Vec<u32> = rare_items.iter().cloned().collect();
+    rare_items.sort();
+
+    let mut counts: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut rules: Vec<SequentialRule> = vec![];
+    for consequent in rare_items {
+        let end: HashMap<usize, usize> = sequences
+            .iter()
+            .enumerate()
+            .filter_map(|(s, sequence)| {
+                sequence
+                    .iter()
+                    .rposition(|event| event.contains(&consequent))
+                    .map(|last| (s, last))
+            })
+            .collect();
+        let mut projection: Vec<(usize, usize)> = end.keys().map(|&s| (s, 0)).collect();
+        projection.sort();
+
+        let mut search = Search {
+            sequences,
+            consequent,
+            end: &end,
+            consequent_support: end.len() as f64 / sequences.len() as f64,
+            parameters,
+            counts: &mut counts,
+            rules: vec![],
+        };
+        search.grow(&[], &projection);
+        rules.append(&mut search.rules);
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_sequential_rules() {
+        use super::{mine_sequential_rules, read_sequences, SequentialRuleParameters};
+        use itemizer::Itemizer;
+        use std::collections::HashSet;
+
+        let mut itemizer = Itemizer::new();
+        // Sequence 1 is out of order, and has two lines at time 2.
+        let sequences = read_sequences(
+            "1,3,c\n1,1,a\n1,2,b\n1,2,x\n\
+             2,1,a\n2,2,b\n2,3,c\n\
+             3,1,b\n3,2,a\n3,3,c\n\
+             4,1,a\n4,2,b\n"
+                .as_bytes(),
+            &mut itemizer,
+        ).unwrap();
+        let (a, b, c, x) = (
+            itemizer.get_id("a").unwrap(),
+            itemizer.get_id("b").unwrap(),
+            itemizer.get_id("c").unwrap(),
+            itemizer.get_id("x").unwrap(),
+        );
+        assert_eq!(sequences.len(), 4);
+        assert_eq!(sequences[0], vec![vec![a], vec![b, x], vec![c]]);
+        for bad_time in ["noon", "nan", "inf", "-inf"] {
+            let line = format!("1,1,a\n1,{},b\n", bad_time);
+            assert!(read_sequences(line.as_bytes(), &mut itemizer).is_err());
+        }
+
+        let rare_items: HashSet<u32> = [c].iter().cloned().collect();
+        let parameters = SequentialRuleParameters {
+            min_count: 2,
+            max_antecedent_len: 2,
+            min_confidence: 0.0,
+            min_lift: 0.0,
+        };
+        let rules = mine_sequential_rules(&sequences, &rare_items, &parameters);
+        let mut found: Vec<(String, f64)> = rules
+            .iter()
+            .map(|rule| (rule.to_string(&itemizer), rule.confidence))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            found,
+            vec![
+                (String::from("a ==> c"), 0.75),
+                (String::from("a then b ==> c"), 2.0 / 3.0),
+                (String::from("b ==> c"), 0.75),
+            ]
+        );
+    }
+}
@@ -0,0 +1,525 @@
+// HTTP API for running mining jobs, started with `riptree serve`.
+//
+//   POST /datasets              Upload a CSV dataset in the request body.
+//                               Returns {"id": <dataset id>}.
+//   POST /jobs?dataset=<id>&... Start mining a dataset. The query string takes
+//                               max_support, min_confidence, min_lift and
+//                               disable_family_wise_rule_filtering, with the
+//                               same meaning as the command line options.
+//                               Returns {"id": <job id>, "status": "running"}.
+//   GET /jobs/<id>              Poll a job's status.
+//   DELETE /jobs/<id>           Stop a running job. It finishes with the rules
+//                               found so far, and its status says "partial".
+//   GET /jobs/<id>/rules        Download a finished job's rules. Pass
+//                               format=json for JSON, otherwise CSV.
+//
+// Datasets and results are stored as files in the data directory, and
+// downloads are streamed from there, so large rule sets aren't held in memory.
+// Each job is cancelled by its own token, so stopping one leaves the others
+// running.
+
+use riptree::cancel::{run_cancellable, CancelToken};
+use riptree::command_line_args::{Arguments, ServeArguments};
+use riptree::miner::mine;
+use riptree::rule_writer::{write_rules_csv, write_rules_json};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Clone)]
+enum JobStatus {
+    Running,
+    // The number of rules found, and whether the job was stopped early.
+    Finished(usize, bool),
+    Failed(String),
+}
+
+struct State {
+    next_id: u64,
+    datasets: HashSet<u64>,
+    jobs: HashMap<u64, JobStatus>,
+    // The tokens which stop the running jobs.
+    cancel_tokens: HashMap<u64, CancelToken>,
+}
+
+struct JobManager {
+    data_dir: PathBuf,
+    state: Mutex<State>,
+}
+
+impl JobManager {
+    fn new(data_dir: &str) -> JobManager {
+        JobManager {
+            data_dir: PathBuf::from(data_dir),
+            state: Mutex::new(State {
+                next_id: 1,
+                datasets: HashSet::new(),
+                jobs: HashMap::new(),
+                cancel_tokens: HashMap::new(),
+            }),
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        id
+    }
+
+    fn dataset_path(&self, id: u64) -> PathBuf {
+        self.data_dir.join(format!("dataset-{}.csv", id))
+    }
+
+    fn rules_path(&self, id: u64, format: &str) -> PathBuf {
+        self.data_dir.join(format!("job-{}-rules.{}", id, format))
+    }
+
+    fn add_dataset(&self, body: &mut dyn io::Read) -> io::Result<u64> {
+        let id = self.next_id();
+        io::copy(body, &mut BufWriter::new(File::create(self.dataset_path(id))?))?;
+        self.state.lock().unwrap().datasets.insert(id);
+        Ok(id)
+    }
+
+    fn has_dataset(&self, id: u64) -> bool {
+        self.state.lock().unwrap().datasets.contains(&id)
+    }
+
+    fn job_status(&self, id: u64) -> Option<JobStatus> {
+        self.state.lock().unwrap().jobs.get(&id).cloned()
+    }
+
+    fn start_job(manager: &Arc<JobManager>, args: Arguments) -> u64 {
+        let id = manager.next_id();
+        let token = CancelToken::new();
+        {
+            let mut state = manager.state.lock().unwrap();
+            state.jobs.insert(id, JobStatus::Running);
+            state.cancel_tokens.insert(id, token.clone());
+        }
+
+        let manager = Arc::clone(manager);
+        thread::spawn(move || {
+            // Mining panics on some malformed input; report that as a failed
+            // job rather than leaving it running forever.
+            let run = || {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    manager.run_job(id, &args).map_err(|err| err.to_string())
+                }))
+            };
+            let status = match run_cancellable(&token, run) {
+                Ok(Ok((num_rules, partial))) => JobStatus::Finished(num_rules, partial),
+                Ok(Err(err)) => JobStatus::Failed(err),
+                Err(_) => JobStatus::Failed(String::from("Mining failed unexpectedly")),
+            };
+            let mut state = manager.state.lock().unwrap();
+            state.cancel_tokens.remove(&id);
+            state.jobs.insert(id, status);
+        });
+        id
+    }
+
+    // Asks a running job to stop. Returns false if it isn't running.
+    fn cancel_job(&self, id: u64) -> bool {
+        match self.state.lock().unwrap().cancel_tokens.get(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Mines, and writes the rules in each download format. Returns the
+    // number of rules found, and whether mining was stopped early.
+    fn run_job(&self, id: u64, args: &Arguments) -> Result<(usize, bool), Box<dyn Error>> {
+        let result = mine(args)?;
+        {
+            let mut output = BufWriter::new(File::create(self.rules_path(id, "csv"))?);
+            write_rules_csv(&mut output, &result.rules, &result.consequent_groups, &result.itemizer, false)?;
+        }
+        {
+            let mut output = BufWriter::new(File::create(self.rules_path(id, "json"))?);
+            write_rules_json(&mut output, &result.rules, &result.consequent_groups, &result.itemizer, false)?;
+        }
+        Ok((result.rules.len(), result.report.partial))
+    }
+}
+
+// Decodes a query string component: '+' is a space, and %XX the byte with
+// hex value XX. Malformed escapes are kept as they are.
+fn percent_decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 3) {
+            Some(hex) if bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                u8::from_str_radix(&component[i + 1..i + 3], 16).ok()
+            }
+            _ => None,
+        };
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(index) => (percent_decode(&pair[..index]), percent_decode(&pair[index + 1..])),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+fn parse_param<T: ::std::str::FromStr>(
+    params: &HashMap<String, String>,
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid value for {}: '{}'", name, value)),
+        None => Ok(default),
+    }
+}
+
+fn job_arguments(manager: &JobManager, params: &HashMap<String, String>) -> Result<Arguments, String> {
+    let dataset: u64 = match params.get("dataset").map(|id| id.parse()) {
+        Some(Ok(id)) => id,
+        _ => return Err(String::from("Missing or invalid dataset id")),
+    };
+    if !manager.has_dataset(dataset) {
+        return Err(format!("No dataset with id {}", dataset));
+    }
+    let max_support_mode = params.get("max_support").map_or("gaussian", String::as_str).parse()?;
+    let args = Arguments {
+        input_file_paths: vec![manager.dataset_path(dataset).to_string_lossy().into_owned()],
+        max_support_mode,
+        min_confidence: parse_param(params, "min_confidence", 0.0)?,
+        min_lift: parse_param(params, "min_lift", 1.0)?,
+        disable_family_wise_rule_filtering: parse_param(
+            params,
+            "disable_family_wise_rule_filtering",
+            false,
+        )?,
+        ..Arguments::default()
+    };
+    args.validate()?;
+    Ok(args)
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes(&b"Content-Type"[..], value.as_bytes()).unwrap()
+}
+
+fn respond_json(request: Request, status: u16, body: &serde_json::Value) -> io::Result<()> {
+    request.respond(
+        Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type("application/json")),
+    )
+}
+
+fn respond_error(request: Request, status: u16, message: &str) -> io::Result<()> {
+    respond_json(request, status, &json!({ "error": message }))
+}
+
+fn respond_file(request: Request, path: &Path, mime_type: &str) -> io::Result<()> {
+    let file = File::open(path)?;
+    request.respond(Response::from_file(file).with_header(content_type(mime_type)))
+}
+
+fn job_status_json(id: u64, status: &JobStatus) -> serde_json::Value {
+    match *status {
+        JobStatus::Running => json!({ "id": id, "status": "running" }),
+        JobStatus::Finished(num_rules, partial) => {
+            json!({ "id": id, "status": "finished", "rules": num_rules, "partial": partial })
+        }
+        JobStatus::Failed(ref err) => json!({ "id": id, "status": "failed", "error": err }),
+    }
+}
+
+fn handle(manager: &Arc<JobManager>, mut request: Request) -> io::Result<()> {
+    let url = request.url().to_owned();
+    let (path, query) = match url.find('?') {
+        Some(index) => (&url[..index], &url[index + 1..]),
+        None => (&url[..], ""),
+    };
+    let params = parse_query(query);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let method = request.method().clone();
+
+    match (method, &segments[..]) {
+        (Method::Post, ["datasets"]) => match manager.add_dataset(request.as_reader()) {
+            Ok(id) => respond_json(request, 201, &json!({ "id": id })),
+            Err(err) => respond_error(request, 500, &err.to_string()),
+        },
+        (Method::Post, ["jobs"]) => match job_arguments(manager, &params) {
+            Ok(args) => {
+                let id = JobManager::start_job(manager, args);
+                respond_json(request, 202, &job_status_json(id, &JobStatus::Running))
+            }
+            Err(err) => respond_error(request, 400, &err),
+        },
+        (Method::Get, ["jobs", id]) => match id.parse().ok().and_then(|id| {
+            manager.job_status(id).map(|status| job_status_json(id, &status))
+        }) {
+            Some(status) => respond_json(request, 200, &status),
+            None => respond_error(request, 404, "No such job"),
+        },
+        (Method::Delete, ["jobs", id]) => {
+            let id: u64 = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return respond_error(request, 404, "No such job"),
+            };
+            if manager.cancel_job(id) {
+                respond_json(request, 202, &job_status_json(id, &JobStatus::Running))
+            } else if manager.job_status(id).is_some() {
+                respond_error(request, 409, "Job isn't running")
+            } else {
+                respond_error(request, 404, "No such job")
+            }
+        }
+        (Method::Get, ["jobs", id, "rules"]) => {
+            let id: u64 = match id.parse() {
+                Ok(id) => id,
+                Err(_) => return respond_error(request, 404, "No such job"),
+            };
+            match manager.job_status(id) {
+                Some(JobStatus::Finished(..)) => {
+                    if params.get("format").map(String::as_str) == Some("json") {
+                        respond_file(request, &manager.rules_path(id, "json"), "application/json")
+                    } else {
+                        respond_file(request, &manager.rules_path(id, "csv"), "text/csv")
+                    }
+                }
+                Some(_) => respond_error(request, 409, "Job hasn't finished"),
+                None => respond_error(request, 404, "No such job"),
+            }
+        }
+        _ => respond_error(request, 404, "Not found"),
+    }
+}
+
+fn handle_requests(server: &Server, manager: &Arc<JobManager>) {
+    for request in server.incoming_requests() {
+        if let Err(err) = handle(manager, request) {
+            error!("Error responding to request: {}", err);
+        }
+    }
+}
+
+pub fn serve(args: &ServeArguments) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&args.data_dir)?;
+    let server = match Server::http((args.host.as_str(), args.port)) {
+        Ok(server) => server,
+        Err(err) => return Err(err.to_string().into()),
+    };
+    info!(
+        "Listening on {}:{}, storing data in {}",
+        args.host, args.port, args.data_dir
+    );
+
+    let manager = Arc::new(JobManager::new(&args.data_dir));
+    handle_requests(&server, &manager);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_query() {
+        use super::parse_query;
+
+        let params = parse_query("dataset=3&min_lift=5&flag");
+        assert_eq!(params.len(), 3);
+        assert_eq!(params["dataset"], "3");
+        assert_eq!(params["min_lift"], "5");
+        assert_eq!(params["flag"], "");
+        assert!(parse_query("").is_empty());
+
+        // Components are percent-decoded, keeping malformed escapes.
+        let params =
+            parse_query("input=my%20file.csv&name=caf%C3%A9+au+lait&bad=%zz&percent=100%&max%5Fsupport=pareto");
+        assert_eq!(params["input"], "my file.csv");
+        assert_eq!(params["name"], "café au lait");
+        assert_eq!(params["bad"], "%zz");
+        assert_eq!(params["percent"], "100%");
+        assert_eq!(params["max_support"], "pareto");
+    }
+
+    // Waits for a job to stop running, and returns its status.
+    fn wait_for_job(manager: &super::JobManager, id: u64) -> super::JobStatus {
+        use super::JobStatus;
+        use std::thread;
+        use std::time::Duration;
+
+        for _ in 0..600 {
+            match manager.job_status(id) {
+                Some(JobStatus::Running) => thread::sleep(Duration::from_millis(100)),
+                Some(status) => return status,
+                None => panic!("No job {}", id),
+            }
+        }
+        panic!("Job {} didn't finish", id);
+    }
+
+    #[test]
+    fn test_job_manager() {
+        use super::{job_arguments, parse_query, JobManager, JobStatus};
+        use std::env;
+        use std::fs::{self, File};
+        use std::sync::Arc;
+
+        let dir = env::temp_dir().join(format!("riptree-job-manager-test-{}", std::process::id()));
+        let manager = Arc::new(JobManager::new(&dir.to_string_lossy()));
+        fs::create_dir_all(&dir).unwrap();
+        let dataset = manager.add_dataset(&mut File::open("datasets/UCI-zoo.csv").unwrap()).unwrap();
+        assert!(manager.has_dataset(dataset));
+        assert!(!manager.has_dataset(dataset + 1));
+
+        let query = format!("dataset={}&max_support=pareto&min_confidence=0.9&min_lift=5", dataset);
+        let params = parse_query(&query);
+        let id = JobManager::start_job(&manager, job_arguments(&manager, &params).unwrap());
+        let num_rules = match wait_for_job(&manager, id) {
+            JobStatus::Finished(num_rules, partial) => {
+                assert!(!partial);
+                num_rules
+            }
+            JobStatus::Failed(err) => panic!("Job failed: {}", err),
+            JobStatus::Running => unreachable!(),
+        };
+        assert!(num_rules > 0);
+        // The CSV has a header line and a line per rule.
+        let csv = fs::read_to_string(manager.rules_path(id, "csv")).unwrap();
+        assert_eq!(csv.lines().count(), num_rules + 1);
+        assert!(fs::metadata(manager.rules_path(id, "json")).is_ok());
+        // A finished job can't be stopped.
+        assert!(!manager.cancel_job(id));
+
+        let params = parse_query(&format!("dataset={}&min_lift=-1", dataset));
+        assert!(job_arguments(&manager, &params).is_err());
+        assert!(job_arguments(&manager, &parse_query("dataset=99")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Sends an HTTP request to `address`, and returns the response's status
+    // code and body.
+    fn request(address: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            address,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_at(response.find("\r\n\r\n").unwrap());
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body[4..].to_owned())
+    }
+
+    #[test]
+    fn test_endpoints() {
+        use super::{handle_requests, JobManager};
+        use serde_json::{self, Value};
+        use std::env;
+        use std::fs;
+        use std::sync::Arc;
+        use std::thread;
+        use tiny_http::Server;
+
+        let dir = env::temp_dir().join(format!("riptree-server-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap().to_string();
+        let manager = Arc::new(JobManager::new(&dir.to_string_lossy()));
+        let server_manager = Arc::clone(&manager);
+        thread::spawn(move || handle_requests(&server, &server_manager));
+        let json = |body: &str| -> Value { serde_json::from_str(body).unwrap() };
+
+        let zoo = fs::read_to_string("datasets/UCI-zoo.csv").unwrap();
+        let (status, body) = request(&address, "POST", "/datasets", &zoo);
+        assert_eq!(status, 201);
+        let dataset = json(&body)["id"].as_u64().unwrap();
+
+        let (status, body) = request(&address, "POST", "/jobs?dataset=99", "");
+        assert_eq!(status, 400);
+        assert_eq!(json(&body)["error"], "No dataset with id 99");
+        let (status, _) = request(&address, "POST", &format!("/jobs?dataset={}&min_lift=x", dataset), "");
+        assert_eq!(status, 400);
+
+        // The options are percent-decoded.
+        let (status, body) = request(
+            &address,
+            "POST",
+            &format!("/jobs?dataset={}&max%5Fsupport=pareto&min_confidence=0.9&min_lift=5", dataset),
+            "",
+        );
+        assert_eq!(status, 202);
+        let job = json(&body);
+        assert_eq!(job["status"], "running");
+        let id = job["id"].as_u64().unwrap();
+        wait_for_job(&manager, id);
+
+        let (status, body) = request(&address, "GET", &format!("/jobs/{}", id), "");
+        assert_eq!(status, 200);
+        let job = json(&body);
+        assert_eq!(job["status"], "finished");
+        assert_eq!(job["partial"], false);
+        let num_rules = job["rules"].as_u64().unwrap() as usize;
+        assert!(num_rules > 0);
+
+        let (status, body) = request(&address, "GET", &format!("/jobs/{}/rules?format=%6Ason", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(json(&body).as_array().unwrap().len(), num_rules);
+        let (status, body) = request(&address, "GET", &format!("/jobs/{}/rules", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(body.lines().count(), num_rules + 1);
+
+        let (status, _) = request(&address, "DELETE", &format!("/jobs/{}", id), "");
+        assert_eq!(status, 409);
+        for path in ["/jobs/99", "/jobs/99/rules", "/jobs/x", "/nowhere"] {
+            let (status, _) = request(&address, "GET", path, "");
+            assert_eq!(status, 404, "{}", path);
+        }
+        let (status, _) = request(&address, "DELETE", "/jobs/99", "");
+        assert_eq!(status, 404);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,616 @@
+// Tests of whether the association between an itemset A and an itemset B is
+// statistically significant, given their contingency table.
+
+use rand::distributions::normal::StandardNormal;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::cmp::{max, min};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// ln(n!) is looked up in a table for n below this, and approximated with
+// Stirling's series above it, where the series is accurate to f64 precision.
+const LN_TABLE_SIZE: usize = 1024;
+
+// The 2x2 contingency table of itemsets A and B: the total number of
+// transactions (n), and the number containing A (a), B (b), and both (ab).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Contingency {
+    pub n: u32,
+    pub a: u32,
+    pub b: u32,
+    pub ab: u32,
+}
+
+impl Contingency {
+    pub fn new(n: u32, a: u32, b: u32, ab: u32) -> Contingency {
+        Contingency { n, a, b, ab }
+    }
+
+    pub fn a_not_b(&self) -> u32 {
+        self.a - self.ab
+    }
+
+    pub fn not_a_b(&self) -> u32 {
+        self.b - self.ab
+    }
+
+    pub fn neither(&self) -> u32 {
+        self.n - (self.a + self.b - self.ab)
+    }
+
+    // The support, confidence and lift of the rule A ==> B. Those which would
+    // divide by zero, because no transactions contain A or B, are 0.
+    pub fn support(&self) -> f64 {
+        if self.n > 0 {
+            self.ab as f64 / self.n as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn confidence(&self) -> f64 {
+        if self.a > 0 {
+            self.ab as f64 / self.a as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn lift(&self) -> f64 {
+        if self.b > 0 {
+            self.confidence() * self.n as f64 / self.b as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+pub enum SignificanceTest {
+    // Fisher's exact test, computed from log factorials.
+    Fisher { ln_table: Vec<f64> },
+    // Pearson's chi-square test with one degree of freedom, optionally with
+    // Yates' continuity correction. Much cheaper than Fisher's test when
+    // counts are large, and needs no lookup table.
+    ChiSquare { yates_correction: bool },
+    // A permutation test: the transactions containing B are shuffled among
+    // all transactions this many times, and the p-value is the fraction of
+    // shuffles in which A and B occur together at least as often as they
+    // really do. Makes no approximations, so suits tiny counts, but is too
+    // slow to prune itemsets with, so is only used to filter rules.
+    Permutation { permutations: usize },
+}
+
+impl SignificanceTest {
+    pub fn fisher() -> SignificanceTest {
+        let mut ln_table = Vec::with_capacity(LN_TABLE_SIZE);
+        ln_table.push(0.0);
+        ln_table.push(0.0);
+        for i in 2..LN_TABLE_SIZE {
+            let prev = ln_table[i - 1];
+            ln_table.push(prev + (i as f64).ln());
+        }
+        SignificanceTest::Fisher { ln_table }
+    }
+
+    pub fn chi_square(yates_correction: bool) -> SignificanceTest {
+        SignificanceTest::ChiSquare { yates_correction }
+    }
+
+    pub fn permutation(permutations: usize) -> SignificanceTest {
+        SignificanceTest::Permutation { permutations }
+    }
+
+    pub fn pval(&self, table: &Contingency) -> f64 {
+        match *self {
+            SignificanceTest::Fisher { ref ln_table } => fisher_pval(table, ln_table),
+            SignificanceTest::ChiSquare { yates_correction } => {
+                chi_square_pval(table, yates_correction)
+            }
+            SignificanceTest::Permutation { permutations } => {
+                permutation_pval(table, permutations)
+            }
+        }
+    }
+}
+
+const NUM_SHARDS: usize = 64;
+
+// Memoizes a test's p-values by contingency table. Rules on rare items often
+// have the same counts as each other, and Fisher's and the permutation test
+// are costly, so filtering many rules reuses many p-values. Like
+// SupportCache, the map is split into shards, each behind its own lock, so
+// threads filtering rules in parallel rarely wait on each other.
+pub struct PValueCache<'a> {
+    test: &'a SignificanceTest,
+    shards: Vec<Mutex<HashMap<Contingency, f64>>>,
+    hits: AtomicUsize,
+}
+
+impl<'a> PValueCache<'a> {
+    pub fn new(test: &'a SignificanceTest) -> PValueCache<'a> {
+        PValueCache {
+            test,
+            shards: (0..NUM_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    // Returns the same as SignificanceTest::pval().
+    pub fn pval(&self, table: &Contingency) -> f64 {
+        let mut hasher = DefaultHasher::new();
+        table.hash(&mut hasher);
+        let shard = &self.shards[hasher.finish() as usize % NUM_SHARDS];
+        if let Some(&pval) = shard.lock().unwrap().get(table) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return pval;
+        }
+        let pval = self.test.pval(table);
+        shard.lock().unwrap().insert(*table, pval);
+        pval
+    }
+
+    // The number of distinct tables tested, and the number of lookups which
+    // found them already tested.
+    pub fn stats(&self) -> (usize, usize) {
+        let len = self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum();
+        (len, self.hits.load(Ordering::Relaxed))
+    }
+}
+
+fn lfactorial(n: u32, ln_table: &[f64]) -> f64 {
+    if (n as usize) < ln_table.len() {
+        return ln_table[n as usize];
+    }
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (2.0 * PI * n).ln() + 1.0 / (12.0 * n) - 1.0 / (360.0 * n.powi(3)) +
+        1.0 / (1260.0 * n.powi(5))
+}
+
+fn fisher_pval(table: &Contingency, ln_table: &[f64]) -> f64 {
+    let Contingency { n, a, b, ab } = *table;
+    (lfactorial(b, ln_table) + lfactorial(n - b, ln_table) + lfactorial(a, ln_table) +
+        lfactorial(n - a, ln_table) - lfactorial(ab, ln_table) -
+        lfactorial(table.not_a_b(), ln_table) - lfactorial(table.a_not_b(), ln_table) -
+        lfactorial(table.neither(), ln_table) - lfactorial(n, ln_table))
+        .exp()
+}
+
+// Complementary error function, with fractional error less than 1.2e-7.
+// From Numerical Recipes in C, 2nd edition, section 6.2.
+fn erfc(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 10] = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ];
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = COEFFICIENTS.iter().rev().fold(0.0, |acc, &c| acc * t + c);
+    let r = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+fn chi_square_pval(table: &Contingency, yates_correction: bool) -> f64 {
+    let (ab, a, b, n) = (table.ab as f64, table.a as f64, table.b as f64, table.n as f64);
+    let denominator = a * (n - a) * b * (n - b);
+    if denominator == 0.0 {
+        // An itemset is in none or all of the transactions, so there's no
+        // evidence of any association.
+        return 1.0;
+    }
+    let mut difference =
+        (ab * table.neither() as f64 - table.a_not_b() as f64 * table.not_a_b() as f64).abs();
+    if yates_correction {
+        difference = (difference - n / 2.0).max(0.0);
+    }
+    let chi_square = n * difference * difference / denominator;
+    // The survival function of the chi-square distribution with one degree
+    // of freedom.
+    erfc((chi_square / 2.0).sqrt())
+}
+
+// The log of the gamma function, for x > 0, by the Lanczos approximation.
+// From Numerical Recipes in C, 2nd edition, section 6.1.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    let mut y = x;
+    for c in COEFFICIENTS.iter() {
+        y += 1.0;
+        series += c / y;
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+// The regularized upper incomplete gamma function Q(a, x), by its series
+// below a + 1 and its continued fraction above. From Numerical Recipes in C,
+// 2nd edition, section 6.2.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const ITERATIONS: usize = 200;
+    const EPSILON: f64 = 3.0e-12;
+    const TINY: f64 = 1.0e-300;
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut denominator = a;
+        for _ in 0..ITERATIONS {
+            denominator += 1.0;
+            term *= x / denominator;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        return (1.0 - sum * prefactor).max(0.0);
+    }
+    // Lentz's method.
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    (prefactor * h).min(1.0)
+}
+
+// The survival function of the chi-square distribution with the given
+// degrees of freedom.
+pub fn chi_square_survival(chi_square: f64, degrees_of_freedom: usize) -> f64 {
+    gamma_q(degrees_of_freedom as f64 / 2.0, chi_square / 2.0)
+}
+
+// The p-value of the chi-square test of homogeneity of a 2xk table, given
+// as the (successes, failures) in each of k groups: the chance of counts at
+// least as uneven if each group had the same rate of success. Empty groups
+// are left out, and a table with fewer than two groups, or with no
+// successes or no failures, gives 1.
+pub fn homogeneity_pval(counts: &[(u32, u32)]) -> f64 {
+    let counts: Vec<(f64, f64)> = counts
+        .iter()
+        .filter(|&&(successes, failures)| successes + failures > 0)
+        .map(|&(successes, failures)| (successes as f64, failures as f64))
+        .collect();
+    let successes: f64 = counts.iter().map(|&(successes, _)| successes).sum();
+    let failures: f64 = counts.iter().map(|&(_, failures)| failures).sum();
+    if counts.len() < 2 || successes == 0.0 || failures == 0.0 {
+        return 1.0;
+    }
+    let total = successes + failures;
+    let chi_square: f64 = counts
+        .iter()
+        .map(|&(s, f)| {
+            let expected_s = (s + f) * successes / total;
+            let expected_f = (s + f) * failures / total;
+            (s - expected_s).powi(2) / expected_s + (f - expected_f).powi(2) / expected_f
+        })
+        .sum();
+    chi_square_survival(chi_square, counts.len() - 1)
+}
+
+// Returns a random number generator seeded by a rule's contingency table, so
+// the same rule gets the same results each run.
+fn seeded_rng(table: &Contingency) -> XorShiftRng {
+    let counts = [table.ab, table.a, table.b, table.n];
+    XorShiftRng::from_seed(counts.map(|count| count.wrapping_mul(0x9e37_79b9) ^ 0x85eb_ca6b))
+}
+
+fn permutation_pval(table: &Contingency, permutations: usize) -> f64 {
+    let Contingency { n, a, b, ab } = *table;
+    let mut rng = seeded_rng(table);
+    // Shuffling B's transactions, the number landing among A's is the same as
+    // shuffling A's among B's, so draw whichever is fewer.
+    let (draws, targets) = (min(a, b), max(a, b));
+    let mut as_extreme = 0;
+    for _ in 0..permutations {
+        let (mut remaining, mut remaining_targets, mut hits) = (n, targets, 0);
+        for _ in 0..draws {
+            if rng.gen_range(0, remaining) < remaining_targets {
+                hits += 1;
+                remaining_targets -= 1;
+            }
+            remaining -= 1;
+        }
+        if hits >= ab {
+            as_extreme += 1;
+        }
+    }
+    // Counting the observed dataset as one of the permutations, so the
+    // p-value is never 0.
+    (as_extreme + 1) as f64 / (permutations + 1) as f64
+}
+
+// Returns the odds ratio of the association, and the lower and upper bounds
+// of its Wald 95% confidence interval. If any cell of the contingency table is
+// zero, which is common for rare items, 0.5 is added to every cell (the
+// Haldane-Anscombe correction) so the ratio and interval are finite.
+pub fn odds_ratio(table: &Contingency) -> (f64, f64, f64) {
+    let mut cells = [
+        table.ab as f64,
+        table.a_not_b() as f64,
+        table.not_a_b() as f64,
+        table.neither() as f64,
+    ];
+    if cells.contains(&0.0) {
+        for cell in cells.iter_mut() {
+            *cell += 0.5;
+        }
+    }
+    let ln_ratio = (cells[0] * cells[3] / (cells[1] * cells[2])).ln();
+    let standard_error = cells.iter().map(|cell| 1.0 / cell).sum::<f64>().sqrt();
+    (
+        ln_ratio.exp(),
+        (ln_ratio - 1.96 * standard_error).exp(),
+        (ln_ratio + 1.96 * standard_error).exp(),
+    )
+}
+
+// Draws from the binomial distribution. Small means are drawn exactly, by
+// inversion, and larger ones from the normal approximation.
+fn binomial<R: Rng>(rng: &mut R, trials: u32, p: f64) -> u32 {
+    if p <= 0.0 {
+        return 0;
+    }
+    if p >= 1.0 {
+        return trials;
+    }
+    if p > 0.5 {
+        return trials - binomial(rng, trials, 1.0 - p);
+    }
+    let mean = trials as f64 * p;
+    if mean < 30.0 {
+        let q = 1.0 - p;
+        let ratio = p / q;
+        let mut probability = q.powf(trials as f64);
+        let mut u: f64 = rng.gen();
+        let mut x = 0;
+        while u > probability && x < trials {
+            u -= probability;
+            x += 1;
+            probability *= ratio * (trials - x + 1) as f64 / x as f64;
+        }
+        x
+    } else {
+        let StandardNormal(z) = rng.gen();
+        let x = (mean + z * (mean * (1.0 - p)).sqrt()).round();
+        x.max(0.0).min(trials as f64) as u32
+    }
+}
+
+// Returns the 95% percentile bootstrap confidence intervals of the confidence
+// and lift of the rule A ==> B, as (lower, upper) pairs. Resampling n
+// transactions with replacement only changes the contingency table, so each
+// resample draws it directly, from the multinomial distribution over
+// transactions with both, only A, only B, or neither. Resamples which would
+// divide by zero have a confidence or lift of 0.
+pub fn bootstrap_intervals(table: &Contingency, resamples: usize) -> ((f64, f64), (f64, f64)) {
+    let mut rng = seeded_rng(table);
+    let n = table.n;
+    let n_f = n as f64;
+    let (p_ab, p_a, p_b) = (
+        table.ab as f64 / n_f,
+        table.a_not_b() as f64 / n_f,
+        table.not_a_b() as f64 / n_f,
+    );
+    let mut confidences = Vec::with_capacity(resamples);
+    let mut lifts = Vec::with_capacity(resamples);
+    for _ in 0..resamples {
+        let both = binomial(&mut rng, n, p_ab);
+        let rest = 1.0 - p_ab;
+        let only_a = binomial(&mut rng, n - both, if rest > 0.0 { p_a / rest } else { 0.0 });
+        let rest = rest - p_a;
+        let only_b = binomial(&mut rng, n - both - only_a, if rest > 0.0 { p_b / rest } else { 0.0 });
+        let resample = Contingency::new(n, both + only_a, both + only_b, both);
+        confidences.push(resample.confidence());
+        lifts.push(resample.lift());
+    }
+    (percentile_interval(confidences), percentile_interval(lifts))
+}
+
+// Returns the 2.5th and 97.5th percentiles of `values`.
+fn percentile_interval(mut values: Vec<f64>) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let at = |fraction: f64| values[((values.len() - 1) as f64 * fraction).round() as usize];
+    (at(0.025), at(0.975))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_contingency() {
+        use super::Contingency;
+
+        let table = Contingency::new(100, 30, 40, 10);
+        assert_eq!(table.a_not_b(), 20);
+        assert_eq!(table.not_a_b(), 30);
+        assert_eq!(table.neither(), 40);
+        assert_eq!(table.support(), 0.1);
+        assert_eq!(table.confidence(), 1.0 / 3.0);
+        assert!((table.lift() - 10.0 / 12.0).abs() < 1e-12);
+        let empty = Contingency::new(100, 0, 0, 0);
+        assert_eq!(empty.neither(), 100);
+        assert_eq!((empty.confidence(), empty.lift()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pvalue_cache() {
+        use super::{Contingency, PValueCache, SignificanceTest};
+
+        let fisher = SignificanceTest::fisher();
+        let cache = PValueCache::new(&fisher);
+        let table = Contingency::new(100, 30, 40, 10);
+        assert_eq!(cache.pval(&table), fisher.pval(&table));
+        assert_eq!(cache.pval(&Contingency::new(100, 30, 40, 10)), fisher.pval(&table));
+        let other = Contingency::new(1000, 10, 12, 9);
+        assert_eq!(cache.pval(&other), fisher.pval(&other));
+        assert_eq!(cache.stats(), (2, 1));
+    }
+
+    #[test]
+    fn test_lfactorial() {
+        use super::{lfactorial, SignificanceTest};
+
+        let ln_table = match SignificanceTest::fisher() {
+            SignificanceTest::Fisher { ln_table } => ln_table,
+            _ => unreachable!(),
+        };
+        // Compare Stirling's series against summing logs.
+        let mut expected = ln_table[ln_table.len() - 1];
+        for n in ln_table.len()..100_000 {
+            expected += (n as f64).ln();
+            let actual = lfactorial(n as u32, &ln_table);
+            assert!(((actual - expected) / expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_chi_square() {
+        use super::{Contingency, SignificanceTest};
+
+        let assert_close = |actual: f64, expected: f64| {
+            assert!(
+                ((actual - expected) / expected).abs() < 1e-6,
+                "{} != {}",
+                actual,
+                expected
+            );
+        };
+        let chi2 = SignificanceTest::chi_square(false);
+        let yates = SignificanceTest::chi_square(true);
+        let (moderate, strong) = (Contingency::new(100, 30, 40, 10), Contingency::new(1000, 10, 12, 9));
+        assert_close(chi2.pval(&moderate), 0.37299848361348714);
+        assert_close(yates.pval(&moderate), 0.5040358664525048);
+        assert_close(chi2.pval(&strong), 4.017341235497023e-148);
+        assert_close(yates.pval(&strong), 3.934296930783016e-132);
+        assert_eq!(chi2.pval(&Contingency::new(10, 5, 10, 5)), 1.0);
+    }
+
+    #[test]
+    fn test_homogeneity() {
+        use super::{chi_square_survival, homogeneity_pval};
+
+        // Critical values of the chi-square distribution at 0.05.
+        for &(critical, dof) in [(3.841, 1), (5.991, 2), (7.815, 3), (18.307, 10)].iter() {
+            assert!((chi_square_survival(critical, dof) - 0.05).abs() < 1e-3, "{} {}", critical, dof);
+        }
+        assert_eq!(chi_square_survival(0.0, 3), 1.0);
+        assert!(chi_square_survival(100.0, 2) < 1e-20);
+
+        // The same rate in each group.
+        assert!(homogeneity_pval(&[(10, 90), (20, 180), (5, 45)]) > 0.99);
+        // Chi-square of 2x2 table [[30, 70], [10, 90]] is 12.5.
+        let p = homogeneity_pval(&[(30, 70), (10, 90)]);
+        assert!((p - chi_square_survival(12.5, 1)).abs() < 1e-9);
+        assert!(p < 0.001);
+        // Empty groups are left out, and degenerate tables give 1.
+        assert_eq!(homogeneity_pval(&[(30, 70), (0, 0), (10, 90)]), p);
+        assert_eq!(homogeneity_pval(&[(30, 70)]), 1.0);
+        assert_eq!(homogeneity_pval(&[(0, 70), (0, 90)]), 1.0);
+    }
+
+    #[test]
+    fn test_permutation() {
+        use super::{Contingency, SignificanceTest};
+
+        let permutation = SignificanceTest::permutation(1000);
+        // No shuffle puts 9 of 12 transactions among 10 of 1000.
+        assert_eq!(permutation.pval(&Contingency::new(1000, 10, 12, 9)), 1.0 / 1001.0);
+        // 10 together is fewer than the 12 expected by chance.
+        let table = Contingency::new(100, 30, 40, 10);
+        let pval = permutation.pval(&table);
+        assert!(pval > 0.5 && pval < 1.0);
+        assert_eq!(permutation.pval(&table), pval);
+        // B is in every transaction, so always occurs with A.
+        assert_eq!(permutation.pval(&Contingency::new(10, 5, 10, 5)), 1.0);
+    }
+
+    #[test]
+    fn test_bootstrap_intervals() {
+        use super::{bootstrap_intervals, Contingency};
+
+        // Confidence 0.5 and lift 5, from small counts, and from the same
+        // proportions with large counts, which should give narrower intervals.
+        let small = bootstrap_intervals(&Contingency::new(100, 10, 10, 5), 1000);
+        let large = bootstrap_intervals(&Contingency::new(100000, 10000, 10000, 5000), 1000);
+        let width = |(lower, upper): (f64, f64)| upper - lower;
+        for &(confidence, lift) in [small, large].iter() {
+            assert!(confidence.0 < 0.5 && 0.5 < confidence.1);
+            assert!(lift.0 < 5.0 && 5.0 < lift.1);
+        }
+        assert!(width(large.0) < width(small.0));
+        assert!(width(large.1) < width(small.1));
+        assert_eq!(bootstrap_intervals(&Contingency::new(100, 10, 10, 5), 1000), small);
+
+        // The antecedent always occurs with the consequent.
+        assert_eq!(bootstrap_intervals(&Contingency::new(10, 10, 10, 10), 100).0, (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_odds_ratio() {
+        use super::{odds_ratio, Contingency};
+
+        let assert_close = |actual: f64, expected: f64| {
+            assert!(
+                ((actual - expected) / expected).abs() < 1e-6,
+                "{} != {}",
+                actual,
+                expected
+            );
+        };
+        // Cells 10, 20, 30, 40.
+        let (ratio, lower, upper) = odds_ratio(&Contingency::new(100, 30, 40, 10));
+        assert_close(ratio, 0.6666666666666666);
+        assert_close(lower, 0.2725103677809769);
+        assert_close(upper, 1.630926735241336);
+        // Cells 5, 0, 1, 94, corrected to 5.5, 0.5, 1.5, 94.5.
+        let (ratio, lower, upper) = odds_ratio(&Contingency::new(100, 5, 6, 5));
+        assert_close(ratio, 693.0);
+        assert!(lower < ratio && ratio < upper && upper.is_finite());
+    }
+}
@@ -0,0 +1,152 @@
+// Sketches, which summarize many keys in a fixed amount of memory.
+//
+// A Count-Min sketch, which counts keys in a fixed amount of memory, for
+// --approx-index. Each key is counted in one counter in each of `depth`
+// rows, chosen by a hash seeded differently for each row, and its count is
+// estimated as the least of those counters. Colliding keys only ever add to
+// a counter, so estimates are never below the true count, and with width
+// ceil(e / epsilon) and depth ceil(ln(1 / delta)), an estimate is more than
+// epsilon times the total weight added above the true count with
+// probability at most delta.
+
+use std::collections::hash_map::DefaultHasher;
+use std::f64::consts::E;
+use std::hash::{Hash, Hasher};
+
+pub struct CountMinSketch {
+    width: usize,
+    // The rows of counters, one after the other.
+    counters: Vec<u64>,
+    total: u64,
+}
+
+impl CountMinSketch {
+    pub fn new(epsilon: f64, delta: f64) -> CountMinSketch {
+        let width = (E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as usize;
+        CountMinSketch {
+            width,
+            counters: vec![0; width * depth],
+            total: 0,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.counters.len() / self.width
+    }
+
+    fn counter<K: Hash + ?Sized>(&self, row: usize, key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        row * self.width + (hasher.finish() % self.width as u64) as usize
+    }
+
+    pub fn add<K: Hash + ?Sized>(&mut self, key: &K, weight: u64) {
+        for row in 0..self.depth() {
+            let counter = self.counter(row, key);
+            self.counters[counter] += weight;
+        }
+        self.total += weight;
+    }
+
+    pub fn estimate<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        (0..self.depth())
+            .map(|row| self.counters[self.counter(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    // The bound on how far above the true counts estimates are, with
+    // probability 1 - delta.
+    pub fn error_bound(&self) -> f64 {
+        E / self.width as f64 * self.total as f64
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.counters.len() * std::mem::size_of::<u64>()
+    }
+}
+
+// A HyperLogLog sketch (Flajolet et al., 2007), which estimates the number
+// of distinct keys added, for --dry-run's estimate of the tree's size. Each
+// key's hash picks one of 2^precision registers, which keeps the most
+// leading zeros seen in the rest of the hashes picking it; the more distinct
+// keys, the more zeros. Estimates have a relative standard error of about
+// 1.04 / sqrt(2^precision).
+pub struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u32) -> HyperLogLog {
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn add<K: Hash + ?Sized>(&mut self, key: &K) {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - self.precision)) as usize;
+        let rank = ((hash << self.precision).leading_zeros() + 1).min(64 - self.precision + 1) as u8;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&rank| 2f64.powi(-(rank as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        // Small cardinalities are better estimated from the empty registers.
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            estimate
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_count_min_sketch() {
+        use super::CountMinSketch;
+
+        let mut sketch = CountMinSketch::new(0.01, 0.01);
+        for i in 0..1000u32 {
+            sketch.add(&[i, i + 1][..], (i % 7 + 1) as u64);
+        }
+        let bound = sketch.error_bound();
+        for i in 0..1000u32 {
+            let estimate = sketch.estimate(&[i, i + 1][..]);
+            let count = (i % 7 + 1) as u64;
+            assert!(estimate >= count);
+            assert!((estimate - count) as f64 <= bound);
+        }
+        assert!(sketch.estimate(&[1u32, 3][..]) as f64 <= bound);
+    }
+
+    #[test]
+    fn test_hyperloglog() {
+        use super::HyperLogLog;
+
+        let mut sketch = HyperLogLog::new(12);
+        assert_eq!(sketch.estimate(), 0.0);
+        for &n in [100u32, 10_000, 200_000].iter() {
+            let mut sketch = HyperLogLog::new(12);
+            // Each key added twice, which mustn't count.
+            for i in 0..2 * n {
+                sketch.add(&(i % n));
+            }
+            let error = (sketch.estimate() - n as f64).abs() / n as f64;
+            assert!(error < 0.05, "{} distinct keys estimated as {}", n, sketch.estimate());
+        }
+        sketch.add("a");
+        assert!((sketch.estimate() - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,253 @@
+// Caps the memory rip_growth uses, for --max-memory. The conditional trees
+// being grown, and the itemsets found so far, are counted against the cap by
+// their estimated size. When growing another rare item at the top level of
+// the tree would exceed it, that item's conditional pattern base is written
+// to a temporary spill file instead, and grown later, one item at a time,
+// once the rest have finished. Something is always allowed to grow, so a
+// single item larger than the cap is still mined.
+//
+// The spill file holds, for each item, the item, the number of paths in its
+// pattern base, then each path's count, length and items, all as little
+// endian u32s.
+
+use fptree::PatternBase;
+use mining_state::{read_u32, write_u32};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub struct MemoryBudget {
+    cap: usize,
+    // Bytes held by growth in progress, released as it finishes.
+    reserved: AtomicUsize,
+    // Bytes held by the itemsets found, which are kept until mining ends.
+    retained: AtomicUsize,
+}
+
+// Bytes reserved from a budget, which are released when this is dropped.
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: usize,
+}
+
+impl<'a> Drop for Reservation<'a> {
+    fn drop(&mut self) {
+        self.budget.reserved.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(cap: usize) -> MemoryBudget {
+        MemoryBudget {
+            cap,
+            reserved: AtomicUsize::new(0),
+            retained: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn in_use(&self) -> usize {
+        self.reserved.load(Ordering::SeqCst) + self.retained.load(Ordering::SeqCst)
+    }
+
+    // Reserves `bytes` even if that exceeds the cap.
+    pub fn reserve(&self, bytes: usize) -> Reservation<'_> {
+        self.reserved.fetch_add(bytes, Ordering::SeqCst);
+        Reservation { budget: self, bytes }
+    }
+
+    // Reserves `bytes` if they fit under the cap, or if nothing else is
+    // reserved.
+    pub fn try_reserve(&self, bytes: usize) -> Option<Reservation<'_>> {
+        let retained = self.retained.load(Ordering::SeqCst);
+        let mut reserved = self.reserved.load(Ordering::SeqCst);
+        loop {
+            if reserved > 0 && reserved + retained + bytes > self.cap {
+                return None;
+            }
+            match self.reserved.compare_exchange_weak(
+                reserved,
+                reserved + bytes,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Some(Reservation { budget: self, bytes }),
+                Err(current) => reserved = current,
+            }
+        }
+    }
+
+    // Counts `bytes` against the cap until mining ends.
+    pub fn retain(&self, bytes: usize) {
+        self.retained.fetch_add(bytes, Ordering::SeqCst);
+    }
+}
+
+// Distinguishes the spill files of concurrent runs in the same process.
+static NEXT_SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct Writer {
+    output: Option<BufWriter<File>>,
+    // The items spilled, in order.
+    items: Vec<u32>,
+}
+
+// Pattern bases set aside to grow later. The file is only created once
+// something is spilled, and is removed when this is dropped.
+pub struct SpillFile {
+    path: PathBuf,
+    writer: Mutex<Writer>,
+}
+
+impl Default for SpillFile {
+    fn default() -> SpillFile {
+        SpillFile::new()
+    }
+}
+
+impl SpillFile {
+    pub fn new() -> SpillFile {
+        let id = NEXT_SPILL_ID.fetch_add(1, Ordering::SeqCst);
+        SpillFile {
+            path: env::temp_dir().join(format!("riptree-spill-{}-{}.bin", process::id(), id)),
+            writer: Mutex::new(Writer { output: None, items: vec![] }),
+        }
+    }
+
+    // The number of items spilled.
+    pub fn len(&self) -> usize {
+        self.writer.lock().unwrap().items.len()
+    }
+
+    // The items spilled, in the order they were written, so they can still
+    // be grown if the file can't be read back.
+    pub fn items(&self) -> Vec<u32> {
+        self.writer.lock().unwrap().items.clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn write(&self, item: u32, pattern_base: &PatternBase) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.output.is_none() {
+            writer.output = Some(BufWriter::new(File::create(&self.path)?));
+        }
+        {
+            let output = writer.output.as_mut().unwrap();
+            write_u32(output, item)?;
+            write_u32(output, pattern_base.len() as u32)?;
+            for &(ref path, count) in pattern_base.iter() {
+                write_u32(output, count)?;
+                write_u32(output, path.len() as u32)?;
+                for &item in path.iter() {
+                    write_u32(output, item)?;
+                }
+            }
+        }
+        writer.items.push(item);
+        Ok(())
+    }
+
+    // Returns the spilled items and their pattern bases, read one at a time.
+    pub fn read(&self) -> io::Result<SpillReader> {
+        let mut writer = self.writer.lock().unwrap();
+        let remaining = writer.items.len();
+        let input = match writer.output {
+            Some(ref mut output) => {
+                output.flush()?;
+                Some(BufReader::new(File::open(&self.path)?))
+            }
+            None => None,
+        };
+        Ok(SpillReader { input, remaining })
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let writer = self.writer.get_mut().unwrap();
+        if writer.output.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub struct SpillReader {
+    input: Option<BufReader<File>>,
+    remaining: usize,
+}
+
+impl SpillReader {
+    fn read_entry(&mut self) -> io::Result<(u32, PatternBase)> {
+        let input = self.input.as_mut().unwrap();
+        let item = read_u32(input)?;
+        let num_paths = read_u32(input)?;
+        let mut pattern_base = Vec::with_capacity(num_paths as usize);
+        for _ in 0..num_paths {
+            let count = read_u32(input)?;
+            let len = read_u32(input)?;
+            let path = (0..len).map(|_| read_u32(input)).collect::<io::Result<Vec<u32>>>()?;
+            pattern_base.push((path, count));
+        }
+        Ok((item, pattern_base))
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = io::Result<(u32, PatternBase)>;
+
+    fn next(&mut self) -> Option<io::Result<(u32, PatternBase)>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.read_entry())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_spill() {
+        use super::{MemoryBudget, SpillFile};
+        use std::fs;
+
+        let budget = MemoryBudget::new(100);
+        {
+            let first = budget.try_reserve(60).unwrap();
+            assert!(budget.try_reserve(50).is_none());
+            let second = budget.try_reserve(40).unwrap();
+            assert_eq!(budget.in_use(), 100);
+            drop(first);
+            drop(second);
+        }
+        assert_eq!(budget.in_use(), 0);
+        // With nothing reserved, anything fits, so growth can proceed.
+        budget.retain(80);
+        assert!(budget.try_reserve(500).is_some());
+        assert_eq!(budget.in_use(), 80);
+
+        let spill = SpillFile::new();
+        assert!(spill.is_empty());
+        assert_eq!(spill.read().unwrap().count(), 0);
+        spill.write(3, &vec![(vec![1, 2], 5), (vec![], 1)]).unwrap();
+        spill.write(4, &vec![]).unwrap();
+        assert_eq!(spill.len(), 2);
+        let entries: Vec<_> = spill.read().unwrap().map(|entry| entry.unwrap()).collect();
+        assert_eq!(entries, vec![(3, vec![(vec![1, 2], 5), (vec![], 1)]), (4, vec![])]);
+
+        let path = spill.path.clone();
+        assert!(fs::metadata(&path).is_ok());
+        drop(spill);
+        assert!(fs::metadata(&path).is_err());
+    }
+}
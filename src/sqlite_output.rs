@@ -0,0 +1,203 @@
+// Writes the results of a run to an SQLite database, for --output-sqlite, so
+// they can be queried with SQL, e.g. joining rules to their items, or
+// filtering by lift, without loading a giant CSV into something first.
+//
+// Rules and itemsets refer to their items through rule_items and
+// itemset_items. Rules also have their antecedent and consequent as text, as
+// in the CSV output, for reading at a glance. The metadata table has the size
+// of the dataset, and the parameters table the options of the run, as in
+// --report.
+
+use command_line_args::Arguments;
+use config::config_table;
+use fptree::ItemSet;
+use miner::MiningResult;
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::fs;
+use std::io;
+use toml;
+
+const SCHEMA: &str = "
+    CREATE TABLE metadata (key TEXT PRIMARY KEY, value);
+    CREATE TABLE parameters (name TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+    CREATE TABLE itemsets (
+        id INTEGER PRIMARY KEY,
+        size INTEGER NOT NULL,
+        count INTEGER NOT NULL,
+        support REAL NOT NULL
+    );
+    CREATE TABLE itemset_items (
+        itemset_id INTEGER NOT NULL REFERENCES itemsets(id),
+        item_id INTEGER NOT NULL REFERENCES items(id)
+    );
+    CREATE TABLE rules (
+        id INTEGER PRIMARY KEY,
+        antecedent TEXT NOT NULL,
+        consequent TEXT NOT NULL,
+        confidence REAL NOT NULL,
+        lift REAL NOT NULL,
+        support REAL NOT NULL,
+        p_value REAL,
+        odds_ratio REAL
+    );
+    CREATE TABLE rule_items (
+        rule_id INTEGER NOT NULL REFERENCES rules(id),
+        item_id INTEGER NOT NULL REFERENCES items(id),
+        side TEXT NOT NULL CHECK (side IN ('antecedent', 'consequent'))
+    );
+    CREATE INDEX itemset_items_item ON itemset_items(item_id);
+    CREATE INDEX rule_items_item ON rule_items(item_id);
+";
+
+// Writes `result` to a new database at `path`, replacing any existing one.
+pub fn write_sqlite(path: &str, result: &MiningResult, args: &Arguments) -> Result<(), Box<dyn Error>> {
+    if let Err(err) = fs::remove_file(path) {
+        if err.kind() != io::ErrorKind::NotFound {
+            return Err(format!("Can't replace {}: {}", path, err).into());
+        }
+    }
+    let mut connection = Connection::open(path)?;
+    // Everything is written in one transaction, which is much faster than
+    // committing each row.
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    write_metadata(&transaction, result, args)?;
+
+    {
+        let mut insert = transaction.prepare("INSERT INTO items (id, name) VALUES (?1, ?2)")?;
+        for id in 1..=result.itemizer.max_item_id() {
+            insert.execute(params![id, result.itemizer.str_of(id)])?;
+        }
+    }
+
+    write_itemsets(&transaction, &result.itemsets, result.report.num_transactions)?;
+
+    {
+        let mut insert_rule = transaction.prepare(
+            "INSERT INTO rules (id, antecedent, consequent, confidence, lift, support, p_value, odds_ratio) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        let mut insert_item = transaction.prepare("INSERT INTO rule_items (rule_id, item_id, side) VALUES (?1, ?2, ?3)")?;
+        for (id, rule) in result.rules.iter().enumerate() {
+            let id = id as i64 + 1;
+            insert_rule.execute(params![
+                id,
+                rule.antecedent_to_string(&result.itemizer),
+                rule.consequent_to_string(&result.itemizer),
+                rule.confidence(),
+                rule.lift(),
+                rule.support(),
+                rule.p_value(),
+                rule.odds_ratio().map(|odds_ratio| odds_ratio.ratio()),
+            ])?;
+            for item in rule.antecedent() {
+                insert_item.execute(params![id, item, "antecedent"])?;
+            }
+            for item in rule.consequent() {
+                insert_item.execute(params![id, item, "consequent"])?;
+            }
+        }
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
+fn write_metadata(connection: &Connection, result: &MiningResult, args: &Arguments) -> rusqlite::Result<()> {
+    let report = &result.report;
+    let mut insert = connection.prepare("INSERT INTO metadata (key, value) VALUES (?1, ?2)")?;
+    insert.execute(params!["transactions", report.num_transactions as i64])?;
+    insert.execute(params!["distinct_transactions", report.num_distinct_transactions as i64])?;
+    insert.execute(params!["items", report.num_items as i64])?;
+    insert.execute(params!["rare_items", report.num_rare_items as i64])?;
+    insert.execute(params!["rare_item_cutoff", report.rare_item_cutoff])?;
+    insert.execute(params!["itemsets", report.num_itemsets as i64])?;
+    insert.execute(params!["rules", result.rules.len() as i64])?;
+    insert.execute(params!["partial", report.partial])?;
+
+    let mut insert = connection.prepare("INSERT INTO parameters (name, value) VALUES (?1, ?2)")?;
+    for (name, value) in config_table(args) {
+        let value = match value {
+            toml::Value::String(s) => s,
+            value => value.to_string(),
+        };
+        insert.execute(params![name, value])?;
+    }
+    Ok(())
+}
+
+fn write_itemsets(connection: &Connection, itemsets: &[ItemSet], num_transactions: usize) -> rusqlite::Result<()> {
+    let mut insert_itemset =
+        connection.prepare("INSERT INTO itemsets (id, size, count, support) VALUES (?1, ?2, ?3, ?4)")?;
+    let mut insert_item = connection.prepare("INSERT INTO itemset_items (itemset_id, item_id) VALUES (?1, ?2)")?;
+    for (id, itemset) in itemsets.iter().enumerate() {
+        let id = id as i64 + 1;
+        let support = itemset.count as f64 / num_transactions as f64;
+        insert_itemset.execute(params![id, itemset.items.len() as i64, itemset.count, support])?;
+        for item in &itemset.items {
+            insert_item.execute(params![id, item])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_write_sqlite() {
+        use super::write_sqlite;
+        use command_line_args::{Arguments, MaxSupportMode};
+        use miner::mine;
+        use rusqlite::Connection;
+        use std::env;
+        use std::fs;
+
+        let dir = env::temp_dir().join(format!("riptree-sqlite-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rules.db").to_string_lossy().into_owned();
+        let args = Arguments {
+            input_file_paths: vec![String::from("datasets/UCI-zoo.csv")],
+            output_sqlite_path: path.clone(),
+            max_support_mode: MaxSupportMode::Pareto,
+            min_confidence: 0.9,
+            min_lift: 5.0,
+            ..Arguments::default()
+        };
+        let result = mine(&args).unwrap();
+        assert!(!result.rules.is_empty() && !result.itemsets.is_empty());
+        // Writing twice replaces the first database.
+        write_sqlite(&path, &result, &args).unwrap();
+        write_sqlite(&path, &result, &args).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let count = |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM rules"), result.rules.len() as i64);
+        assert_eq!(count("SELECT COUNT(*) FROM itemsets"), result.itemsets.len() as i64);
+        assert_eq!(count("SELECT value FROM metadata WHERE key = 'transactions'"), 101);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM rule_items WHERE side = 'consequent'"),
+            result.rules.iter().map(|rule| rule.consequent().len() as i64).sum::<i64>()
+        );
+
+        // The first rule's antecedent, rebuilt from its items.
+        let rule = &result.rules[0];
+        let antecedent: String = connection
+            .query_row(
+                "SELECT group_concat(name, ' ') FROM \
+                 (SELECT items.name FROM rule_items JOIN items ON items.id = rule_items.item_id \
+                 WHERE rule_id = 1 AND side = 'antecedent' ORDER BY items.id)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(antecedent, rule.antecedent_to_string(&result.itemizer));
+        let min_confidence: String = connection
+            .query_row("SELECT value FROM parameters WHERE name = 'min-confidence'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(min_confidence, "0.9");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
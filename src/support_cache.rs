@@ -0,0 +1,85 @@
+// Memoizes the counts of itemsets in an Index. Filtering rules counts each
+// rule's antecedent and the union of its antecedent and consequent, and the
+// same itemsets recur across many rules, e.g. as the antecedent of rules with
+// different consequents, so caching saves intersecting their tid lists again.
+// The cache is split into shards, each behind its own lock, so threads
+// filtering rules in parallel rarely wait on each other.
+
+use index::Index;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const NUM_SHARDS: usize = 64;
+
+pub struct SupportCache<'a> {
+    index: &'a Index,
+    // Counts keyed by sorted item ids.
+    shards: Vec<Mutex<HashMap<Vec<u32>, usize>>>,
+    hits: AtomicUsize,
+}
+
+impl<'a> SupportCache<'a> {
+    pub fn new(index: &'a Index) -> SupportCache<'a> {
+        SupportCache {
+            index,
+            shards: (0..NUM_SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    // Returns the same as Index::count().
+    pub fn count(&self, items: &[u32]) -> usize {
+        // The index stores the counts of single items already.
+        if items.len() < 2 {
+            return self.index.count(items);
+        }
+        let mut key = items.to_vec();
+        key.sort();
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let shard = &self.shards[hasher.finish() as usize % NUM_SHARDS];
+
+        if let Some(&count) = shard.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return count;
+        }
+        // Counted without holding the lock, so other threads using the shard
+        // aren't held up.
+        let count = self.index.count(&key);
+        shard.lock().unwrap().insert(key, count);
+        count
+    }
+
+    // The number of itemsets counted, and the number of lookups which found
+    // them already counted.
+    pub fn stats(&self) -> (usize, usize) {
+        let len = self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum();
+        (len, self.hits.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_support_cache() {
+        use super::SupportCache;
+        use index::Index;
+
+        let mut index = Index::new();
+        index.insert(&[1, 2, 3], 2);
+        index.insert(&[1, 2], 1);
+        index.insert(&[2, 3], 1);
+
+        let cache = SupportCache::new(&index);
+        assert_eq!(cache.count(&[1]), 3);
+        assert_eq!(cache.count(&[1, 2]), 3);
+        assert_eq!(cache.count(&[2, 1]), 3);
+        assert_eq!(cache.count(&[3, 2, 1]), 2);
+        assert_eq!(cache.count(&[4, 1]), 0);
+        // Single items aren't cached, and [2, 1] was found as [1, 2].
+        assert_eq!(cache.stats(), (3, 1));
+    }
+}
@@ -0,0 +1,105 @@
+// A hierarchy of item categories, for mining generalized rules such as
+// "dairy ==> rare-item" as well as rules between the items themselves.
+//
+// Taxonomy files have one chain of categories per line, from most to least
+// specific, e.g. "skim milk,milk,dairy". A line of just "item,parent" works
+// too. Each item can have only one parent.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+pub struct Taxonomy {
+    parents: HashMap<String, String>,
+}
+
+impl Taxonomy {
+    pub fn from_file(path: &str) -> io::Result<Taxonomy> {
+        Taxonomy::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Taxonomy> {
+        let mut parents: HashMap<String, String> = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            let chain: Vec<&str> = line
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .collect();
+            for pair in chain.windows(2) {
+                let (child, parent) = (pair[0], pair[1]);
+                if let Some(existing) = parents.get(child) {
+                    if existing != parent {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "Item '{}' has two parents in the taxonomy, '{}' and '{}'",
+                                child, existing, parent
+                            ),
+                        ));
+                    }
+                }
+                parents.insert(String::from(child), String::from(parent));
+            }
+        }
+        Ok(Taxonomy { parents })
+    }
+
+    // Returns the item's ancestors, starting with its parent.
+    pub fn ancestors(&self, item: &str) -> Vec<&str> {
+        let mut ancestors: Vec<&str> = vec![];
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut item = item;
+        while let Some(parent) = self.parents.get(item) {
+            // Stop at cycles rather than looping forever.
+            if !seen.insert(parent) {
+                break;
+            }
+            ancestors.push(parent);
+            item = parent;
+        }
+        ancestors
+    }
+
+    // Returns the item's ancestor `level` steps down from the top of the
+    // hierarchy, where level 0 is the most general categories. Items already
+    // at or above that level are returned unchanged.
+    pub fn ancestor_at_level<'a>(&'a self, item: &'a str, level: usize) -> &'a str {
+        let ancestors = self.ancestors(item);
+        if ancestors.len() <= level {
+            return item;
+        }
+        ancestors[ancestors.len() - 1 - level]
+    }
+
+    pub fn is_ancestor(&self, ancestor: &str, item: &str) -> bool {
+        self.ancestors(item).contains(&ancestor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_taxonomy() {
+        use super::Taxonomy;
+
+        let taxonomy = Taxonomy::read("skim milk,milk,dairy\nbrie,cheese\ncheese,dairy\n".as_bytes())
+            .unwrap();
+        assert_eq!(taxonomy.ancestors("skim milk"), vec!["milk", "dairy"]);
+        assert_eq!(taxonomy.ancestors("brie"), vec!["cheese", "dairy"]);
+        assert!(taxonomy.ancestors("dairy").is_empty());
+        assert!(taxonomy.ancestors("bread").is_empty());
+
+        assert_eq!(taxonomy.ancestor_at_level("skim milk", 0), "dairy");
+        assert_eq!(taxonomy.ancestor_at_level("skim milk", 1), "milk");
+        assert_eq!(taxonomy.ancestor_at_level("skim milk", 2), "skim milk");
+        assert_eq!(taxonomy.ancestor_at_level("dairy", 1), "dairy");
+        assert_eq!(taxonomy.ancestor_at_level("bread", 0), "bread");
+
+        assert!(taxonomy.is_ancestor("dairy", "brie"));
+        assert!(!taxonomy.is_ancestor("milk", "brie"));
+
+        assert!(Taxonomy::read("brie,cheese\nbrie,dairy\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+// Top-K mining finds the K itemsets with the highest counts, so there's no
+// need to guess a threshold. Only itemsets with at least two rare items count
+// towards K, since rules are only made from itemsets whose antecedent, the
+// itemset without the rare consequent, was mined too, and so contains a rare
+// item. While mining, the count of the Kth best itemset found so far is a
+// lower bound on the final Kth count, and since extending an itemset never
+// increases its count, itemsets below it can't lead to any of the top K and
+// needn't be grown further.
+
+use fptree::ItemSet;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+// Returns true if the itemset counts towards the K.
+fn can_make_rules(items: &[u32], rare_items: &HashSet<u32>) -> bool {
+    items.iter().filter(|item| rare_items.contains(item)).count() > 1
+}
+
+pub struct TopK {
+    k: usize,
+    rare_items: HashSet<u32>,
+    // The counts of the K best itemsets found so far, smallest on top.
+    counts: Mutex<BinaryHeap<Reverse<u32>>>,
+    min_count: AtomicU32,
+}
+
+impl TopK {
+    pub fn new(k: usize, rare_items: &HashSet<u32>) -> TopK {
+        TopK {
+            k,
+            rare_items: rare_items.clone(),
+            counts: Mutex::new(BinaryHeap::with_capacity(k + 1)),
+            min_count: AtomicU32::new(0),
+        }
+    }
+
+    // Records an itemset found, raising the threshold once K itemsets have
+    // been found.
+    pub fn insert(&self, items: &[u32], count: u32) {
+        if !can_make_rules(items, &self.rare_items) {
+            return;
+        }
+        let mut counts = self.counts.lock().unwrap();
+        counts.push(Reverse(count));
+        if counts.len() > self.k {
+            counts.pop();
+        }
+        if counts.len() == self.k {
+            if let Some(&Reverse(min_count)) = counts.peek() {
+                self.min_count.store(min_count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Itemsets with counts below this can't be in the top K.
+    pub fn min_count(&self) -> u32 {
+        self.min_count.load(Ordering::Relaxed)
+    }
+}
+
+// Returns the K itemsets with at least two rare items with the highest
+// counts. Itemsets tied with the Kth are kept too, so the result doesn't
+// depend on the order itemsets are found. Other itemsets with at least the
+// Kth count are kept, as they may be the antecedents of rules.
+pub fn select_top_k(
+    mut itemsets: Vec<ItemSet>,
+    k: usize,
+    rare_items: &HashSet<u32>,
+) -> Vec<ItemSet> {
+    let mut counts: Vec<u32> = itemsets
+        .iter()
+        .filter(|itemset| can_make_rules(&itemset.items, rare_items))
+        .map(|itemset| itemset.count)
+        .collect();
+    if counts.len() <= k {
+        return itemsets;
+    }
+    counts.sort_by(|a, b| b.cmp(a));
+    let min_count = counts[k - 1];
+    itemsets.retain(|itemset| itemset.count >= min_count);
+    itemsets
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_top_k() {
+        use super::{select_top_k, TopK};
+        use fptree::ItemSet;
+        use std::collections::HashSet;
+
+        let rare_items: HashSet<u32> = [1, 2, 3].iter().cloned().collect();
+        let top_k = TopK::new(2, &rare_items);
+        top_k.insert(&[1, 2], 5);
+        assert_eq!(top_k.min_count(), 0);
+        top_k.insert(&[1, 4], 6);
+        assert_eq!(top_k.min_count(), 0);
+        top_k.insert(&[2, 3], 3);
+        assert_eq!(top_k.min_count(), 3);
+        top_k.insert(&[1, 2, 3], 4);
+        assert_eq!(top_k.min_count(), 4);
+        top_k.insert(&[1, 3], 1);
+        assert_eq!(top_k.min_count(), 4);
+
+        let itemsets = vec![
+            ItemSet::new(vec![1], 5),
+            ItemSet::new(vec![2], 4),
+            ItemSet::new(vec![3], 2),
+            ItemSet::new(vec![1, 4], 5),
+            ItemSet::new(vec![1, 2], 4),
+            ItemSet::new(vec![1, 3], 2),
+            ItemSet::new(vec![2, 3], 2),
+            ItemSet::new(vec![1, 2, 3], 1),
+        ];
+        let selected = select_top_k(itemsets.clone(), 1, &rare_items);
+        assert_eq!(selected.len(), 4);
+        assert!(selected.iter().all(|itemset| itemset.count >= 4));
+        // Ties with the Kth are kept.
+        assert_eq!(select_top_k(itemsets.clone(), 2, &rare_items).len(), 7);
+        assert_eq!(select_top_k(itemsets, 10, &rare_items).len(), 8);
+    }
+}
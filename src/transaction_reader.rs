@@ -1,43 +1,667 @@
-use std::fs::File;
-use std::io::BufReader;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
 use std::io::prelude::*;
 use itemizer::Itemizer;
+use arff::ArffReader;
+use drift::parse_timestamp;
+use command_line_args::{BadLinePolicy, ClassColumn, Encoding, InputFormat, TimestampColumn, WeightColumn};
+#[cfg(feature = "parquet")]
+use parquet_input::Baskets;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashSet;
+use std::ops::Range;
+use std::str;
 
+// A transaction's items, and the number of transactions it counts as.
+#[derive(Clone)]
+pub struct Transaction {
+    pub items: Vec<u32>,
+    pub weight: u32,
+}
+
+// Seed for selecting lines at random. Mining reads the dataset twice, and
+// both passes must see the same lines, as must the pass which reads the
+// lines held out from mining, so the seed is fixed.
+const LINE_SEED: [u32; 4] = [0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb];
+
+// How to read the transactions in input files.
+#[derive(Clone, Default)]
+pub struct InputOptions {
+    // If not given, each file's format is found from its name.
+    pub format: Option<InputFormat>,
+    // The column of CSV and FIMI files holding each transaction's weight, if
+    // any.
+    pub weight_column: Option<WeightColumn>,
+    // The column of CSV and FIMI files holding each transaction's timestamp,
+    // if any, which is taken off before the weight column.
+    pub timestamp_column: Option<TimestampColumn>,
+    // The column of CSV and FIMI files holding each transaction's class, if
+    // any, which is taken off after the timestamp and before the weight. If
+    // class is given, only the transactions of that class are read.
+    pub class_column: Option<ClassColumn>,
+    pub class: Option<String>,
+    // The column of Parquet files holding each transaction's items.
+    pub items_column: String,
+    // CSV files have a header row of column names, and each cell is an item
+    // "column=value". Cells which are empty or in null_values are missing;
+    // they're dropped, or if missing_value isn't empty, have it as their
+    // value.
+    pub tabular: bool,
+    pub null_values: Vec<String>,
+    pub missing_value: String,
+    pub encoding: Encoding,
+    pub on_bad_line: BadLinePolicy,
+}
+
+impl InputOptions {
+    // Returns the item for a cell of tabular input, if it isn't dropped.
+    fn tabular_item(&self, column: &str, value: &str) -> Option<String> {
+        let value = value.trim();
+        if !value.is_empty() && !self.null_values.iter().any(|null| null == value) {
+            Some(format!("{}={}", column, value))
+        } else if !self.missing_value.is_empty() {
+            Some(format!("{}={}", column, self.missing_value))
+        } else {
+            None
+        }
+    }
+}
+
+// An open input file. CSV and FIMI files are read a line at a time, and
+// split on the separator; other formats are read a transaction at a time.
+enum Input {
+    Delimited(BufReader<File>, Separator),
+    Arff(ArffReader),
+    #[cfg(feature = "parquet")]
+    Parquet(Baskets),
+}
+
+#[derive(Clone, Copy)]
+enum Separator {
+    Comma,
+    Whitespace,
+}
+
+// What a pass over the input files read: the weight of the transactions
+// read from each file, and the number of bad lines skipped.
+#[derive(Default)]
+pub struct ReadCounts {
+    files: Vec<usize>,
+    bad_lines: usize,
+}
+
+// Reads the transactions in one or more files, one after the other, as if
+// they were concatenated.
 pub struct TransactionReader<'a> {
-    reader: BufReader<File>,
+    paths: &'a [String],
+    // The file being read, which is paths[next_path - 1], and the number of
+    // the line last read from it.
+    input: Option<Input>,
+    next_path: usize,
+    line_number: usize,
     itemizer: &'a mut Itemizer,
+    options: &'a InputOptions,
+    // The column names of the file being read, if it's tabular, less the
+    // timestamp and weight columns.
+    header: Vec<String>,
+    // If selecting lines at random, a number in [0,1) is drawn for each line,
+    // and the line is read if the number is in the range.
+    line_selector: Option<(Range<f64>, XorShiftRng)>,
+    // If given, the weight of the transactions read from each file is added
+    // to its entry, and bad lines skipped are counted.
+    counts: Option<&'a RefCell<ReadCounts>>,
+    // The timestamp of the last transaction read, in seconds if it was a
+    // date, and whether any timestamp read was a date.
+    timestamp: Option<f64>,
+    dated: bool,
+    // The class of the last transaction read.
+    class: Option<String>,
 }
 
 impl<'a> TransactionReader<'a> {
-    pub fn new(path: &str, itemizer: &'a mut Itemizer) -> TransactionReader<'a> {
-        let file = File::open(path).unwrap();
-        let reader = BufReader::new(file);
+    pub fn new(
+        paths: &'a [String],
+        itemizer: &'a mut Itemizer,
+        options: &'a InputOptions,
+        line_range: Option<Range<f64>>,
+        counts: Option<&'a RefCell<ReadCounts>>,
+    ) -> TransactionReader<'a> {
         TransactionReader {
-            reader: reader,
+            paths,
+            input: None,
+            next_path: 0,
+            line_number: 0,
             itemizer,
+            options,
+            header: vec![],
+            line_selector: line_range.map(|range| (range, XorShiftRng::from_seed(LINE_SEED))),
+            counts,
+            timestamp: None,
+            dated: false,
+            class: None,
+        }
+    }
+
+    // The timestamp of the transaction last returned, with
+    // InputOptions::timestamp_column.
+    pub fn timestamp(&self) -> Option<f64> {
+        self.timestamp
+    }
+
+    // Whether any of the timestamps read were dates, rather than numbers.
+    pub fn timestamps_are_dates(&self) -> bool {
+        self.dated
+    }
+
+    // The class of the transaction last returned, with
+    // InputOptions::class_column.
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    // Opens the next file, returning false if there are none left.
+    fn open_next(&mut self) -> io::Result<bool> {
+        let path = match self.paths.get(self.next_path) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        self.next_path += 1;
+        self.line_number = 0;
+        let format = self.options.format.unwrap_or_else(|| InputFormat::of_path(path));
+        let has_weights = format == InputFormat::Csv || format == InputFormat::Fimi;
+        if self.options.weight_column.is_some() && !has_weights {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't read {}: --weight-column only applies to CSV and FIMI input", path),
+            ));
+        }
+        if self.options.timestamp_column.is_some() && !has_weights {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't read {}: --timestamp-column only applies to CSV and FIMI input", path),
+            ));
+        }
+        if self.options.class_column.is_some() && !has_weights {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't read {}: --class-column only applies to CSV and FIMI input", path),
+            ));
+        }
+        if self.options.tabular && format != InputFormat::Csv {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't read {}: --tabular only applies to CSV input", path),
+            ));
         }
+        let open = |path: &str| {
+            File::open(path)
+                .map(BufReader::new)
+                .map_err(|err| io::Error::new(err.kind(), format!("Can't open {}: {}", path, err)))
+        };
+        self.input = Some(match format {
+            InputFormat::Csv if self.options.tabular => {
+                let mut reader = open(path)?;
+                self.header = self.read_header(&mut reader)?;
+                self.line_number = 1;
+                Input::Delimited(reader, Separator::Comma)
+            }
+            InputFormat::Csv => Input::Delimited(open(path)?, Separator::Comma),
+            InputFormat::Fimi => Input::Delimited(open(path)?, Separator::Whitespace),
+            InputFormat::Arff => Input::Arff(ArffReader::open(path)?),
+            InputFormat::Parquet => self.open_parquet(path)?,
+        });
+        Ok(true)
+    }
+
+    // Reads the column names from the first line of a tabular CSV file.
+    fn read_header(&self, reader: &mut BufReader<File>) -> io::Result<Vec<String>> {
+        let mut bytes = vec![];
+        reader.read_until(b'\n', &mut bytes)?;
+        let line = decode_line(&bytes, self.options.encoding, true).map_err(|problem| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Bad header of {}: {}", self.paths[self.next_path - 1], problem),
+            )
+        })?;
+        let mut header: Vec<String> = line.split(',').map(|column| String::from(column.trim())).collect();
+        match self.options.timestamp_column {
+            None => {}
+            Some(TimestampColumn::First) => {
+                header.remove(0);
+            }
+            Some(TimestampColumn::Last) => {
+                header.pop();
+            }
+        }
+        match self.options.class_column {
+            None => {}
+            Some(ClassColumn::First) => {
+                header.remove(0);
+            }
+            Some(ClassColumn::Last) => {
+                header.pop();
+            }
+        }
+        match self.options.weight_column {
+            None => {}
+            Some(WeightColumn::First) => {
+                header.remove(0);
+            }
+            Some(WeightColumn::Last) => {
+                header.pop();
+            }
+        }
+        Ok(header)
+    }
+
+    // Handles a line of the file being read which can't be parsed, as
+    // InputOptions::on_bad_line says. Returns the error to stop reading with,
+    // or None if the line is skipped.
+    fn bad_line(&mut self, message: &str) -> Option<io::Error> {
+        let message = format!(
+            "Bad line {} of {}: {}",
+            self.line_number,
+            self.paths[self.next_path - 1],
+            message
+        );
+        match self.options.on_bad_line {
+            BadLinePolicy::Error => return Some(io::Error::new(io::ErrorKind::InvalidData, message)),
+            BadLinePolicy::Skip => {}
+            BadLinePolicy::Log => warn!("{}; skipping it.", message),
+        }
+        if let Some(counts) = self.counts {
+            counts.borrow_mut().bad_lines += 1;
+        }
+        None
+    }
+
+    #[cfg(feature = "parquet")]
+    fn open_parquet(&self, path: &str) -> io::Result<Input> {
+        Ok(Input::Parquet(Baskets::open(path, &self.options.items_column)?))
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn open_parquet(&self, path: &str) -> io::Result<Input> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Can't read {}: Parquet input needs riptree built with the 'parquet' feature",
+                path
+            ),
+        ))
+    }
+}
+
+// Converts item names to ids, removing duplicate, stop and excluded items, and
+// normalizing, aliasing, discretizing and generalizing items if there's a
+// normalizer, aliases, discretizer or taxonomy.
+pub fn itemize<'s, I>(items: I, itemizer: &mut Itemizer) -> io::Result<Vec<u32>>
+where
+    I: Iterator<Item = &'s str>,
+{
+    let mut ids: HashSet<u32> = HashSet::new();
+    for item in items.map(|s| s.trim()) {
+        let item = itemizer.alias(itemizer.normalize(item));
+        if itemizer.stop(&item) {
+            continue;
+        }
+        let item = itemizer.discretize(&item);
+        let item: &str = &item;
+        if itemizer.taxonomy().is_some() {
+            for item in itemizer.generalize(item) {
+                if !itemizer.is_excluded(&item) {
+                    ids.insert(itemizer.try_id_of(&item)?);
+                }
+            }
+        } else if !itemizer.is_excluded(item) {
+            ids.insert(itemizer.try_id_of(item)?);
+        }
+    }
+    Ok(ids.into_iter().collect())
+}
+
+// Decodes a line of CSV or FIMI input, without its line ending, and if it's
+// the first line of a file, without any byte order mark, which Excel writes
+// at the start of CSV files. Returns the problem if the line is bad.
+fn decode_line(bytes: &[u8], encoding: Encoding, first_line: bool) -> Result<Cow<'_, str>, &'static str> {
+    let mut bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    if first_line {
+        bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
     }
+    let line = match encoding {
+        Encoding::Utf8 => Cow::Borrowed(str::from_utf8(bytes).map_err(|_| "it isn't valid UTF-8")?),
+        Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&byte| byte as char).collect()),
+    };
+    if line.contains('\0') {
+        return Err("it contains a NUL character");
+    }
+    Ok(line)
+}
+
+fn parse_weight(s: &str) -> io::Result<u32> {
+    s.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid transaction weight '{}'", s.trim()),
+        )
+    })
 }
 
 impl<'a> Iterator for TransactionReader<'a> {
-    type Item = Vec<u32>;
-    fn next(&mut self) -> Option<Vec<u32>> {
-        let mut line = String::new();
+    type Item = io::Result<Transaction>;
+    fn next(&mut self) -> Option<io::Result<Transaction>> {
+        let mut bytes: Vec<u8> = vec![];
+        let mut basket: Vec<String> = vec![];
         loop {
-            let len = self.reader.read_line(&mut line).unwrap();
-            if len == 0 {
-                return None;
+            let input = match self.input {
+                Some(ref mut input) => input,
+                None => match self.open_next() {
+                    Ok(true) => continue,
+                    Ok(false) => return None,
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+            let separator = match *input {
+                Input::Delimited(ref mut reader, separator) => {
+                    bytes.clear();
+                    match reader.read_until(b'\n', &mut bytes) {
+                        Ok(0) => {
+                            self.input = None;
+                            continue;
+                        }
+                        Ok(_) => {}
+                        Err(err) => return Some(Err(err)),
+                    }
+                    self.line_number += 1;
+                    Some(separator)
+                }
+                Input::Arff(ref mut rows) => {
+                    basket = match rows.next() {
+                        None => {
+                            self.input = None;
+                            continue;
+                        }
+                        Some(Ok(basket)) => basket,
+                        Some(Err(err)) => return Some(Err(err)),
+                    };
+                    None
+                }
+                #[cfg(feature = "parquet")]
+                Input::Parquet(ref mut baskets) => {
+                    basket = match baskets.next() {
+                        None => {
+                            self.input = None;
+                            continue;
+                        }
+                        Some(Ok(basket)) => basket,
+                        Some(Err(err)) => return Some(Err(err)),
+                    };
+                    None
+                }
+            };
+            // Skip unselected lines before doing any parsing.
+            if let Some((ref range, ref mut rng)) = self.line_selector {
+                if !range.contains(&rng.next_f64()) {
+                    continue;
+                }
             }
-            let splits = line.split(",")
-                .map(|s| self.itemizer.id_of(s.trim()))
-                .collect::<HashSet<u32>>()
-                .iter()
-                .cloned()
-                .collect::<Vec<u32>>();
-            if splits.len() > 0 {
-                return Some(splits);
+            let line = match separator.map(|_| decode_line(&bytes, self.options.encoding, self.line_number == 1)) {
+                None => Cow::Borrowed(""),
+                Some(Ok(line)) => line,
+                Some(Err(problem)) => match self.bad_line(problem) {
+                    Some(err) => return Some(Err(err)),
+                    None => continue,
+                },
+            };
+            // Tables often end with a blank line, which has no cells.
+            if self.options.tabular && line.trim().is_empty() {
+                continue;
+            }
+            let mut fields: Vec<&str> = match separator {
+                Some(Separator::Comma) => line.split(',').collect(),
+                Some(Separator::Whitespace) => line.split_whitespace().collect(),
+                None => basket.iter().map(|item| item.as_str()).collect(),
+            };
+            if fields.is_empty() {
+                continue;
             }
+            let timestamp = match self.options.timestamp_column {
+                None => None,
+                Some(TimestampColumn::First) => Some(fields.remove(0)),
+                Some(TimestampColumn::Last) => fields.pop(),
+            };
+            if let Some(timestamp) = timestamp {
+                match parse_timestamp(timestamp) {
+                    Ok((seconds, dated)) => {
+                        self.timestamp = Some(seconds);
+                        self.dated |= dated;
+                    }
+                    Err(err) => match self.bad_line(&err.to_string()) {
+                        Some(err) => return Some(Err(err)),
+                        None => continue,
+                    },
+                }
+                if fields.is_empty() {
+                    continue;
+                }
+            }
+            let class = match self.options.class_column {
+                None => None,
+                Some(ClassColumn::First) => Some(fields.remove(0).trim()),
+                Some(ClassColumn::Last) => fields.pop().map(str::trim),
+            };
+            if let Some(class) = class {
+                if let Some(ref only) = self.options.class {
+                    if only != class {
+                        continue;
+                    }
+                }
+                self.class = Some(class.to_owned());
+                if fields.is_empty() {
+                    continue;
+                }
+            }
+            let weight = match self.options.weight_column {
+                None => Ok(1),
+                Some(WeightColumn::First) => parse_weight(fields.remove(0)),
+                Some(WeightColumn::Last) => parse_weight(fields.pop().unwrap()),
+            };
+            let weight = match weight {
+                Ok(weight) => weight,
+                Err(err) => match self.bad_line(&err.to_string()) {
+                    Some(err) => return Some(Err(err)),
+                    None => continue,
+                },
+            };
+            let items = if self.options.tabular {
+                if fields.len() != self.header.len() {
+                    let message = format!(
+                        "it has {} cells, but the header has {} columns",
+                        fields.len(),
+                        self.header.len()
+                    );
+                    match self.bad_line(&message) {
+                        Some(err) => return Some(Err(err)),
+                        None => continue,
+                    }
+                }
+                let cells: Vec<String> = self
+                    .header
+                    .iter()
+                    .zip(fields)
+                    .filter_map(|(column, value)| self.options.tabular_item(column, value))
+                    .collect();
+                itemize(cells.iter().map(|cell| cell.as_str()), self.itemizer)
+            } else {
+                itemize(fields.into_iter(), self.itemizer)
+            };
+            let items = match items {
+                Ok(items) => items,
+                Err(err) => return Some(Err(err)),
+            };
+            // Transactions with zero weight don't count towards anything.
+            if !items.is_empty() && weight > 0 {
+                if let Some(counts) = self.counts {
+                    counts.borrow_mut().files[self.next_path - 1] += weight as usize;
+                }
+                return Some(Ok(Transaction { items, weight }));
+            }
+        }
+    }
+}
+
+pub type Transactions<'a> = Box<dyn Iterator<Item = io::Result<Transaction>> + 'a>;
+
+// A dataset which can be iterated over more than once; mining makes one pass
+// to count item frequencies, and a second pass to build the tree.
+pub trait TransactionSource {
+    fn read<'a>(&'a self, itemizer: &'a mut Itemizer) -> Transactions<'a>;
+
+    // The files read by the last pass, and the weight of the transactions
+    // read from each. Empty if the source isn't files.
+    fn file_counts(&self) -> Vec<(String, usize)> {
+        vec![]
+    }
+
+    // The number of bad lines the last pass skipped, per
+    // InputOptions::on_bad_line.
+    fn num_bad_lines(&self) -> usize {
+        0
+    }
+}
+
+// Expands each directory in `paths` into the files in it, sorted by name and
+// skipping hidden files, so that e.g. a directory of one file per day is
+// read in order.
+pub fn expand_input_paths(paths: &[String]) -> io::Result<Vec<String>> {
+    let mut files = vec![];
+    for path in paths {
+        if !fs::metadata(path).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+            files.push(path.clone());
+            continue;
+        }
+        let mut entries = vec![];
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() && !entry.file_name().to_string_lossy().starts_with('.') {
+                entries.push(entry.path().to_string_lossy().into_owned());
+            }
+        }
+        entries.sort();
+        files.extend(entries);
+    }
+    Ok(files)
+}
+
+// CSV files with one transaction per line, optionally with a column of
+// transaction weights, or Parquet files with one per row, read as if
+// concatenated. If line_range is set, only a pseudo-random subset of the
+// lines are read; see TransactionReader. This is used for sampling, and for
+// splitting the dataset into lines to mine and lines to hold out.
+pub struct CsvFile {
+    pub paths: Vec<String>,
+    pub options: InputOptions,
+    pub line_range: Option<Range<f64>>,
+    counts: RefCell<ReadCounts>,
+}
+
+impl CsvFile {
+    pub fn new(
+        paths: Vec<String>,
+        options: InputOptions,
+        line_range: Option<Range<f64>>,
+    ) -> CsvFile {
+        CsvFile {
+            paths,
+            options,
+            line_range,
+            counts: RefCell::new(ReadCounts::default()),
         }
     }
 }
+
+impl TransactionSource for CsvFile {
+    fn read<'a>(&'a self, itemizer: &'a mut Itemizer) -> Transactions<'a> {
+        *self.counts.borrow_mut() = ReadCounts {
+            files: vec![0; self.paths.len()],
+            bad_lines: 0,
+        };
+        Box::new(TransactionReader::new(
+            &self.paths,
+            itemizer,
+            &self.options,
+            self.line_range.clone(),
+            Some(&self.counts),
+        ))
+    }
+
+    fn file_counts(&self) -> Vec<(String, usize)> {
+        self.paths
+            .iter()
+            .cloned()
+            .zip(self.counts.borrow().files.iter().cloned())
+            .collect()
+    }
+
+    fn num_bad_lines(&self) -> usize {
+        self.counts.borrow().bad_lines
+    }
+}
+
+// Transactions already in memory, for when there's no file system to read
+// from, such as in the browser.
+impl TransactionSource for [Vec<String>] {
+    fn read<'a>(&'a self, itemizer: &'a mut Itemizer) -> Transactions<'a> {
+        Box::new(
+            self.iter()
+                .map(move |items| itemize(items.iter().map(|s| s.as_str()), itemizer))
+                .filter(|items| items.as_ref().map_or(true, |items| !items.is_empty()))
+                .map(|items| items.map(|items| Transaction { items, weight: 1 })),
+        )
+    }
+}
+
+// Transactions already itemized, such as those saved by a previous run of
+// `riptree update`, followed by newly arrived transactions in a CSV file.
+pub struct AppendedCsvFile<'t> {
+    pub previous: &'t [Transaction],
+    pub appended: CsvFile,
+}
+
+impl<'t> TransactionSource for AppendedCsvFile<'t> {
+    fn read<'a>(&'a self, itemizer: &'a mut Itemizer) -> Transactions<'a> {
+        Box::new(
+            self.previous
+                .iter()
+                .map(|transaction| Ok(transaction.clone()))
+                .chain(self.appended.read(itemizer)),
+        )
+    }
+
+    fn file_counts(&self) -> Vec<(String, usize)> {
+        self.appended.file_counts()
+    }
+
+    fn num_bad_lines(&self) -> usize {
+        self.appended.num_bad_lines()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_decode_line() {
+        use super::decode_line;
+        use command_line_args::Encoding;
+
+        assert_eq!(decode_line(b"a,b\r\n", Encoding::Utf8, false).unwrap(), "a,b");
+        assert_eq!(decode_line(b"\xef\xbb\xbfa,b\n", Encoding::Utf8, true).unwrap(), "a,b");
+        assert_eq!(decode_line(b"caf\xc3\xa9", Encoding::Utf8, false).unwrap(), "caf\u{e9}");
+        assert_eq!(decode_line(b"caf\xe9\r\n", Encoding::Latin1, false).unwrap(), "caf\u{e9}");
+        assert_eq!(decode_line(b"caf\xe9", Encoding::Utf8, false).unwrap_err(), "it isn't valid UTF-8");
+        assert_eq!(decode_line(b"a,\0", Encoding::Latin1, false).unwrap_err(), "it contains a NUL character");
+    }
+}
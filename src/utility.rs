@@ -0,0 +1,319 @@
+// High-utility itemset mining finds itemsets worth a lot in total, such as
+// rare but expensive items bought together, rather than ones which are merely
+// frequent. Each item has a unit utility, e.g. its price or profit, given in
+// a utilities file with lines of "item,utility". Items in transactions may
+// have a quantity, written "item:quantity", which defaults to 1. An itemset's
+// utility is the sum, over the transactions containing it, of the quantity
+// times the unit utility of each of its items.
+//
+// Itemsets are mined with HUI-Miner (Liu and Qu, 2012), which keeps a utility
+// list for each itemset: its utility in each transaction containing it, and
+// the utility of the items which could still be added to it there. The sum
+// of both bounds the utility of every itemset grown from it, so growth stops
+// once that is below the minimum utility.
+
+use itemizer::Itemizer;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub struct UtilityTable {
+    utilities: HashMap<String, f64>,
+}
+
+impl UtilityTable {
+    pub fn from_file(path: &str) -> io::Result<UtilityTable> {
+        UtilityTable::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<UtilityTable> {
+        let mut utilities: HashMap<String, f64> = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.rsplitn(2, ',');
+            let utility = fields.next().unwrap().trim();
+            let item = match fields.next() {
+                Some(item) => item.trim(),
+                None => return Err(invalid_data(format!("Expected 'item,utility', got '{}'", line))),
+            };
+            let utility: f64 = utility
+                .parse()
+                .map_err(|_| invalid_data(format!("Invalid utility '{}'", utility)))?;
+            utilities.insert(String::from(item), utility);
+        }
+        Ok(UtilityTable { utilities })
+    }
+
+    pub fn utility_of(&self, item: &str) -> Option<f64> {
+        self.utilities.get(item).cloned()
+    }
+}
+
+// A transaction's items, with the utility of each in that transaction.
+pub type UtilityTransaction = Vec<(u32, f64)>;
+
+// Splits "item:quantity" into its parts. Cells without a quantity, or whose
+// quantity isn't a number, are an item bought once.
+fn parse_item(cell: &str) -> (&str, f64) {
+    if let Some(colon) = cell.rfind(':') {
+        if let Ok(quantity) = cell[colon + 1..].trim().parse() {
+            return (cell[..colon].trim(), quantity);
+        }
+    }
+    (cell, 1.0)
+}
+
+pub fn read_utility_transactions<R: BufRead>(
+    reader: R,
+    itemizer: &mut Itemizer,
+    table: &UtilityTable,
+) -> io::Result<Vec<UtilityTransaction>> {
+    let mut transactions: Vec<UtilityTransaction> = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        let mut utilities: HashMap<u32, f64> = HashMap::new();
+        for cell in line.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (item, quantity) = parse_item(cell);
+            let utility = match table.utility_of(item) {
+                Some(utility) => utility,
+                None => {
+                    return Err(invalid_data(format!(
+                        "Item '{}' isn't in the utilities file",
+                        item
+                    )))
+                }
+            };
+            *utilities.entry(itemizer.try_id_of(item)?).or_insert(0.0) += quantity * utility;
+        }
+        if !utilities.is_empty() {
+            transactions.push(utilities.into_iter().collect());
+        }
+    }
+    Ok(transactions)
+}
+
+pub struct HighUtilityItemset {
+    pub items: Vec<u32>,
+    pub utility: f64,
+    pub support: f64,
+}
+
+impl HighUtilityItemset {
+    pub fn to_string(&self, itemizer: &Itemizer) -> String {
+        let mut names: Vec<&str> = self.items.iter().map(|&id| itemizer.str_of(id)).collect();
+        names.sort();
+        names.join(" ")
+    }
+}
+
+struct Entry {
+    tid: usize,
+    // The itemset's utility in the transaction.
+    utility: f64,
+    // The utility of the items after the itemset's in the transaction.
+    remaining_utility: f64,
+}
+
+struct UtilityList {
+    item: u32,
+    entries: Vec<Entry>,
+}
+
+impl UtilityList {
+    fn utility(&self) -> f64 {
+        self.entries.iter().map(|entry| entry.utility).sum()
+    }
+
+    fn remaining_utility(&self) -> f64 {
+        self.entries.iter().map(|entry| entry.remaining_utility).sum()
+    }
+}
+
+// Returns the utility list of prefix+x+y, given those of prefix+x and
+// prefix+y. The prefix's utility is counted in both, so is subtracted once.
+fn join(prefix: Option<&UtilityList>, x: &UtilityList, y: &UtilityList) -> UtilityList {
+    let mut entries: Vec<Entry> = vec![];
+    let mut j = 0;
+    for x_entry in x.entries.iter() {
+        while j < y.entries.len() && y.entries[j].tid < x_entry.tid {
+            j += 1;
+        }
+        if j == y.entries.len() {
+            break;
+        }
+        let y_entry = &y.entries[j];
+        if y_entry.tid != x_entry.tid {
+            continue;
+        }
+        let prefix_utility = match prefix {
+            Some(prefix) => {
+                let k = prefix
+                    .entries
+                    .binary_search_by_key(&x_entry.tid, |entry| entry.tid)
+                    .unwrap();
+                prefix.entries[k].utility
+            }
+            None => 0.0,
+        };
+        entries.push(Entry {
+            tid: x_entry.tid,
+            utility: x_entry.utility + y_entry.utility - prefix_utility,
+            remaining_utility: y_entry.remaining_utility,
+        });
+    }
+    UtilityList { item: y.item, entries }
+}
+
+struct Search<'a> {
+    min_utility: f64,
+    rare_items: &'a HashSet<u32>,
+    num_transactions: usize,
+    itemsets: Vec<HighUtilityItemset>,
+}
+
+impl<'a> Search<'a> {
+    // Extends the itemset `prefix` with each of `lists`, which hold the
+    // utility lists of prefix plus each item which may be added to it, in
+    // the order items are added.
+    fn grow(&mut self, prefix: &[u32], prefix_list: Option<&UtilityList>, lists: &[UtilityList]) {
+        for (i, list) in lists.iter().enumerate() {
+            let mut itemset: Vec<u32> = Vec::from(prefix);
+            itemset.push(list.item);
+            let has_rare_item = itemset.iter().any(|item| self.rare_items.contains(item));
+
+            let utility = list.utility();
+            // Only sets of two or more items are co-purchases.
+            if utility >= self.min_utility && has_rare_item && itemset.len() > 1 {
+                self.itemsets.push(HighUtilityItemset {
+                    items: itemset.clone(),
+                    utility,
+                    support: list.entries.len() as f64 / self.num_transactions as f64,
+                });
+            }
+
+            let rest = &lists[i + 1..];
+            if utility + list.remaining_utility() < self.min_utility
+                || (!has_rare_item && !rest.iter().any(|l| self.rare_items.contains(&l.item)))
+            {
+                continue;
+            }
+            let extensions: Vec<UtilityList> = rest
+                .iter()
+                .map(|other| join(prefix_list, list, other))
+                .filter(|extension| !extension.entries.is_empty())
+                .collect();
+            self.grow(&itemset, Some(list), &extensions);
+        }
+    }
+}
+
+// Returns the itemsets of two or more items, containing at least one rare
+// item, whose utility is at least min_utility.
+pub fn hui_miner(
+    transactions: &[UtilityTransaction],
+    min_utility: f64,
+    rare_items: &HashSet<u32>,
+) -> Vec<HighUtilityItemset> {
+    // An item's transaction weighted utility, the total utility of the
+    // transactions containing it, bounds the utility of any itemset
+    // containing it, so items below the minimum can be dropped.
+    let mut twu: HashMap<u32, f64> = HashMap::new();
+    for transaction in transactions {
+        let transaction_utility: f64 = transaction.iter().map(|&(_, utility)| utility).sum();
+        for &(item, _) in transaction {
+            *twu.entry(item).or_insert(0.0) += transaction_utility;
+        }
+    }
+    // Items are added to itemsets in increasing order of TWU, which keeps
+    // the utility lists short.
+    let mut items: Vec<u32> = twu
+        .iter()
+        .filter(|&(_, &twu)| twu >= min_utility)
+        .map(|(&item, _)| item)
+        .collect();
+    items.sort_by(|a, b| twu[a].partial_cmp(&twu[b]).unwrap().then(a.cmp(b)));
+    let order: HashMap<u32, usize> = items.iter().enumerate().map(|(i, &item)| (item, i)).collect();
+
+    let mut lists: Vec<UtilityList> = items
+        .iter()
+        .map(|&item| UtilityList {
+            item,
+            entries: vec![],
+        })
+        .collect();
+    for (tid, transaction) in transactions.iter().enumerate() {
+        let mut transaction: Vec<(u32, f64)> = transaction
+            .iter()
+            .cloned()
+            .filter(|(item, _)| order.contains_key(item))
+            .collect();
+        transaction.sort_by_key(|(item, _)| order[item]);
+        let mut remaining_utility: f64 = transaction.iter().map(|&(_, utility)| utility).sum();
+        for (item, utility) in transaction {
+            remaining_utility -= utility;
+            lists[order[&item]].entries.push(Entry {
+                tid,
+                utility,
+                remaining_utility,
+            });
+        }
+    }
+
+    let mut search = Search {
+        min_utility,
+        rare_items,
+        num_transactions: transactions.len(),
+        itemsets: vec![],
+    };
+    search.grow(&[], None, &lists);
+    search.itemsets
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_hui_miner() {
+        use super::{hui_miner, read_utility_transactions, UtilityTable};
+        use itemizer::Itemizer;
+        use std::collections::HashSet;
+
+        let table = UtilityTable::read("bread,1\nmilk,2\ncaviar,50\nchampagne,40\n".as_bytes())
+            .unwrap();
+        assert!(UtilityTable::read("bread\n".as_bytes()).is_err());
+        assert!(UtilityTable::read("bread,cheap\n".as_bytes()).is_err());
+
+        let mut itemizer = Itemizer::new();
+        let transactions = read_utility_transactions(
+            "bread:2,milk\nbread,milk:3\ncaviar,champagne:2\ncaviar,bread\nbread,milk\n"
+                .as_bytes(),
+            &mut itemizer,
+            &table,
+        ).unwrap();
+        assert_eq!(transactions.len(), 5);
+        assert!(read_utility_transactions("bread,cheese\n".as_bytes(), &mut itemizer, &table)
+            .is_err());
+
+        let caviar = itemizer.get_id("caviar").unwrap();
+        let rare_items: HashSet<u32> = [caviar].iter().cloned().collect();
+        let itemsets = hui_miner(&transactions, 60.0, &rare_items);
+        let mut found: Vec<(String, f64)> = itemsets
+            .iter()
+            .map(|itemset| (itemset.to_string(&itemizer), itemset.utility))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        // Bread and milk have high utility together, 15, but not over the
+        // minimum, and don't contain the rare item anyway.
+        assert_eq!(found, vec![(String::from("caviar champagne"), 130.0)]);
+        assert_eq!(itemsets[0].support, 0.2);
+
+        let itemsets = hui_miner(&transactions, 50.0, &rare_items);
+        assert_eq!(itemsets.len(), 2);
+    }
+}
@@ -0,0 +1,60 @@
+// wasm-bindgen interface for mining in the browser. Build with:
+//   cargo build --lib --release --target wasm32-unknown-unknown --features wasm
+// and then run wasm-bindgen over the output to generate the JavaScript glue.
+
+use command_line_args::{Arguments, MaxSupportMode};
+use miner::mine_transactions;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct MinedRule {
+    pub antecedent: String,
+    pub consequent: String,
+    pub confidence: f64,
+    pub lift: f64,
+    pub support: f64,
+}
+
+// Mines `csv`, which holds one comma separated transaction per line, as the
+// CLI would read from a file.
+#[wasm_bindgen(js_name = mineRules)]
+pub fn mine_rules(
+    csv: &str,
+    max_support_mode: &str,
+    min_confidence: f64,
+    min_lift: f64,
+) -> Result<Vec<MinedRule>, JsValue> {
+    let max_support_mode: MaxSupportMode = max_support_mode.parse().map_err(JsValue::from)?;
+    if let MaxSupportMode::Gaussian = max_support_mode {
+        // The random datasets are generated with rand::thread_rng(), which
+        // can't be seeded from the OS in the browser.
+        return Err(JsValue::from(
+            "The gaussian max support mode isn't supported in WebAssembly, use 'pareto'",
+        ));
+    }
+
+    let transactions: Vec<Vec<String>> = csv
+        .lines()
+        .map(|line| line.split(',').map(String::from).collect())
+        .collect();
+    let args = Arguments {
+        max_support_mode,
+        min_confidence,
+        min_lift,
+        ..Arguments::default()
+    };
+    let result =
+        mine_transactions(&transactions, &args).map_err(|err| JsValue::from(err.to_string()))?;
+
+    Ok(result
+        .rules
+        .iter()
+        .map(|rule| MinedRule {
+            antecedent: rule.antecedent_to_string(&result.itemizer),
+            consequent: rule.consequent_to_string(&result.itemizer),
+            confidence: rule.confidence(),
+            lift: rule.lift(),
+            support: rule.support(),
+        })
+        .collect())
+}
@@ -0,0 +1,172 @@
+// End-to-end tests: mines the small datasets in testdata/ with the options
+// given on the command line, from parsing them to writing the rules, and
+// compares the rules with the golden files in testdata/golden/, so changes
+// to tree growth or rule generation which change the rules found are caught.
+//
+// testdata/quest.csv was made by
+// `riptree gen-data --output testdata/quest.csv --transactions 1000 --items 100 --patterns 50 --seed 7`.
+//
+// When a change to the rules is intended, rerun with UPDATE_GOLDEN=1 to
+// rewrite the golden files, and check the diff.
+
+extern crate riptree;
+
+use riptree::command_line_args::parse_args_or_exit;
+use riptree::miner::mine;
+use riptree::rule_writer::write_rules_csv;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Case {
+    // The golden file in testdata/golden/ the case's rules must match.
+    // Options which shouldn't change the rules, like the algorithm, share
+    // one.
+    golden: &'static str,
+    args: &'static [&'static str],
+}
+
+const ZOO: &[&str] = &[
+    "--input",
+    "testdata/zoo.csv",
+    "--max-support",
+    "pareto",
+    "--pareto-fraction",
+    "0.1",
+    "--min-confidence",
+    "0.9",
+    "--min-lift",
+    "5",
+];
+const QUEST: &[&str] = &[
+    "--input",
+    "testdata/quest.csv",
+    "--max-support",
+    "pareto",
+    "--pareto-fraction",
+    "0.1",
+    "--min-confidence",
+    "0.3",
+    "--min-lift",
+    "2",
+];
+
+const CASES: &[Case] = &[
+    Case { golden: "zoo", args: &[] },
+    Case { golden: "zoo", args: &["--algorithm", "eclat"] },
+    Case { golden: "zoo", args: &["--algorithm", "apriori"] },
+    Case { golden: "zoo", args: &["--algorithm", "hmine"] },
+    Case { golden: "zoo-top-k", args: &["--top-k", "25", "--metrics", "kulc"] },
+    Case { golden: "zoo-antecedent-len", args: &["--max-antecedent-len", "2"] },
+    Case { golden: "zoo-redundant", args: &["--filter-redundant-rules", "confidence"] },
+    Case { golden: "zoo-grouped", args: &["--group-by-consequent", "--sort-rules-by", "lift"] },
+    Case { golden: "zoo-selected", args: &["--max-rules-per-consequent", "2"] },
+    Case { golden: "quest", args: &[] },
+    Case { golden: "quest", args: &["--algorithm", "eclat"] },
+    Case {
+        golden: "quest-chi2",
+        args: &["--significance-test", "chi2", "--disable-family-wise-rule-filtering"],
+    },
+];
+
+// Rounds a number to 9 decimal places, dropping trailing zeros, so results
+// differing only by floating point error, e.g. from summing in another
+// order, compare equal.
+fn normalize_field(field: &str) -> String {
+    match field.parse::<f64>() {
+        Ok(x) if x.is_finite() => {
+            let rounded = format!("{:.9}", x);
+            let rounded = rounded.trim_end_matches('0').trim_end_matches('.');
+            if rounded == "-0" {
+                String::from("0")
+            } else {
+                rounded.to_owned()
+            }
+        }
+        _ => field.to_owned(),
+    }
+}
+
+// The header, then the rules in sorted order, as rules with equal sort keys
+// may be written in any order.
+fn normalize(csv: &str) -> String {
+    let mut lines = csv.lines();
+    let header = lines.next().unwrap_or_default().to_owned();
+    let mut rules: Vec<String> = lines
+        .map(|line| line.split(", ").map(normalize_field).collect::<Vec<String>>().join(", "))
+        .collect();
+    rules.sort();
+    let mut normalized = header;
+    for rule in rules {
+        normalized.push('\n');
+        normalized.push_str(&rule);
+    }
+    normalized.push('\n');
+    normalized
+}
+
+fn run(case: &Case) -> String {
+    let base = if case.golden.starts_with("zoo") { ZOO } else { QUEST };
+    // The rules are written to a buffer rather than --output, which is
+    // only required to parse.
+    let command_line: Vec<String> = vec!["riptree", "--output", "unused.csv"]
+        .into_iter()
+        .chain(base.iter().cloned())
+        .chain(case.args.iter().cloned())
+        .map(String::from)
+        .collect();
+    let args = parse_args_or_exit(command_line);
+    let result = mine(&args).unwrap();
+    let mut output: Vec<u8> = vec![];
+    write_rules_csv(
+        &mut output,
+        &result.rules,
+        &result.consequent_groups,
+        &result.itemizer,
+        result.report.partial,
+    )
+    .unwrap();
+    normalize(&String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_golden_files() {
+    let update = env::var("UPDATE_GOLDEN").is_ok();
+    // With UPDATE_GOLDEN, each golden file is written by the first case
+    // using it, and the others are checked against it.
+    let mut written: HashSet<&str> = HashSet::new();
+    let mut failures: Vec<String> = vec![];
+    for case in CASES {
+        let path = Path::new("testdata/golden").join(format!("{}.csv", case.golden));
+        let rules = run(case);
+        assert!(rules.lines().count() > 1, "{} {:?} found no rules", case.golden, case.args);
+        if update && written.insert(case.golden) {
+            fs::write(&path, &rules).unwrap();
+            continue;
+        }
+        let golden = match fs::read_to_string(&path) {
+            Ok(golden) => golden,
+            Err(err) => {
+                failures.push(format!("{}: {}", path.display(), err));
+                continue;
+            }
+        };
+        if golden == rules {
+            continue;
+        }
+        let mut message = format!("{} {:?} doesn't match {}:", case.golden, case.args, path.display());
+        for line in golden.lines().filter(|line| !rules.lines().any(|rule| rule == *line)) {
+            message.push_str(&format!("\n  - {}", line));
+        }
+        for line in rules.lines().filter(|line| !golden.lines().any(|rule| rule == *line)) {
+            message.push_str(&format!("\n  + {}", line));
+        }
+        failures.push(message);
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nRerun with UPDATE_GOLDEN=1 if the change is intended.",
+        failures.join("\n")
+    );
+}